@@ -1,4 +1,11 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::logging::{LogFormat, LogRotation};
+use vajra_scanner_tcp::{DEFAULT_VERSION_INTENSITY, MAX_VERSION_INTENSITY};
+
+/// Upper bound for `--retries`; more only multiplies time spent on dead ports
+pub const MAX_RETRIES: u32 = 10;
 
 #[derive(Parser)]
 #[command(name = "vajra")]
@@ -11,45 +18,218 @@ pub struct Cli {
     /// Increase verbosity (-v, -vv, -vvv)
     #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
+
+    /// Also write logs to this file (stdout logging stays on)
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Rotate the log file: never, hourly, daily, or at a size such as 100M
+    #[arg(long, default_value = "never", requires = "log_file", global = true)]
+    pub log_rotation: LogRotation,
+
+    /// Log line format, on stdout and in the log file: compact or json
+    #[arg(long, default_value = "compact", global = true)]
+    pub log_format: LogFormat,
 }
 
+// Parsed once at startup; boxing the scan options buys nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
-    Scan {
-        /// Targets (IP or hostname). Example: 127.0.0.1 or example.com
-        #[arg(short = 't', long, required = true)]
-        targets: String,
+    Scan(ScanArgs),
 
-        /// Ports to scan. Examples: 80,443 or 1-1024 or 22,80-90
-        #[arg(short, long, default_value = "80")]
-        ports: String,
+    /// Print the known port → service mappings
+    ListServices {
+        /// Optional `port,protocol,service` CSV or nmap `services` file whose entries are listed as overrides
+        #[arg(long)]
+        port_table: Option<PathBuf>,
+
+        /// Output format: text, json
+        #[arg(short, long, default_value = "text")]
+        output_format: String,
+    },
+
+    /// Merge JSON result files (e.g. from sharded scans) into one report
+    Merge {
+        /// Result files written with `--output-format json`
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Cut banners in the output to this many characters (0 = unlimited)
+        #[arg(long, default_value_t = crate::output::DEFAULT_MAX_BANNER_LEN)]
+        max_banner_len: usize,
+
+        /// Output format: text, json, csv
+        #[arg(short, long, default_value = "text")]
+        output_format: String,
+    },
+
+    /// Compare two scans and report ports newly open, no longer open, or
+    /// running a different service
+    Diff {
+        /// Earlier result file written with `--output-format json`
+        old: PathBuf,
+
+        /// Later result file written with `--output-format json`
+        new: PathBuf,
+
+        /// Output format: text, json
+        #[arg(short, long, default_value = "text")]
+        output_format: String,
+    },
+
+    /// Check raw-socket capabilities needed by raw scans, without scanning
+    CheckPrivileges,
+}
+
+/// Scan hosts and ports
+#[derive(Args)]
+pub struct ScanArgs {
+    /// Targets (IP or hostname, optionally with :port). Example: 127.0.0.1 or example.com:8080
+    #[arg(short = 't', long, required = true)]
+    pub targets: String,
+
+    /// Ports to scan. Examples: 80,443 or 1-1024 or 22,80-90
+    #[arg(short, long, default_value = "80")]
+    pub ports: String,
+
+    /// Scan the N most common TCP ports (up to 1000, like nmap's --top-ports); overrides --ports
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..=1000))]
+    pub top_ports: Option<u16>,
 
     /// Max concurrent workers
     #[arg(short, long, default_value = "500")]
-    concurrency: usize,
+    pub concurrency: usize,
 
     /// Rate limit (requests per second)
     #[arg(short = 'r', long, default_value = "2000")]
-    rate_limit: u64,
+    pub rate_limit: u64,
 
     /// Timeout in milliseconds
     #[arg(long, default_value = "1000")]
-    timeout: u64,
+    pub timeout: u64,
 
     /// Banner grab timeout in milliseconds (controls how long we wait for service banners)
     #[arg(long, default_value = "300")]
-    banner_timeout: u64,
+    pub banner_timeout: u64,
 
-        /// Output format: text, json, csv
-        #[arg(short, long, default_value = "text")]
-        output_format: String,
+    /// How aggressively banners are probed (0 = passive only, 9 = every probe)
+    #[arg(long, default_value_t = DEFAULT_VERSION_INTENSITY, value_parser = clap::value_parser!(u8).range(0..=MAX_VERSION_INTENSITY as i64))]
+    pub version_intensity: u8,
+
+    /// Retries per probe (0-10); overrides the preset's default
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=MAX_RETRIES as i64))]
+    pub retries: Option<u32>,
+
+    /// Total time budget per host in seconds; remaining ports are skipped once exceeded
+    #[arg(long)]
+    pub host_timeout: Option<u64>,
+
+    /// Derive each probe's timeout from measured round trips, starting from --timeout
+    #[arg(long)]
+    pub adaptive_timeout: bool,
+
+    /// Max probes in flight to any one host (default: unlimited, only --concurrency applies)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub per_host_concurrency: Option<u32>,
+
+    /// Max concurrent DNS lookups when resolving hostname targets
+    #[arg(long, default_value_t = vajra_target_resolver::DEFAULT_DNS_CONCURRENCY)]
+    pub dns_concurrency: usize,
+
+    /// Scan only the first address each hostname resolves to
+    #[arg(long, overrides_with = "resolve_all")]
+    pub first_ip_only: bool,
+
+    /// Scan every address each hostname resolves to (default)
+    #[arg(long, overrides_with = "first_ip_only")]
+    pub resolve_all: bool,
+
+    /// Ping each host first (ICMP echo, then TCP SYN/ACK) and port-scan only those that answer
+    #[arg(long)]
+    pub discovery: bool,
+
+    /// Prior JSON result file; only results not already in it are reported
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Write raw reply packets (raw scans) and banners (tcp scan) to this file
+    #[arg(long)]
+    pub dump: Option<PathBuf>,
+
+    /// Write probe and response packets to a pcap file (synthesized for tcp scans)
+    #[arg(long)]
+    pub pcap: Option<PathBuf>,
+
+    /// nmap-service-probes style file; its probes are run against open TCP ports
+    #[arg(long)]
+    pub service_db: Option<PathBuf>,
+
+    /// `port,protocol,service` CSV or nmap `services` file overriding the built-in port → service names
+    #[arg(long)]
+    pub port_table: Option<PathBuf>,
+
+    /// Probe UDP-typical ports (53, 123, 161, 500, ...) over UDP and the rest with --scan-type
+    #[arg(long)]
+    pub auto_protocol: bool,
+
+    /// Handshake with TLS ports (443, 8443, 993, ...) and report version, cipher and certificate names (tcp scan)
+    #[arg(long)]
+    pub tls_probe: bool,
+
+    /// Rescan ports a raw TCP scan leaves filtered, unfiltered or open|filtered with a TCP connect
+    #[arg(long)]
+    pub fallback_connect: bool,
+
+    /// Don't print periodic progress lines to stderr (printed when stderr is not a terminal)
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Also write each result to this CSV file as soon as it is scanned, so partial results survive an interrupted scan
+    #[arg(long)]
+    pub stream_csv: Option<PathBuf>,
+
+    /// Print only the summary (counts, duration, rate, services found) instead of every result
+    #[arg(long)]
+    pub summary_only: bool,
+
+    /// Probe targets in a random order instead of host by host, port by port
+    #[arg(long)]
+    pub randomize: bool,
+
+    /// Seed for --randomize, to repeat the order of an earlier run (implies --randomize)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Look up the reverse-DNS (PTR) name of each scanned host and show it next to the address
+    #[arg(long)]
+    pub resolve_ptr: bool,
+
+    /// Only list open ports (the summary still counts every port)
+    #[arg(long)]
+    pub open: bool,
+
+    /// Save scan progress to this file; if it exists, resume the scan it records instead
+    #[arg(long, value_name = "STATEFILE", conflicts_with = "stream_csv")]
+    pub resume: Option<PathBuf>,
+
+    /// Seconds between saves of the --resume file
+    #[arg(long, default_value_t = 60, requires = "resume", value_parser = clap::value_parser!(u64).range(1..))]
+    pub autosave_interval: u64,
+
+    /// Cut banners in the output to this many characters (0 = unlimited)
+    #[arg(long, default_value_t = crate::output::DEFAULT_MAX_BANNER_LEN)]
+    pub max_banner_len: usize,
+
+    /// Output format: text, compact, json, csv, xml (nmap-compatible), or protobuf:<file> (requires the `protobuf` feature)
+    #[arg(short, long, default_value = "text")]
+    pub output_format: String,
 
     /// Preset: fast, balanced, accurate, stealth
     #[arg(long, default_value = "balanced", value_parser = ["fast","balanced","accurate","stealth"])]
-    preset: String,
+    pub preset: String,
 
-        /// Scanner type to use for this job: "tcp" (connect) or "syn" (SYN scan)
-        #[arg(long, default_value = "tcp", value_parser = ["tcp", "syn"])]
-        scan_type: String,
-    },
+    /// Scanner type: "tcp" (connect), "syn", "fin", "null", "xmas", "ack" (raw TCP probes), "udp" or "sctp" (raw SCTP INIT)
+    #[arg(long, default_value = "tcp", value_parser = ["tcp", "syn", "fin", "null", "xmas", "ack", "udp", "sctp"])]
+    pub scan_type: String,
 }
@@ -0,0 +1,88 @@
+//! Baseline filtering for continuous monitoring
+//!
+//! A baseline is a prior JSON result file. After a scan, results whose
+//! target already appears in the baseline with the same state and service
+//! are suppressed, so only new hosts, ports and services are reported.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use vajra_common::{PortState, ProbeResult, Target};
+
+use crate::merge::read_json_results;
+
+/// Known state and service name for each previously scanned target
+#[derive(Default)]
+pub struct Baseline {
+    known: HashMap<Target, (PortState, Option<String>)>,
+}
+
+impl Baseline {
+    /// Load a baseline written with `--output-format json`
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::from_results(read_json_results(path)?.results))
+    }
+
+    /// Build a baseline from prior results
+    pub fn from_results(results: Vec<ProbeResult>) -> Self {
+        let known = results
+            .into_iter()
+            .map(|r| (r.target, (r.state, r.service.map(|s| s.service))))
+            .collect();
+        Self { known }
+    }
+
+    /// Whether `result` matches the baseline entry for its target
+    pub fn contains(&self, result: &ProbeResult) -> bool {
+        self.known.get(&result.target).is_some_and(|(state, service)| {
+            *state == result.state
+                && service.as_deref() == result.service.as_ref().map(|s| s.service.as_str())
+        })
+    }
+
+    /// Drop results already present in the baseline
+    pub fn filter_new(&self, results: Vec<ProbeResult>) -> Vec<ProbeResult> {
+        results.into_iter().filter(|r| !self.contains(r)).collect()
+    }
+
+    /// Number of targets in the baseline
+    pub fn len(&self) -> usize {
+        self.known.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use vajra_common::ServiceMatch;
+
+    fn result(host: u8, port: u16, state: PortState, service: &str) -> ProbeResult {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, host));
+        ProbeResult::new(Target::new(ip, port), state).with_service(ServiceMatch::new(service))
+    }
+
+    #[test]
+    fn test_baseline_suppresses_unchanged_ports() {
+        let baseline = Baseline::from_results(vec![
+            result(1, 22, PortState::Open, "ssh"),
+            result(1, 80, PortState::Open, "http"),
+        ]);
+
+        let scanned = vec![
+            result(1, 22, PortState::Open, "ssh"),         // unchanged
+            result(1, 80, PortState::Open, "http-proxy"),  // new service
+            result(1, 443, PortState::Open, "https"),      // new port
+            result(2, 22, PortState::Open, "ssh"),         // new host
+        ];
+        let new: Vec<(u8, u16)> = baseline
+            .filter_new(scanned)
+            .iter()
+            .map(|r| match r.target.ip {
+                IpAddr::V4(ip) => (ip.octets()[3], r.target.port),
+                IpAddr::V6(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(new, vec![(1, 80), (1, 443), (2, 22)]);
+    }
+}
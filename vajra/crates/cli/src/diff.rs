@@ -0,0 +1,272 @@
+//! Comparison of two scans of the same network, like nmap's ndiff
+//!
+//! Only open ports are compared: per host, ports that are newly open, ports
+//! that are no longer open, and open ports whose service (name, product or
+//! version) changed. Everything else is noise for monitoring purposes.
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use vajra_common::{PortState, ProbeResult, Protocol, ServiceMatch};
+
+/// An open port as seen in one scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPort {
+    pub port: u16,
+    pub protocol: Protocol,
+    /// Service, product and version, e.g. "ssh OpenSSH 9.6"
+    pub service: Option<String>,
+}
+
+/// A port open in the old scan and not in the new one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosedPort {
+    pub port: OpenPort,
+    /// State in the new scan; `None` if the new scan did not cover it
+    pub now: Option<PortState>,
+}
+
+/// An open port whose service changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceChange {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Changes on one host
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostDiff {
+    pub added: Vec<OpenPort>,
+    pub removed: Vec<ClosedPort>,
+    pub changed: Vec<ServiceChange>,
+}
+
+/// Changes between two scans, per host in address order. Hosts without
+/// changes are left out.
+#[derive(Debug, Default)]
+pub struct ScanDiff {
+    pub hosts: BTreeMap<IpAddr, HostDiff>,
+}
+
+type PortKey = (IpAddr, u16, Protocol);
+
+/// Service name followed by product and version, as far as known
+fn describe_service(service: &ServiceMatch) -> String {
+    [Some(&service.service), service.product.as_ref(), service.version.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// " <service>" for text lines, or nothing when unknown
+fn service_suffix(service: &Option<String>) -> String {
+    service.as_ref().map(|s| format!(" {}", s)).unwrap_or_default()
+}
+
+fn open_port(result: &ProbeResult) -> OpenPort {
+    OpenPort {
+        port: result.target.port,
+        protocol: result.target.protocol,
+        service: result.service.as_ref().map(describe_service),
+    }
+}
+
+impl ScanDiff {
+    /// Compare `old` with `new`
+    pub fn compute(old: &[ProbeResult], new: &[ProbeResult]) -> Self {
+        let key = |r: &ProbeResult| (r.target.ip, r.target.port, r.target.protocol);
+        let old_by_key: HashMap<PortKey, &ProbeResult> = old.iter().map(|r| (key(r), r)).collect();
+        let new_by_key: HashMap<PortKey, &ProbeResult> = new.iter().map(|r| (key(r), r)).collect();
+        let mut diff = ScanDiff::default();
+
+        for (k, now) in &new_by_key {
+            if now.state != PortState::Open {
+                continue;
+            }
+            let host = diff.hosts.entry(k.0).or_default();
+            match old_by_key.get(k).filter(|before| before.state == PortState::Open) {
+                None => host.added.push(open_port(now)),
+                Some(before) => {
+                    let (old, new) = (open_port(before).service, open_port(now).service);
+                    if old != new {
+                        host.changed.push(ServiceChange { port: k.1, protocol: k.2, old, new });
+                    }
+                }
+            }
+        }
+        for (k, before) in &old_by_key {
+            let now = new_by_key.get(k).map(|r| r.state);
+            if before.state == PortState::Open && now != Some(PortState::Open) {
+                diff.hosts
+                    .entry(k.0)
+                    .or_default()
+                    .removed
+                    .push(ClosedPort { port: open_port(before), now });
+            }
+        }
+
+        diff.hosts.retain(|_, host| !host.is_empty());
+        for host in diff.hosts.values_mut() {
+            host.added.sort_by_key(|p| (p.port, p.protocol.as_str()));
+            host.removed.sort_by_key(|p| (p.port.port, p.port.protocol.as_str()));
+            host.changed.sort_by_key(|c| (c.port, c.protocol.as_str()));
+        }
+        diff
+    }
+
+    /// Total (added, removed, changed) ports over all hosts
+    pub fn counts(&self) -> (usize, usize, usize) {
+        self.hosts.values().fold((0, 0, 0), |(a, r, c), host| {
+            (a + host.added.len(), r + host.removed.len(), c + host.changed.len())
+        })
+    }
+
+    /// Render in `format`: text or json
+    pub fn format(&self, format: &str) -> Result<String> {
+        match format.to_lowercase().as_str() {
+            "text" | "table" | "t" => Ok(self.to_text()),
+            "json" | "j" => Ok(serde_json::to_string_pretty(&self.to_json())?),
+            other => Err(anyhow!("unsupported diff format '{}' (expected text or json)", other)),
+        }
+    }
+
+    /// `+` newly open, `-` no longer open, `~` service changed
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (ip, host) in &self.hosts {
+            let _ = writeln!(out, "{}:", ip);
+            for p in &host.added {
+                let _ = writeln!(out, "  + {}/{} open{}", p.port, p.protocol.as_str(), service_suffix(&p.service));
+            }
+            for p in &host.removed {
+                let now = p.now.map_or_else(|| "not scanned".to_string(), |s| s.to_string());
+                let _ = writeln!(
+                    out,
+                    "  - {}/{}{} (now {})",
+                    p.port.port,
+                    p.port.protocol.as_str(),
+                    service_suffix(&p.port.service),
+                    now
+                );
+            }
+            for c in &host.changed {
+                let _ = writeln!(
+                    out,
+                    "  ~ {}/{} {} -> {}",
+                    c.port,
+                    c.protocol.as_str(),
+                    c.old.as_deref().unwrap_or("unknown"),
+                    c.new.as_deref().unwrap_or("unknown")
+                );
+            }
+        }
+        let (added, removed, changed) = self.counts();
+        if added + removed + changed == 0 {
+            out.push_str("No changes\n");
+        } else {
+            let _ = writeln!(out, "\n{} added, {} removed, {} changed", added, removed, changed);
+        }
+        out
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let port = |p: &OpenPort| json!({ "port": p.port, "protocol": p.protocol.as_str(), "service": p.service });
+        let hosts: Vec<_> = self
+            .hosts
+            .iter()
+            .map(|(ip, host)| {
+                json!({
+                    "ip": ip.to_string(),
+                    "added": host.added.iter().map(port).collect::<Vec<_>>(),
+                    "removed": host.removed.iter().map(|p| {
+                        let mut entry = port(&p.port);
+                        entry["now"] = json!(p.now.map(|s| s.to_string()));
+                        entry
+                    }).collect::<Vec<_>>(),
+                    "changed": host.changed.iter().map(|c| json!({
+                        "port": c.port,
+                        "protocol": c.protocol.as_str(),
+                        "old": c.old,
+                        "new": c.new,
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let (added, removed, changed) = self.counts();
+        json!({
+            "hosts": hosts,
+            "summary": { "added": added, "removed": removed, "changed": changed },
+        })
+    }
+}
+
+impl HostDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use vajra_common::Target;
+
+    fn result(host: u8, port: u16, state: PortState, service: Option<(&str, &str)>) -> ProbeResult {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, host));
+        let mut r = ProbeResult::new(Target::new(ip, port), state);
+        if let Some((name, version)) = service {
+            r = r.with_service(ServiceMatch::new(name).with_version(version.to_string()));
+        }
+        r
+    }
+
+    #[test]
+    fn test_diff_reports_open_port_changes() {
+        let old = vec![
+            result(1, 22, PortState::Open, Some(("ssh", "8.2"))),
+            result(1, 80, PortState::Open, None),
+            result(1, 443, PortState::Closed, None),
+            result(2, 25, PortState::Open, None),
+            result(3, 53, PortState::Open, None),
+        ];
+        let new = vec![
+            result(1, 22, PortState::Open, Some(("ssh", "9.6"))),
+            result(1, 80, PortState::Closed, None),
+            result(1, 443, PortState::Open, None),
+            result(2, 25, PortState::Open, None),
+            result(4, 3389, PortState::Open, None),
+        ];
+        let diff = ScanDiff::compute(&old, &new);
+
+        // Host 2 is unchanged
+        let hosts: Vec<String> = diff.hosts.keys().map(|ip| ip.to_string()).collect();
+        assert_eq!(hosts, ["10.0.0.1", "10.0.0.3", "10.0.0.4"]);
+        assert_eq!(diff.counts(), (2, 2, 1));
+
+        let text = diff.format("text").unwrap();
+        assert!(text.contains("10.0.0.1:\n  + 443/tcp open\n  - 80/tcp (now closed)\n  ~ 22/tcp ssh 8.2 -> ssh 9.6\n"));
+        assert!(text.contains("10.0.0.3:\n  - 53/tcp (now not scanned)\n"));
+        assert!(text.ends_with("2 added, 2 removed, 1 changed\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&diff.format("json").unwrap()).unwrap();
+        assert_eq!(json["summary"]["added"], 2);
+        assert_eq!(json["hosts"][0]["changed"][0]["new"], "ssh 9.6");
+        assert_eq!(json["hosts"][0]["removed"][0]["now"], "closed");
+        assert_eq!(json["hosts"][2]["added"][0]["port"], 3389);
+    }
+
+    #[test]
+    fn test_identical_scans_have_no_changes() {
+        let scan = vec![result(1, 22, PortState::Open, Some(("ssh", "9.6")))];
+        let diff = ScanDiff::compute(&scan, &scan);
+        assert!(diff.hosts.is_empty());
+        assert_eq!(diff.format("text").unwrap(), "No changes\n");
+    }
+}
@@ -1,8 +1,613 @@
-#![allow(dead_code)]
-// Minimal placeholder for the crate.
-// Replace with real implementation.
+//! Vajra as a library
+//!
+//! [`scan`] runs the pipeline behind `vajra scan` in one call: resolve the
+//! target string, scan every host × port with the chosen scanner through
+//! the orchestrator, and return the results. [`ScanConfig`] exposes the
+//! rest of the scanning pipeline `vajra scan` runs (presets, fallbacks,
+//! auto protocol routing, `host:port` jobs); output, resume, baselines and
+//! the other reporting options stay in the command line tool.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use vajra::{scan, ScanType};
+//! use vajra_common::ScanOptions;
+//!
+//! let results = scan("192.0.2.0/28", &[22, 80, 443], ScanOptions::default(), ScanType::Tcp).await?;
+//! for result in results.iter().filter(|r| r.is_open()) {
+//!     println!("{}:{} open", result.target.ip, result.target.port);
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
-/// Crate: PLACEHOLDER
-pub fn placeholder() -> &'static str {
-    "placeholder"
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+use vajra_common::{
+    DumpWriter, PcapWriter, PortSpec, ProbeResult, Protocol, ScanJob, ScanOptions, Target, TargetGrid,
+};
+use vajra_fingerprint::protocol_hint;
+use vajra_orchestrator::Orchestrator;
+use vajra_scanner_syn::{CaptureHandle, ScanFlavor, SynError, SynScanner};
+use vajra_scanner_tcp::{TcpScanner, DEFAULT_BANNER_TIMEOUT, DEFAULT_VERSION_INTENSITY};
+use vajra_scanner_udp::UdpScanner;
+use vajra_target_resolver::TargetResolver;
+
+/// Which scanner probes the targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanType {
+    /// TCP connect scan; needs no privileges
+    #[default]
+    Tcp,
+    Syn,
+    Fin,
+    Null,
+    Xmas,
+    Ack,
+    /// SCTP INIT scan
+    Sctp,
+    Udp,
+}
+
+impl ScanType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanType::Tcp => "tcp",
+            ScanType::Syn => "syn",
+            ScanType::Fin => "fin",
+            ScanType::Null => "null",
+            ScanType::Xmas => "xmas",
+            ScanType::Ack => "ack",
+            ScanType::Sctp => "sctp",
+            ScanType::Udp => "udp",
+        }
+    }
+
+    /// Transport protocol of the targets this scan probes
+    pub fn protocol(self) -> Protocol {
+        match self {
+            ScanType::Udp => Protocol::UDP,
+            ScanType::Sctp => Protocol::SCTP,
+            _ => Protocol::TCP,
+        }
+    }
+
+    /// Probe type for the scans `SynScanner` runs (raw sockets, root)
+    pub fn flavor(self) -> Option<ScanFlavor> {
+        match self {
+            ScanType::Syn => Some(ScanFlavor::Syn),
+            ScanType::Fin => Some(ScanFlavor::Fin),
+            ScanType::Null => Some(ScanFlavor::Null),
+            ScanType::Xmas => Some(ScanFlavor::Xmas),
+            ScanType::Ack => Some(ScanFlavor::Ack),
+            ScanType::Sctp => Some(ScanFlavor::SctpInit),
+            ScanType::Tcp | ScanType::Udp => None,
+        }
+    }
+}
+
+impl FromStr for ScanType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "tcp" => Ok(ScanType::Tcp),
+            "syn" => Ok(ScanType::Syn),
+            "fin" => Ok(ScanType::Fin),
+            "null" => Ok(ScanType::Null),
+            "xmas" => Ok(ScanType::Xmas),
+            "ack" => Ok(ScanType::Ack),
+            "sctp" => Ok(ScanType::Sctp),
+            "udp" => Ok(ScanType::Udp),
+            other => Err(anyhow!("Invalid scanner type '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for ScanType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Start the capture loop a raw scan with `scanner` needs; keep the handle
+/// alive until the scan ends. Returns `None` when raw sockets are not
+/// permitted and a TCP connect scan can stand in, which is every raw
+/// probe type but SCTP.
+pub fn start_capture(scanner: &SynScanner, flavor: ScanFlavor) -> Result<Option<CaptureHandle>> {
+    match vajra_scanner_syn::init(scanner) {
+        Ok(capture) => Ok(Some(capture)),
+        Err(SynError::NotPermitted) if flavor != ScanFlavor::SctpInit => {
+            warn!("Raw sockets not permitted; falling back to a TCP connect scan");
+            Ok(None)
+        }
+        Err(e) => Err(e).context(
+            "Failed to initialize SYN scanner. Make sure you have CAP_NET_RAW capabilities or run with sudo.",
+        ),
+    }
+}
+
+/// Longest timeout a connect probe waits; slow hosts are better served by
+/// retries than by holding a socket open
+const MAX_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// File descriptors kept out of the concurrency budget for everything but
+/// probe sockets: output and log files, capture sockets, DNS lookups
+const RESERVED_FDS: u64 = 64;
+
+/// Scanner and orchestrator settings for a scan. [`ScanConfig::start`]
+/// registers the scanners and submits the targets; [`scan`] builds one
+/// from [`ScanOptions`] and `vajra scan` from its flags.
+#[derive(Clone)]
+pub struct ScanConfig {
+    pub scan_type: ScanType,
+    /// Probe timeout; connect probes wait at most 5 s of it
+    pub timeout: Duration,
+    /// How long an open TCP port is given to send a banner
+    pub banner_timeout: Duration,
+    /// How aggressively banners are probed (0 = passive only, 9 = every probe)
+    pub version_intensity: u8,
+    /// Resends per probe for every scanner; `None` keeps each one's default
+    pub retries: Option<u32>,
+    /// The `accurate` preset: timeouts of at least 3 s (1 s for banners) and,
+    /// unless `retries` is set, two resends for connect and UDP probes
+    pub accurate: bool,
+    /// Probes in flight; lowered if the open file limit cannot feed them
+    pub concurrency: usize,
+    /// Probes per second
+    pub rate_limit: u32,
+    /// Time budget per host; its remaining ports are skipped once exceeded
+    pub host_timeout: Option<Duration>,
+    /// Derive each probe's timeout from measured round trips, starting at `timeout`
+    pub adaptive_timeout: bool,
+    /// Probes in flight to any one host
+    pub per_host_concurrency: Option<usize>,
+    /// Probe UDP-typical ports over UDP and the rest with `scan_type` (or
+    /// TCP for a UDP scan), each through its own scanner
+    pub auto_protocol: bool,
+    /// Handshake with TLS ports on a TCP connect scan
+    pub tls_probe: bool,
+    /// Rescan ports a raw TCP scan leaves unanswered with a TCP connect
+    pub fallback_connect: bool,
+    /// Scan targets in a pseudo-random order from this seed
+    pub shuffle_seed: Option<u64>,
+    /// Print a progress line on stderr this often
+    pub progress_reports: Option<Duration>,
+    /// Targets an earlier run already scanned; they are skipped
+    pub completed: HashSet<Target>,
+    /// Raw reply packets (raw scans) and banners (TCP scan) are written here
+    pub dump: Option<Arc<DumpWriter>>,
+    /// Probes and responses are written here as pcap
+    pub pcap: Option<Arc<PcapWriter>>,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self::from_options(ScanType::default(), &ScanOptions::default())
+    }
+}
+
+impl ScanConfig {
+    /// Settings for a `scan_type` scan tuned by `opts`; everything else off
+    pub fn from_options(scan_type: ScanType, opts: &ScanOptions) -> Self {
+        Self {
+            scan_type,
+            timeout: opts.timeout,
+            banner_timeout: DEFAULT_BANNER_TIMEOUT,
+            version_intensity: DEFAULT_VERSION_INTENSITY,
+            retries: Some(opts.retries),
+            accurate: false,
+            concurrency: opts.max_concurrency.max(1),
+            rate_limit: opts.rate_limit.map_or(u32::MAX, |rate| u32::try_from(rate).unwrap_or(u32::MAX)),
+            host_timeout: None,
+            adaptive_timeout: false,
+            per_host_concurrency: None,
+            auto_protocol: false,
+            tls_probe: false,
+            fallback_connect: false,
+            shuffle_seed: opts.shuffle_seed,
+            progress_reports: None,
+            completed: HashSet::new(),
+            dump: None,
+            pcap: None,
+        }
+    }
+
+    /// Probe timeout after the preset's floor
+    pub fn probe_timeout(&self) -> Duration {
+        if self.accurate {
+            self.timeout.max(Duration::from_secs(3))
+        } else {
+            self.timeout
+        }
+    }
+
+    fn probe_banner_timeout(&self) -> Duration {
+        if self.accurate {
+            self.banner_timeout.max(Duration::from_secs(1))
+        } else {
+            self.banner_timeout
+        }
+    }
+
+    /// Resends for `scan_type`'s probes: `retries` if set, otherwise the
+    /// preset/scanner default (raw and UDP probes are lossy, so they always
+    /// resend at least once)
+    pub fn retries_for(&self, scan_type: ScanType) -> u32 {
+        if let Some(n) = self.retries {
+            return n;
+        }
+        let preset_retries = if self.accurate { 2 } else { 0 };
+        match scan_type {
+            ScanType::Tcp => preset_retries,
+            ScanType::Udp => preset_retries.max(1),
+            _ => 1,
+        }
+    }
+
+    /// The IP × port grid, in `shuffle_seed` order. Targets use the scan
+    /// type's protocol, or with `auto_protocol` the protocol hinted for
+    /// each port.
+    pub fn grid(&self, ips: Vec<IpAddr>, ports: PortSpec) -> TargetGrid {
+        let mut grid = TargetGrid::new(ips, ports, self.scan_type.protocol());
+        if self.auto_protocol {
+            grid = grid.with_protocol_for(protocol_hint);
+        }
+        if let Some(seed) = self.shuffle_seed {
+            grid = grid.with_shuffle(seed);
+        }
+        grid
+    }
+
+    /// Job scanning each `host:port` endpoint, with the protocol
+    /// [`grid`](Self::grid) would give its port
+    pub fn endpoint_job(&self, endpoints: &[(IpAddr, u16)]) -> ScanJob {
+        let targets = endpoints
+            .iter()
+            .map(|&(ip, port)| {
+                let protocol = if self.auto_protocol { protocol_hint(port) } else { self.scan_type.protocol() };
+                Target::new(ip, port).with_protocol(protocol)
+            })
+            .collect();
+        let mut job = ScanJob::new(targets);
+        job.options.shuffle_seed = self.shuffle_seed;
+        job
+    }
+
+    fn tcp_scanner(&self, retries: u32) -> TcpScanner {
+        let mut scanner = TcpScanner::new()
+            .with_timeout(self.probe_timeout().min(MAX_CONNECT_TIMEOUT))
+            .with_retries(retries)
+            .with_banner_timeout(self.probe_banner_timeout())
+            .with_version_intensity(self.version_intensity)
+            .with_tls_probe(self.tls_probe);
+        if let Some(dump) = &self.dump {
+            scanner = scanner.with_dump(dump.clone());
+        }
+        if let Some(pcap) = &self.pcap {
+            scanner = scanner.with_pcap(pcap.clone());
+        }
+        scanner
+    }
+
+    fn udp_scanner(&self, retries: u32) -> UdpScanner {
+        UdpScanner::new().with_timeout(self.probe_timeout()).with_retries(retries)
+    }
+
+    /// Set up the orchestrator and its scanners, then submit `grid` and,
+    /// if given, a job of `host:port` targets (see
+    /// [`endpoint_job`](Self::endpoint_job)). Raw scan types need
+    /// CAP_NET_RAW; without it SYN-style scans fall back to a TCP connect
+    /// scan.
+    pub async fn start(&self, grid: TargetGrid, endpoints: Option<ScanJob>) -> Result<PreparedScan> {
+        // Every in-flight probe may hold a socket
+        let concurrency = cap_concurrency_to_fd_limit(self.concurrency.max(1));
+        let mut orchestrator = Orchestrator::new(concurrency, self.rate_limit);
+        if let Some(timeout) = self.host_timeout {
+            info!("Host timeout: {}s", timeout.as_secs());
+            orchestrator = orchestrator.with_host_timeout(timeout);
+        }
+        if self.adaptive_timeout {
+            info!("Adaptive timeout: starting at {}ms", self.probe_timeout().as_millis());
+            orchestrator = orchestrator.with_adaptive_timeout(self.probe_timeout());
+        }
+        if let Some(interval) = self.progress_reports {
+            orchestrator = orchestrator.with_progress_reports(interval);
+        }
+        if !self.completed.is_empty() {
+            orchestrator = orchestrator.with_completed(self.completed.clone());
+        }
+        if let Some(limit) = self.per_host_concurrency {
+            info!("Per-host concurrency: {}", limit);
+            orchestrator = orchestrator.with_per_host_concurrency(limit);
+        }
+
+        // Raw scans keep the capture loop alive until the scan ends
+        let mut capture = None;
+        let mut scanner_name = self.scan_type.as_str();
+        let connect_retries = self.retries_for(ScanType::Tcp);
+        match self.scan_type.flavor() {
+            Some(flavor) => {
+                let syn_scanner = SynScanner::new()
+                    .with_timeout(self.probe_timeout())
+                    .with_retries(self.retries_for(self.scan_type))
+                    .with_flavor(flavor);
+                capture = start_capture(&syn_scanner, flavor)?;
+                if capture.is_some() {
+                    vajra_scanner_syn::set_packet_dump(self.dump.clone());
+                    vajra_scanner_syn::set_pcap_writer(self.pcap.clone());
+                    orchestrator.add_scanner(scanner_name, Arc::new(syn_scanner));
+                    if self.fallback_connect && flavor != ScanFlavor::SctpInit {
+                        orchestrator.add_scanner("tcp", Arc::new(self.tcp_scanner(connect_retries)));
+                        orchestrator.set_fallbacks(scanner_name, &["tcp"]);
+                    }
+                } else {
+                    // A connect scan needs no privileges and answers the same question
+                    scanner_name = ScanType::Tcp.as_str();
+                    orchestrator.add_scanner(scanner_name, Arc::new(self.tcp_scanner(connect_retries)));
+                }
+            }
+            None if self.scan_type == ScanType::Udp => {
+                orchestrator.add_scanner(scanner_name, Arc::new(self.udp_scanner(self.retries_for(ScanType::Udp))));
+            }
+            None => orchestrator.add_scanner(scanner_name, Arc::new(self.tcp_scanner(connect_retries))),
+        }
+
+        // Auto protocol: the other protocol's targets go to a companion scanner
+        if self.auto_protocol {
+            if self.scan_type == ScanType::Udp {
+                orchestrator.add_scanner("tcp", Arc::new(self.tcp_scanner(connect_retries)));
+                orchestrator.route_protocol(Protocol::TCP, "tcp");
+            } else {
+                orchestrator.add_scanner("udp", Arc::new(self.udp_scanner(self.retries_for(ScanType::Udp))));
+                orchestrator.route_protocol(Protocol::UDP, "udp");
+            }
+        }
+
+        let job_id = match endpoints {
+            Some(job) if grid.is_empty() => {
+                let id = job.id;
+                orchestrator.submit_job(job).await?;
+                id
+            }
+            Some(job) => {
+                let id = orchestrator.submit_grid(grid).await?;
+                orchestrator.submit_job(job).await?;
+                id
+            }
+            None => orchestrator.submit_grid(grid).await?,
+        };
+
+        Ok(PreparedScan {
+            orchestrator,
+            scanner_name,
+            job_id,
+            concurrency,
+            dump: self.dump.clone(),
+            pcap: self.pcap.clone(),
+            _capture: capture,
+        })
+    }
+}
+
+/// A scan [`ScanConfig::start`] set up: the orchestrator with its scanners
+/// registered and the targets submitted. Run it through
+/// [`orchestrator`](Self::orchestrator) starting with
+/// [`scanner_name`](Self::scanner_name), or with [`run`](Self::run), then
+/// [`finish`](Self::finish) it.
+pub struct PreparedScan {
+    orchestrator: Orchestrator,
+    scanner_name: &'static str,
+    job_id: Uuid,
+    concurrency: usize,
+    dump: Option<Arc<DumpWriter>>,
+    pcap: Option<Arc<PcapWriter>>,
+    _capture: Option<CaptureHandle>,
+}
+
+impl PreparedScan {
+    pub fn orchestrator(&self) -> &Orchestrator {
+        &self.orchestrator
+    }
+
+    /// Registered name of the scanner the run starts with; `tcp` when a raw
+    /// scan fell back to a connect scan
+    pub fn scanner_name(&self) -> &'static str {
+        self.scanner_name
+    }
+
+    /// Id of the first submitted job
+    pub fn job_id(&self) -> Uuid {
+        self.job_id
+    }
+
+    /// Probes in flight, after the open file limit
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Scan every submitted target
+    pub async fn run(&self) -> Result<()> {
+        self.orchestrator.run_all(Some(self.scanner_name)).await
+    }
+
+    /// Stop the capture loop and flush the dump and pcap files, returning
+    /// the orchestrator for its results and totals
+    pub fn finish(self) -> Result<Orchestrator> {
+        drop(self._capture);
+        // Release the capture loop's references so the files are flushed
+        vajra_scanner_syn::set_packet_dump(None);
+        vajra_scanner_syn::set_pcap_writer(None);
+        if let Some(dump) = &self.dump {
+            dump.flush()?;
+        }
+        if let Some(pcap) = &self.pcap {
+            pcap.flush()?;
+        }
+        Ok(self.orchestrator)
+    }
+}
+
+/// Workers an open-file limit of `nofile` can feed: three quarters of what
+/// is left after the reserve, since banner retries and resolver lookups
+/// can briefly hold a second descriptor
+fn concurrency_for_fd_limit(nofile: u64) -> usize {
+    usize::try_from(nofile.saturating_sub(RESERVED_FDS) / 4 * 3).unwrap_or(usize::MAX).max(1)
+}
+
+/// Current `RLIMIT_NOFILE` soft limit, first raised toward the hard limit
+/// when it cannot feed `concurrency` workers. `None` when unlimited or
+/// unknown.
+#[cfg(unix)]
+fn open_file_limit(concurrency: usize) -> Option<u64> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 || limit.rlim_cur == libc::RLIM_INFINITY {
+        return None;
+    }
+    if concurrency_for_fd_limit(limit.rlim_cur) < concurrency && limit.rlim_max > limit.rlim_cur {
+        let raised = libc::rlimit { rlim_cur: limit.rlim_max, rlim_max: limit.rlim_max };
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            info!("Raised the open file limit from {} to {}", limit.rlim_cur, limit.rlim_max);
+            limit = raised;
+        }
+    }
+    (limit.rlim_cur != libc::RLIM_INFINITY).then_some(limit.rlim_cur)
+}
+
+#[cfg(not(unix))]
+fn open_file_limit(_concurrency: usize) -> Option<u64> {
+    None
+}
+
+/// `concurrency`, reduced with a warning if the open-file limit cannot
+/// support it; otherwise most probes would fail with "too many open files"
+fn cap_concurrency_to_fd_limit(concurrency: usize) -> usize {
+    cap_concurrency(concurrency, open_file_limit(concurrency))
+}
+
+/// `concurrency` capped to what an open-file limit of `nofile` can feed
+/// (unchanged when there is no limit)
+fn cap_concurrency(concurrency: usize, nofile: Option<u64>) -> usize {
+    let Some(nofile) = nofile else {
+        return concurrency;
+    };
+    let cap = concurrency_for_fd_limit(nofile);
+    if concurrency <= cap {
+        return concurrency;
+    }
+    warn!(
+        "Reducing concurrency from {} to {}: the open file limit is {} (raise it with `ulimit -n`)",
+        concurrency, cap, nofile
+    );
+    cap
+}
+
+/// Resolve `targets` (IPs, CIDRs, ranges and hostnames, comma-separated),
+/// scan each address on every port in `ports` with `scan_type`, and return
+/// the results.
+///
+/// `opts` sets the probe timeout and retries, the worker count
+/// (`max_concurrency`), the rate limit (unlimited when `None`) and the
+/// scan order (`shuffle_seed`). This is [`ScanConfig::start`] with
+/// [`ScanConfig::from_options`]; build the config yourself for the rest of
+/// what `vajra scan` can do, such as the TLS probe (`tls_probe`).
+pub async fn scan(targets: &str, ports: &[u16], opts: ScanOptions, scan_type: ScanType) -> Result<Vec<ProbeResult>> {
+    let ips = TargetResolver::new().resolve_targets(targets).await?;
+    let ports: PortSpec = ports.iter().copied().collect();
+    let config = ScanConfig::from_options(scan_type, &opts);
+    let prepared = config.start(config.grid(ips, ports.normalized()), None).await?;
+    prepared.run().await?;
+    Ok(prepared.finish()?.get_results().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use vajra_common::PortState;
+
+    #[test]
+    fn test_parse_scan_type() {
+        assert_eq!("SYN".parse::<ScanType>().unwrap(), ScanType::Syn);
+        assert_eq!("sctp".parse::<ScanType>().unwrap().flavor(), Some(ScanFlavor::SctpInit));
+        assert_eq!(ScanType::Udp.protocol(), Protocol::UDP);
+        assert_eq!(ScanType::Tcp.flavor(), None);
+        for scan_type in ["tcp", "fin", "null", "xmas", "ack", "udp"] {
+            assert_eq!(scan_type.parse::<ScanType>().unwrap().to_string(), scan_type);
+        }
+        assert!("ping".parse::<ScanType>().is_err());
+    }
+
+    #[test]
+    fn test_auto_protocol_targets() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let protocol_of = |grid: &TargetGrid, port: u16| {
+            grid.targets().find(|t| t.port == port).unwrap().protocol
+        };
+        let ports = || [53, 80].into_iter().collect::<PortSpec>();
+        let config = |scan_type, auto_protocol| ScanConfig { scan_type, auto_protocol, ..Default::default() };
+
+        let grid = config(ScanType::Tcp, true).grid(vec![ip], ports());
+        assert_eq!(protocol_of(&grid, 53), Protocol::UDP);
+        assert_eq!(protocol_of(&grid, 80), Protocol::TCP);
+
+        // Without the flag every target follows the scan type
+        let grid = config(ScanType::Tcp, false).grid(vec![ip], ports());
+        assert_eq!(protocol_of(&grid, 53), Protocol::TCP);
+        let grid = config(ScanType::Udp, false).grid(vec![ip], ports());
+        assert_eq!(protocol_of(&grid, 80), Protocol::UDP);
+        let grid = config(ScanType::Sctp, false).grid(vec![ip], ports());
+        assert_eq!(protocol_of(&grid, 80), Protocol::SCTP);
+    }
+
+    #[test]
+    fn test_endpoint_job() {
+        let ip = |last| IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+        let endpoints = [(ip(1), 22), (ip(3), 53)];
+        let config = ScanConfig { auto_protocol: true, shuffle_seed: Some(7), ..Default::default() };
+        let job = config.endpoint_job(&endpoints);
+        let targets: Vec<_> = job.targets.iter().map(|t| (t.ip, t.port, t.protocol)).collect();
+        assert_eq!(targets, [(ip(1), 22, Protocol::TCP), (ip(3), 53, Protocol::UDP)]);
+        assert_eq!(job.options.shuffle_seed, Some(7));
+
+        let config = ScanConfig { scan_type: ScanType::Sctp, ..Default::default() };
+        assert!(config.endpoint_job(&endpoints).targets.iter().all(|t| t.protocol == Protocol::SCTP));
+    }
+
+    #[test]
+    fn test_concurrency_for_fd_limit() {
+        assert_eq!(concurrency_for_fd_limit(1024), 720);
+        assert_eq!(concurrency_for_fd_limit(1_048_576), 786_384);
+        // Tiny limits still leave one worker
+        assert_eq!(concurrency_for_fd_limit(16), 1);
+        // The limit only ever lowers the requested value
+        assert_eq!(cap_concurrency(1, Some(1024)), 1);
+        assert_eq!(cap_concurrency(5000, Some(1024)), 720);
+        assert_eq!(cap_concurrency(5000, None), 5000);
+    }
+
+    #[tokio::test]
+    async fn test_scan_localhost() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+        let opts = ScanOptions {
+            timeout: Duration::from_millis(500),
+            retries: 0,
+            max_concurrency: 4,
+            ..Default::default()
+        };
+        let mut results = scan("127.0.0.1", &[closed, open, open], opts, ScanType::Tcp).await.unwrap();
+        results.sort_by_key(|r| r.target.port != open);
+        let states: Vec<_> = results.iter().map(|r| (r.target.port, r.state)).collect();
+        assert_eq!(states, [(open, PortState::Open), (closed, PortState::Closed)]);
+        drop(listener);
+    }
 }
@@ -0,0 +1,312 @@
+//! Log setup: compact (or JSON) stdout output plus an optional log file
+//!
+//! The file can rotate by time (a new `<file>.<date>` per hour or day, via
+//! `tracing-appender`) or by size (the full file moves to `<file>.1`).
+//! File writes go through a background thread; keep the returned guard
+//! alive so buffered lines are flushed on exit.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+/// How log lines are written, on stdout and to the log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One human-readable line per event
+    #[default]
+    Compact,
+    /// One JSON object per event, with the fields of the enclosing spans
+    /// (such as the scan's `scan_id`, `targets` and `scanner`)
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "compact" => Ok(LogFormat::Compact),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("invalid log format '{}' (expected compact or json)", other)),
+        }
+    }
+}
+
+/// When the log file is rotated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+    /// Once the file reaches this many bytes
+    Size(u64),
+}
+
+impl FromStr for LogRotation {
+    type Err = anyhow::Error;
+
+    /// `never`, `hourly`, `daily`, or a size such as `500K`, `100M`, `1G`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "never" => return Ok(LogRotation::Never),
+            "hourly" => return Ok(LogRotation::Hourly),
+            "daily" => return Ok(LogRotation::Daily),
+            _ => {}
+        }
+
+        let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => s.split_at(i),
+            None => (s.as_str(), ""),
+        };
+        let multiplier: u64 = match unit {
+            "" | "b" => 1,
+            "k" | "kb" => 1 << 10,
+            "m" | "mb" => 1 << 20,
+            "g" | "gb" => 1 << 30,
+            _ => return Err(anyhow!("invalid log rotation '{}' (expected never, hourly, daily or a size like 100M)", s)),
+        };
+        let size = digits
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(multiplier))
+            .filter(|&n| n > 0)
+            .ok_or_else(|| anyhow!("invalid log rotation size '{}'", s))?;
+        Ok(LogRotation::Size(size))
+    }
+}
+
+/// Install the global subscriber: stdout always, plus `log_file` if given.
+/// Returns the file writer's guard, which must outlive all logging.
+pub fn init_logging(
+    verbose: u8,
+    format: LogFormat,
+    log_file: Option<&Path>,
+    rotation: LogRotation,
+) -> Result<Option<WorkerGuard>> {
+    let log_level = match verbose {
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(log_level));
+
+    let (file_layer, guard) = match log_file {
+        Some(path) => {
+            let (layer, guard) = file_layer(path, rotation, format)?;
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(format_layer(format, io::stdout, true))
+        .with(file_layer)
+        .init();
+    Ok(guard)
+}
+
+/// Layer writing events to `writer` in `format`
+fn format_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Compact => layer.compact().with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Layer writing events (without ANSI colour) to `path`
+fn file_layer<S>(
+    path: &Path,
+    rotation: LogRotation,
+    format: LogFormat,
+) -> Result<(Box<dyn Layer<S> + Send + Sync>, WorkerGuard)>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let writer: Box<dyn Write + Send> = match rotation {
+        LogRotation::Never => Box::new(open_append(path)?),
+        LogRotation::Hourly => Box::new(rolling(path, Rotation::HOURLY)?),
+        LogRotation::Daily => Box::new(rolling(path, Rotation::DAILY)?),
+        LogRotation::Size(max_bytes) => Box::new(SizeRotatingFile::open(path, max_bytes)?),
+    };
+    let (writer, guard) = tracing_appender::non_blocking(writer);
+    Ok((format_layer(format, writer, false), guard))
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file '{}'", path.display()))
+}
+
+/// Time-rotated appender writing `<file name>.<date>` next to `path`
+fn rolling(path: &Path, rotation: Rotation) -> Result<RollingFileAppender> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("log file '{}' has no file name", path.display()))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(file_name.to_string_lossy())
+        .build(dir)
+        .with_context(|| format!("Failed to open log file '{}'", path.display()))
+}
+
+/// Log file that moves to `<path>.1` (replacing any previous one) once a
+/// write would take it past `max_bytes`
+struct SizeRotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    written: u64,
+}
+
+impl SizeRotatingFile {
+    fn open(path: &Path, max_bytes: u64) -> Result<Self> {
+        let file = open_append(path)?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file,
+            written,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A line longer than the limit still goes to a fresh file whole
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use vajra_common::{Protocol, ScanJob};
+    use vajra_orchestrator::Orchestrator;
+    use vajra_scanner_tcp::TcpScanner;
+
+    #[test]
+    fn test_parse_rotation() {
+        assert_eq!("daily".parse::<LogRotation>().unwrap(), LogRotation::Daily);
+        assert_eq!("Hourly".parse::<LogRotation>().unwrap(), LogRotation::Hourly);
+        assert_eq!("100M".parse::<LogRotation>().unwrap(), LogRotation::Size(100 << 20));
+        assert_eq!("4096".parse::<LogRotation>().unwrap(), LogRotation::Size(4096));
+        assert!("weekly".parse::<LogRotation>().is_err());
+        assert!("0K".parse::<LogRotation>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scan_logs_reach_file() {
+        let dir = std::env::temp_dir().join(format!("vajra-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scan.log");
+
+        let (layer, guard) = file_layer(&path, LogRotation::Never, LogFormat::Compact).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        {
+            // The runtime is single-threaded, so the scan logs on this thread
+            let _default = tracing::subscriber::set_default(subscriber);
+            let mut orch = Orchestrator::new(1, 1000);
+            orch.add_scanner("tcp", Arc::new(TcpScanner::new()));
+            let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &[9], Protocol::TCP)).await.unwrap();
+            orch.run(None).await.unwrap();
+        }
+        // Dropping the guard flushes the background writer
+        drop(guard);
+
+        let log = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(log.contains("Starting job"), "log was: {}", log);
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_fields() {
+        let dir = std::env::temp_dir().join(format!("vajra-log-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scan.log");
+
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        let (layer, guard) = file_layer(&path, LogRotation::Never, LogFormat::Json).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("scan", scan_id = "3f2b", targets = 2u64, scanner = "tcp");
+            span.in_scope(|| tracing::info!(open = 1u64, "Scan finished"));
+        });
+        drop(guard);
+
+        let log = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let line: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert_eq!(line["fields"]["message"], "Scan finished");
+        assert_eq!(line["fields"]["open"], 1);
+        assert_eq!(line["span"]["scan_id"], "3f2b");
+        assert_eq!(line["span"]["targets"], 2);
+        assert_eq!(line["span"]["scanner"], "tcp");
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let dir = std::env::temp_dir().join(format!("vajra-log-size-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scan.log");
+
+        let mut file = SizeRotatingFile::open(&path, 16).unwrap();
+        file.write_all(b"first line\n").unwrap();
+        file.write_all(b"second line\n").unwrap();
+        file.flush().unwrap();
+
+        let current = fs::read_to_string(&path).unwrap();
+        let previous = fs::read_to_string(dir.join("scan.log.1")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((previous.as_str(), current.as_str()), ("first line\n", "second line\n"));
+    }
+}
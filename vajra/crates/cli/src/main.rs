@@ -1,61 +1,74 @@
 mod args;
+mod baseline;
+mod diff;
+mod logging;
+mod merge;
+mod ports;
 mod runner;
 mod output;
+#[cfg(feature = "protobuf")]
+mod proto;
 
 use anyhow::Result;
 use clap::Parser;
-use tracing_subscriber::{fmt, EnvFilter};
 
 use args::{Cli, Commands};
+use logging::init_logging;
+use output::{print_results, print_service_list};
 use runner::run_scan;
+use vajra_fingerprint::PortTable;
+use vajra_scanner_syn::{check_privileges, format_report};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    init_logging(cli.verbose);
+    let _log_guard = init_logging(cli.verbose, cli.log_format, cli.log_file.as_deref(), cli.log_rotation)?;
 
     match cli.command {
-        Commands::Scan {
-            targets,
-            ports,
-            concurrency,
-            rate_limit,
-            timeout,
-            banner_timeout,
+        Commands::Scan(args) => run_scan(args).await?,
+        Commands::ListServices {
+            port_table,
             output_format,
-            scan_type,
-            preset,
         } => {
-            run_scan(
-                targets,
-                ports,
-                concurrency,
-                rate_limit,
-                timeout,
-                banner_timeout,
-                output_format,
-                preset,
-                Some(scan_type),
-            )
-            .await?;
+            let overrides = match port_table {
+                Some(path) => PortTable::load(&path)?,
+                None => PortTable::new(),
+            };
+            print_service_list(&overrides, &output_format)?;
+        }
+        Commands::Merge {
+            files,
+            max_banner_len,
+            output_format,
+        } => {
+            let files = files
+                .iter()
+                .map(|path| merge::read_json_results(path))
+                .collect::<Result<Vec<_>>>()?;
+            let (results, duration) = merge::merge_result_files(files);
+            print_results(&results, &output_format, duration, max_banner_len)?;
+        }
+        Commands::Diff {
+            old,
+            new,
+            output_format,
+        } => {
+            let old = merge::read_json_results(&old)?.results;
+            let new = merge::read_json_results(&new)?.results;
+            print!("{}", diff::ScanDiff::compute(&old, &new).format(&output_format)?);
+        }
+        Commands::CheckPrivileges => {
+            let checks = check_privileges();
+            let exe = std::env::current_exe()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|_| "/path/to/vajra".to_string());
+            print!("{}", format_report(&checks, &exe));
+            if !checks.iter().all(|c| c.is_satisfied()) {
+                return Err(anyhow::anyhow!("raw-socket capabilities missing"));
+            }
         }
     }
 
     Ok(())
 }
 
-fn init_logging(verbose: u8) {
-    let log_level = match verbose {
-        0 => "info",
-        1 => "debug",
-        _ => "trace",
-    };
-
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new(log_level));
-
-    fmt()
-        .with_env_filter(filter)
-        .compact()
-        .init();
-}
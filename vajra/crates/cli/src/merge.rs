@@ -0,0 +1,109 @@
+//! Merging of result files produced by separate (sharded) scans
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+use vajra_common::ProbeResult;
+
+/// Results and duration read from one JSON output file
+pub struct ResultFile {
+    pub results: Vec<ProbeResult>,
+    pub duration: Duration,
+}
+
+/// Read a file written with `--output-format json`
+pub fn read_json_results(path: &Path) -> Result<ResultFile> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read result file {}", path.display()))?;
+    parse_json_results(&content).with_context(|| format!("Invalid result file {}", path.display()))
+}
+
+/// Parse the JSON output document (`scan_info` + `results` grouped by IP)
+fn parse_json_results(content: &str) -> Result<ResultFile> {
+    let doc: serde_json::Value = serde_json::from_str(content)?;
+
+    let duration = doc["scan_info"]["duration_seconds"]
+        .as_f64()
+        .map(Duration::from_secs_f64)
+        .unwrap_or_default();
+
+    let by_ip = doc["results"]
+        .as_object()
+        .ok_or_else(|| anyhow!("missing 'results' object"))?;
+
+    let mut results = Vec::new();
+    for entries in by_ip.values() {
+        let entries = entries
+            .as_array()
+            .ok_or_else(|| anyhow!("'results' entries must be arrays"))?;
+        for entry in entries {
+            results.push(serde_json::from_value(entry.clone())?);
+        }
+    }
+
+    Ok(ResultFile { results, duration })
+}
+
+/// Merge result files into one set.
+///
+/// Duplicate targets keep the first result seen. The merged duration is the
+/// longest input duration, since shards run in parallel.
+pub fn merge_result_files(files: Vec<ResultFile>) -> (Vec<ProbeResult>, Duration) {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    let mut duration = Duration::ZERO;
+
+    for file in files {
+        duration = duration.max(file.duration);
+        for result in file.results {
+            if seen.insert(result.target.clone()) {
+                merged.push(result);
+            }
+        }
+    }
+
+    merged.sort_by(|a, b| {
+        a.target
+            .ip
+            .cmp(&b.target.ip)
+            .then_with(|| a.target.port.cmp(&b.target.port))
+    });
+    (merged, duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use vajra_common::{PortState, Target};
+
+    fn shard_file(ports: &[u16], shard: u32, secs: u64) -> String {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let results: Vec<ProbeResult> = ports
+            .iter()
+            .map(|&p| ProbeResult::new(Target::new(ip, p), PortState::Open).with_shard(shard))
+            .collect();
+        serde_json::json!({
+            "scan_info": { "duration_seconds": secs as f64 },
+            "results": { ip.to_string(): results },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_merge_shard_outputs() {
+        let a = parse_json_results(&shard_file(&[80, 22], 0, 3)).unwrap();
+        let b = parse_json_results(&shard_file(&[443, 80], 1, 5)).unwrap();
+
+        let (merged, duration) = merge_result_files(vec![a, b]);
+        let ports: Vec<u16> = merged.iter().map(|r| r.target.port).collect();
+        assert_eq!(ports, vec![22, 80, 443]);
+        assert_eq!(duration, Duration::from_secs(5));
+
+        // Duplicates keep the first shard's result
+        let http = merged.iter().find(|r| r.target.port == 80).unwrap();
+        assert_eq!(http.shard, Some(0));
+        assert_eq!(merged.iter().find(|r| r.target.port == 443).unwrap().shard, Some(1));
+    }
+}
@@ -1,163 +1,794 @@
 //! Output formatting for scan results
+//!
+//! Each format is an [`OutputSink`]: results are written one at a time and
+//! the sink is finished with the run's totals. Formats that sort or group
+//! (table, JSON, compact) buffer until `finish`; CSV and protobuf stream.
+//! [`stream_to_sink`] feeds a sink straight from `Orchestrator::run_streaming`.
 
-use anyhow::Result;
-use serde_json;
-use std::time::Duration;
-use vajra_common::{PortState, ProbeResult};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use vajra_common::export::{csv_field, csv_row, results_by_ip, CSV_HEADER};
+use vajra_common::{OutputSink, PortState, ProbeResult, ScanStats};
+use vajra_fingerprint::{PortTable, BUILTIN_PORT_SERVICES};
+
+/// Default for `--max-banner-len`
+pub const DEFAULT_MAX_BANNER_LEN: usize = 256;
+
+/// Print scan results in the specified format, with banners cut to
+/// `max_banner_len` characters (0 keeps them whole)
+pub fn print_results(results: &[ProbeResult], format: &str, scan_duration: Duration, max_banner_len: usize) -> Result<()> {
+    let mut sink = TruncateBanners::new(sink_for_format(format)?, max_banner_len);
+    write_to_sink(&mut sink, results, scan_duration)
+}
+
+/// Like [`print_results`], summarizing with the scan's own totals (e.g.
+/// `Orchestrator::get_stats`) rather than counting `results`. With
+/// `open_only` only open ports are listed; the totals are unchanged.
+pub fn print_results_with_stats(
+    results: &[ProbeResult],
+    format: &str,
+    stats: &ScanStats,
+    max_banner_len: usize,
+    open_only: bool,
+) -> Result<()> {
+    let mut sink = TruncateBanners::new(sink_for_format(format)?, max_banner_len);
+    if open_only {
+        write_with_stats(&mut OpenOnly::new(sink), results, stats)
+    } else {
+        write_with_stats(&mut sink, results, stats)
+    }
+}
+
+/// Built-in sink for `format`, writing to stdout (or to a file for
+/// `protobuf:<path>`). Unknown formats fall back to the table.
+pub fn sink_for_format(format: &str) -> Result<Box<dyn OutputSink>> {
+    // File sinks carry a path, so match them before normalizing case
+    if let Some(path) = format.trim().strip_prefix("protobuf:") {
+        return protobuf_sink(path);
+    }
 
-/// Print scan results in the specified format
-pub fn print_results(results: &[ProbeResult], format: &str, scan_duration: Duration) -> Result<()> {
     // Normalize format string
     let format = format.trim().to_lowercase();
-    match format.as_str() {
-        "json" | "j" => print_json(results, scan_duration)?,
-        "csv" | "c" => print_csv(results)?,
-        "table" | "text" | "t" | "" => print_table(results, scan_duration),
+    let out = io::stdout();
+    Ok(match format.as_str() {
+        "json" | "j" => Box::new(JsonSink::new(out)),
+        "csv" | "c" => Box::new(CsvSink::new(out)),
+        "compact" => Box::new(CompactSink::new(out)),
+        "xml" | "x" => Box::new(XmlSink::new(out)),
+        "table" | "text" | "t" | "" => Box::new(TableSink::new(out)),
         _ => {
             eprintln!("Warning: Unknown format '{}', using default table format", format);
-            print_table(results, scan_duration);
+            Box::new(TableSink::new(out))
         }
+    })
+}
+
+/// Feed `results` to `sink`, then finish it with their totals
+pub fn write_to_sink(sink: &mut dyn OutputSink, results: &[ProbeResult], scan_duration: Duration) -> Result<()> {
+    let mut summary = ScanStats::new(results.len());
+    for result in results {
+        summary.update(result);
+    }
+    summary.elapsed = scan_duration;
+    write_with_stats(sink, results, &summary)
+}
+
+/// Feed `results` to `sink`, then finish it with `summary`
+pub fn write_with_stats(sink: &mut dyn OutputSink, results: &[ProbeResult], summary: &ScanStats) -> Result<()> {
+    for result in results {
+        sink.write_result(result)?;
+    }
+    sink.finish(summary)
+}
+
+/// Write results to `sink` as they arrive on `rx` until the channel closes,
+/// then finish it with their totals. Returns the results for further use.
+pub async fn stream_to_sink(
+    sink: &mut dyn OutputSink,
+    mut rx: mpsc::Receiver<ProbeResult>,
+    started: Instant,
+) -> Result<Vec<ProbeResult>> {
+    let mut results = Vec::new();
+    let mut summary = ScanStats::new(0);
+    while let Some(result) = rx.recv().await {
+        sink.write_result(&result)?;
+        summary.update(&result);
+        results.push(result);
+    }
+    summary.total_targets = summary.scanned;
+    summary.elapsed = started.elapsed();
+    sink.finish(&summary)?;
+    Ok(results)
+}
+
+/// Sink adapter cutting each banner to `max_len` characters, ending in
+/// "..." when cut, before passing the result on. 0 means unlimited.
+pub struct TruncateBanners<S: OutputSink> {
+    inner: S,
+    max_len: usize,
+}
+
+impl<S: OutputSink> TruncateBanners<S> {
+    pub fn new(inner: S, max_len: usize) -> Self {
+        Self { inner, max_len }
+    }
+}
+
+impl<S: OutputSink> OutputSink for TruncateBanners<S> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        match result.banner.as_deref().and_then(|b| truncate_banner(b, self.max_len)) {
+            Some(banner) => {
+                let mut result = result.clone();
+                result.banner = Some(banner);
+                self.inner.write_result(&result)
+            }
+            None => self.inner.write_result(result),
+        }
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        self.inner.finish(summary)
     }
-    Ok(())
 }
 
-/// Print results as ASCII table (sorted by IP and port)
-fn print_table(results: &[ProbeResult], scan_duration: Duration) {
-    if results.is_empty() {
-        println!("\nNo results to display.\n");
-        return;
+/// Sink adapter passing on only open ports (`--open`). The summary still
+/// covers every result, since it comes from the caller's totals.
+pub struct OpenOnly<S: OutputSink> {
+    inner: S,
+}
+
+impl<S: OutputSink> OpenOnly<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: OutputSink> OutputSink for OpenOnly<S> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        if result.state == PortState::Open {
+            self.inner.write_result(result)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        self.inner.finish(summary)
+    }
+}
+
+/// `banner` cut to `max_len` characters including the ellipsis, or `None`
+/// if it already fits (or `max_len` is 0)
+fn truncate_banner(banner: &str, max_len: usize) -> Option<String> {
+    if max_len == 0 || banner.chars().count() <= max_len {
+        return None;
+    }
+    if max_len <= 3 {
+        return Some(banner.chars().take(max_len).collect());
+    }
+    let mut cut: String = banner.chars().take(max_len - 3).collect();
+    cut.push_str("...");
+    Some(cut)
+}
+
+/// Sink writing length-delimited protobuf records to `path`
+#[cfg(feature = "protobuf")]
+fn protobuf_sink(path: &str) -> Result<Box<dyn OutputSink>> {
+    Ok(Box::new(crate::proto::ProtobufSink::create(path)?))
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn protobuf_sink(_path: &str) -> Result<Box<dyn OutputSink>> {
+    Err(anyhow::anyhow!(
+        "protobuf output requires building with `--features protobuf`"
+    ))
+}
+
+/// ASCII table sorted by IP and port, with a summary
+pub struct TableSink<W: Write> {
+    out: W,
+    results: Vec<ProbeResult>,
+}
+
+impl<W: Write> TableSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> OutputSink for TableSink<W> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        self.results.push(result.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        write_table(&mut self.out, &mut self.results, summary)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Write results as ASCII table (sorted by IP and port), then the totals
+/// from `summary` whenever anything was scanned, even if no row is listed
+fn write_table<W: Write>(out: &mut W, results: &mut [ProbeResult], summary: &ScanStats) -> io::Result<()> {
+    // Closed ports get no row of their own
+    if results.iter().all(|r| r.state == PortState::Closed) {
+        writeln!(out, "\nNo results to display.\n")?;
+        if summary.scanned > 0 {
+            write_summary(out, summary)?;
+        }
+        return Ok(());
     }
 
     // Sort results by IP address first, then by port number
-    let mut sorted_results = results.to_vec();
-    sorted_results.sort_by(|a, b| {
+    results.sort_by(|a, b| {
         a.target.ip.cmp(&b.target.ip)
             .then_with(|| a.target.port.cmp(&b.target.port))
     });
 
-    println!("\n{:-<80}", "");
-    println!(
+    writeln!(out, "\n{:-<80}", "")?;
+    writeln!(
+        out,
         "{:<20} {:<8} {:<15} {:<40}",
         "HOST", "PORT", "STATE", "SERVICE/VERSION"
-    );
-    println!("{:-<80}", "");
+    )?;
+    writeln!(out, "{:-<80}", "")?;
 
-    let mut open_count = 0;
-    let mut closed_count = 0;
-    let mut filtered_count = 0;
-
-    for result in &sorted_results {
+    for result in results.iter() {
         match result.state {
             PortState::Open => {
                 // Build service display string with product and version
                 let service_display = format_service_display(result);
                 
-                println!(
+                writeln!(
+                    out,
                     "{:<20} {:<8} {:<15} {:<40}",
-                    result.target.ip.to_string(),
+                    format_host(result),
                     result.target.port,
                     result.state,
                     service_display
-                );
-                open_count += 1;
+                )?;
             }
-            PortState::Filtered | PortState::OpenFiltered => {
+            PortState::Filtered | PortState::OpenFiltered | PortState::Unfiltered => {
                 // Show filtered ports with service names and versions (like nmap)
                 let service_display = format_service_display(result);
                 
-                println!(
+                writeln!(
+                    out,
                     "{:<20} {:<8} {:<15} {:<40}",
-                    result.target.ip.to_string(),
+                    format_host(result),
                     result.target.port,
                     result.state,
                     service_display
-                );
-                filtered_count += 1;
+                )?;
             }
-            PortState::Closed => {
-                closed_count += 1;
+            PortState::Closed => {}
+            PortState::Skipped => {
+                let state = match &result.reason {
+                    Some(reason) => format!("{} ({})", result.state, reason),
+                    None => result.state.to_string(),
+                };
+                writeln!(
+                    out,
+                    "{:<20} {:<8} {:<15} {:<40}",
+                    format_host(result),
+                    result.target.port,
+                    state,
+                    format_service_display(result)
+                )?;
             }
         }
     }
 
-    println!("{:-<80}", "");
-    println!("\n📊 Summary:");
-    println!("  Total scanned: {}", results.len());
-    println!("  ✓ Open ports: {}", open_count);
-    println!("  ✗ Closed ports: {}", closed_count);
-    println!("  ⊘ Filtered: {}", filtered_count);
-    println!("  ⏱️  Scan duration: {}", format_duration(scan_duration));
-    println!();
+    writeln!(out, "{:-<80}", "")?;
+    write_summary(out, summary)
 }
 
-/// Print results as JSON
-fn print_json(results: &[ProbeResult], scan_duration: Duration) -> Result<()> {
-    use serde_json::json;
-    
-    // Group results by IP for better organization
-    let mut results_by_ip = std::collections::BTreeMap::new();
-    for result in results {
-        results_by_ip
-            .entry(result.target.ip.to_string())
-            .or_insert_with(Vec::new)
-            .push(serde_json::to_value(result)?);
+/// The table's summary block, from the scan's totals rather than the rows
+/// shown, so hidden rows (`--open`, a baseline) still count
+fn write_summary<W: Write>(out: &mut W, summary: &ScanStats) -> io::Result<()> {
+    writeln!(out, "\n📊 Summary:")?;
+    writeln!(out, "  Total scanned: {}", summary.scanned)?;
+    writeln!(out, "  ✓ Open ports: {}", summary.open_ports)?;
+    writeln!(out, "  ✗ Closed ports: {}", summary.closed_ports)?;
+    writeln!(out, "  ⊘ Filtered: {}", summary.filtered_ports)?;
+    if summary.open_filtered_ports > 0 {
+        writeln!(out, "  ? Open|filtered: {}", summary.open_filtered_ports)?;
     }
-    
-    let output = json!({
-        "scan_info": {
-            "duration_seconds": scan_duration.as_secs_f64(),
-            "duration_formatted": format_duration(scan_duration),
-            "total_targets": results_by_ip.len(),
-            "total_scanned": results.len()
-        },
-        "results": results_by_ip
+    if summary.unfiltered_ports > 0 {
+        writeln!(out, "  ↔ Unfiltered: {}", summary.unfiltered_ports)?;
+    }
+    if summary.skipped_ports > 0 {
+        writeln!(out, "  ⏭ Skipped: {}", summary.skipped_ports)?;
+    }
+    if summary.errors > 0 {
+        writeln!(out, "  ⚠ Failed probes: {}", summary.errors)?;
+    }
+    if !summary.average_rtt.is_zero() {
+        writeln!(out, "  Average RTT: {:.2}ms", summary.average_rtt.as_secs_f64() * 1000.0)?;
+    }
+    writeln!(out, "  ⏱️  Scan duration: {}", format_duration(summary.elapsed))?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Only the summary of a scan, in the format of the matching full sink:
+/// counts, duration, rate and a tally of the services found on open ports.
+/// Results themselves are not written (`--summary-only`).
+pub struct SummarySink<W: Write> {
+    out: W,
+    format: SummaryFormat,
+    hosts: BTreeSet<IpAddr>,
+    services: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SummaryFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+impl<W: Write> SummarySink<W> {
+    fn new(out: W, format: SummaryFormat) -> Self {
+        Self {
+            out,
+            format,
+            hosts: BTreeSet::new(),
+            services: BTreeMap::new(),
+        }
+    }
+}
+
+/// Summary-only sink for `format`, writing to stdout. Compact output gets the
+/// table summary; protobuf is rejected since its summary record carries
+/// no service tally.
+pub fn summary_sink_for_format(format: &str) -> Result<SummarySink<io::Stdout>> {
+    let format = match format.trim().to_lowercase().as_str() {
+        "json" | "j" => SummaryFormat::Json,
+        "csv" | "c" => SummaryFormat::Csv,
+        f if f.starts_with("protobuf:") => anyhow::bail!("--summary-only does not support protobuf output"),
+        _ => SummaryFormat::Table,
+    };
+    Ok(SummarySink::new(io::stdout(), format))
+}
+
+impl<W: Write> OutputSink for SummarySink<W> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        self.hosts.insert(result.target.ip);
+        if result.state == PortState::Open {
+            if let Some(service) = &result.service {
+                *self.services.entry(service.service.clone()).or_default() += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        match self.format {
+            SummaryFormat::Table => {
+                write_summary(&mut self.out, summary)?;
+                if !self.services.is_empty() {
+                    writeln!(self.out, "🔎 Services:")?;
+                    for (service, count) in &self.services {
+                        writeln!(self.out, "  {:<20} {}", service, count)?;
+                    }
+                    writeln!(self.out)?;
+                }
+            }
+            SummaryFormat::Json => {
+                let output = serde_json::json!({
+                    "scan_info": {
+                        "duration_seconds": summary.elapsed.as_secs_f64(),
+                        "duration_formatted": format_duration(summary.elapsed),
+                        "total_targets": self.hosts.len(),
+                        "total_scanned": summary.scanned
+                    },
+                    "summary": {
+                        "open": summary.open_ports,
+                        "closed": summary.closed_ports,
+                        "filtered": summary.filtered_ports,
+                        "open_filtered": summary.open_filtered_ports,
+                        "unfiltered": summary.unfiltered_ports,
+                        "skipped": summary.skipped_ports,
+                        "errors": summary.errors,
+                        "rate_per_second": summary.rate(),
+                        "services": self.services
+                    }
+                });
+                writeln!(self.out, "{}", serde_json::to_string_pretty(&output)?)?;
+            }
+            SummaryFormat::Csv => {
+                writeln!(self.out, "metric,value")?;
+                let counts = [
+                    ("hosts", self.hosts.len()),
+                    ("scanned", summary.scanned),
+                    ("open", summary.open_ports),
+                    ("closed", summary.closed_ports),
+                    ("filtered", summary.filtered_ports),
+                    ("open_filtered", summary.open_filtered_ports),
+                    ("unfiltered", summary.unfiltered_ports),
+                    ("skipped", summary.skipped_ports),
+                    ("errors", summary.errors),
+                ];
+                for (metric, value) in counts {
+                    writeln!(self.out, "{},{}", metric, value)?;
+                }
+                writeln!(self.out, "duration_seconds,{:.3}", summary.elapsed.as_secs_f64())?;
+                writeln!(self.out, "rate_per_second,{:.1}", summary.rate())?;
+                for (service, count) in &self.services {
+                    writeln!(self.out, "{},{}", csv_field(&format!("service:{}", service)), count)?;
+                }
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// JSON document grouping results by IP
+pub struct JsonSink<W: Write> {
+    out: W,
+    results: Vec<ProbeResult>,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> OutputSink for JsonSink<W> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        self.results.push(result.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        use serde_json::json;
+
+        // Group results by IP for better organization
+        let results_by_ip = results_by_ip(&self.results)?;
+        let output = json!({
+            "scan_info": {
+                "duration_seconds": summary.elapsed.as_secs_f64(),
+                "duration_formatted": format_duration(summary.elapsed),
+                "total_targets": results_by_ip.len(),
+                "total_scanned": summary.scanned
+            },
+            "results": results_by_ip
+        });
+
+        writeln!(self.out, "{}", serde_json::to_string_pretty(&output)?)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// nmap-compatible XML (`<nmaprun>`, one `<host>` per IP), close enough to
+/// nmap's DTD for tools that import it (Metasploit `db_import`, ndiff)
+pub struct XmlSink<W: Write> {
+    out: W,
+    results: Vec<ProbeResult>,
+}
+
+impl<W: Write> XmlSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> OutputSink for XmlSink<W> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        self.results.push(result.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        // The scan ended now and started `elapsed` earlier
+        let end = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let start = end.saturating_sub(summary.elapsed);
+        write_xml(&mut self.out, &mut self.results, summary, start.as_secs(), end.as_secs())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Write results as an nmap `<nmaprun>` document; `start`/`end` are Unix
+/// timestamps in seconds
+fn write_xml<W: Write>(out: &mut W, results: &mut [ProbeResult], summary: &ScanStats, start: u64, end: u64) -> io::Result<()> {
+    results.sort_by(|a, b| {
+        a.target.ip.cmp(&b.target.ip)
+            .then_with(|| a.target.port.cmp(&b.target.port))
     });
-    
-    println!("{}", serde_json::to_string_pretty(&output)?);
+
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<!DOCTYPE nmaprun>")?;
+    writeln!(
+        out,
+        "<nmaprun scanner=\"vajra\" start=\"{}\" version=\"{}\" xmloutputversion=\"1.05\">",
+        start,
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    let mut hosts = 0;
+    let mut rest = &results[..];
+    while let Some(first) = rest.first() {
+        let ip = first.target.ip;
+        let (host, tail) = rest.split_at(rest.iter().take_while(|r| r.target.ip == ip).count());
+        rest = tail;
+        hosts += 1;
+        writeln!(out, "<host starttime=\"{}\" endtime=\"{}\">", start, end)?;
+        // Only hosts that produced results are listed, so they are up
+        writeln!(out, "<status state=\"up\" reason=\"user-set\" reason_ttl=\"0\"/>")?;
+        let addrtype = if ip.is_ipv4() { "ipv4" } else { "ipv6" };
+        writeln!(out, "<address addr=\"{}\" addrtype=\"{}\"/>", ip, addrtype)?;
+        match &host[0].hostname {
+            Some(name) => writeln!(out, "<hostnames><hostname name=\"{}\" type=\"PTR\"/></hostnames>", xml_escape(name))?,
+            None => writeln!(out, "<hostnames/>")?,
+        }
+        writeln!(out, "<ports>")?;
+        for result in host {
+            writeln!(
+                out,
+                "<port protocol=\"{}\" portid=\"{}\"><state state=\"{}\" reason=\"{}\" reason_ttl=\"0\"/>",
+                result.target.protocol.as_str(),
+                result.target.port,
+                result.state,
+                xml_escape(result.reason.as_deref().unwrap_or(default_reason(result.state)))
+            )?;
+            if let Some(service) = &result.service {
+                // nmap reports confidence 0-10, and 3 for names guessed from
+                // the port table; "probed" means matched from a response
+                let probed = service.product.is_some() || service.version.is_some();
+                write!(out, "<service name=\"{}\"", xml_escape(&service.service))?;
+                if let Some(product) = &service.product {
+                    write!(out, " product=\"{}\"", xml_escape(product))?;
+                }
+                if let Some(version) = &service.version {
+                    write!(out, " version=\"{}\"", xml_escape(version))?;
+                }
+                if probed {
+                    let conf = (service.confidence.clamp(0.0, 1.0) * 10.0).round() as u8;
+                    writeln!(out, " method=\"probed\" conf=\"{}\"/>", conf)?;
+                } else {
+                    writeln!(out, " method=\"table\" conf=\"3\"/>")?;
+                }
+            }
+            writeln!(out, "</port>")?;
+        }
+        writeln!(out, "</ports>")?;
+        writeln!(out, "</host>")?;
+    }
+
+    writeln!(out, "<runstats>")?;
+    writeln!(
+        out,
+        "<finished time=\"{}\" elapsed=\"{:.2}\" summary=\"{} host(s) with results, {} probe(s) in {}\" exit=\"success\"/>",
+        end,
+        summary.elapsed.as_secs_f64(),
+        hosts,
+        summary.scanned,
+        format_duration(summary.elapsed)
+    )?;
+    writeln!(out, "<hosts up=\"{}\" down=\"0\" total=\"{}\"/>", hosts, hosts)?;
+    writeln!(out, "</runstats>")?;
+    writeln!(out, "</nmaprun>")?;
     Ok(())
 }
 
-/// Print results as CSV
-fn print_csv(results: &[ProbeResult]) -> Result<()> {
-    // Enhanced CSV headers with more information
-    println!("ip,port,state,service,product,version,banner,rtt_ms");
+/// nmap's `reason` for a state when the scanner gave none
+fn default_reason(state: PortState) -> &'static str {
+    match state {
+        PortState::Open => "syn-ack",
+        PortState::Closed | PortState::Unfiltered => "reset",
+        PortState::Filtered | PortState::OpenFiltered => "no-response",
+        PortState::Skipped => "skipped",
+    }
+}
+
+/// Escape `value` for use in an XML attribute
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Characters XML 1.0 cannot carry at all
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// One line per host (see [`format_compact`])
+pub struct CompactSink<W: Write> {
+    out: W,
+    results: Vec<ProbeResult>,
+}
 
+impl<W: Write> CompactSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> OutputSink for CompactSink<W> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        self.results.push(result.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &ScanStats) -> Result<()> {
+        write!(self.out, "{}", format_compact(&self.results))?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Render one line per host with its open ports and service names, e.g.
+/// `10.0.0.1  up  open: 22(ssh),80(http)`. A host is `up` if any probe got
+/// a reply.
+fn format_compact(results: &[ProbeResult]) -> String {
+    let mut by_ip: BTreeMap<_, Vec<&ProbeResult>> = BTreeMap::new();
     for result in results {
-        // Get service info
-        let service = result.service.as_ref().map(|s| s.service.as_str()).unwrap_or("");
-        let product = result.service.as_ref().and_then(|s| s.product.as_ref()).map(|s| s.as_str()).unwrap_or("");
-        let version = result.service.as_ref().and_then(|s| s.version.as_ref()).map(|s| s.as_str()).unwrap_or("");
-        
-        // Escape and format banner
-        let banner = result
-            .banner
-            .as_ref()
-            .map(|b| {
-                let escaped = b
-                    .replace('"', "\"\"")
-                    .replace('\n', " ")
-                    .replace('\r', "");
-                format!("\"{}\"", escaped)
+        by_ip.entry(result.target.ip).or_default().push(result);
+    }
+
+    let mut out = String::new();
+    for (_, mut host_results) in by_ip {
+        host_results.sort_by_key(|r| r.target.port);
+        let host = format_host(host_results[0]);
+        let up = host_results.iter().any(|r| {
+            matches!(r.state, PortState::Open | PortState::Closed | PortState::Unfiltered)
+        });
+        let open: Vec<String> = host_results
+            .iter()
+            .filter(|r| r.state == PortState::Open)
+            .map(|r| match &r.service {
+                Some(s) => format!("{}({})", r.target.port, s.service),
+                None => r.target.port.to_string(),
             })
-            .unwrap_or_else(|| "\"\"".to_string());
-
-        // Print CSV line with enhanced fields
-        println!(
-            "{},{},{},\"{}\",\"{}\",\"{}\",{},{}",
-            result.target.ip,
-            result.target.port,
-            result.state,
-            service,
-            product,
-            version,
-            banner,
-            result.rtt.as_millis()
-        );
+            .collect();
+        let open = if open.is_empty() { "none".to_string() } else { open.join(",") };
+        out.push_str(&format!("{}  {}  open: {}\n", host, if up { "up" } else { "down" }, open));
     }
+    out
+}
+
+/// CSV rows, streamed as results arrive
+pub struct CsvSink<W: Write> {
+    out: W,
+    header_written: bool,
+    flush_each_row: bool,
+}
 
+impl<W: Write> CsvSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            header_written: false,
+            flush_each_row: false,
+        }
+    }
+
+    /// Flush after the header and after every row, so each result reaches
+    /// the writer as soon as it is written rather than when buffers fill
+    pub fn with_flush_per_row(mut self) -> Self {
+        self.flush_each_row = true;
+        self
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(self.out, "{}", CSV_HEADER)?;
+            self.header_written = true;
+            if self.flush_each_row {
+                self.out.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CsvSink<BufWriter<File>> {
+    /// Sink writing to a new file at `path`, flushed per row so partial
+    /// results survive an interrupted scan
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create CSV output '{}'", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)).with_flush_per_row())
+    }
+}
+
+impl<W: Write> OutputSink for CsvSink<W> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        self.write_header()?;
+        writeln!(self.out, "{}", csv_row(result))?;
+        if self.flush_each_row {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &ScanStats) -> Result<()> {
+        // An empty scan still gets a header
+        self.write_header()?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Print the port → service table (built-in entries plus any loaded overrides)
+pub fn print_service_list(overrides: &PortTable, format: &str) -> Result<()> {
+    print!("{}", format_service_list(overrides, format)?);
     Ok(())
 }
 
+/// Render the port → service table as text or JSON
+fn format_service_list(overrides: &PortTable, format: &str) -> Result<String> {
+    use serde_json::json;
+
+    let mut builtin: Vec<(u16, &str)> = BUILTIN_PORT_SERVICES.to_vec();
+    builtin.sort_by_key(|(port, _)| *port);
+
+    let format = format.trim().to_lowercase();
+    match format.as_str() {
+        "json" | "j" => {
+            let builtin: Vec<_> = builtin
+                .iter()
+                .map(|(port, service)| json!({ "port": port, "service": service }))
+                .collect();
+            let overrides: Vec<_> = overrides
+                .entries()
+                .into_iter()
+                .map(|(port, proto, service)| {
+                    json!({ "port": port, "protocol": proto.as_str(), "service": service })
+                })
+                .collect();
+            let output = json!({ "builtin": builtin, "overrides": overrides });
+            Ok(format!("{}\n", serde_json::to_string_pretty(&output)?))
+        }
+        "table" | "text" | "t" | "" => {
+            let mut out = format!("{:<8} {:<20}\n", "PORT", "SERVICE");
+            for (port, service) in &builtin {
+                out.push_str(&format!("{:<8} {:<20}\n", port, service));
+            }
+            if !overrides.is_empty() {
+                out.push_str(&format!("\nOverrides ({}):\n", overrides.len()));
+                for (port, proto, service) in overrides.entries() {
+                    let port_proto = format!("{}/{}", port, proto.as_str());
+                    out.push_str(&format!("{:<8} {:<20}\n", port_proto, service));
+                }
+            }
+            Ok(out)
+        }
+        _ => Err(anyhow::anyhow!("Unknown format '{}' (expected text or json)", format)),
+    }
+}
+
 /// Format service display string with product and version information
 /// Shows: service (product) version
 fn format_service_display(result: &ProbeResult) -> String {
@@ -173,10 +804,21 @@ fn format_service_display(result: &ProbeResult) -> String {
         if let Some(ref version) = service_match.version {
             display.push_str(&format!(" {}", version));
         }
+
+        // HTTP triage details: [401] "Router Login"
+        if let Some(status) = service_match.http_status {
+            display.push_str(&format!(" [{}]", status));
+        }
+        if let Some(ref title) = service_match.http_title {
+            display.push_str(&format!(" \"{}\"", title));
+        }
+        if service_match.cdn_edge {
+            display.push_str(" [cdn]");
+        }
         
-        // Truncate if too long
-        if display.len() > 38 {
-            format!("{}...", &display[..35])
+        // Truncate if too long (by chars: titles may be non-ASCII)
+        if display.chars().count() > 38 {
+            format!("{}...", display.chars().take(35).collect::<String>())
         } else {
             display
         }
@@ -196,6 +838,14 @@ fn format_service_display(result: &ProbeResult) -> String {
     }
 }
 
+/// Host cell: the address, followed by its PTR name when known
+fn format_host(result: &ProbeResult) -> String {
+    match &result.hostname {
+        Some(name) => format!("{} ({})", result.target.ip, name),
+        None => result.target.ip.to_string(),
+    }
+}
+
 /// Format duration in a human-readable way
 fn format_duration(duration: Duration) -> String {
     let total_secs = duration.as_secs();
@@ -235,8 +885,11 @@ mod tests {
             .with_rtt(Duration::from_millis(10));
 
         let results = vec![result];
-        let json_result = print_json(&results, Duration::from_secs(5));
-        assert!(json_result.is_ok());
+        let mut sink = JsonSink::new(Vec::new());
+        write_to_sink(&mut sink, &results, Duration::from_secs(5)).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&sink.out).unwrap();
+        assert_eq!(value["scan_info"]["total_scanned"], 1);
+        assert_eq!(value["results"]["127.0.0.1"][0]["target"]["port"], 80);
     }
 
     #[test]
@@ -247,8 +900,172 @@ mod tests {
             .with_rtt(Duration::from_millis(10));
 
         let results = vec![result];
-        let csv_result = print_csv(&results);
-        assert!(csv_result.is_ok());
+        let mut sink = CsvSink::new(Vec::new());
+        write_to_sink(&mut sink, &results, Duration::from_secs(5)).unwrap();
+        let csv = String::from_utf8(sink.out).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("127.0.0.1,80,open,"));
+    }
+
+    #[test]
+    fn test_csv_banner_truncated_to_max_len() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let banner = "HTTP/1.1 200 OK Server: ".to_string() + &"x".repeat(500);
+        let result = ProbeResult::new(vajra_common::Target::new(ip, 80), PortState::Open).with_banner(banner.clone());
+        let banner_field = |csv: &str| csv.lines().nth(1).unwrap().split(',').nth(6).unwrap().trim_matches('"').to_string();
+
+        let mut sink = TruncateBanners::new(CsvSink::new(Vec::new()), 40);
+        write_to_sink(&mut sink, std::slice::from_ref(&result), Duration::from_secs(1)).unwrap();
+        let field = banner_field(&String::from_utf8(sink.inner.out).unwrap());
+        assert_eq!(field.chars().count(), 40);
+        assert_eq!(field, format!("{}...", &banner[..37]));
+
+        // 0 keeps the banner whole
+        let mut sink = TruncateBanners::new(CsvSink::new(Vec::new()), 0);
+        write_to_sink(&mut sink, std::slice::from_ref(&result), Duration::from_secs(1)).unwrap();
+        assert_eq!(banner_field(&String::from_utf8(sink.inner.out).unwrap()), banner);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_csv_flushes_each_row() {
+        let dir = std::env::temp_dir().join(format!("vajra-csv-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scan.csv");
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let mut sink = CsvSink::create(&path).unwrap();
+        for port in 1..=3u16 {
+            let result = ProbeResult::new(vajra_common::Target::new(ip, port), PortState::Open)
+                .with_banner("say \"hi\"\r\n".to_string());
+            sink.write_result(&result).unwrap();
+
+            // The row is on disk before the next result arrives
+            let csv = std::fs::read_to_string(&path).unwrap();
+            let lines: Vec<&str> = csv.lines().collect();
+            assert_eq!(lines.len(), 1 + port as usize);
+            assert!(lines[0].starts_with("ip,port,state,"));
+            assert!(lines[port as usize].starts_with(&format!("127.0.0.1,{},open,", port)));
+            assert!(lines[port as usize].contains(",\"say \"\"hi\"\" \","));
+        }
+
+        // Results arriving over a channel take the same path
+        let (tx, rx) = mpsc::channel(4);
+        let result = ProbeResult::new(vajra_common::Target::new(ip, 4), PortState::Closed);
+        tx.send(result).await.unwrap();
+        drop(tx);
+        let streamed = stream_to_sink(&mut sink, rx, Instant::now()).await.unwrap();
+        assert_eq!(streamed.len(), 1);
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.lines().last().unwrap().starts_with("127.0.0.1,4,closed,"));
+    }
+
+    #[test]
+    fn test_summary_only_json_omits_results() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let results = vec![
+            ProbeResult::new(vajra_common::Target::new(ip, 22), PortState::Open)
+                .with_service(vajra_common::ServiceMatch::new("ssh")),
+            ProbeResult::new(vajra_common::Target::new(ip, 80), PortState::Open)
+                .with_service(vajra_common::ServiceMatch::new("http")),
+            ProbeResult::new(vajra_common::Target::new(ip, 8080), PortState::Open)
+                .with_service(vajra_common::ServiceMatch::new("http")),
+            ProbeResult::new(vajra_common::Target::new(ip, 81), PortState::Closed),
+            ProbeResult::new(vajra_common::Target::new(ip, 82), PortState::Unfiltered),
+        ];
+
+        let mut sink = SummarySink::new(Vec::new(), SummaryFormat::Json);
+        write_to_sink(&mut sink, &results, Duration::from_secs(2)).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&sink.out).unwrap();
+        assert_eq!(value["scan_info"]["total_scanned"], 5);
+        assert_eq!(value["summary"]["open"], 3);
+        assert_eq!(value["summary"]["closed"], 1);
+        assert_eq!((&value["summary"]["filtered"], &value["summary"]["unfiltered"]), (&0.into(), &1.into()));
+        assert_eq!(value["summary"]["rate_per_second"], 2.5);
+        assert_eq!(value["summary"]["services"], serde_json::json!({"http": 2, "ssh": 1}));
+        assert!(value.get("results").is_none());
+    }
+
+    #[test]
+    fn test_xml_output() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5));
+        let mut results = vec![
+            ProbeResult::new(vajra_common::Target::new(ip, 80), PortState::Open).with_service(
+                vajra_common::ServiceMatch::new("http")
+                    .with_product("Apache \"httpd\" & co".to_string())
+                    .with_version("2.4.41".to_string()),
+            ),
+            ProbeResult::new(vajra_common::Target::new(ip, 22), PortState::Closed),
+        ];
+        let mut summary = ScanStats::new(2);
+        results.iter().for_each(|r| summary.update(r));
+        summary.elapsed = Duration::from_secs(3);
+
+        let mut out = Vec::new();
+        write_xml(&mut out, &mut results, &summary, 1_700_000_000, 1_700_000_003).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+
+        assert!(xml.contains("<nmaprun scanner=\"vajra\" start=\"1700000000\""));
+        assert!(xml.contains("<address addr=\"10.0.0.5\" addrtype=\"ipv4\"/>"));
+        // Ports are sorted, and attribute values are escaped
+        let closed = xml.find("portid=\"22\"><state state=\"closed\" reason=\"reset\"").unwrap();
+        let open = xml.find("portid=\"80\"><state state=\"open\" reason=\"syn-ack\"").unwrap();
+        assert!(closed < open);
+        assert!(xml.contains(
+            "<service name=\"http\" product=\"Apache &quot;httpd&quot; &amp; co\" version=\"2.4.41\" method=\"probed\" conf=\"10\"/>"
+        ));
+        assert!(xml.contains("<finished time=\"1700000003\" elapsed=\"3.00\""));
+        assert!(xml.trim_end().ends_with("</nmaprun>"));
+    }
+
+    #[test]
+    fn test_open_only_keeps_full_totals() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let results: Vec<ProbeResult> = [
+            (22, PortState::Open),
+            (23, PortState::Closed),
+            (80, PortState::Filtered),
+            (81, PortState::OpenFiltered),
+        ]
+        .into_iter()
+        .map(|(port, state)| ProbeResult::new(vajra_common::Target::new(ip, port), state))
+        .collect();
+        let mut stats = ScanStats::new(results.len());
+        results.iter().for_each(|r| stats.update(r));
+
+        let mut sink = OpenOnly::new(JsonSink::new(Vec::new()));
+        write_with_stats(&mut sink, &results, &stats).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&sink.inner.out).unwrap();
+        let listed = value["results"]["127.0.0.1"].as_array().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["target"]["port"], 22);
+        assert_eq!(value["scan_info"]["total_scanned"], 4);
+
+        let mut sink = OpenOnly::new(TableSink::new(Vec::new()));
+        write_with_stats(&mut sink, &results, &stats).unwrap();
+        let table = String::from_utf8(sink.inner.out).unwrap();
+        assert!(!table.contains("filtered   "));
+        assert!(table.contains("Closed ports: 1"));
+        assert!(table.contains("⊘ Filtered: 1"));
+        // The breakdown comes from the totals, not the (hidden) rows
+        assert!(table.contains("Open|filtered: 1"));
+    }
+
+    #[test]
+    fn test_open_only_without_open_ports_keeps_summary() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let results = vec![ProbeResult::new(vajra_common::Target::new(ip, 23), PortState::Closed)];
+        let mut stats = ScanStats::new(results.len());
+        results.iter().for_each(|r| stats.update(r));
+
+        let mut sink = OpenOnly::new(TableSink::new(Vec::new()));
+        write_with_stats(&mut sink, &results, &stats).unwrap();
+        let table = String::from_utf8(sink.inner.out).unwrap();
+        assert!(table.contains("No results to display."));
+        assert!(table.contains("Total scanned: 1"));
+        assert!(table.contains("Closed ports: 1"));
     }
 
     #[test]
@@ -259,9 +1076,90 @@ mod tests {
             .with_rtt(Duration::from_millis(10));
 
         let results = vec![result];
-        print_table(&results, Duration::from_secs(5));
+        let mut sink = TableSink::new(Vec::new());
+        write_to_sink(&mut sink, &results, Duration::from_secs(5)).unwrap();
+        let table = String::from_utf8(sink.out).unwrap();
+        assert!(table.contains("Open ports: 1"));
+    }
+
+    #[test]
+    fn test_custom_sink_receives_every_result() {
+        /// Embedder-style sink that keeps everything in memory
+        #[derive(Default)]
+        struct Collect {
+            results: Vec<ProbeResult>,
+            summary: Option<ScanStats>,
+        }
+
+        impl OutputSink for Collect {
+            fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+                self.results.push(result.clone());
+                Ok(())
+            }
+
+            fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+                self.summary = Some(summary.clone());
+                Ok(())
+            }
+        }
+
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let results: Vec<ProbeResult> = [(22, PortState::Open), (23, PortState::Closed), (80, PortState::Open)]
+            .into_iter()
+            .map(|(port, state)| ProbeResult::new(vajra_common::Target::new(ip, port), state))
+            .collect();
+
+        let mut sink = Collect::default();
+        write_to_sink(&mut sink, &results, Duration::from_secs(2)).unwrap();
+
+        let ports: Vec<u16> = sink.results.iter().map(|r| r.target.port).collect();
+        assert_eq!(ports, [22, 23, 80]);
+        let summary = sink.summary.unwrap();
+        assert_eq!((summary.scanned, summary.open_ports, summary.closed_ports), (3, 2, 1));
+        assert_eq!(summary.elapsed, Duration::from_secs(2));
     }
     
+    #[test]
+    fn test_format_compact() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let result = |port, service| {
+            ProbeResult::new(vajra_common::Target::new(ip, port), PortState::Open)
+                .with_service(vajra_common::ServiceMatch::new(service))
+        };
+        let results = vec![
+            result(443, "https"),
+            result(22, "ssh"),
+            ProbeResult::new(vajra_common::Target::new(ip, 25), PortState::Closed),
+            result(80, "http"),
+        ];
+
+        assert_eq!(
+            format_compact(&results),
+            "10.0.0.1  up  open: 22(ssh),80(http),443(https)\n"
+        );
+
+        // A PTR name from --resolve-ptr follows the address
+        let named: Vec<ProbeResult> = results.into_iter().map(|r| r.with_hostname("gw.lan")).collect();
+        assert!(format_compact(&named).starts_with("10.0.0.1 (gw.lan)  up  open: 22(ssh)"));
+    }
+
+    #[test]
+    fn test_format_service_list() {
+        let overrides = PortTable::parse("2222,tcp,ssh").unwrap();
+
+        let text = format_service_list(&overrides, "text").unwrap();
+        assert!(text.lines().any(|l| l.split_whitespace().eq(["22", "ssh"])));
+        assert!(text.lines().any(|l| l.split_whitespace().eq(["443", "https"])));
+        assert!(text.lines().any(|l| l.split_whitespace().eq(["2222/tcp", "ssh"])));
+
+        let json = format_service_list(&overrides, "json").unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let builtin = value["builtin"].as_array().unwrap();
+        assert!(builtin.iter().any(|e| e["port"] == 22 && e["service"] == "ssh"));
+        assert!(builtin.iter().any(|e| e["port"] == 443 && e["service"] == "https"));
+        assert_eq!(value["overrides"][0]["port"], 2222);
+    }
+
     #[test]
     fn test_format_duration() {
         use super::format_duration;
@@ -0,0 +1,341 @@
+//! Protobuf encoding of scan results (`protobuf` feature)
+//!
+//! Message types mirror `proto/vajra.proto`. Results are written as a stream
+//! of length-delimited `Record` messages for high-volume pipelines.
+
+use anyhow::{anyhow, Result};
+use prost::Message;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::info;
+use vajra_common::{OutputSink, PortState, Protocol, ScanStats, Target};
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ServiceMatch {
+    #[prost(string, tag = "1")]
+    pub service: String,
+    #[prost(string, optional, tag = "2")]
+    pub product: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub version: Option<String>,
+    #[prost(float, tag = "4")]
+    pub confidence: f32,
+    #[prost(uint32, optional, tag = "5")]
+    pub http_status: Option<u32>,
+    #[prost(string, optional, tag = "6")]
+    pub http_title: Option<String>,
+    #[prost(message, optional, tag = "7")]
+    pub tls: Option<TlsInfo>,
+    #[prost(bool, tag = "8")]
+    pub cdn_edge: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TlsInfo {
+    #[prost(string, tag = "1")]
+    pub version: String,
+    #[prost(string, tag = "2")]
+    pub cipher: String,
+    #[prost(string, optional, tag = "3")]
+    pub subject_cn: Option<String>,
+    #[prost(string, repeated, tag = "4")]
+    pub subject_alt_names: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProbeResult {
+    #[prost(string, tag = "1")]
+    pub ip: String,
+    #[prost(uint32, tag = "2")]
+    pub port: u32,
+    #[prost(string, tag = "3")]
+    pub protocol: String,
+    #[prost(string, tag = "4")]
+    pub state: String,
+    #[prost(string, optional, tag = "5")]
+    pub banner: Option<String>,
+    #[prost(message, optional, tag = "6")]
+    pub service: Option<ServiceMatch>,
+    #[prost(uint64, tag = "7")]
+    pub timestamp_us: u64,
+    #[prost(uint64, tag = "8")]
+    pub rtt_us: u64,
+    #[prost(string, optional, tag = "9")]
+    pub reason: Option<String>,
+    #[prost(string, optional, tag = "10")]
+    pub os_guess: Option<String>,
+    #[prost(string, optional, tag = "11")]
+    pub scanner: Option<String>,
+    #[prost(string, optional, tag = "12")]
+    pub hostname: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ScanSummary {
+    #[prost(double, tag = "1")]
+    pub duration_seconds: f64,
+    #[prost(uint64, tag = "2")]
+    pub total_scanned: u64,
+    #[prost(uint64, tag = "3")]
+    pub open: u64,
+    #[prost(uint64, tag = "4")]
+    pub closed: u64,
+    #[prost(uint64, tag = "5")]
+    pub filtered: u64,
+    #[prost(uint64, tag = "6")]
+    pub skipped: u64,
+    #[prost(uint64, tag = "7")]
+    pub open_filtered: u64,
+    #[prost(uint64, tag = "8")]
+    pub unfiltered: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Record {
+    #[prost(oneof = "record::Kind", tags = "1, 2")]
+    pub kind: Option<record::Kind>,
+}
+
+pub mod record {
+    // Summaries are written once per stream; boxing results buys nothing
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Result(super::ProbeResult),
+        #[prost(message, tag = "2")]
+        Summary(super::ScanSummary),
+    }
+}
+
+impl From<&vajra_common::ProbeResult> for ProbeResult {
+    fn from(result: &vajra_common::ProbeResult) -> Self {
+        let timestamp_us = result
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+
+        Self {
+            ip: result.target.ip.to_string(),
+            port: u32::from(result.target.port),
+            protocol: result.target.protocol.as_str().to_string(),
+            state: result.state.to_string(),
+            banner: result.banner.clone(),
+            service: result.service.as_ref().map(|s| ServiceMatch {
+                service: s.service.clone(),
+                product: s.product.clone(),
+                version: s.version.clone(),
+                confidence: s.confidence,
+                http_status: s.http_status.map(u32::from),
+                http_title: s.http_title.clone(),
+                tls: s.tls.as_ref().map(|t| TlsInfo {
+                    version: t.version.clone(),
+                    cipher: t.cipher.clone(),
+                    subject_cn: t.subject_cn.clone(),
+                    subject_alt_names: t.subject_alt_names.clone(),
+                }),
+                cdn_edge: s.cdn_edge,
+            }),
+            timestamp_us,
+            rtt_us: result.rtt.as_micros() as u64,
+            reason: result.reason.clone(),
+            os_guess: result.os_guess.clone(),
+            scanner: result.scanner.clone(),
+            hostname: result.hostname.clone(),
+        }
+    }
+}
+
+impl TryFrom<ProbeResult> for vajra_common::ProbeResult {
+    type Error = anyhow::Error;
+
+    fn try_from(msg: ProbeResult) -> Result<Self> {
+        let ip = msg
+            .ip
+            .parse()
+            .map_err(|_| anyhow!("invalid ip '{}'", msg.ip))?;
+        let port = u16::try_from(msg.port).map_err(|_| anyhow!("invalid port {}", msg.port))?;
+        let protocol = match msg.protocol.as_str() {
+            "tcp" => Protocol::TCP,
+            "udp" => Protocol::UDP,
+            "sctp" => Protocol::SCTP,
+            other => return Err(anyhow!("unknown protocol '{}'", other)),
+        };
+        let state = match msg.state.as_str() {
+            "open" => PortState::Open,
+            "closed" => PortState::Closed,
+            "filtered" => PortState::Filtered,
+            "open|filtered" => PortState::OpenFiltered,
+            "unfiltered" => PortState::Unfiltered,
+            "skipped" => PortState::Skipped,
+            other => return Err(anyhow!("unknown port state '{}'", other)),
+        };
+
+        let mut result =
+            vajra_common::ProbeResult::new(Target::new(ip, port).with_protocol(protocol), state)
+                .with_rtt(Duration::from_micros(msg.rtt_us));
+        result.timestamp = UNIX_EPOCH + Duration::from_micros(msg.timestamp_us);
+        // Decoded results have no in-process monotonic time
+        result.monotonic = None;
+        result.banner = msg.banner;
+        result.reason = msg.reason;
+        result.os_guess = msg.os_guess;
+        result.scanner = msg.scanner;
+        result.hostname = msg.hostname;
+        result.service = match msg.service {
+            Some(s) => Some(vajra_common::ServiceMatch {
+                service: s.service,
+                product: s.product,
+                version: s.version,
+                confidence: s.confidence,
+                http_status: s
+                    .http_status
+                    .map(u16::try_from)
+                    .transpose()
+                    .map_err(|_| anyhow!("invalid http status"))?,
+                http_title: s.http_title,
+                tls: s.tls.map(|t| vajra_common::TlsInfo {
+                    version: t.version,
+                    cipher: t.cipher,
+                    subject_cn: t.subject_cn,
+                    subject_alt_names: t.subject_alt_names,
+                }),
+                cdn_edge: s.cdn_edge,
+            }),
+            None => None,
+        };
+        Ok(result)
+    }
+}
+
+/// Sink writing each result as a length-delimited `Record`, followed by a
+/// summary record on `finish`
+pub struct ProtobufSink<W: Write> {
+    out: W,
+    buf: Vec<u8>,
+    /// Output path reported once the stream is complete
+    path: Option<String>,
+}
+
+impl<W: Write> ProtobufSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            buf: Vec::with_capacity(256),
+            path: None,
+        }
+    }
+
+    fn write_record(&mut self, kind: record::Kind) -> Result<()> {
+        let record = Record { kind: Some(kind) };
+        self.buf.clear();
+        record.encode_length_delimited(&mut self.buf)?;
+        self.out.write_all(&self.buf)?;
+        Ok(())
+    }
+}
+
+impl ProtobufSink<BufWriter<File>> {
+    /// Sink writing to a new file at `path`
+    pub fn create(path: &str) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| anyhow!("Failed to create protobuf output '{}': {}", path, e))?;
+        let mut sink = Self::new(BufWriter::new(file));
+        sink.path = Some(path.to_string());
+        Ok(sink)
+    }
+}
+
+impl<W: Write> OutputSink for ProtobufSink<W> {
+    fn write_result(&mut self, result: &vajra_common::ProbeResult) -> Result<()> {
+        self.write_record(record::Kind::Result(result.into()))
+    }
+
+    fn finish(&mut self, stats: &ScanStats) -> Result<()> {
+        // Totals come from the scan, so they cover results never written here
+        let summary = ScanSummary {
+            duration_seconds: stats.elapsed.as_secs_f64(),
+            total_scanned: stats.scanned as u64,
+            open: stats.open_ports as u64,
+            closed: stats.closed_ports as u64,
+            filtered: stats.filtered_ports as u64,
+            skipped: stats.skipped_ports as u64,
+            open_filtered: stats.open_filtered_ports as u64,
+            unfiltered: stats.unfiltered_ports as u64,
+        };
+        self.write_record(record::Kind::Summary(summary))?;
+        self.out.flush()?;
+        if let Some(path) = &self.path {
+            info!("Wrote {} result(s) to {}", stats.scanned, path);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    /// Decode a buffer of length-delimited `Record`s
+    fn read_records(mut data: &[u8]) -> Result<Vec<Record>> {
+        let mut records = Vec::new();
+        while !data.is_empty() {
+            records.push(Record::decode_length_delimited(&mut data)?);
+        }
+        Ok(records)
+    }
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        let target = Target::tcp(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)), 22);
+        let original = vajra_common::ProbeResult::new(target, PortState::Open)
+            .with_rtt(Duration::from_micros(1500))
+            .with_banner("SSH-2.0-OpenSSH_8.2".to_string())
+            .with_service(
+                vajra_common::ServiceMatch::new("ssh")
+                    .with_product("OpenSSH".to_string())
+                    .with_version("8.2".to_string())
+                    .with_http_status(401)
+                    .with_http_title("Login".to_string()),
+            )
+            .with_os_guess("Linux")
+            .with_scanner("tcp")
+            .with_hostname("gw.example.test");
+
+        let mut sink = ProtobufSink::new(Vec::new());
+        crate::output::write_to_sink(&mut sink, std::slice::from_ref(&original), Duration::from_secs(2))
+            .unwrap();
+
+        let records = read_records(&sink.out).unwrap();
+        assert_eq!(records.len(), 2);
+
+        let decoded = match records[0].kind.clone() {
+            Some(record::Kind::Result(r)) => vajra_common::ProbeResult::try_from(r).unwrap(),
+            other => panic!("expected result record, got {:?}", other),
+        };
+        assert_eq!(decoded.target, original.target);
+        assert_eq!(decoded.state, original.state);
+        assert_eq!(decoded.rtt, original.rtt);
+        assert_eq!(decoded.banner, original.banner);
+        assert_eq!(decoded.os_guess.as_deref(), Some("Linux"));
+        assert_eq!(decoded.scanner.as_deref(), Some("tcp"));
+        assert_eq!(decoded.hostname.as_deref(), Some("gw.example.test"));
+        let service = decoded.service.unwrap();
+        assert_eq!(service.service, "ssh");
+        assert_eq!(service.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(service.version.as_deref(), Some("8.2"));
+        assert_eq!(service.http_status, Some(401));
+        assert_eq!(service.http_title.as_deref(), Some("Login"));
+
+        match &records[1].kind {
+            Some(record::Kind::Summary(s)) => {
+                assert_eq!(s.total_scanned, 1);
+                assert_eq!(s.open, 1);
+            }
+            other => panic!("expected summary record, got {:?}", other),
+        }
+    }
+}
@@ -1,26 +1,70 @@
 // runner.rs
 use anyhow::{anyhow, Result, Context};
-use std::{net::{IpAddr, Ipv4Addr, ToSocketAddrs}, sync::Arc, time::{Duration, Instant}};
-use tracing::info;
+use vajra::{ScanConfig, ScanType};
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::{path::Path, sync::Arc, time::{Duration, Instant}};
+use tracing::field::{display, Empty};
+use tracing::{info, instrument, warn, Span};
 use vajra_orchestrator::Orchestrator;
 use vajra_scanner_tcp::TcpScanner;
-use vajra_scanner_syn::SynScanner;
-use vajra_common::{ScanJob, Target};
-use crate::output::print_results;
+use vajra_scanner_syn::{HostDiscovery, SynError};
+use vajra_common::{DumpWriter, PcapWriter, PortSpec, PortState, ProbeResult, Protocol, ScanState, ScanStats, Scanner};
+use vajra_fingerprint::{set_port_table, PortTable, ServiceProbeDb};
+use crate::args::ScanArgs;
+use crate::baseline::Baseline;
+use crate::ports::top_ports;
+use crate::output::{
+    print_results_with_stats, stream_to_sink, summary_sink_for_format, write_with_stats, CsvSink, TruncateBanners,
+};
 use vajra_target_resolver::TargetResolver;
 
-pub async fn run_scan(
-    targets: String,
-    ports: String,
-    concurrency: usize,
-    rate_limit: u64,
-    timeout: u64,
-    banner_timeout: u64,
-    output_format: String,
-    preset: String,
-    scan_type: Option<String>,
-) -> Result<()> {
-    let scan_type = scan_type.unwrap_or_else(|| "tcp".to_string());
+/// Everything logged during the scan is inside a `scan` span whose
+/// `scan_id`, `targets` and `scanner` fields are filled in as they become
+/// known, so structured log output carries them on every event.
+#[instrument(name = "scan", skip_all, fields(scan_id = Empty, targets = Empty, scanner = Empty))]
+pub async fn run_scan(args: ScanArgs) -> Result<()> {
+    let ScanArgs {
+        targets,
+        ports,
+        top_ports,
+        concurrency,
+        rate_limit,
+        timeout,
+        banner_timeout,
+        version_intensity,
+        retries,
+        host_timeout,
+        adaptive_timeout,
+        per_host_concurrency,
+        dns_concurrency,
+        first_ip_only,
+        discovery,
+        // The default; only present so it can override --first-ip-only
+        resolve_all: _,
+        baseline,
+        dump,
+        pcap,
+        service_db,
+        port_table,
+        auto_protocol,
+        tls_probe,
+        fallback_connect,
+        quiet,
+        stream_csv,
+        summary_only,
+        randomize,
+        seed,
+        resolve_ptr,
+        open: open_only,
+        resume,
+        autosave_interval,
+        max_banner_len,
+        output_format,
+        preset,
+        scan_type,
+    } = args;
+    let scan_type: ScanType = scan_type.parse()?;
     info!("Starting scan...");
     info!("Targets: {}", targets);
     info!("Ports: {}", ports);
@@ -28,83 +72,404 @@ pub async fn run_scan(
     info!("Rate limit: {}/s", rate_limit);
     info!("Scanner type: {}", scan_type);
 
-    // Parse targets and ports
-    let ips = TargetResolver::resolve_targets(&targets).await?;
-    let port_list = parse_ports(&ports)?;
+    // Load the baseline before scanning so a bad file fails fast
+    let baseline = baseline
+        .map(|path| Baseline::load(&path))
+        .transpose()?;
 
-    // Apply preset adjustments for accuracy vs speed
-    // 'accurate' preset increases timeout and enables retries/bigger banner timeout
-    let mut effective_timeout = timeout;
-    let mut effective_banner_timeout = banner_timeout;
-    let mut effective_retries = 0u32;
-    if preset == "accurate" {
-        effective_timeout = effective_timeout.max(3000);
-        effective_banner_timeout = effective_banner_timeout.max(1000);
-        effective_retries = 2;
+    let dump = dump
+        .map(|path| {
+            DumpWriter::create(&path)
+                .map(Arc::new)
+                .with_context(|| format!("Failed to create dump file {}", path.display()))
+        })
+        .transpose()?;
+    let pcap = pcap
+        .map(|path| {
+            PcapWriter::create(&path)
+                .map(Arc::new)
+                .with_context(|| format!("Failed to create pcap file {}", path.display()))
+        })
+        .transpose()?;
+    let service_db = service_db
+        .map(|path| ServiceProbeDb::load(&path).map(Arc::new))
+        .transpose()?;
+    if let Some(path) = port_table {
+        let table = PortTable::load(&path)?;
+        info!("Loaded {} port table entries from {}", table.len(), path.display());
+        set_port_table(Some(table));
     }
-    
-    // Build scan target list (IP × Port combinations)
-    let mut scan_targets = Vec::new();
-    for ip in &ips {
-        for port in &port_list {
-            scan_targets.push(Target::new(*ip, *port));
+
+    // A saved state fixes the hosts and ports; otherwise parse them
+    let saved = match &resume {
+        Some(path) if path.exists() => {
+            let state = ScanState::load(path)?;
+            info!(
+                "Resuming scan from {}: {} of {} target(s) already done",
+                path.display(),
+                state.completed.len(),
+                state.total_targets()
+            );
+            Some(state)
         }
+        _ => None,
+    };
+    // Forward lookups of the targets and PTR lookups share the DNS limit
+    let resolver = TargetResolver::new()
+        .with_dns_concurrency(dns_concurrency)
+        .with_first_ip_only(first_ip_only);
+    // Targets written `host:port` are scanned on that port alone
+    let (ips, port_list, pinned) = match &saved {
+        Some(state) => (state.ips.clone(), state.ports.clone(), Vec::new()),
+        None => {
+            let endpoints = resolver.resolve_endpoints(&targets).await?;
+            let (mut ips, mut pinned) = split_endpoints(endpoints);
+            if pinned.is_empty() {
+                if discovery {
+                    ips = discover_hosts(ips, Duration::from_millis(timeout)).await?;
+                }
+            } else if resume.is_some() {
+                return Err(anyhow!("--resume does not support host:port targets"));
+            } else if discovery {
+                // Each host is pinged once, whatever ports it is listed with
+                let mut hosts = ips.clone();
+                hosts.extend(pinned.iter().map(|&(ip, _)| ip));
+                hosts.sort_unstable();
+                hosts.dedup();
+                let up: HashSet<_> = discover_hosts(hosts, Duration::from_millis(timeout)).await?.into_iter().collect();
+                ips.retain(|ip| up.contains(ip));
+                pinned.retain(|(ip, _)| up.contains(ip));
+            }
+            let port_list = match top_ports {
+                Some(n) => {
+                    info!("Scanning the top {} TCP ports", n);
+                    top_ports_spec(n as usize)
+                }
+                None => parse_ports(&ports)?,
+            };
+            (ips, port_list, pinned)
+        }
+    };
+    let resume = resume.map(|path| {
+        let state = saved.unwrap_or_else(|| ScanState::new(ips.clone(), port_list.clone()));
+        (path, state)
+    });
+
+    let mut config = ScanConfig {
+        scan_type,
+        timeout: Duration::from_millis(timeout),
+        banner_timeout: Duration::from_millis(banner_timeout),
+        version_intensity,
+        retries,
+        // 'accurate' raises the timeouts and resends more
+        accurate: preset == "accurate",
+        concurrency,
+        rate_limit: u32::try_from(rate_limit).unwrap_or(u32::MAX),
+        host_timeout: host_timeout.map(Duration::from_secs),
+        adaptive_timeout,
+        per_host_concurrency: per_host_concurrency.map(|limit| limit as usize),
+        auto_protocol,
+        tls_probe,
+        fallback_connect,
+        shuffle_seed: None,
+        // Without a terminal, periodic lines on stderr keep logs informed
+        progress_reports: (!quiet && !std::io::stderr().is_terminal()).then_some(PROGRESS_REPORT_INTERVAL),
+        completed: resume.as_ref().map(|(_, state)| state.completed.clone()).unwrap_or_default(),
+        dump,
+        pcap,
+    };
+    if randomize || seed.is_some() {
+        let seed = seed.unwrap_or_else(random_seed);
+        info!("Randomizing scan order (seed {}; pass --seed {} to repeat it)", seed, seed);
+        config.shuffle_seed = Some(seed);
     }
+
+    // Build the IP × Port grid; targets are generated as the scan runs
+    let (ip_count, port_count) = (ips.len(), port_list.len());
+    let grid = config.grid(ips, port_list);
+    let target_count = grid.len() + pinned.len();
     
     // Log scan configuration
-    info!("Found {} IPv4 address(es)", ips.len());
-    info!("Port range: {} port(s)", port_list.len());
-    if ips.len() > 1 {
-        info!("Total scan targets: {} ({} IPs × {} ports)", scan_targets.len(), ips.len(), port_list.len());
-    } else {
-        info!("Total scan targets: {} port(s)", scan_targets.len());
+    info!("Found {} address(es)", ip_count);
+    info!("Port range: {} port(s)", port_count);
+    if ip_count > 1 {
+        info!("Total scan targets: {} ({} IPs × {} ports)", grid.len(), ip_count, port_count);
+    } else if ip_count == 1 {
+        info!("Total scan targets: {} port(s)", grid.len());
     }
-
-    // Initialize orchestrator
-    let mut orchestrator = Orchestrator::new(concurrency, rate_limit as u32);
-
-    // Register scanner
-    match scan_type.as_str() {
-        "tcp" => {
-                let optimized_timeout = Duration::from_millis(effective_timeout.min(5000));
-                let tcp_scanner = TcpScanner::new()
-                    .with_timeout(optimized_timeout)
-                    .with_retries(effective_retries)
-                    .with_banner_timeout(Duration::from_millis(effective_banner_timeout));
-            orchestrator.add_scanner("tcp", Arc::new(tcp_scanner));
-        }
-        "syn" => {
-            vajra_scanner_syn::init()
-                .context("Failed to initialize SYN scanner. Make sure you have CAP_NET_RAW capabilities or run with sudo.")?;
-            
-            let syn_scanner = SynScanner::new()
-                .with_timeout(Duration::from_millis(timeout))
-                .with_retries(1);
-            orchestrator.add_scanner("syn", Arc::new(syn_scanner));
-        }
-        _ => return Err(anyhow!("Invalid scanner type '{}'", scan_type)),
+    if !pinned.is_empty() {
+        info!("Plus {} host:port target(s)", pinned.len());
+    }
+    if grid.len() > LARGE_SCAN_TARGETS {
+        warn!(
+            "Scanning {} targets ({} IPs × {} ports); consider narrowing the port range or splitting the host list",
+            grid.len(), ip_count, port_count
+        );
     }
 
-    // Submit job and run
-    let job = ScanJob::new(scan_targets);
-    orchestrator.submit_job(job).await?;
+    // Register the scanners, then submit the grid plus a job for host:port
+    // targets so they run together
+    let endpoint_job = (!pinned.is_empty()).then(|| config.endpoint_job(&pinned));
+    let prepared = config.start(grid, endpoint_job).await?;
+    let scanner_name = prepared.scanner_name();
+    let orchestrator = prepared.orchestrator();
+    let span = Span::current();
+    span.record("targets", target_count as u64);
+    span.record("scanner", scanner_name);
+    span.record("scan_id", display(prepared.job_id()));
     
     // Start timing the scan
     let scan_start = Instant::now();
-    orchestrator.run(Some(&scan_type)).await?;
-    let scan_duration = scan_start.elapsed();
+    let streamed = match stream_csv {
+        Some(path) => {
+            // Rows reach the file as results arrive, before any post-processing
+            let mut sink = TruncateBanners::new(CsvSink::create(&path)?, max_banner_len);
+            let (handle, rx) = orchestrator.run_streaming(Some(scanner_name));
+            let results = stream_to_sink(&mut sink, rx, scan_start).await?;
+            handle.await??;
+            info!("Streamed {} result(s) to {}", results.len(), path.display());
+            Some(results)
+        }
+        None => {
+            match &resume {
+                Some((path, state)) => {
+                    let interval = Duration::from_secs(autosave_interval);
+                    run_with_autosave(orchestrator, scanner_name, state, path, interval).await?
+                }
+                None => orchestrator.run_all(Some(scanner_name)).await?,
+            }
+            None
+        }
+    };
+    // Totals cover every scanned target, including any the baseline hides
+    let mut stats = orchestrator.get_stats().await;
+    let concurrency = prepared.concurrency();
 
     // Collect results and print
-    let results = orchestrator.get_results().await;
-    print_results(&results, &output_format, scan_duration)?;
+    let orchestrator = prepared.finish()?;
+    let mut results = match streamed {
+        Some(results) => results,
+        None => orchestrator.get_results().await,
+    };
+    if let Some((_, state)) = resume {
+        // Report the whole scan, not just what this run covered
+        let mut previous = ScanStats::new(state.results.len());
+        state.results.iter().for_each(|r| previous.update(r));
+        stats.merge(&previous);
+        results.splice(0..0, state.results);
+    }
+    if scanner_name == "syn" && mostly_filtered(&stats) {
+        check_capture(&results, config.probe_timeout().min(Duration::from_secs(2))).await;
+    }
+    if resolve_ptr {
+        // One lookup per host, however many ports it has
+        let names = resolver.reverse_lookup_all(results.iter().map(|r| r.target.ip)).await;
+        info!("Resolved PTR names for {} host(s)", names.len());
+        for result in &mut results {
+            result.hostname = names.get(&result.target.ip).cloned();
+        }
+    }
+    if let Some(db) = service_db {
+        info!("Running {} service probe(s) against open ports", db.len());
+        probe_services(&mut results, db, config.probe_timeout(), concurrency).await;
+    }
+    if let Some(baseline) = baseline {
+        let total = results.len();
+        results = baseline.filter_new(results);
+        info!("Baseline ({} entries) suppressed {} of {} result(s)", baseline.len(), total - results.len(), total);
+    }
+    if summary_only {
+        write_with_stats(&mut summary_sink_for_format(&output_format)?, &results, &stats)?;
+    } else {
+        print_results_with_stats(&results, &output_format, &stats, max_banner_len, open_only)?;
+    }
     Ok(())
 }
 
 // target parsing/resolution is delegated to `vajra-target-resolver`
 
-/// Parses a port string like "80,443,1000-1010" into a vector of u16 ports
-fn parse_ports(ports_str: &str) -> Result<Vec<u16>> {
-    let mut ports = Vec::new();
+/// How often progress lines are printed for non-interactive runs
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Target count above which the scan size is worth a warning
+const LARGE_SCAN_TARGETS: usize = 1_000_000;
+
+/// Fresh seed for `--randomize` without `--seed`
+fn random_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    // std seeds each RandomState from OS randomness
+    std::collections::hash_map::RandomState::new().build_hasher().finish()
+}
+
+/// Keep the hosts that answer a ping or TCP probe, in their original
+/// order. Without raw socket privileges every host is kept.
+async fn discover_hosts(ips: Vec<std::net::IpAddr>, timeout: Duration) -> Result<Vec<std::net::IpAddr>> {
+    let discovery = HostDiscovery::new().with_timeout(timeout).with_tcp_probes(true);
+    match discovery.discover(&ips).await {
+        Ok(alive) => {
+            let total = ips.len();
+            let up: Vec<_> = ips.into_iter().filter(|ip| alive.contains(ip)).collect();
+            info!("Host discovery: {} of {} host(s) up", up.len(), total);
+            Ok(up)
+        }
+        Err(SynError::NotPermitted) => {
+            warn!("Raw sockets not permitted; skipping host discovery and scanning every host");
+            Ok(ips)
+        }
+        Err(e) => Err(e).context("Host discovery failed"),
+    }
+}
+
+/// Split resolved endpoints into hosts scanned on every `--ports` port and
+/// `host:port` targets scanned on their own port
+fn split_endpoints(endpoints: Vec<(std::net::IpAddr, Option<u16>)>) -> (Vec<std::net::IpAddr>, Vec<(std::net::IpAddr, u16)>) {
+    let mut ips = Vec::new();
+    let mut pinned = Vec::new();
+    for (ip, port) in endpoints {
+        match port {
+            Some(port) => pinned.push((ip, port)),
+            None => ips.push(ip),
+        }
+    }
+    (ips, pinned)
+}
+
+/// Run the probe database against open TCP ports, replacing the detected
+/// service wherever a probe matches. At most `concurrency` ports are probed
+/// at once.
+async fn probe_services(
+    results: &mut [ProbeResult],
+    db: Arc<ServiceProbeDb>,
+    timeout: Duration,
+    concurrency: usize,
+) {
+    let permits = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (idx, result) in results.iter().enumerate() {
+        if result.state != PortState::Open || result.target.protocol != Protocol::TCP {
+            continue;
+        }
+        let addr = std::net::SocketAddr::new(result.target.ip, result.target.port);
+        let (db, permits) = (db.clone(), permits.clone());
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok()?;
+            db.probe_service(addr, timeout).await.map(|service| (idx, service))
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(Some((idx, service))) = joined {
+            results[idx].service = Some(service);
+        }
+    }
+}
+
+/// Share of filtered results at which a SYN scan looks like it saw no replies
+const ALL_FILTERED_RATIO: f64 = 0.99;
+
+/// Ports re-checked with a TCP connect when a SYN scan saw no replies
+const CAPTURE_CHECK_SAMPLE: usize = 8;
+
+/// Run the scan, saving `state` plus the results so far to `path` every
+/// `interval` and once more when the scan ends
+async fn run_with_autosave(
+    orchestrator: &Orchestrator,
+    scanner_name: &str,
+    state: &ScanState,
+    path: &Path,
+    interval: Duration,
+) -> Result<()> {
+    let save = |results: Vec<ProbeResult>| {
+        let mut snapshot = state.clone();
+        snapshot.record(results);
+        snapshot.save(path)
+    };
+
+    let run = orchestrator.run(Some(scanner_name));
+    tokio::pin!(run);
+    let mut ticks = tokio::time::interval(interval);
+    // The first tick completes immediately
+    ticks.tick().await;
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut run => break outcome,
+            _ = ticks.tick() => {
+                if let Err(e) = save(orchestrator.get_results().await) {
+                    warn!("Failed to save scan state to {}: {}", path.display(), e);
+                }
+            }
+        }
+    };
+    save(orchestrator.get_results().await)
+        .with_context(|| format!("Failed to save scan state to {}", path.display()))?;
+    info!("Saved scan state to {}", path.display());
+    outcome
+}
+
+/// Whether (nearly) every probe went unanswered: `filtered` or, for a SYN
+/// scan's silent ports, `open|filtered`
+fn mostly_filtered(stats: &ScanStats) -> bool {
+    let silent = stats.filtered_ports + stats.open_filtered_ports;
+    stats.scanned > 0 && silent as f64 / stats.scanned as f64 >= ALL_FILTERED_RATIO
+}
+
+/// Whether a connect sample contradicts an all-filtered SYN scan: a connect
+/// that got any answer (open or refused) means replies reach this host, so
+/// the capture socket is what missed them
+fn capture_missed_replies(stats: &ScanStats, connect_sample: &[PortState]) -> bool {
+    mostly_filtered(stats)
+        && connect_sample
+            .iter()
+            .any(|state| matches!(state, PortState::Open | PortState::Closed))
+}
+
+/// Connect to a few of the ports a SYN scan left unanswered and warn if they
+/// answer. Replies routed back on an interface the capture socket is not
+/// watching otherwise make every port look filtered without any error.
+async fn check_capture(results: &[ProbeResult], timeout: Duration) {
+    let filtered: Vec<&ProbeResult> = results.iter().filter(|r| r.is_filtered()).collect();
+    // Spread the sample over the hosts and ports scanned
+    let step = (filtered.len() / CAPTURE_CHECK_SAMPLE).max(1);
+    let scanner = Arc::new(TcpScanner::new().with_timeout(timeout).with_retries(0));
+    let mut tasks = tokio::task::JoinSet::new();
+    for result in filtered.iter().step_by(step).take(CAPTURE_CHECK_SAMPLE) {
+        let (scanner, target) = (scanner.clone(), result.target.clone());
+        tasks.spawn(async move { scanner.scan(&target).await.map(|r| r.state) });
+    }
+    let mut sample = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(Ok(state)) = joined {
+            sample.push(state);
+        }
+    }
+
+    let mut stats = ScanStats::new(results.len());
+    results.iter().for_each(|r| stats.update(r));
+    if capture_missed_replies(&stats, &sample) {
+        let answered = sample.iter().filter(|s| matches!(s, PortState::Open | PortState::Closed)).count();
+        warn!(
+            "SYN scan saw no replies, but TCP connect got an answer from {} of {} sampled port(s). \
+             The capture socket may be missing replies (asymmetric routing, or replies arriving on \
+             another interface); results below are likely wrong. Try --scan-type tcp or --fallback-connect.",
+            answered,
+            sample.len()
+        );
+    }
+}
+
+/// The `n` most common ports as a spec, most frequent first
+fn top_ports_spec(n: usize) -> PortSpec {
+    let mut ports = PortSpec::new();
+    for port in top_ports(n) {
+        ports.push_range(port..=port);
+    }
+    ports
+}
+
+/// Parses a port string like "80,443,1000-1010" into port ranges, sorted
+/// and with duplicates removed
+fn parse_ports(ports_str: &str) -> Result<PortSpec> {
+    let mut ports = PortSpec::new();
 
     for part in ports_str.split(',') {
         let part = part.trim();
@@ -124,24 +489,32 @@ fn parse_ports(ports_str: &str) -> Result<Vec<u16>> {
             if start > end {
                 return Err(anyhow!("Invalid range: start > end"));
             }
+            if start == 0 {
+                return Err(anyhow!("Invalid port range: {} (port 0 cannot be scanned)", part));
+            }
 
-            ports.extend(start..=end);
+            ports.push_range(start..=end);
         } else {
             let port: u16 = part.parse().context(format!("Invalid port: {}", part))?;
-            ports.push(port);
+            if port == 0 {
+                return Err(anyhow!("Invalid port: 0 (port 0 cannot be scanned)"));
+            }
+            ports.push_range(port..=port);
         }
     }
 
     if ports.is_empty() {
         Err(anyhow!("No ports specified"))
     } else {
-        Ok(ports)
+        // Each port is probed (and counted) once
+        Ok(ports.normalized())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
     use tokio::runtime::Runtime;
 
     #[test]
@@ -149,33 +522,119 @@ mod tests {
         // moved to target_resolver tests
     }
 
+    #[test]
+    fn test_retries_flag_reaches_scanners() {
+        use crate::args::{Cli, Commands};
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["vajra", "scan", "-t", "127.0.0.1", "--retries", "4"]).unwrap();
+        let Commands::Scan(args) = cli.command else { panic!("expected scan") };
+        assert_eq!(args.retries, Some(4));
+        let config = |retries, accurate| ScanConfig { retries, accurate, ..Default::default() };
+        for scan_type in [ScanType::Tcp, ScanType::Syn, ScanType::Udp] {
+            assert_eq!(config(args.retries, false).retries_for(scan_type), 4);
+        }
+
+        // Defaults without the flag
+        assert_eq!(config(None, false).retries_for(ScanType::Tcp), 0);
+        assert_eq!(config(None, true).retries_for(ScanType::Tcp), 2);
+        assert_eq!(config(None, false).retries_for(ScanType::Syn), 1);
+        assert_eq!(config(None, false).retries_for(ScanType::Udp), 1);
+
+        assert!(Cli::try_parse_from(["vajra", "scan", "-t", "127.0.0.1", "--retries", "11"]).is_err());
+    }
+
+    #[test]
+    fn test_first_ip_only_flag() {
+        use crate::args::{Cli, Commands};
+        use clap::Parser;
+
+        let first_ip_only = |extra: &[&str]| {
+            let args = ["vajra", "scan", "-t", "example.com"].iter().chain(extra);
+            let Commands::Scan(args) = Cli::try_parse_from(args).unwrap().command else {
+                panic!("expected scan")
+            };
+            args.first_ip_only
+        };
+        // Every address is scanned unless asked otherwise; the last flag wins
+        assert!(!first_ip_only(&[]));
+        assert!(first_ip_only(&["--first-ip-only"]));
+        assert!(!first_ip_only(&["--first-ip-only", "--resolve-all"]));
+        assert!(first_ip_only(&["--resolve-all", "--first-ip-only"]));
+    }
+
+    #[test]
+    fn test_capture_check_decision() {
+        let stats = |filtered: usize, open: usize| ScanStats {
+            scanned: filtered + open,
+            filtered_ports: filtered,
+            open_ports: open,
+            ..Default::default()
+        };
+        let answered = [PortState::Filtered, PortState::Open, PortState::Filtered];
+        let silent = [PortState::Filtered, PortState::Filtered];
+
+        // Every SYN probe filtered, yet a connect got through
+        assert!(capture_missed_replies(&stats(1000, 0), &answered));
+        assert!(capture_missed_replies(&stats(1000, 0), &[PortState::Closed]));
+        // Connects time out too: the hosts really are firewalled or down
+        assert!(!capture_missed_replies(&stats(1000, 0), &silent));
+        assert!(!capture_missed_replies(&stats(1000, 0), &[]));
+        // The SYN scan did see replies
+        assert!(!capture_missed_replies(&stats(900, 100), &answered));
+        assert!(!capture_missed_replies(&stats(0, 0), &answered));
+    }
+
+    #[test]
+    fn test_top_ports_flag() {
+        use crate::args::{Cli, Commands};
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["vajra", "scan", "-t", "127.0.0.1", "--top-ports", "100"]).unwrap();
+        let Commands::Scan(args) = cli.command else { panic!("expected scan") };
+        let spec = top_ports_spec(args.top_ports.unwrap() as usize);
+        assert_eq!(spec.len(), 100);
+        assert_eq!(spec.iter().take(3).collect::<Vec<_>>(), vec![80, 23, 443]);
+
+        assert!(Cli::try_parse_from(["vajra", "scan", "-t", "127.0.0.1", "--top-ports", "0"]).is_err());
+        assert!(Cli::try_parse_from(["vajra", "scan", "-t", "127.0.0.1", "--top-ports", "1001"]).is_err());
+    }
+
+    #[test]
+    fn test_host_port_targets() {
+        let ip = |last| IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+        let (ips, pinned) = split_endpoints(vec![(ip(1), Some(22)), (ip(2), None), (ip(3), Some(53))]);
+        assert_eq!(ips, [ip(2)]);
+        assert_eq!(pinned, [(ip(1), 22), (ip(3), 53)]);
+    }
+
     #[test]
     fn test_parse_ports_single() {
-        let ports = parse_ports("80").unwrap();
+        let ports: Vec<u16> = parse_ports("80").unwrap().iter().collect();
         assert_eq!(ports, vec![80]);
     }
 
     #[test]
     fn test_parse_ports_multiple() {
-        let ports = parse_ports("22,80,443").unwrap();
+        let ports: Vec<u16> = parse_ports("22,80,443").unwrap().iter().collect();
         assert_eq!(ports, vec![22, 80, 443]);
     }
 
     #[test]
     fn test_parse_ports_range() {
-        let ports = parse_ports("1-3").unwrap();
+        let ports: Vec<u16> = parse_ports("1-3").unwrap().iter().collect();
         assert_eq!(ports, vec![1, 2, 3]);
     }
 
     #[test]
     fn test_parse_ports_mixed() {
-        let ports = parse_ports("22,80-82,443").unwrap();
+        let ports: Vec<u16> = parse_ports("22,80-82,443").unwrap().iter().collect();
         assert_eq!(ports, vec![22, 80, 81, 82, 443]);
     }
 
     #[test]
     fn test_parse_ports_whitespace() {
-        let ports = parse_ports(" 80 , 443 ").unwrap();
+        let ports: Vec<u16> = parse_ports(" 80 , 443 ").unwrap().iter().collect();
         assert_eq!(ports, vec![80, 443]);
     }
 
@@ -194,20 +653,38 @@ mod tests {
         assert!(parse_ports("90-80").is_err());
     }
 
+    #[test]
+    fn test_parse_ports_rejects_zero() {
+        let err = parse_ports("0").unwrap_err();
+        assert!(err.to_string().contains("port 0"), "{}", err);
+        assert!(parse_ports("22,0").is_err());
+        assert!(parse_ports("0-100").is_err());
+    }
+
+    #[test]
+    fn test_parse_ports_dedup() {
+        let ports: Vec<u16> = parse_ports("80,80").unwrap().iter().collect();
+        assert_eq!(ports, vec![80]);
+
+        // Overlapping ranges are scanned once, in ascending order
+        let ports: Vec<u16> = parse_ports("443,1-5,3-7,4").unwrap().iter().collect();
+        assert_eq!(ports, vec![1, 2, 3, 4, 5, 6, 7, 443]);
+    }
+
     #[test]
     fn test_parse_targets_async() {
         let rt = Runtime::new().unwrap();
     // single IP via target_resolver
-    let ips = rt.block_on(TargetResolver::resolve_targets("8.8.8.8")).unwrap();
+    let ips = rt.block_on(TargetResolver::new().resolve_targets("8.8.8.8")).unwrap();
     assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(8,8,8,8))]);
 
     // multiple comma separated
-    let ips = rt.block_on(TargetResolver::resolve_targets("8.8.8.8,1.1.1.1")).unwrap();
+    let ips = rt.block_on(TargetResolver::new().resolve_targets("8.8.8.8,1.1.1.1")).unwrap();
     assert!(ips.contains(&IpAddr::V4(Ipv4Addr::new(8,8,8,8))));
     assert!(ips.contains(&IpAddr::V4(Ipv4Addr::new(1,1,1,1))));
 
     // range
-    let ips = rt.block_on(TargetResolver::resolve_targets("192.168.1.1-192.168.1.3")).unwrap();
+    let ips = rt.block_on(TargetResolver::new().resolve_targets("192.168.1.1-192.168.1.3")).unwrap();
     assert_eq!(ips.len(), 3);
     }
 }
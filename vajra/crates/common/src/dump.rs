@@ -0,0 +1,198 @@
+//! Raw response dump for offline analysis
+//!
+//! Scanners can record the raw bytes they receive (captured reply packets,
+//! TCP banners) keyed by target. The file starts with the 4-byte magic
+//! `VJDP` and a version byte, followed by frames:
+//!
+//! ```text
+//! kind: u8 | family: u8 (4 or 6) | ip: 4 or 16 bytes | port: u16
+//! | timestamp_us: u64 | len: u32 | data: len bytes
+//! ```
+//!
+//! Integers are big-endian; the timestamp is microseconds since the Unix
+//! epoch.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{VajraError, VajraResult};
+
+const MAGIC: &[u8; 4] = b"VJDP";
+const VERSION: u8 = 1;
+
+/// What a dump record holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpKind {
+    /// Captured reply packet, starting at the IP header
+    Packet = 1,
+    /// Bytes read from an open TCP connection
+    Banner = 2,
+}
+
+/// One framed record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpRecord {
+    pub kind: DumpKind,
+    pub ip: IpAddr,
+    pub port: u16,
+    pub timestamp: SystemTime,
+    pub data: Vec<u8>,
+}
+
+/// Thread-safe writer of dump records, shared between scanners
+pub struct DumpWriter {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl DumpWriter {
+    /// Create (or truncate) a dump file
+    pub fn create(path: &Path) -> VajraResult<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write the file header to `out` and wrap it
+    pub fn new<W: Write + Send + 'static>(mut out: W) -> VajraResult<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        Ok(Self {
+            out: Mutex::new(Box::new(out)),
+        })
+    }
+
+    /// Append a record for `ip:port`, timestamped now
+    pub fn write(&self, kind: DumpKind, ip: IpAddr, port: u16, data: &[u8]) -> VajraResult<()> {
+        let timestamp_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+
+        let mut frame = Vec::with_capacity(32 + data.len());
+        frame.push(kind as u8);
+        match ip {
+            IpAddr::V4(v4) => {
+                frame.push(4);
+                frame.extend_from_slice(&v4.octets());
+            }
+            IpAddr::V6(v6) => {
+                frame.push(6);
+                frame.extend_from_slice(&v6.octets());
+            }
+        }
+        frame.extend_from_slice(&port.to_be_bytes());
+        frame.extend_from_slice(&timestamp_us.to_be_bytes());
+        frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(data);
+
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        out.write_all(&frame)?;
+        Ok(())
+    }
+
+    /// Flush buffered records to the underlying writer
+    pub fn flush(&self) -> VajraResult<()> {
+        self.out.lock().unwrap_or_else(|e| e.into_inner()).flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for DumpWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Read every record from a dump file
+pub fn read_dump_file(path: &Path) -> VajraResult<Vec<DumpRecord>> {
+    read_dump(File::open(path)?)
+}
+
+/// Read every record from a dump stream
+pub fn read_dump<R: Read>(mut input: R) -> VajraResult<Vec<DumpRecord>> {
+    let mut buf = Vec::new();
+    input.read_to_end(&mut buf)?;
+
+    if buf.len() < 5 || &buf[..4] != MAGIC {
+        return Err(VajraError::Parse("not a vajra dump file".to_string()));
+    }
+    if buf[4] != VERSION {
+        return Err(VajraError::Parse(format!("unsupported dump version {}", buf[4])));
+    }
+
+    let mut rest = &buf[5..];
+    let mut records = Vec::new();
+    while !rest.is_empty() {
+        records.push(read_frame(&mut rest)?);
+    }
+    Ok(records)
+}
+
+/// Read one frame from the front of `input`
+fn read_frame(input: &mut &[u8]) -> VajraResult<DumpRecord> {
+    let [kind, family] = read_array(input)?;
+    let kind = match kind {
+        1 => DumpKind::Packet,
+        2 => DumpKind::Banner,
+        other => return Err(VajraError::Parse(format!("unknown dump record kind {}", other))),
+    };
+    let ip = match family {
+        4 => IpAddr::V4(Ipv4Addr::from(read_array::<4>(input)?)),
+        6 => IpAddr::V6(Ipv6Addr::from(read_array::<16>(input)?)),
+        other => return Err(VajraError::Parse(format!("unknown address family {}", other))),
+    };
+    let port = u16::from_be_bytes(read_array(input)?);
+    let timestamp_us = u64::from_be_bytes(read_array(input)?);
+    let len = u32::from_be_bytes(read_array(input)?) as usize;
+    if input.len() < len {
+        return Err(VajraError::Parse("truncated dump record".to_string()));
+    }
+    let (data, rest) = input.split_at(len);
+    *input = rest;
+
+    Ok(DumpRecord {
+        kind,
+        ip,
+        port,
+        timestamp: UNIX_EPOCH + Duration::from_micros(timestamp_us),
+        data: data.to_vec(),
+    })
+}
+
+fn read_array<const N: usize>(input: &mut &[u8]) -> VajraResult<[u8; N]> {
+    let mut out = [0u8; N];
+    input
+        .read_exact(&mut out)
+        .map_err(|_| VajraError::Parse("truncated dump record".to_string()))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_round_trip() {
+        let path = std::env::temp_dir().join(format!("vajra_dump_{}.bin", std::process::id()));
+        let v4 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+        let writer = DumpWriter::create(&path).unwrap();
+        writer.write(DumpKind::Packet, v4, 80, &[0x45, 0, 0, 40]).unwrap();
+        writer.write(DumpKind::Banner, v6, 22, b"SSH-2.0-OpenSSH_9.6\r\n").unwrap();
+        drop(writer);
+
+        let records = read_dump_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].kind, records[0].ip, records[0].port), (DumpKind::Packet, v4, 80));
+        assert_eq!(records[0].data, vec![0x45, 0, 0, 40]);
+        assert_eq!((records[1].kind, records[1].ip, records[1].port), (DumpKind::Banner, v6, 22));
+        assert_eq!(records[1].data, b"SSH-2.0-OpenSSH_9.6\r\n");
+
+        assert!(read_dump(&b"nope"[..]).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Result formats shared by report writers and `Storage` exports
+//!
+//! The CLI's CSV and JSON reports and the default `Storage::export_*`
+//! methods build their rows and documents here, so a stored job exports
+//! exactly like a report of the same results.
+
+use std::collections::BTreeMap;
+
+use crate::types::ProbeResult;
+
+/// Header row of the CSV format
+pub const CSV_HEADER: &str = "ip,port,state,service,product,version,banner,rtt_ms,http_status,http_title,hostname";
+
+/// Quote `value` as an RFC 4180 field: wrapped in double quotes, with
+/// embedded quotes doubled
+pub fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// CSV row for `result` (without the line ending), matching [`CSV_HEADER`]
+pub fn csv_row(result: &ProbeResult) -> String {
+    let service = result.service.as_ref();
+    let product = service.and_then(|s| s.product.as_deref()).unwrap_or("");
+    let version = service.and_then(|s| s.version.as_deref()).unwrap_or("");
+    let http_status = service.and_then(|s| s.http_status).map(|c| c.to_string()).unwrap_or_default();
+    let http_title = service.and_then(|s| s.http_title.as_deref()).unwrap_or("");
+
+    // Banners stay on one line
+    let banner = result
+        .banner
+        .as_ref()
+        .map(|b| b.replace('\n', " ").replace('\r', ""))
+        .unwrap_or_default();
+
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{}",
+        result.target.ip,
+        result.target.port,
+        result.state,
+        csv_field(service.map(|s| s.service.as_str()).unwrap_or("")),
+        csv_field(product),
+        csv_field(version),
+        csv_field(&banner),
+        result.rtt.as_millis(),
+        http_status,
+        csv_field(http_title),
+        csv_field(result.hostname.as_deref().unwrap_or(""))
+    )
+}
+
+/// Results as JSON values grouped by IP address, as in the JSON report
+pub fn results_by_ip<'a, I>(results: I) -> serde_json::Result<BTreeMap<String, Vec<serde_json::Value>>>
+where
+    I: IntoIterator<Item = &'a ProbeResult>,
+{
+    let mut by_ip: BTreeMap<String, Vec<serde_json::Value>> = BTreeMap::new();
+    for result in results {
+        by_ip
+            .entry(result.target.ip.to_string())
+            .or_default()
+            .push(serde_json::to_value(result)?);
+    }
+    Ok(by_ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PortState, ServiceMatch, Target};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_csv_row_quotes_text_fields() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 80);
+        let result = ProbeResult::new(target, PortState::Open)
+            .with_banner("HTTP/1.1 200 OK\r\nServer: \"x\"".to_string())
+            .with_service(ServiceMatch::new("http").with_http_status(200));
+
+        assert_eq!(
+            csv_row(&result),
+            r#"10.0.0.1,80,open,"http","","","HTTP/1.1 200 OK Server: ""x""",0,200,"","""#
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Lazy IP × port target sets
+//!
+//! A full-range scan of many hosts is tens of millions of targets. Rather
+//! than materializing them in a `ScanJob`, a [`TargetGrid`] keeps the host
+//! list and the port ranges and yields targets on demand. A shuffled grid
+//! visits the same targets in a seeded pseudo-random order, still one at a
+//! time (see [`Permutation`]).
+
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Protocol, Target};
+
+/// Port set stored as ranges, e.g. `22,80-90` is two ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortSpec {
+    ranges: Vec<RangeInclusive<u16>>,
+}
+
+impl PortSpec {
+    /// Create an empty spec.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an inclusive range (a single port is `p..=p`).
+    pub fn push_range(&mut self, range: RangeInclusive<u16>) {
+        self.ranges.push(range);
+    }
+
+    /// Ranges in the order they were added.
+    #[inline]
+    #[must_use]
+    pub fn ranges(&self) -> &[RangeInclusive<u16>] {
+        &self.ranges
+    }
+
+    /// Number of ports (duplicates across ranges are counted each time).
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(|r| r.clone().count()).sum()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.iter().all(|r| r.is_empty())
+    }
+
+    /// Iterate ports in order without allocating.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.ranges.iter().cloned().flatten()
+    }
+
+    /// Sort the ranges and merge overlapping or adjacent ones, so every
+    /// port appears once, in ascending order.
+    #[must_use]
+    pub fn normalized(mut self) -> Self {
+        self.ranges.retain(|r| !r.is_empty());
+        self.ranges.sort_by_key(|r| *r.start());
+        let mut merged: Vec<RangeInclusive<u16>> = Vec::with_capacity(self.ranges.len());
+        for range in self.ranges {
+            match merged.last_mut() {
+                Some(last) if u32::from(*range.start()) <= u32::from(*last.end()) + 1 => {
+                    if range.end() > last.end() {
+                        *last = *last.start()..=*range.end();
+                    }
+                }
+                _ => merged.push(range),
+            }
+        }
+        self.ranges = merged;
+        self
+    }
+}
+
+impl FromIterator<u16> for PortSpec {
+    fn from_iter<I: IntoIterator<Item = u16>>(ports: I) -> Self {
+        Self {
+            ranges: ports.into_iter().map(|p| p..=p).collect(),
+        }
+    }
+}
+
+/// Every IP × every port, generated lazily (host-major order, like
+/// [`ScanJob::from_ips_and_ports`](crate::ScanJob::from_ips_and_ports),
+/// unless shuffled).
+#[derive(Debug, Clone)]
+pub struct TargetGrid {
+    ips: Vec<IpAddr>,
+    ports: PortSpec,
+    protocol: Protocol,
+    protocol_for: Option<fn(u16) -> Protocol>,
+    shuffle: Option<u64>,
+    /// Running port count at the end of each range, for indexing a
+    /// shuffled grid
+    port_ends: Vec<usize>,
+}
+
+impl TargetGrid {
+    #[must_use]
+    pub fn new(ips: Vec<IpAddr>, ports: PortSpec, protocol: Protocol) -> Self {
+        Self {
+            ips,
+            ports,
+            protocol,
+            protocol_for: None,
+            shuffle: None,
+            port_ends: Vec::new(),
+        }
+    }
+
+    /// Visit targets in a pseudo-random order derived from `seed`, mixing
+    /// hosts and ports. The same seed always gives the same order.
+    #[must_use]
+    pub fn with_shuffle(mut self, seed: u64) -> Self {
+        self.port_ends = self
+            .ports
+            .ranges
+            .iter()
+            .scan(0, |end, r| {
+                *end += r.clone().count();
+                Some(*end)
+            })
+            .collect();
+        self.shuffle = Some(seed);
+        self
+    }
+
+    /// Pick each target's protocol from its port instead of the fixed one.
+    #[must_use]
+    pub fn with_protocol_for(mut self, protocol_for: fn(u16) -> Protocol) -> Self {
+        self.protocol_for = Some(protocol_for);
+        self
+    }
+
+    /// Total number of targets (saturating).
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ips.len().saturating_mul(self.ports.len())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate targets without consuming the grid.
+    pub fn targets(&self) -> Box<dyn Iterator<Item = Target> + '_> {
+        if let Some(seed) = self.shuffle {
+            return Box::new(Permutation::new(self.len() as u64, seed).map(move |i| self.target_at(i)));
+        }
+        let (protocol, protocol_for) = (self.protocol, self.protocol_for);
+        Box::new(self.ips.iter().flat_map(move |&ip| {
+            self.ports
+                .iter()
+                .map(move |port| target(ip, port, protocol, protocol_for))
+        }))
+    }
+
+    /// Consume the grid into an owning target iterator.
+    pub fn into_targets(self) -> Box<dyn Iterator<Item = Target> + Send + 'static> {
+        if let Some(seed) = self.shuffle {
+            let order = Permutation::new(self.len() as u64, seed);
+            return Box::new(order.map(move |i| self.target_at(i)));
+        }
+        let Self {
+            ips,
+            ports,
+            protocol,
+            protocol_for,
+            ..
+        } = self;
+        Box::new(ips.into_iter().flat_map(move |ip| {
+            ports
+                .ranges
+                .clone()
+                .into_iter()
+                .flatten()
+                .map(move |port| target(ip, port, protocol, protocol_for))
+        }))
+    }
+
+    /// Target at host-major position `index` (needs `port_ends`)
+    fn target_at(&self, index: u64) -> Target {
+        let port_count = self.port_ends.last().copied().unwrap_or(0) as u64;
+        let ip = self.ips[(index / port_count) as usize];
+        let offset = (index % port_count) as usize;
+        let range = self.port_ends.partition_point(|&end| end <= offset);
+        let range_start = if range == 0 { 0 } else { self.port_ends[range - 1] };
+        let port = self.ports.ranges[range].start() + (offset - range_start) as u16;
+        target(ip, port, self.protocol, self.protocol_for)
+    }
+}
+
+/// Seeded bijective shuffle of `0..len`, yielding one index at a time
+/// without storing the order. Indices go through a 4-round Feistel network
+/// over the next even power of two; results past `len` are fed back in
+/// (cycle walking) until one lands inside.
+#[derive(Debug, Clone)]
+pub struct Permutation {
+    len: u64,
+    half_bits: u32,
+    keys: [u64; 4],
+    next: u64,
+}
+
+impl Permutation {
+    #[must_use]
+    pub fn new(len: u64, seed: u64) -> Self {
+        let bits = (64 - len.saturating_sub(1).leading_zeros()).max(2);
+        let mut state = seed;
+        Self {
+            len,
+            half_bits: bits.div_ceil(2),
+            keys: std::array::from_fn(|_| splitmix64(&mut state)),
+            next: 0,
+        }
+    }
+
+    /// Position `index` (< `len`) is mapped to
+    #[must_use]
+    pub fn get(&self, index: u64) -> u64 {
+        let mut x = self.encrypt(index);
+        while x >= self.len {
+            x = self.encrypt(x);
+        }
+        x
+    }
+
+    fn encrypt(&self, x: u64) -> u64 {
+        let mask = u64::MAX >> (64 - self.half_bits);
+        let (mut left, mut right) = (x >> self.half_bits, x & mask);
+        for key in self.keys {
+            let mut state = right ^ key;
+            (left, right) = (right, left ^ (splitmix64(&mut state) & mask));
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+impl Iterator for Permutation {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.next >= self.len {
+            return None;
+        }
+        let index = self.get(self.next);
+        self.next += 1;
+        Some(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = usize::try_from(self.len - self.next).unwrap_or(usize::MAX);
+        (left, Some(left))
+    }
+}
+
+/// SplitMix64 step: advances `state` and returns a well-mixed output
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[inline]
+fn target(ip: IpAddr, port: u16, protocol: Protocol, protocol_for: Option<fn(u16) -> Protocol>) -> Target {
+    let protocol = protocol_for.map_or(protocol, |f| f(port));
+    Target::new(ip, port).with_protocol(protocol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_full_range_grid_is_lazy() {
+        let ips: Vec<IpAddr> = (0..1000u32).map(|i| IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + i))).collect();
+        let mut ports = PortSpec::new();
+        ports.push_range(1..=65535);
+
+        let grid = TargetGrid::new(ips, ports, Protocol::TCP);
+        assert_eq!(grid.len(), 65_535_000);
+        // Only the host list and one range are stored
+        assert_eq!(grid.ports.ranges().len(), 1);
+
+        let mut targets = grid.into_targets();
+        let first: Vec<u16> = targets.by_ref().take(3).map(|t| t.port).collect();
+        assert_eq!(first, [1, 2, 3]);
+        // Nothing was collected up front: the iterator's lower bound is tiny
+        assert!(targets.size_hint().0 < 65_535);
+
+        // Host-major order crosses to the next host after the last port
+        let next_host = targets.nth(65_532).unwrap();
+        assert_eq!((next_host.ip, next_host.port), (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 1));
+    }
+
+    #[test]
+    fn test_normalized_merges_overlaps() {
+        let mut ports = PortSpec::new();
+        for range in [3..=7, 80..=80, 1..=5, 80..=80, 8..=9, 65535..=65535, 65534..=65535] {
+            ports.push_range(range);
+        }
+        let ports = ports.normalized();
+        assert_eq!(ports.ranges(), &[1..=9, 80..=80, 65534..=65535]);
+        assert_eq!(ports.len(), 12);
+    }
+
+    #[test]
+    fn test_permutation_is_bijective() {
+        for len in [0u64, 1, 2, 3, 1000, 4097] {
+            let mut seen: Vec<u64> = Permutation::new(len, 42).collect();
+            seen.sort_unstable();
+            assert_eq!(seen, (0..len).collect::<Vec<_>>(), "len {}", len);
+        }
+        let a: Vec<u64> = Permutation::new(1000, 7).collect();
+        assert_eq!(a, Permutation::new(1000, 7).collect::<Vec<_>>());
+        assert_ne!(a, Permutation::new(1000, 8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_shuffled_grid_covers_every_target() {
+        let ips: Vec<IpAddr> = (1..=4u8).map(|i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect();
+        let mut ports = PortSpec::new();
+        ports.push_range(20..=25);
+        ports.push_range(80..=80);
+        ports.push_range(8000..=8009);
+        let grid = TargetGrid::new(ips, ports, Protocol::TCP);
+        let ordered: Vec<(IpAddr, u16)> = grid.targets().map(|t| (t.ip, t.port)).collect();
+
+        let shuffled = grid.clone().with_shuffle(1234);
+        let order: Vec<(IpAddr, u16)> = shuffled.targets().map(|t| (t.ip, t.port)).collect();
+        assert_ne!(order, ordered);
+        // Hosts are interleaved rather than finished one at a time
+        assert!(order[..8].iter().any(|&(ip, _)| ip != order[0].0));
+        let again: Vec<(IpAddr, u16)> = shuffled.into_targets().map(|t| (t.ip, t.port)).collect();
+        assert_eq!(again, order);
+
+        let mut sorted = order;
+        sorted.sort();
+        let mut expected = ordered;
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_grid_protocol_for() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let grid = TargetGrid::new(vec![ip], [53, 80].into_iter().collect(), Protocol::TCP)
+            .with_protocol_for(|port| if port == 53 { Protocol::UDP } else { Protocol::TCP });
+        let protocols: Vec<Protocol> = grid.targets().map(|t| t.protocol).collect();
+        assert_eq!(protocols, [Protocol::UDP, Protocol::TCP]);
+    }
+}
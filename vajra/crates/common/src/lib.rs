@@ -9,15 +9,27 @@
 //! - High-performance batch operations
 //! - Comprehensive error handling
 
+pub mod dump;
 pub mod error;
+pub mod export;
+pub mod grid;
+pub mod pcap;
+pub mod rtt;
+pub mod state;
 pub mod traits;
 pub mod types;
 
 // Re-export commonly used types
+pub use dump::{DumpKind, DumpRecord, DumpWriter};
 pub use error::{VajraError, VajraResult};
-pub use traits::{Fingerprinter, RateLimiter, Scanner, Storage};
+pub use grid::{Permutation, PortSpec, TargetGrid};
+pub use pcap::PcapWriter;
+pub use rtt::{RttEstimator, RTT_MIN_SAMPLES};
+pub use state::ScanState;
+pub use traits::{Fingerprinter, OutputSink, RateLimiter, Scanner, Storage};
 pub use types::{
-    PortState, ProbeResult, Protocol, ScanJob, ScanOptions, ScanStats, ServiceMatch, Target,
+    PortState, ProbeResult, Protocol, ScanJob, ScanOptions, ScanStats, ServiceMatch, ShardInfo,
+    Target, TlsInfo,
 };
 
 /// Version information
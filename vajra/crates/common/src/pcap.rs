@@ -0,0 +1,194 @@
+//! libpcap export of probe and response packets
+//!
+//! Packets are written with link type `LINKTYPE_RAW` (101): each record
+//! starts at the IP header, so raw probes and captured replies can be
+//! stored as-is. Scanners without raw access can build stand-in packets
+//! for what they observed with `synthesize_tcp_packet`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::VajraResult;
+
+/// Link type for packets that begin with an IPv4 or IPv6 header
+pub const LINKTYPE_RAW: u32 = 101;
+const SNAPLEN: u32 = 65535;
+
+/// TCP flag bits accepted by `synthesize_tcp_packet`
+pub mod tcp_flags {
+    pub const FIN: u8 = 0x01;
+    pub const SYN: u8 = 0x02;
+    pub const RST: u8 = 0x04;
+    pub const PSH: u8 = 0x08;
+    pub const ACK: u8 = 0x10;
+}
+
+/// Thread-safe libpcap file writer, shared between scanners
+pub struct PcapWriter {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl PcapWriter {
+    /// Create (or truncate) a pcap file
+    pub fn create(path: &Path) -> VajraResult<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write the pcap global header to `out` and wrap it
+    pub fn new<W: Write + Send + 'static>(mut out: W) -> VajraResult<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes()); // magic (microsecond timestamps)
+        header.extend_from_slice(&2u16.to_le_bytes()); // version major
+        header.extend_from_slice(&4u16.to_le_bytes()); // version minor
+        header.extend_from_slice(&0i32.to_le_bytes()); // timezone offset
+        header.extend_from_slice(&0u32.to_le_bytes()); // timestamp accuracy
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        out.write_all(&header)?;
+        Ok(Self {
+            out: Mutex::new(Box::new(out)),
+        })
+    }
+
+    /// Append one packet (starting at the IP header), timestamped now
+    pub fn write_packet(&self, packet: &[u8]) -> VajraResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let incl_len = packet.len().min(SNAPLEN as usize);
+
+        let mut record = Vec::with_capacity(16 + incl_len);
+        record.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&now.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&(incl_len as u32).to_le_bytes());
+        record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        record.extend_from_slice(&packet[..incl_len]);
+
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        out.write_all(&record)?;
+        Ok(())
+    }
+
+    /// Flush buffered packets to the underlying writer
+    pub fn flush(&self) -> VajraResult<()> {
+        self.out.lock().unwrap_or_else(|e| e.into_inner()).flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for PcapWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Build an IP + TCP packet with valid checksums for an observed event.
+/// Returns an empty packet if `src` and `dst` are different IP versions.
+pub fn synthesize_tcp_packet(
+    src: SocketAddr,
+    dst: SocketAddr,
+    flags: u8,
+    seq: u32,
+    ack: u32,
+    payload: &[u8],
+) -> Vec<u8> {
+    let tcp_len = 20 + payload.len();
+    let mut tcp = Vec::with_capacity(tcp_len);
+    tcp.extend_from_slice(&src.port().to_be_bytes());
+    tcp.extend_from_slice(&dst.port().to_be_bytes());
+    tcp.extend_from_slice(&seq.to_be_bytes());
+    tcp.extend_from_slice(&ack.to_be_bytes());
+    tcp.push(0x50); // data offset: 5 words
+    tcp.push(flags);
+    tcp.extend_from_slice(&65535u16.to_be_bytes()); // window
+    tcp.extend_from_slice(&[0, 0, 0, 0]); // checksum, urgent pointer
+    tcp.extend_from_slice(payload);
+
+    let mut packet = match (src.ip(), dst.ip()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let mut ip = vec![0x45, 0];
+            ip.extend_from_slice(&((20 + tcp_len) as u16).to_be_bytes());
+            ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]); // id, DF, TTL, TCP, checksum
+            ip.extend_from_slice(&s.octets());
+            ip.extend_from_slice(&d.octets());
+            let ip_sum = checksum(&[&ip]);
+            ip[10..12].copy_from_slice(&ip_sum.to_be_bytes());
+
+            let pseudo = [&s.octets()[..], &d.octets()[..], &[0, 6], &(tcp_len as u16).to_be_bytes()].concat();
+            let tcp_sum = checksum(&[&pseudo, &tcp]);
+            tcp[16..18].copy_from_slice(&tcp_sum.to_be_bytes());
+            ip
+        }
+        (IpAddr::V6(s), IpAddr::V6(d)) => {
+            let mut ip = vec![0x60, 0, 0, 0];
+            ip.extend_from_slice(&(tcp_len as u16).to_be_bytes());
+            ip.extend_from_slice(&[6, 64]); // next header TCP, hop limit
+            ip.extend_from_slice(&s.octets());
+            ip.extend_from_slice(&d.octets());
+
+            let pseudo = [&s.octets()[..], &d.octets()[..], &(tcp_len as u32).to_be_bytes(), &[0, 0, 0, 6]].concat();
+            let tcp_sum = checksum(&[&pseudo, &tcp]);
+            tcp[16..18].copy_from_slice(&tcp_sum.to_be_bytes());
+            ip
+        }
+        _ => return Vec::new(),
+    };
+    packet.extend_from_slice(&tcp);
+    packet
+}
+
+/// Internet checksum over the concatenation of `parts`
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let bytes = parts.iter().flat_map(|p| p.iter().copied());
+    let mut sum = 0u32;
+    let mut high = None;
+    for b in bytes {
+        match high.take() {
+            None => high = Some(b),
+            Some(h) => sum += u32::from(u16::from_be_bytes([h, b])),
+        }
+    }
+    if let Some(h) = high {
+        sum += u32::from(h) << 8;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcap_header_and_record() {
+        let path = std::env::temp_dir().join(format!("vajra_pcap_{}.pcap", std::process::id()));
+        let src: SocketAddr = "10.0.0.1:40000".parse().unwrap();
+        let dst: SocketAddr = "10.0.0.2:80".parse().unwrap();
+        let syn = synthesize_tcp_packet(src, dst, tcp_flags::SYN, 0, 0, &[]);
+        assert_eq!(syn.len(), 40);
+        // Checksums over a valid header fold to zero
+        assert_eq!(checksum(&[&syn[..20]]), 0);
+
+        let writer = PcapWriter::create(&path).unwrap();
+        writer.write_packet(&syn).unwrap();
+        drop(writer);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let le32 = |off: usize| u32::from_le_bytes(bytes[off..off + 4].try_into().unwrap());
+
+        assert_eq!(bytes.len(), 24 + 16 + syn.len());
+        assert_eq!(le32(0), 0xa1b2_c3d4);
+        assert_eq!(&bytes[4..8], &[2, 0, 4, 0]);
+        assert_eq!(le32(16), SNAPLEN);
+        assert_eq!(le32(20), LINKTYPE_RAW);
+
+        // Record header: incl_len == orig_len == packet length, then the packet
+        assert_eq!((le32(32), le32(36)), (40, 40));
+        assert_eq!(&bytes[40..], &syn[..]);
+    }
+}
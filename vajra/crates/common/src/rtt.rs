@@ -0,0 +1,146 @@
+//! Round-trip time estimation for adaptive probe timeouts
+//!
+//! Follows TCP's retransmission timer (RFC 6298), as nmap does for its
+//! probe timeouts: a smoothed RTT and its mean deviation are updated from
+//! each answered probe, and the timeout is `srtt + 4 * rttvar`. Until a
+//! few replies have been seen the configured timeout is used instead.
+
+use crate::types::{PortState, ProbeResult};
+use std::time::Duration;
+
+/// Replies needed before the estimate replaces the initial timeout
+pub const RTT_MIN_SAMPLES: u32 = 4;
+
+/// Weight of the deviation in the timeout (RFC 6298's K)
+const RTTVAR_FACTOR: u32 = 4;
+
+/// Rolling RTT estimate and the probe timeout derived from it
+#[derive(Debug, Clone)]
+pub struct RttEstimator {
+    initial: Duration,
+    min: Duration,
+    max: Duration,
+    srtt: Option<Duration>,
+    rttvar: Duration,
+    samples: u32,
+}
+
+impl RttEstimator {
+    /// Estimator that answers `initial` until it has enough samples, then
+    /// keeps the timeout between 100ms and 10s (nmap's defaults)
+    pub fn new(initial: Duration) -> Self {
+        Self {
+            initial,
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            srtt: None,
+            rttvar: Duration::ZERO,
+            samples: 0,
+        }
+    }
+
+    /// Keep the adaptive timeout within `min..=max`
+    pub fn with_bounds(mut self, min: Duration, max: Duration) -> Self {
+        self.min = min;
+        self.max = max.max(min);
+        self
+    }
+
+    /// Add one measured round trip
+    pub fn record(&mut self, rtt: Duration) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                let deviation = srtt.max(rtt) - srtt.min(rtt);
+                self.rttvar = (self.rttvar * 3 + deviation) / 4;
+                self.srtt = Some((srtt * 7 + rtt) / 8);
+            }
+        }
+        self.samples += 1;
+    }
+
+    /// Add the RTT of `result` if the target answered. Unanswered probes
+    /// (filtered, open|filtered, skipped) only measure the timeout itself.
+    pub fn record_result(&mut self, result: &ProbeResult) {
+        let answered = !matches!(result.state, PortState::Filtered | PortState::OpenFiltered | PortState::Skipped);
+        if answered && !result.rtt.is_zero() {
+            self.record(result.rtt);
+        }
+    }
+
+    /// Timeout for the next probe
+    pub fn timeout(&self) -> Duration {
+        match self.srtt {
+            Some(srtt) if self.samples >= RTT_MIN_SAMPLES => {
+                (srtt + self.rttvar * RTTVAR_FACTOR).clamp(self.min, self.max)
+            }
+            _ => self.initial,
+        }
+    }
+
+    /// Smoothed RTT, once any reply has been seen
+    pub fn srtt(&self) -> Option<Duration> {
+        self.srtt
+    }
+
+    /// Number of replies recorded
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Target;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_initial_until_enough_samples() {
+        let ms = Duration::from_millis;
+        let mut rtt = RttEstimator::new(ms(2000));
+        for _ in 0..RTT_MIN_SAMPLES - 1 {
+            rtt.record(ms(20));
+        }
+        assert_eq!(rtt.timeout(), ms(2000));
+
+        rtt.record(ms(20));
+        // srtt 20ms plus 4 * ~4.2ms deviation, raised to the 100ms floor
+        assert_eq!(rtt.timeout(), ms(100));
+        assert_eq!(rtt.srtt(), Some(ms(20)));
+        let unbounded = rtt.clone().with_bounds(Duration::ZERO, ms(10_000));
+        assert_eq!(unbounded.timeout(), ms(20) + Duration::from_micros(16_875));
+    }
+
+    #[test]
+    fn test_timeout_tracks_slow_links_within_bounds() {
+        let ms = Duration::from_millis;
+        let mut rtt = RttEstimator::new(ms(500)).with_bounds(ms(50), ms(3000));
+        for sample in [600, 800, 700, 900, 650] {
+            rtt.record(ms(sample));
+        }
+        // Slower than the initial timeout: a fixed 500ms would filter these hosts
+        let timeout = rtt.timeout();
+        assert!(timeout > ms(900) && timeout <= ms(3000), "{:?}", timeout);
+
+        let mut fast = RttEstimator::new(ms(500)).with_bounds(ms(50), ms(3000));
+        (0..10).for_each(|_| fast.record(Duration::from_micros(300)));
+        assert_eq!(fast.timeout(), ms(50));
+    }
+
+    #[test]
+    fn test_only_answered_results_count() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80);
+        let mut rtt = RttEstimator::new(Duration::from_secs(1));
+        let rtt_of = |state| ProbeResult::new(target.clone(), state).with_rtt(Duration::from_millis(5));
+        rtt.record_result(&rtt_of(PortState::Filtered));
+        rtt.record_result(&rtt_of(PortState::OpenFiltered));
+        assert_eq!(rtt.samples(), 0);
+        rtt.record_result(&rtt_of(PortState::Closed));
+        rtt.record_result(&rtt_of(PortState::Open));
+        assert_eq!(rtt.samples(), 2);
+    }
+}
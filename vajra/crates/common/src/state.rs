@@ -0,0 +1,118 @@
+//! Resumable scan state
+//!
+//! A [`ScanState`] records what a scan covers (hosts × ports), which targets
+//! are done, and their results. Saved periodically during a long scan, it
+//! lets a restarted scan skip the finished targets instead of starting over.
+//! The target list is kept as the host list and port ranges a
+//! [`TargetGrid`](crate::TargetGrid) is built from, so the file stays small
+//! however many targets the scan has.
+
+use std::collections::HashSet;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{VajraError, VajraResult};
+use crate::grid::PortSpec;
+use crate::types::{ProbeResult, Target};
+
+/// Progress of one scan, serialized as JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanState {
+    /// Hosts to scan
+    pub ips: Vec<IpAddr>,
+    /// Ports scanned on every host
+    pub ports: PortSpec,
+    /// Targets that already have a result
+    pub completed: HashSet<Target>,
+    /// Results so far
+    pub results: Vec<ProbeResult>,
+}
+
+impl ScanState {
+    /// State of a scan that has not started yet
+    #[must_use]
+    pub fn new(ips: Vec<IpAddr>, ports: PortSpec) -> Self {
+        Self {
+            ips,
+            ports,
+            ..Default::default()
+        }
+    }
+
+    /// Total number of targets the scan covers
+    #[must_use]
+    pub fn total_targets(&self) -> usize {
+        self.ips.len().saturating_mul(self.ports.len())
+    }
+
+    /// Add results, marking their targets completed
+    pub fn record<I: IntoIterator<Item = ProbeResult>>(&mut self, results: I) {
+        for result in results {
+            if self.completed.insert(result.target.clone()) {
+                self.results.push(result);
+            }
+        }
+    }
+
+    /// Write the state to `path`. The file is replaced atomically, so a
+    /// crash mid-save leaves the previous state intact.
+    pub fn save(&self, path: &Path) -> VajraResult<()> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| VajraError::Storage(format!("failed to serialize scan state: {}", e)))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a state written by [`save`](ScanState::save)
+    pub fn load(path: &Path) -> VajraResult<Self> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data)
+            .map_err(|e| VajraError::Parse(format!("invalid scan state {}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortState;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut state = ScanState::new(vec![ip], [22, 80, 443].into_iter().collect());
+        state.record([
+            ProbeResult::new(Target::new(ip, 22), PortState::Open),
+            ProbeResult::new(Target::new(ip, 80), PortState::Closed),
+            // Recording a target twice keeps the first result
+            ProbeResult::new(Target::new(ip, 22), PortState::Filtered),
+        ]);
+
+        let path = std::env::temp_dir().join(format!("vajra-state-{}.json", std::process::id()));
+        state.save(&path).unwrap();
+        let loaded = ScanState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.total_targets(), 3);
+        assert_eq!(loaded.ports, state.ports);
+        assert_eq!(loaded.results.len(), 2);
+        assert_eq!(loaded.results[0].state, PortState::Open);
+        assert!(loaded.completed.contains(&Target::new(ip, 80)));
+        assert!(!loaded.completed.contains(&Target::new(ip, 443)));
+    }
+
+    #[test]
+    fn test_load_rejects_garbage() {
+        let path = std::env::temp_dir().join(format!("vajra-state-bad-{}.json", std::process::id()));
+        std::fs::write(&path, b"not json").unwrap();
+        let err = ScanState::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, VajraError::Parse(_)));
+    }
+}
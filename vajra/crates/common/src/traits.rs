@@ -5,9 +5,11 @@
 //! - Async-first design
 //! - Minimal allocations in trait signatures
 
-use crate::types::{ProbeResult, ScanOptions, ServiceMatch, Target};
+use crate::export;
+use crate::types::{ProbeResult, ScanOptions, ScanStats, ServiceMatch, Target};
 use anyhow::Result;
 use async_trait::async_trait;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Core scanner trait - all scanners must implement this
@@ -16,6 +18,15 @@ pub trait Scanner: Send + Sync {
     /// Scan a single target
     async fn scan(&self, target: &Target) -> Result<ProbeResult>;
 
+    /// Scan a single target, waiting `timeout` for a reply instead of the
+    /// scanner's configured timeout (e.g. one derived from measured RTTs).
+    ///
+    /// Default implementation ignores `timeout` and calls scan().
+    async fn scan_with_timeout(&self, target: &Target, timeout: Duration) -> Result<ProbeResult> {
+        let _ = timeout;
+        self.scan(target).await
+    }
+
     /// Batch scan multiple targets (optimized for high throughput)
     ///
     /// Default implementation calls scan() for each target, but
@@ -67,16 +78,17 @@ pub trait Fingerprinter: Send + Sync {
     }
 }
 
-/// Storage backend trait for persisting results
+/// Storage backend trait for persisting results, keyed by the id of the
+/// job that produced them
 #[async_trait]
 pub trait Storage: Send + Sync {
     /// Store a single result
-    async fn store_result(&self, result: &ProbeResult) -> Result<()>;
+    async fn store_result(&self, job_id: Uuid, result: &ProbeResult) -> Result<()>;
 
     /// Store multiple results (batch operation)
-    async fn store_batch(&self, results: &[ProbeResult]) -> Result<()> {
+    async fn store_batch(&self, job_id: Uuid, results: &[ProbeResult]) -> Result<()> {
         for result in results {
-            self.store_result(result).await?;
+            self.store_result(job_id, result).await?;
         }
         Ok(())
     }
@@ -84,26 +96,27 @@ pub trait Storage: Send + Sync {
     /// Get all results for a job
     async fn get_results(&self, job_id: Uuid) -> Result<Vec<ProbeResult>>;
 
-    /// Export results as JSON
-    async fn export_json(&self, job_id: Uuid) -> Result<String>;
+    /// Export results as JSON: the job id, the result count, and the
+    /// results grouped by IP as in the JSON report
+    async fn export_json(&self, job_id: Uuid) -> Result<String> {
+        let results = self.get_results(job_id).await?;
+        let output = serde_json::json!({
+            "job_id": job_id,
+            "total_scanned": results.len(),
+            "results": export::results_by_ip(&results)?,
+        });
+        Ok(serde_json::to_string_pretty(&output)?)
+    }
 
-    /// Export results as CSV
+    /// Export results as CSV, in the same columns as the CSV report
     async fn export_csv(&self, job_id: Uuid) -> Result<String> {
         let results = self.get_results(job_id).await?;
-        let mut csv = String::from("target,port,protocol,state,rtt_ms,banner\n");
-        
-        for result in results {
-            csv.push_str(&format!(
-                "{},{},{},{},{},{}\n",
-                result.target.ip,
-                result.target.port,
-                result.target.protocol.as_str(),
-                result.state,
-                result.rtt.as_millis(),
-                result.banner.as_deref().unwrap_or("")
-            ));
+        let mut csv = String::from(export::CSV_HEADER);
+        csv.push('\n');
+        for result in &results {
+            csv.push_str(&export::csv_row(result));
+            csv.push('\n');
         }
-        
         Ok(csv)
     }
 
@@ -111,7 +124,33 @@ pub trait Storage: Send + Sync {
     async fn clear_results(&self, job_id: Uuid) -> Result<()>;
 }
 
+/// Destination for scan results: a report format, a SIEM feed, a database.
+///
+/// Results are written one at a time, then `finish` is called once with
+/// the run's totals. Sinks that need every result before writing (sorted
+/// or grouped output) buffer until `finish`.
+pub trait OutputSink {
+    /// Record one result
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()>;
+
+    /// Complete the output; `summary.elapsed` is the scan duration
+    fn finish(&mut self, summary: &ScanStats) -> Result<()>;
+}
+
+impl<S: OutputSink + ?Sized> OutputSink for Box<S> {
+    fn write_result(&mut self, result: &ProbeResult) -> Result<()> {
+        (**self).write_result(result)
+    }
+
+    fn finish(&mut self, summary: &ScanStats) -> Result<()> {
+        (**self).finish(summary)
+    }
+}
+
 /// Rate limiter trait for controlling scan speed
+///
+/// Limiters are shared by every worker of a run, so all methods take
+/// `&self`; implementations use interior mutability.
 #[async_trait]
 pub trait RateLimiter: Send + Sync {
     /// Wait until next operation is allowed
@@ -121,7 +160,13 @@ pub trait RateLimiter: Send + Sync {
     fn current_rate(&self) -> f64;
 
     /// Update rate limit
-    fn set_rate(&mut self, rate: u64);
+    fn set_rate(&self, rate: u64);
+
+    /// Report whether a probe was answered (`timed_out == false`) or lost,
+    /// for limiters that adapt to loss. Ignored by default.
+    async fn record_outcome(&self, timed_out: bool) {
+        let _ = timed_out;
+    }
 }
 
 #[cfg(test)]
@@ -7,13 +7,14 @@
 //! - minimal panicking/allocations in methods used during scanning
 //!
 //! NOTE: kept `SystemTime` for `timestamp` so serde-friendly serialization is preserved.
-//! If you want absolute microsecond accuracy for internal timing, consider adding
-//! an `Instant` field annotated `#[serde(skip)]` in follow-up changes.
+//! Precise internal timing uses the companion `Instant`s `started_at` (probe
+//! sent) and `monotonic` (result created), which are `#[serde(skip)]` and
+//! therefore only present on results created in-process.
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::net::IpAddr;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use uuid::Uuid;
 
 /// Supported network protocols.
@@ -21,6 +22,7 @@ use uuid::Uuid;
 pub enum Protocol {
     TCP,
     UDP,
+    SCTP,
 }
 
 impl Protocol {
@@ -30,6 +32,7 @@ impl Protocol {
         match self {
             Protocol::TCP => "tcp",
             Protocol::UDP => "udp",
+            Protocol::SCTP => "sctp",
         }
     }
 }
@@ -41,6 +44,10 @@ pub enum PortState {
     Closed,
     Filtered,
     OpenFiltered,
+    /// Reachable but open/closed undetermined (ACK scan)
+    Unfiltered,
+    /// Not probed (see `ProbeResult::reason`)
+    Skipped,
 }
 
 impl fmt::Display for PortState {
@@ -50,6 +57,8 @@ impl fmt::Display for PortState {
             PortState::Closed => "closed",
             PortState::Filtered => "filtered",
             PortState::OpenFiltered => "open|filtered",
+            PortState::Unfiltered => "unfiltered",
+            PortState::Skipped => "skipped",
         };
         f.write_str(s)
     }
@@ -119,8 +128,33 @@ pub struct ProbeResult {
     pub banner: Option<String>,
     pub service: Option<ServiceMatch>,
     pub timestamp: SystemTime,
+    /// Monotonic creation time for ordering/diagnostics (not serialized).
+    #[serde(skip)]
+    pub monotonic: Option<Instant>,
+    /// Monotonic time the probe was sent, set by the scanner (not serialized).
+    #[serde(skip)]
+    pub started_at: Option<Instant>,
     /// Round-trip time measured for the probe (Duration::ZERO when unknown).
     pub rtt: Duration,
+    /// Index of the shard that produced this result (distributed scans only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard: Option<u32>,
+    /// Why the state was assigned: the cause when not probed normally (e.g.
+    /// "host-timeout"), or the reply that decided it for raw scans (e.g.
+    /// "syn-ack flags=0x12").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Coarse OS family guessed from the reply's TTL and TCP window (raw
+    /// scans only), e.g. "Linux".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os_guess: Option<String>,
+    /// Name of the registered scanner that produced this result (e.g.
+    /// "syn", or "tcp" when a fallback confirmed it).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scanner: Option<String>,
+    /// Reverse-DNS (PTR) name of the target's address, when looked up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
 }
 
 impl ProbeResult {
@@ -134,7 +168,14 @@ impl ProbeResult {
             banner: None,
             service: None,
             timestamp: SystemTime::now(),
+            monotonic: Some(Instant::now()),
+            started_at: None,
             rtt: Duration::ZERO,
+            shard: None,
+            reason: None,
+            os_guess: None,
+            scanner: None,
+            hostname: None,
         }
     }
 
@@ -146,6 +187,14 @@ impl ProbeResult {
         self
     }
 
+    /// Builder: record when the probe was sent.
+    #[inline]
+    #[must_use]
+    pub fn with_started_at(mut self, started_at: Instant) -> Self {
+        self.started_at = Some(started_at);
+        self
+    }
+
     /// Builder: attach banner string.
     #[inline]
     #[must_use]
@@ -154,6 +203,46 @@ impl ProbeResult {
         self
     }
 
+    /// Builder: tag with the producing shard index.
+    #[inline]
+    #[must_use]
+    pub fn with_shard(mut self, shard: u32) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Builder: record why the result was not probed normally.
+    #[inline]
+    #[must_use]
+    pub fn with_reason<S: Into<String>>(mut self, reason: S) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Builder: record the guessed OS family of the target host.
+    #[inline]
+    #[must_use]
+    pub fn with_os_guess<S: Into<String>>(mut self, os: S) -> Self {
+        self.os_guess = Some(os.into());
+        self
+    }
+
+    /// Builder: record the name of the scanner that produced the result.
+    #[inline]
+    #[must_use]
+    pub fn with_scanner<S: Into<String>>(mut self, scanner: S) -> Self {
+        self.scanner = Some(scanner.into());
+        self
+    }
+
+    /// Builder: record the target's reverse-DNS name.
+    #[inline]
+    #[must_use]
+    pub fn with_hostname<S: Into<String>>(mut self, hostname: S) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
     /// Builder: attach service match.
     #[inline]
     #[must_use]
@@ -162,6 +251,28 @@ impl ProbeResult {
         self
     }
 
+    /// Monotonic instant the result is ordered by: when its probe was sent
+    /// if the scanner recorded it, else when the result was created.
+    #[inline]
+    pub fn instant(&self) -> Option<Instant> {
+        self.started_at.or(self.monotonic)
+    }
+
+    /// Time elapsed between `earlier` and this result, by [`instant`].
+    ///
+    /// Uses the monotonic clock when both results carry it, falling back to
+    /// the wall-clock `timestamp` (e.g. for deserialized results).
+    /// Returns `None` if `earlier` is actually later.
+    ///
+    /// [`instant`]: ProbeResult::instant
+    #[inline]
+    pub fn elapsed_since(&self, earlier: &ProbeResult) -> Option<Duration> {
+        match (self.instant(), earlier.instant()) {
+            (Some(now), Some(then)) => now.checked_duration_since(then),
+            _ => self.timestamp.duration_since(earlier.timestamp).ok(),
+        }
+    }
+
     /// Update RTT after construction (avoids reallocation).
     #[inline]
     pub fn set_rtt(&mut self, rtt: Duration) {
@@ -201,6 +312,34 @@ pub struct ServiceMatch {
     pub product: Option<String>,
     pub version: Option<String>,
     pub confidence: f32,
+    /// HTTP status code from the banner's status line (HTTP services only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// HTML `<title>` when the banner includes body bytes (HTTP services only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_title: Option<String>,
+    /// Negotiated TLS parameters and leaf certificate (TLS probe only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsInfo>,
+    /// The reply came from a CDN or cloud WAF edge (named in `product`),
+    /// not from the origin server (HTTP services only).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cdn_edge: bool,
+}
+
+/// Result of a TLS handshake with a service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsInfo {
+    /// Negotiated protocol version, e.g. "TLSv1.3".
+    pub version: String,
+    /// Negotiated cipher suite, e.g. "TLS13_AES_128_GCM_SHA256".
+    pub cipher: String,
+    /// Common name of the leaf certificate's subject.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_cn: Option<String>,
+    /// DNS names and IP addresses from the leaf certificate's SAN extension.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subject_alt_names: Vec<String>,
 }
 
 impl ServiceMatch {
@@ -212,6 +351,10 @@ impl ServiceMatch {
             product: None,
             version: None,
             confidence: 1.0,
+            http_status: None,
+            http_title: None,
+            tls: None,
+            cdn_edge: false,
         }
     }
 
@@ -235,6 +378,37 @@ impl ServiceMatch {
         self.confidence = confidence;
         self
     }
+
+    #[inline]
+    #[must_use]
+    pub fn with_http_status(mut self, status: u16) -> Self {
+        self.http_status = Some(status);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_http_title(mut self, title: String) -> Self {
+        self.http_title = Some(title);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_tls(mut self, tls: TlsInfo) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Mark the service as a CDN edge named `cdn`, which becomes the product
+    #[inline]
+    #[must_use]
+    pub fn with_cdn_edge(mut self, cdn: String) -> Self {
+        self.product = Some(cdn);
+        self.version = None;
+        self.cdn_edge = true;
+        self
+    }
 }
 
 /// Scan job: collection of targets + options + metadata.
@@ -247,6 +421,20 @@ pub struct ScanJob {
     pub options: ScanOptions,
     pub priority: u8,
     pub created_at: SystemTime,
+    /// Set when this job is one shard of a larger, split job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard: Option<ShardInfo>,
+}
+
+/// Position of a shard within a split job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardInfo {
+    /// Id of the job that was split.
+    pub parent: Uuid,
+    /// Zero-based shard index.
+    pub index: u32,
+    /// Total number of shards.
+    pub count: u32,
 }
 
 impl ScanJob {
@@ -259,6 +447,7 @@ impl ScanJob {
             options: ScanOptions::default(),
             priority: 0,
             created_at: SystemTime::now(),
+            shard: None,
         }
     }
 
@@ -281,6 +470,65 @@ impl ScanJob {
     pub fn target_count(&self) -> usize {
         self.targets.len()
     }
+
+    /// Build a job from the IP × port cross-product (IP-major order).
+    #[must_use]
+    pub fn from_ips_and_ports(ips: &[IpAddr], ports: &[u16], protocol: Protocol) -> Self {
+        let mut targets = Vec::with_capacity(ips.len().saturating_mul(ports.len()));
+        for &ip in ips {
+            for &port in ports {
+                targets.push(Target::new(ip, port).with_protocol(protocol));
+            }
+        }
+        Self::new(targets)
+    }
+
+    /// Split into jobs of at most `chunk_size` targets (a size of 0 is treated as 1).
+    ///
+    /// Each chunk gets a fresh id and inherits options and priority.
+    #[must_use]
+    pub fn split(&self, chunk_size: usize) -> Vec<ScanJob> {
+        self.targets
+            .chunks(chunk_size.max(1))
+            .map(|chunk| {
+                ScanJob::new(chunk.to_vec())
+                    .with_options(self.options.clone())
+                    .with_priority(self.priority)
+            })
+            .collect()
+    }
+
+    /// Deterministically split into exactly `count` shards for distributed workers.
+    ///
+    /// Targets are assigned in contiguous, balanced blocks (sizes differ by at
+    /// most one), so every target lands in exactly one shard. Shards may be
+    /// empty when `count` exceeds the target count; a `count` of 0 is treated as 1.
+    #[must_use]
+    pub fn shard(&self, count: u32) -> Vec<ScanJob> {
+        let count = count.max(1);
+        let n = count as usize;
+        let base = self.targets.len() / n;
+        let extra = self.targets.len() % n;
+
+        let mut start = 0;
+        (0..count)
+            .map(|index| {
+                let len = base + usize::from((index as usize) < extra);
+                let targets = self.targets[start..start + len].to_vec();
+                start += len;
+
+                let mut job = ScanJob::new(targets)
+                    .with_options(self.options.clone())
+                    .with_priority(self.priority);
+                job.shard = Some(ShardInfo {
+                    parent: self.id,
+                    index,
+                    count,
+                });
+                job
+            })
+            .collect()
+    }
 }
 
 /// Scan behaviour tuning options.
@@ -293,6 +541,10 @@ pub struct ScanOptions {
     pub fingerprint: bool,
     pub max_concurrency: usize,
     pub rate_limit: Option<u64>, // packets per second
+    /// Scan targets in a pseudo-random order from this seed instead of the
+    /// order given.
+    #[serde(default)]
+    pub shuffle_seed: Option<u64>,
 }
 
 impl Default for ScanOptions {
@@ -303,6 +555,7 @@ impl Default for ScanOptions {
             fingerprint: false,
             max_concurrency: 10_000,
             rate_limit: None,
+            shuffle_seed: None,
         }
     }
 }
@@ -318,6 +571,7 @@ impl ScanOptions {
             fingerprint: false,
             max_concurrency: 20_000,
             rate_limit: None,
+            shuffle_seed: None,
         }
     }
 
@@ -331,6 +585,7 @@ impl ScanOptions {
             fingerprint: true,
             max_concurrency: 5_000,
             rate_limit: None,
+            shuffle_seed: None,
         }
     }
 
@@ -344,6 +599,7 @@ impl ScanOptions {
             fingerprint: false,
             max_concurrency: 100,
             rate_limit: Some(100),
+            shuffle_seed: None,
         }
     }
 }
@@ -359,6 +615,15 @@ pub struct ScanStats {
     pub open_ports: usize,
     pub closed_ports: usize,
     pub filtered_ports: usize,
+    /// Ports that stayed silent where silence may mean open (SYN, FIN,
+    /// NULL, Xmas, SCTP and UDP scans)
+    #[serde(default)]
+    pub open_filtered_ports: usize,
+    /// Ports an ACK scan found reachable (a RST came back)
+    #[serde(default)]
+    pub unfiltered_ports: usize,
+    #[serde(default)]
+    pub skipped_ports: usize,
     pub errors: usize,
     pub average_rtt: Duration,
     pub elapsed: Duration,
@@ -398,15 +663,18 @@ impl ScanStats {
 
     /// Incrementally update stats. Intentionally minimal allocations.
     ///
-    /// Note: `result.rtt` should be Duration::ZERO if not measured.
+    /// Note: `result.rtt` should be Duration::ZERO if not measured. Scanners
+    /// measure it on the monotonic clock from `started_at`, so wall-clock
+    /// steps (e.g. NTP) during a scan do not skew the average.
     pub fn update(&mut self, result: &ProbeResult) {
         self.scanned = self.scanned.saturating_add(1);
         match result.state {
             PortState::Open => self.open_ports = self.open_ports.saturating_add(1),
             PortState::Closed => self.closed_ports = self.closed_ports.saturating_add(1),
-            PortState::Filtered | PortState::OpenFiltered => {
-                self.filtered_ports = self.filtered_ports.saturating_add(1)
-            }
+            PortState::Filtered => self.filtered_ports = self.filtered_ports.saturating_add(1),
+            PortState::OpenFiltered => self.open_filtered_ports = self.open_filtered_ports.saturating_add(1),
+            PortState::Unfiltered => self.unfiltered_ports = self.unfiltered_ports.saturating_add(1),
+            PortState::Skipped => self.skipped_ports = self.skipped_ports.saturating_add(1),
         }
 
         // Update rolling average RTT using integer arithmetic:
@@ -422,6 +690,37 @@ impl ScanStats {
             self.average_rtt = Duration::from_nanos(new_avg_nanos as u64);
         }
     }
+
+    /// Fold in the stats of another part of the same scan (e.g. one shard).
+    ///
+    /// Counters are summed, `average_rtt` is weighted by each side's
+    /// `scanned` count (same integer-nanos arithmetic as [`update`]), and
+    /// `elapsed` is the longer of the two since parts run in parallel.
+    ///
+    /// [`update`]: ScanStats::update
+    pub fn merge(&mut self, other: &ScanStats) {
+        let (n_self, n_other) = (self.scanned as u128, other.scanned as u128);
+        let tot = self
+            .average_rtt
+            .as_nanos()
+            .saturating_mul(n_self)
+            .saturating_add(other.average_rtt.as_nanos().saturating_mul(n_other));
+        // Both sides empty: keep the current average
+        if let Some(avg_nanos) = tot.checked_div(n_self + n_other) {
+            self.average_rtt = Duration::from_nanos(avg_nanos as u64);
+        }
+
+        self.total_targets = self.total_targets.saturating_add(other.total_targets);
+        self.scanned = self.scanned.saturating_add(other.scanned);
+        self.open_ports = self.open_ports.saturating_add(other.open_ports);
+        self.closed_ports = self.closed_ports.saturating_add(other.closed_ports);
+        self.filtered_ports = self.filtered_ports.saturating_add(other.filtered_ports);
+        self.open_filtered_ports = self.open_filtered_ports.saturating_add(other.open_filtered_ports);
+        self.unfiltered_ports = self.unfiltered_ports.saturating_add(other.unfiltered_ports);
+        self.skipped_ports = self.skipped_ports.saturating_add(other.skipped_ports);
+        self.errors = self.errors.saturating_add(other.errors);
+        self.elapsed = self.elapsed.max(other.elapsed);
+    }
 }
 
 #[cfg(test)]
@@ -429,6 +728,44 @@ mod tests {
     use super::*;
     use std::net::Ipv4Addr;
 
+    #[test]
+    fn probe_result_monotonic_ordering() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80);
+        let first = ProbeResult::new(target.clone(), PortState::Open);
+        std::thread::sleep(Duration::from_millis(2));
+        let second = ProbeResult::new(target, PortState::Closed);
+
+        assert!(first.monotonic.is_some() && second.monotonic.is_some());
+        assert!(first.monotonic < second.monotonic);
+        assert!(second.elapsed_since(&first).unwrap() >= Duration::from_millis(2));
+        assert_eq!(first.elapsed_since(&second), None);
+
+        // Monotonic time is not serialized; elapsed falls back to wall clock
+        let json = serde_json::to_string(&second).unwrap();
+        let restored: ProbeResult = serde_json::from_str(&json).unwrap();
+        assert!(restored.monotonic.is_none());
+        assert!(restored.elapsed_since(&first).is_some());
+    }
+
+    #[test]
+    fn probe_result_orders_by_send_time() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80);
+        let sent = Instant::now();
+        std::thread::sleep(Duration::from_millis(2));
+        let early = ProbeResult::new(target.clone(), PortState::Open);
+        // Sent later but finished first
+        let late = ProbeResult::new(target, PortState::Closed).with_started_at(Instant::now());
+        let early = early.with_started_at(sent);
+
+        assert_eq!(early.instant(), Some(sent));
+        assert!(late.elapsed_since(&early).unwrap() >= Duration::from_millis(2));
+        assert_eq!(early.elapsed_since(&late), None);
+
+        let json = serde_json::to_string(&early).unwrap();
+        let restored: ProbeResult = serde_json::from_str(&json).unwrap();
+        assert!(restored.started_at.is_none());
+    }
+
     #[test]
     fn target_creation() {
         let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
@@ -474,5 +811,129 @@ mod tests {
         // average_rtt should be between 5 and 15
         assert!(stats.average_rtt >= Duration::from_millis(5));
         assert!(stats.average_rtt <= Duration::from_millis(15));
+
+        // An ACK scan's RST means reachable, not filtered
+        stats.update(&ProbeResult::new(t.clone(), PortState::Unfiltered));
+        assert_eq!((stats.filtered_ports, stats.unfiltered_ports), (0, 1));
+        stats.update(&ProbeResult::new(t, PortState::OpenFiltered));
+        assert_eq!((stats.filtered_ports, stats.open_filtered_ports), (0, 1));
+        let mut merged = ScanStats::new(0);
+        merged.merge(&stats);
+        assert_eq!((merged.open_filtered_ports, merged.unfiltered_ports), (1, 1));
+    }
+
+    #[test]
+    fn scan_job_cross_product() {
+        let ips = [
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)),
+        ];
+        let job = ScanJob::from_ips_and_ports(&ips, &[22, 80, 443, 8080], Protocol::UDP);
+        assert_eq!(job.target_count(), 12);
+        assert_eq!(job.targets[0], Target::udp(ips[0], 22));
+        assert_eq!(job.targets[4], Target::udp(ips[1], 22));
+        assert!(job.targets.iter().all(|t| t.protocol == Protocol::UDP));
+    }
+
+    #[test]
+    fn scan_job_split_chunks() {
+        let ips = [IpAddr::V4(Ipv4Addr::LOCALHOST)];
+        let ports: Vec<u16> = (1..=10).collect();
+        let job = ScanJob::from_ips_and_ports(&ips, &ports, Protocol::TCP).with_priority(7);
+
+        let chunks = job.split(4);
+        assert_eq!(chunks.iter().map(ScanJob::target_count).collect::<Vec<_>>(), vec![4, 4, 2]);
+        assert!(chunks.iter().all(|c| c.priority == 7 && c.id != job.id));
+
+        let rejoined: Vec<Target> = chunks.into_iter().flat_map(|c| c.targets).collect();
+        assert_eq!(rejoined, job.targets);
+
+        assert_eq!(job.split(0).len(), 10);
+        assert!(ScanJob::new(Vec::new()).split(4).is_empty());
+    }
+
+    #[test]
+    fn scan_job_shards_cover_all_targets_once() {
+        let ips: Vec<IpAddr> = (0..10).map(|i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect();
+        let ports: Vec<u16> = (1..=100).collect();
+        let job = ScanJob::from_ips_and_ports(&ips, &ports, Protocol::TCP);
+        assert_eq!(job.target_count(), 1000);
+
+        let shards = job.shard(4);
+        assert_eq!(shards.len(), 4);
+        for (i, shard) in shards.iter().enumerate() {
+            let info = shard.shard.unwrap();
+            assert_eq!(info.parent, job.id);
+            assert_eq!(info.index, i as u32);
+            assert_eq!(info.count, 4);
+            assert_eq!(shard.target_count(), 250);
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for target in shards.iter().flat_map(|s| s.targets.iter()) {
+            assert!(seen.insert(target.clone()), "duplicate target {}", target);
+        }
+        assert_eq!(seen.len(), 1000);
+
+        // Deterministic: same split every time
+        let again = job.shard(4);
+        assert!(shards.iter().zip(&again).all(|(a, b)| a.targets == b.targets));
+
+        // Uneven split stays balanced
+        let sizes: Vec<usize> = job.split(1000)[0].shard(3).iter().map(ScanJob::target_count).collect();
+        assert_eq!(sizes, vec![334, 333, 333]);
+    }
+
+    #[test]
+    fn scan_stats_merge_matches_single_pass() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let states = [
+            PortState::Open,
+            PortState::Closed,
+            PortState::Filtered,
+            PortState::Open,
+            PortState::Skipped,
+            PortState::OpenFiltered,
+        ];
+        let results: Vec<ProbeResult> = states
+            .iter()
+            .enumerate()
+            .map(|(i, &state)| {
+                ProbeResult::new(Target::new(ip, i as u16 + 1), state)
+                    .with_rtt(Duration::from_millis(10 * (i as u64 + 1)))
+            })
+            .collect();
+
+        let mut single = ScanStats::new(results.len());
+        results.iter().for_each(|r| single.update(r));
+
+        let (first, second) = results.split_at(4);
+        let mut merged = ScanStats::new(first.len());
+        first.iter().for_each(|r| merged.update(r));
+        merged.elapsed = Duration::from_secs(3);
+        let mut other = ScanStats::new(second.len());
+        second.iter().for_each(|r| other.update(r));
+        other.errors = 1;
+        other.elapsed = Duration::from_secs(5);
+        merged.merge(&other);
+
+        assert_eq!(merged.total_targets, single.total_targets);
+        assert_eq!(merged.scanned, single.scanned);
+        assert_eq!(merged.open_ports, single.open_ports);
+        assert_eq!(merged.closed_ports, single.closed_ports);
+        assert_eq!(merged.filtered_ports, single.filtered_ports);
+        assert_eq!(merged.skipped_ports, single.skipped_ports);
+        assert_eq!(merged.average_rtt, single.average_rtt);
+        assert_eq!(merged.average_rtt, Duration::from_millis(35));
+        assert_eq!(merged.errors, 1);
+        // Parts run side by side, so the slowest one is the scan's duration
+        assert_eq!(merged.elapsed, Duration::from_secs(5));
+
+        // Merging an empty part changes nothing
+        let before = merged.clone();
+        merged.merge(&ScanStats::default());
+        assert_eq!(merged.average_rtt, before.average_rtt);
+        assert_eq!(merged.scanned, before.scanned);
     }
 }
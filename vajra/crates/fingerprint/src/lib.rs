@@ -1,31 +1,134 @@
 //! Fingerprint Engine - Service detection and identification
 //!
 //! This module provides service detection capabilities including:
-//! - Port-based service identification
+//! - Port-based service identification, extensible with a loaded port table
 //! - Banner-based service detection
+//! - UDP reply detection (DNS, NTP, SNMP, NetBIOS)
 //! - Combined detection strategies
+//! - Global registry for custom rules and signatures
+//! - Signatures loaded from nmap-style probe files (`FingerprintEngine`)
+//! - Active service probing with an nmap-style probe database (`ServiceProbeDb`)
 
+mod port_table;
+mod probe_file;
+mod registry;
 mod service_detector;
+mod udp_detector;
 
+pub use port_table::{set_port_table, PortTable};
+pub use probe_file::{load_probe_file, parse_probe_file, ServiceProbe, ServiceProbeDb};
+pub use registry::{
+    clear_rules, register_rule, register_signatures, DetectionRule, RuleRegistry, Signature,
+};
+pub use udp_detector::detect_udp_service;
 pub use service_detector::{
     detect_service,
+    detect_service_for_protocol,
     detect_service_from_banner,
+    detect_service_from_bytes,
     detect_service_from_port,
+    detect_service_raw,
+    protocol_hint,
+    BUILTIN_PORT_SERVICES,
+    UDP_PORT_HINTS,
 };
 
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use vajra_common::{Fingerprinter, ProbeResult, ServiceMatch};
+
 /// Fingerprint Engine for advanced service detection
-pub struct FingerprintEngine;
+///
+/// Banners are matched against signatures loaded with `load_signatures`
+/// first (in file order), then against the built-in detection chain
+/// (`detect_service`). Like the rest of that chain, its port fallback is
+/// process-wide: install a port table with `set_port_table`.
+#[derive(Default)]
+pub struct FingerprintEngine {
+    signatures: Vec<Signature>,
+}
 
 impl FingerprintEngine {
     /// Create a new fingerprint engine
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Add signatures directly (evaluated after previously loaded ones)
+    pub fn add_signatures<I: IntoIterator<Item = Signature>>(&mut self, signatures: I) {
+        self.signatures.extend(signatures);
     }
 }
 
-impl Default for FingerprintEngine {
-    fn default() -> Self {
-        Self::new()
+#[async_trait]
+impl Fingerprinter for FingerprintEngine {
+    async fn identify(&self, result: &ProbeResult) -> Result<Option<ServiceMatch>> {
+        let banner = result.banner.as_deref();
+        if let Some(b) = banner {
+            if let Some(service) = self.signatures.iter().find_map(|s| s.matches(b)) {
+                return Ok(Some(service));
+            }
+        }
+        Ok(detect_service(result.target.port, banner))
+    }
+
+    /// Load `match` directives from an nmap-style probe file
+    async fn load_signatures(&mut self, path: &str) -> Result<()> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read probe file {}", path))?;
+        self.signatures.extend(parse_probe_file(&content)?);
+        Ok(())
+    }
+
+    fn signature_count(&self) -> usize {
+        self.signatures.len()
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use vajra_common::{PortState, Target};
+
+    #[tokio::test]
+    async fn test_engine_loads_and_identifies() {
+        let path = std::env::temp_dir().join(format!("vajra_probes_{}.txt", std::process::id()));
+        std::fs::write(&path, "match redis-custom m|^\\+PONG| p/Redis/\n").unwrap();
+
+        let mut engine = FingerprintEngine::new();
+        engine.load_signatures(path.to_str().unwrap()).await.unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(engine.signature_count(), 1);
+
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6379);
+        let result = ProbeResult::new(target.clone(), PortState::Open).with_banner("+PONG".to_string());
+        let svc = engine.identify(&result).await.unwrap().unwrap();
+        assert_eq!((svc.service.as_str(), svc.product.as_deref()), ("redis-custom", Some("Redis")));
+
+        // No banner match falls back to built-in detection
+        let result = ProbeResult::new(target, PortState::Open);
+        assert_eq!(engine.identify(&result).await.unwrap().unwrap().service, "redis");
+    }
+
+    #[tokio::test]
+    async fn test_engine_port_table_overrides_builtin() {
+        let path = std::env::temp_dir().join(format!("vajra_ports_{}.txt", std::process::id()));
+        std::fs::write(&path, "8080,tcp,jenkins\nsnmp-alt 8161/udp\n").unwrap();
+
+        set_port_table(Some(PortTable::load(&path).unwrap()));
+        std::fs::remove_file(&path).ok();
+        let engine = FingerprintEngine::new();
+
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        let result = ProbeResult::new(target, PortState::Open);
+        assert_eq!(engine.identify(&result).await.unwrap().unwrap().service, "jenkins");
+        assert_eq!(detect_service_for_protocol(8161, vajra_common::Protocol::UDP).unwrap().service, "snmp-alt");
+        // Ports missing from the table keep their built-in names
+        assert_eq!(detect_service_from_port(22).unwrap().service, "ssh");
+
+        set_port_table(None);
+        assert_eq!(detect_service_from_port(8080).unwrap().service, "http-proxy");
+    }
+}
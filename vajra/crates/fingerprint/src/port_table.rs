@@ -0,0 +1,191 @@
+//! User-supplied port → service overrides
+//!
+//! Loads a simple `port,protocol,service` CSV file, or nmap's `services`
+//! file (`name port/protocol [frequency] [# comment]`), so users can extend
+//! or replace the built-in port table without recompiling. Blank lines and
+//! `#` comments are ignored.
+//!
+//! A table installed with `set_port_table` is consulted by
+//! `detect_service_from_port` before the built-in table.
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use vajra_common::Protocol;
+
+/// Port → service overrides loaded from a file
+#[derive(Debug, Clone, Default)]
+pub struct PortTable {
+    entries: HashMap<(u16, Protocol), String>,
+}
+
+impl PortTable {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a table from a `port,protocol,service` CSV or nmap `services` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read port table {}", path.display()))?;
+        Self::parse(&content)
+    }
+
+    /// Parse table content: one `port,protocol,service` or nmap
+    /// `name port/protocol` entry per line (the two may be mixed).
+    /// nmap's placeholder name `unknown` is skipped so it never shadows a
+    /// built-in entry.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut entries = HashMap::new();
+
+        for (idx, line) in content.lines().enumerate() {
+            // nmap comments may contain commas, so strip them first
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if !line.contains(',') {
+                let (port, protocol, service) = parse_services_line(line)
+                    .with_context(|| format!("line {}: expected 'name port/protocol', got '{}'", idx + 1, line))?;
+                if service != "unknown" {
+                    entries.insert((port, protocol), service.to_string());
+                }
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 3 {
+                return Err(anyhow!(
+                    "line {}: expected 'port,protocol,service', got '{}'",
+                    idx + 1,
+                    line
+                ));
+            }
+
+            let port: u16 = fields[0]
+                .parse()
+                .with_context(|| format!("line {}: invalid port '{}'", idx + 1, fields[0]))?;
+            let protocol = parse_protocol(fields[1]).with_context(|| format!("line {}", idx + 1))?;
+            if fields[2].is_empty() {
+                return Err(anyhow!("line {}: empty service name", idx + 1));
+            }
+
+            entries.insert((port, protocol), fields[2].to_string());
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Look up the service name for a port/protocol pair
+    pub fn get(&self, port: u16, protocol: Protocol) -> Option<&str> {
+        self.entries.get(&(port, protocol)).map(String::as_str)
+    }
+
+    /// All entries sorted by port then protocol
+    pub fn entries(&self) -> Vec<(u16, Protocol, &str)> {
+        let mut entries: Vec<(u16, Protocol, &str)> = self
+            .entries
+            .iter()
+            .map(|((port, proto), service)| (*port, *proto, service.as_str()))
+            .collect();
+        entries.sort_by_key(|(port, proto, _)| (*port, proto.as_str()));
+        entries
+    }
+
+    /// Number of loaded entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Split an nmap `services` line into (port, protocol, service)
+fn parse_services_line(line: &str) -> Result<(u16, Protocol, &str)> {
+    let mut fields = line.split_whitespace();
+    let (Some(service), Some(port_proto)) = (fields.next(), fields.next()) else {
+        return Err(anyhow!("missing port/protocol"));
+    };
+    let (port, protocol) = port_proto
+        .split_once('/')
+        .ok_or_else(|| anyhow!("missing protocol in '{}'", port_proto))?;
+    let port = port.parse().with_context(|| format!("invalid port '{}'", port))?;
+    Ok((port, parse_protocol(protocol)?, service))
+}
+
+fn parse_protocol(name: &str) -> Result<Protocol> {
+    match name.to_ascii_lowercase().as_str() {
+        "tcp" => Ok(Protocol::TCP),
+        "udp" => Ok(Protocol::UDP),
+        "sctp" => Ok(Protocol::SCTP),
+        other => Err(anyhow!("unknown protocol '{}'", other)),
+    }
+}
+
+/// Table consulted by `detect_service_from_port` ahead of the built-ins
+static PORT_TABLE: Lazy<RwLock<Option<Arc<PortTable>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install `table` as the process-wide override table; `None` restores
+/// the built-in defaults.
+pub fn set_port_table(table: Option<PortTable>) {
+    *PORT_TABLE.write().unwrap_or_else(|e| e.into_inner()) = table.map(Arc::new);
+}
+
+/// Service name for a port/protocol pair in the installed table, if any
+pub(crate) fn lookup_port_table(port: u16, protocol: Protocol) -> Option<String> {
+    PORT_TABLE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()?
+        .get(port, protocol)
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_table() {
+        let table = PortTable::parse("# custom\n2222,tcp,ssh\n\n5353, udp, mdns\n").unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(2222, Protocol::TCP), Some("ssh"));
+        assert_eq!(table.get(5353, Protocol::UDP), Some("mdns"));
+        assert_eq!(table.get(5353, Protocol::TCP), None);
+    }
+
+    #[test]
+    fn test_parse_nmap_services() {
+        let content = "\
+# Fields: name port/proto frequency comment
+tcpmux\t1/tcp\t0.001995\t# TCP Port Service Multiplexer, RFC 1078
+unknown\t3/tcp\t0.001000
+domain\t53/udp\t0.213496\t# Domain Name Server
+sctp-app\t2905/sctp\t0.000000
+9999,tcp,custom-admin
+";
+        let table = PortTable::parse(content).unwrap();
+        assert_eq!(table.len(), 4);
+        assert_eq!(table.get(1, Protocol::TCP), Some("tcpmux"));
+        assert_eq!(table.get(53, Protocol::UDP), Some("domain"));
+        assert_eq!(table.get(2905, Protocol::SCTP), Some("sctp-app"));
+        assert_eq!(table.get(9999, Protocol::TCP), Some("custom-admin"));
+        assert_eq!(table.get(3, Protocol::TCP), None);
+    }
+
+    #[test]
+    fn test_parse_port_table_errors() {
+        assert!(PortTable::parse("abc,tcp,ssh").is_err());
+        assert!(PortTable::parse("22,icmp,ssh").is_err());
+        assert!(PortTable::parse("ssh 22").is_err());
+        assert!(PortTable::parse("ssh x/tcp").is_err());
+        assert!(PortTable::parse("22,tcp").is_err());
+    }
+}
@@ -0,0 +1,475 @@
+//! nmap-style service probe files
+//!
+//! Supports the practical subset of `nmap-service-probes` needed to run
+//! probes: `Probe`, `ports`, `match` and `softmatch`. Other directives
+//! (`rarity`, `sslports`, `totalwaitms`, ...) and comments are skipped.
+//! `match`/`softmatch` lines before the first `Probe` belong to an implicit
+//! NULL probe, so plain signature files work too. A match directive looks
+//! like:
+//!
+//! ```text
+//! match ssh m|^SSH-([\d.]+)-OpenSSH_([\w.]+)|i p/OpenSSH/ v/$2/
+//! ```
+//!
+//! The pattern may use any delimiter after `m` and takes the `i`
+//! (case-insensitive) and `s` (dot matches newline) flags. From the
+//! version info, `p/product/` and `v/version/` are kept; both may reference
+//! capture groups. Patterns using PCRE features the `regex` crate lacks
+//! (backreferences, lookaround) are skipped.
+
+use anyhow::{anyhow, Context, Result};
+use regex::RegexBuilder;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Instant};
+use vajra_common::{Protocol, ServiceMatch};
+
+use crate::registry::Signature;
+
+/// Upper bound on response bytes read per probe
+const MAX_RESPONSE: usize = 4096;
+
+/// One `Probe` directive with the directives that follow it
+#[derive(Debug, Clone)]
+pub struct ServiceProbe {
+    pub protocol: Protocol,
+    pub name: String,
+    /// Bytes sent after connecting (empty for the NULL probe)
+    pub payload: Vec<u8>,
+    /// Ports the probe is meant for; empty means every port
+    pub ports: Vec<RangeInclusive<u16>>,
+    pub matches: Vec<Signature>,
+    pub softmatches: Vec<Signature>,
+}
+
+impl ServiceProbe {
+    fn new(protocol: Protocol, name: String, payload: Vec<u8>) -> Self {
+        Self {
+            protocol,
+            name,
+            payload,
+            ports: Vec::new(),
+            matches: Vec::new(),
+            softmatches: Vec::new(),
+        }
+    }
+
+    /// Whether the probe should be sent to `port`
+    pub fn applies_to(&self, port: u16) -> bool {
+        self.ports.is_empty() || self.ports.iter().any(|r| r.contains(&port))
+    }
+
+    /// Match a response: hard matches first, then softmatches
+    pub fn match_response(&self, response: &str) -> Option<ServiceMatch> {
+        self.matches
+            .iter()
+            .chain(&self.softmatches)
+            .find_map(|s| s.matches(response))
+    }
+}
+
+/// Probes loaded from an `nmap-service-probes`-style file
+#[derive(Debug, Clone, Default)]
+pub struct ServiceProbeDb {
+    probes: Vec<ServiceProbe>,
+}
+
+impl ServiceProbeDb {
+    /// Load a probe database from a file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read probe file {}", path.display()))?;
+        Self::parse(&content)
+    }
+
+    /// Parse probe file content
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut probes: Vec<ServiceProbe> = Vec::new();
+
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            let (directive, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let parsed = match directive {
+                "Probe" => parse_probe(rest).map(|probe| probes.push(probe)),
+                "ports" => parse_ports(rest).map(|ports| current(&mut probes).ports = ports),
+                "match" => parse_signature(rest).map(|sig| current(&mut probes).matches.extend(sig)),
+                "softmatch" => {
+                    parse_signature(rest).map(|sig| current(&mut probes).softmatches.extend(sig))
+                }
+                _ => Ok(()),
+            };
+            parsed.with_context(|| format!("line {}", idx + 1))?;
+        }
+
+        Ok(Self { probes })
+    }
+
+    /// All probes in file order
+    pub fn probes(&self) -> &[ServiceProbe] {
+        &self.probes
+    }
+
+    /// Number of probes
+    pub fn len(&self) -> usize {
+        self.probes.len()
+    }
+
+    /// Whether the database has no probes
+    pub fn is_empty(&self) -> bool {
+        self.probes.is_empty()
+    }
+
+    /// Probes to send to `port` over `protocol`, in file order
+    pub fn probes_for(&self, port: u16, protocol: Protocol) -> impl Iterator<Item = &ServiceProbe> {
+        self.probes
+            .iter()
+            .filter(move |p| p.protocol == protocol && p.applies_to(port))
+    }
+
+    /// Run the applicable TCP probes against an open port.
+    ///
+    /// Each probe gets a fresh connection and `timeout` to answer. The first
+    /// hard match wins; otherwise the first softmatch seen is returned.
+    pub async fn probe_service(&self, addr: SocketAddr, timeout: Duration) -> Option<ServiceMatch> {
+        let mut soft = None;
+        for probe in self.probes_for(addr.port(), Protocol::TCP) {
+            // A port that stops accepting connections won't answer later probes either
+            let response = send_probe(addr, &probe.payload, timeout).await.ok()?;
+            if response.is_empty() {
+                continue;
+            }
+            let response = String::from_utf8_lossy(&response);
+            if let Some(service) = probe.matches.iter().find_map(|s| s.matches(&response)) {
+                return Some(service);
+            }
+            if soft.is_none() {
+                soft = probe.softmatches.iter().find_map(|s| s.matches(&response));
+            }
+        }
+        soft
+    }
+}
+
+/// The probe that directives attach to, creating the implicit NULL probe if needed
+fn current(probes: &mut Vec<ServiceProbe>) -> &mut ServiceProbe {
+    if probes.is_empty() {
+        probes.push(ServiceProbe::new(Protocol::TCP, "NULL".to_string(), Vec::new()));
+    }
+    probes.last_mut().expect("probe list is non-empty")
+}
+
+/// Connect, send `payload` and collect the reply until EOF, a full buffer or `timeout`
+async fn send_probe(addr: SocketAddr, payload: &[u8], timeout: Duration) -> std::io::Result<Vec<u8>> {
+    let deadline = Instant::now() + timeout;
+    let mut stream = timeout_at(deadline, TcpStream::connect(addr))
+        .await
+        .map_err(|_| std::io::ErrorKind::TimedOut)??;
+    if !payload.is_empty() {
+        stream.write_all(payload).await?;
+    }
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    while response.len() < MAX_RESPONSE {
+        match timeout_at(deadline, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+            // A reset after some data still leaves a usable response
+            Ok(Err(e)) if response.is_empty() => return Err(e),
+            Ok(Err(_)) => break,
+        }
+    }
+    response.truncate(MAX_RESPONSE);
+    Ok(response)
+}
+
+/// Parse `<TCP|UDP> <name> q<d><payload><d>`
+fn parse_probe(rest: &str) -> Result<ServiceProbe> {
+    let mut parts = rest.trim().splitn(3, ' ');
+    let protocol = match parts.next() {
+        Some("TCP") => Protocol::TCP,
+        Some("UDP") => Protocol::UDP,
+        other => return Err(anyhow!("unknown probe protocol '{}'", other.unwrap_or(""))),
+    };
+    let name = parts
+        .next()
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| anyhow!("missing probe name"))?;
+    let payload = parts
+        .next()
+        .and_then(|p| p.trim_start().strip_prefix('q'))
+        .and_then(delimited)
+        .ok_or_else(|| anyhow!("expected probe string 'q|...|'"))?
+        .0;
+
+    Ok(ServiceProbe::new(protocol, name.to_string(), unescape(payload)?))
+}
+
+/// Parse a `ports` list such as `80,443,8000-8010`
+fn parse_ports(rest: &str) -> Result<Vec<RangeInclusive<u16>>> {
+    rest.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|part| {
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            let start: u16 = start.parse().with_context(|| format!("invalid port '{}'", part))?;
+            let end: u16 = end.parse().with_context(|| format!("invalid port '{}'", part))?;
+            if start > end {
+                return Err(anyhow!("invalid port range '{}'", part));
+            }
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+/// Decode the C-style escapes used in probe strings (`\r`, `\n`, `\0`, `\xHH`, ...)
+fn unescape(s: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        let escaped = match bytes.next().ok_or_else(|| anyhow!("trailing '\\' in probe string"))? {
+            b'0' => 0,
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'x' => {
+                let mut digit = || bytes.next().and_then(|c| (c as char).to_digit(16));
+                match (digit(), digit()) {
+                    (Some(hi), Some(lo)) => (hi << 4 | lo) as u8,
+                    _ => return Err(anyhow!("invalid \\x escape in probe string")),
+                }
+            }
+            other => other,
+        };
+        out.push(escaped);
+    }
+    Ok(out)
+}
+
+/// Load signatures from a probe file
+pub fn load_probe_file(path: &Path) -> Result<Vec<Signature>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read probe file {}", path.display()))?;
+    parse_probe_file(&content)
+}
+
+/// Parse the `match`/`softmatch` directives of a probe file into a flat list
+///
+/// Each probe contributes its matches followed by its softmatches.
+pub fn parse_probe_file(content: &str) -> Result<Vec<Signature>> {
+    Ok(ServiceProbeDb::parse(content)?
+        .probes
+        .into_iter()
+        .flat_map(|probe| probe.matches.into_iter().chain(probe.softmatches))
+        .collect())
+}
+
+/// Parse one `match`/`softmatch` directive; `None` if the regex is unsupported
+fn parse_signature(rest: &str) -> Result<Option<Signature>> {
+    let directive = parse_match(rest)?;
+    let Ok(pattern) = RegexBuilder::new(&directive.pattern)
+        .case_insensitive(directive.flags.contains('i'))
+        .dot_matches_new_line(directive.flags.contains('s'))
+        .build()
+    else {
+        return Ok(None);
+    };
+
+    let mut signature = Signature::new(directive.service, pattern);
+    if let Some(product) = directive.product {
+        signature = signature.with_product(product);
+    }
+    if let Some(version) = directive.version {
+        signature = signature.with_version(version);
+    }
+    Ok(Some(signature))
+}
+
+/// Fields of one `match` directive
+struct MatchDirective {
+    service: String,
+    pattern: String,
+    flags: String,
+    product: Option<String>,
+    version: Option<String>,
+}
+
+/// Parse `<service> m<d><pattern><d>[flags] [versioninfo...]`
+fn parse_match(rest: &str) -> Result<MatchDirective> {
+    let (service, rest) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("expected '<service> m/<pattern>/'"))?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix('m')
+        .ok_or_else(|| anyhow!("expected pattern starting with 'm'"))?;
+    let (pattern, rest) = delimited(rest).ok_or_else(|| anyhow!("unterminated pattern"))?;
+    let flags: String = rest.chars().take_while(|c| !c.is_whitespace()).collect();
+
+    let mut directive = MatchDirective {
+        service: service.to_string(),
+        pattern: pattern.to_string(),
+        flags,
+        product: None,
+        version: None,
+    };
+
+    // Version info fields: `<name><d><value><d>[flags]`, e.g. p/nginx/ or cpe:/a:x/a
+    let mut rest = rest.trim_start_matches(|c: char| !c.is_whitespace());
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+            .ok_or_else(|| anyhow!("version field '{}' has no value", rest))?;
+        let name = &rest[..name_len];
+        let (value, tail) = delimited(&rest[name_len..])
+            .ok_or_else(|| anyhow!("unterminated version field '{}'", name))?;
+        match name {
+            "p" => directive.product = Some(value.to_string()),
+            "v" => directive.version = Some(value.to_string()),
+            _ => {}
+        }
+        rest = tail.trim_start_matches(|c: char| !c.is_whitespace());
+    }
+
+    Ok(directive)
+}
+
+/// Split `<d>value<d>tail` on its leading delimiter character
+fn delimited(s: &str) -> Option<(&str, &str)> {
+    let delim = s.chars().next()?;
+    let body = &s[delim.len_utf8()..];
+    let end = body.find(delim)?;
+    Some((&body[..end], &body[end + delim.len_utf8()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBES: &str = r#"
+# Sample probe file
+Probe TCP NULL q||
+totalwaitms 6000
+match ssh m|^SSH-([\d.]+)-OpenSSH_([\w.]+)|i p/OpenSSH/ v/$2/ i/protocol $1/ cpe:/a:openbsd:openssh:$2/
+softmatch ftp m=^220[ -]=
+match http m%^HTTP/1\.[01] \d\d\d.*\r\nServer: nginx/([\d.]+)%s p/nginx/ v/$1/
+match broken m|(?<=x)y|
+"#;
+
+    #[test]
+    fn test_parse_probe_file() {
+        let signatures = parse_probe_file(PROBES).unwrap();
+        // The lookbehind pattern is unsupported and skipped
+        assert_eq!(signatures.len(), 3);
+
+        let ssh = signatures[0].matches("ssh-2.0-OpenSSH_8.9p1 Ubuntu").unwrap();
+        assert_eq!(ssh.service, "ssh");
+        assert_eq!(ssh.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(ssh.version.as_deref(), Some("8.9p1"));
+
+        let http = signatures[1]
+            .matches("HTTP/1.1 200 OK\r\nServer: nginx/1.24.0\r\n")
+            .unwrap();
+        assert_eq!(http.version.as_deref(), Some("1.24.0"));
+
+        // Softmatches follow the hard matches of their probe
+        let ftp = signatures[2].matches("220 ProFTPD ready").unwrap();
+        assert_eq!((ftp.service.as_str(), ftp.product), ("ftp", None));
+    }
+
+    #[test]
+    fn test_parse_probe_file_errors() {
+        assert!(parse_probe_file("match ssh").is_err());
+        assert!(parse_probe_file("match ssh x|^SSH|").is_err());
+        assert!(parse_probe_file("match ssh m|^SSH").is_err());
+        assert!(parse_probe_file("match ssh m|^SSH| p/OpenSSH").is_err());
+    }
+
+    const SERVICE_PROBES: &str = r#"
+Probe TCP NULL q||
+match ssh m|^SSH-([\d.]+)-OpenSSH_([\w.]+)| p/OpenSSH/ v/$2/
+
+Probe TCP GetRequest q|GET / HTTP/1.0\r\n\r\n|
+rarity 1
+ports 80,8000-8010
+match http m|^HTTP/1\.[01] \d\d\d.*\r\nServer: Apache/([\d.]+)|s p/Apache httpd/ v/$1/
+softmatch http m|^HTTP/1\.[01] \d\d\d|
+
+Probe UDP DNSStatusRequest q|\0\0\x10\0\0\0\0\0\0\0\0\0|
+ports 53
+"#;
+
+    #[test]
+    fn test_parse_service_probe_db() {
+        let db = ServiceProbeDb::parse(SERVICE_PROBES).unwrap();
+        assert_eq!(db.len(), 3);
+
+        let get = &db.probes()[1];
+        assert_eq!(get.name, "GetRequest");
+        assert_eq!(get.payload, b"GET / HTTP/1.0\r\n\r\n");
+        assert_eq!(get.ports, vec![80..=80, 8000..=8010]);
+        assert_eq!(db.probes()[2].payload[2], 0x10);
+
+        let names: Vec<&str> = db.probes_for(8005, Protocol::TCP).map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["NULL", "GetRequest"]);
+        assert_eq!(db.probes_for(22, Protocol::TCP).count(), 1);
+
+        let svc = get
+            .match_response("HTTP/1.1 200 OK\r\nServer: Apache/2.4.57 (Debian)\r\n")
+            .unwrap();
+        assert_eq!(svc.product.as_deref(), Some("Apache httpd"));
+        assert_eq!(svc.version.as_deref(), Some("2.4.57"));
+        // Unknown servers fall back to the softmatch
+        let soft = get.match_response("HTTP/1.1 404 Not Found\r\n").unwrap();
+        assert_eq!((soft.service.as_str(), soft.product), ("http", None));
+
+        assert!(ServiceProbeDb::parse("Probe SCTP X q||").is_err());
+        assert!(ServiceProbeDb::parse("Probe TCP X q|\\xZZ|").is_err());
+        assert!(ServiceProbeDb::parse("Probe TCP X q||\nports 90-80").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_probe_service_against_listener() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            // Silent until spoken to, so only the GetRequest probe gets an answer
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 256];
+                    if let Ok(n) = stream.read(&mut buf).await {
+                        if buf[..n].starts_with(b"GET ") {
+                            let reply = b"HTTP/1.1 200 OK\r\nServer: Apache/2.4.57\r\n\r\n";
+                            stream.write_all(reply).await.ok();
+                        }
+                    }
+                });
+            }
+        });
+
+        let content = SERVICE_PROBES.replace("ports 80,8000-8010", &format!("ports {}", addr.port()));
+        let db = ServiceProbeDb::parse(&content).unwrap();
+        let svc = db.probe_service(addr, Duration::from_millis(200)).await.unwrap();
+        assert_eq!(svc.service, "http");
+        assert_eq!(svc.version.as_deref(), Some("2.4.57"));
+    }
+}
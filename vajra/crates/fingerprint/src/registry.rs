@@ -0,0 +1,248 @@
+//! Global detection rule registry
+//!
+//! Detection sources are consulted in a fixed order of precedence:
+//! 1. User-registered closures (`register_rule`)
+//! 2. File-loaded regex signatures (`register_signatures`)
+//! 3. Built-in banner heuristics (`detect_service_from_bytes`)
+//! 4. Built-in port table (`detect_service_from_port`)
+//!
+//! The first source that returns a match wins. Rules registered at startup
+//! apply globally to every `detect_service` call.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::sync::{Arc, RwLock};
+use vajra_common::ServiceMatch;
+
+use crate::service_detector::{detect_service_from_bytes, detect_service_from_port};
+
+/// User-supplied detection rule: receives the port and optional banner
+pub type DetectionRule = dyn Fn(u16, Option<&str>) -> Option<ServiceMatch> + Send + Sync;
+
+/// Regex signature matched against banners
+///
+/// `product` and `version` may reference capture groups as `$1`, `$2`, ...
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub service: String,
+    pub pattern: Regex,
+    pub product: Option<String>,
+    pub version: Option<String>,
+}
+
+impl Signature {
+    /// Create a signature for `service` matching `pattern`
+    pub fn new<S: Into<String>>(service: S, pattern: Regex) -> Self {
+        Self {
+            service: service.into(),
+            pattern,
+            product: None,
+            version: None,
+        }
+    }
+
+    /// Set the product template
+    pub fn with_product<S: Into<String>>(mut self, product: S) -> Self {
+        self.product = Some(product.into());
+        self
+    }
+
+    /// Set the version template
+    pub fn with_version<S: Into<String>>(mut self, version: S) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Match the banner and expand product/version templates
+    pub fn matches(&self, banner: &str) -> Option<ServiceMatch> {
+        let caps = self.pattern.captures(banner)?;
+        let expand = |template: &String| {
+            let mut out = String::new();
+            caps.expand(template, &mut out);
+            out
+        };
+
+        let mut svc = ServiceMatch::new(self.service.clone());
+        if let Some(p) = self.product.as_ref().map(expand).filter(|p| !p.is_empty()) {
+            svc = svc.with_product(p);
+        }
+        if let Some(v) = self.version.as_ref().map(expand).filter(|v| !v.is_empty()) {
+            svc = svc.with_version(v);
+        }
+        Some(svc)
+    }
+}
+
+/// Ordered collection of custom rules and signatures
+#[derive(Default)]
+pub struct RuleRegistry {
+    rules: Vec<Arc<DetectionRule>>,
+    signatures: Vec<Signature>,
+}
+
+impl RuleRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a custom rule (highest precedence, evaluated in registration order)
+    pub fn add_rule<F>(&mut self, rule: F)
+    where
+        F: Fn(u16, Option<&str>) -> Option<ServiceMatch> + Send + Sync + 'static,
+    {
+        self.rules.push(Arc::new(rule));
+    }
+
+    /// Add regex signatures (evaluated after custom rules)
+    pub fn add_signatures<I: IntoIterator<Item = Signature>>(&mut self, signatures: I) {
+        self.signatures.extend(signatures);
+    }
+
+    /// Number of registered custom rules
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Number of registered signatures
+    pub fn signature_count(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Remove all rules and signatures
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.signatures.clear();
+    }
+
+    /// Resolve a service using the full precedence chain
+    pub fn detect(&self, port: u16, banner: Option<&str>) -> Option<ServiceMatch> {
+        self.detect_raw(port, banner.map(str::as_bytes))
+    }
+
+    /// Like `detect`, for a banner in raw bytes. Rules and signatures see
+    /// it decoded (lossily); the built-in heuristics parse binary
+    /// greetings from the bytes themselves.
+    pub fn detect_raw(&self, port: u16, banner: Option<&[u8]>) -> Option<ServiceMatch> {
+        let text = banner.map(String::from_utf8_lossy);
+        let text = text.as_deref().map(str::trim);
+        for rule in &self.rules {
+            if let Some(service) = rule(port, text) {
+                return Some(service);
+            }
+        }
+
+        if let (Some(b), Some(raw)) = (text, banner) {
+            for signature in &self.signatures {
+                if let Some(service) = signature.matches(b) {
+                    return Some(service);
+                }
+            }
+
+            if let Some(service) = detect_service_from_bytes(raw, port) {
+                return Some(service);
+            }
+        }
+
+        detect_service_from_port(port)
+    }
+}
+
+/// Process-wide registry consulted by `detect_service`
+static REGISTRY: Lazy<RwLock<RuleRegistry>> = Lazy::new(|| RwLock::new(RuleRegistry::new()));
+
+/// Register a global custom detection rule
+pub fn register_rule<F>(rule: F)
+where
+    F: Fn(u16, Option<&str>) -> Option<ServiceMatch> + Send + Sync + 'static,
+{
+    REGISTRY
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .add_rule(rule);
+}
+
+/// Register global regex signatures
+pub fn register_signatures<I: IntoIterator<Item = Signature>>(signatures: I) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .add_signatures(signatures);
+}
+
+/// Remove all globally registered rules and signatures
+pub fn clear_rules() {
+    REGISTRY.write().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Resolve a service against the global registry
+pub(crate) fn detect_with_registry(port: u16, banner: Option<&[u8]>) -> Option<ServiceMatch> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .detect_raw(port, banner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTTP_BANNER: &str = "HTTP/1.1 200 OK\r\nServer: nginx/1.18.0\r\n";
+
+    #[test]
+    fn test_precedence_order() {
+        let mut registry = RuleRegistry::new();
+
+        // Built-in heuristics win over the port table
+        assert_eq!(
+            registry
+                .detect(80, Some(HTTP_BANNER))
+                .unwrap()
+                .product
+                .as_deref(),
+            Some("nginx")
+        );
+
+        // Signatures win over built-in heuristics
+        registry.add_signatures([Signature::new(
+            "http",
+            Regex::new(r"(?i)server: (\w+)/([\d.]+)").unwrap(),
+        )
+        .with_product("sig-$1")
+        .with_version("$2")]);
+        let svc = registry.detect(80, Some(HTTP_BANNER)).unwrap();
+        assert_eq!(svc.product.as_deref(), Some("sig-nginx"));
+        assert_eq!(svc.version.as_deref(), Some("1.18.0"));
+
+        // Custom rules win over signatures
+        registry.add_rule(|port, _| (port == 80).then(|| ServiceMatch::new("custom-web")));
+        assert_eq!(
+            registry.detect(80, Some(HTTP_BANNER)).unwrap().service,
+            "custom-web"
+        );
+
+        // Non-matching rules fall through to the port table
+        assert_eq!(registry.detect(22, None).unwrap().service, "ssh");
+    }
+
+    #[test]
+    fn test_global_override_wins() {
+        const MARKER: &str = "X-Registry-Test: override";
+        register_rule(|port, banner| {
+            (port == 80 && banner.is_some_and(|b| b.contains(MARKER)))
+                .then(|| ServiceMatch::new("custom-http"))
+        });
+
+        let banner = format!("{HTTP_BANNER}{MARKER}\r\n");
+        let svc = crate::detect_service(80, Some(&banner)).unwrap();
+        assert_eq!(svc.service, "custom-http");
+
+        // Without the marker the built-in detection still applies
+        assert_eq!(
+            crate::detect_service(80, Some(HTTP_BANNER))
+                .unwrap()
+                .service,
+            "http"
+        );
+    }
+}
@@ -2,171 +2,201 @@
 //! 
 //! Provides fast service identification similar to nmap's service detection
 
-use vajra_common::ServiceMatch;
+use vajra_common::{Protocol, ServiceMatch};
 
-/// Detect service from port number (comprehensive port mappings)
+use crate::port_table::lookup_port_table;
+
+/// Built-in port → service table (comprehensive port mappings)
 /// Based on IANA assigned ports and common services
 /// Organized by service category for easy maintenance
+pub static BUILTIN_PORT_SERVICES: &[(u16, &str)] = &[
+    // File Transfer Protocol
+    (20, "ftp-data"),
+    (21, "ftp"),
+    (990, "ftps"),
+
+    // Secure Shell
+    (22, "ssh"),
+
+    // Telnet
+    (23, "telnet"),
+
+    // Simple Mail Transfer Protocol
+    (25, "smtp"),
+    (465, "smtps"),
+    (587, "submission"),
+
+    // Domain Name System
+    (53, "domain"),
+
+    // Hypertext Transfer Protocol
+    (80, "http"),
+    (443, "https"),
+    (8000, "http-alt"),
+    (8080, "http-proxy"),
+    (8443, "https-alt"),
+    (8888, "http-alt"),
+    (9000, "http-alt"),
+    (3000, "http-alt"),
+    (5000, "http-alt"),
+
+    // Post Office Protocol
+    (109, "pop2"),
+    (110, "pop3"),
+    (995, "pop3s"),
+    (106, "pop3pw"),
+
+    // Internet Message Access Protocol
+    (143, "imap"),
+    (220, "imap3"),
+    (993, "imaps"),
+
+    // Remote Procedure Call / Microsoft Services
+    (111, "rpcbind"),
+    (135, "msrpc"),
+    (139, "netbios-ssn"),
+    (445, "microsoft-ds"),
+    (3389, "rdp"),
+    (5985, "wsman"),
+    (5986, "wsmans"),
+
+    // Simple Network Management Protocol
+    (161, "snmp"),
+    (162, "snmptrap"),
+
+    // Lightweight Directory Access Protocol
+    (389, "ldap"),
+    (636, "ldaps"),
+
+    // Network Time Protocol
+    (123, "ntp"),
+
+    // Network News Transfer Protocol
+    (119, "nntp"),
+
+    // Border Gateway Protocol
+    (179, "bgp"),
+
+    // Finger Protocol
+    (79, "finger"),
+
+    // Kerberos
+    (88, "kerberos"),
+
+    // Talk / Chat Services
+    (517, "talk"),
+    (518, "ntalk"),
+    (194, "irc"),
+    (6667, "irc"),
+    (6697, "ircs"),
+
+    // Git
+    (9418, "git"),
+
+    // System Logging
+    (514, "syslog"),
+
+    // Remote Sync
+    (873, "rsync"),
+
+    // Network File System
+    (2049, "nfs"),
+
+    // SOCKS Proxy
+    (1080, "socks"),
+
+    // Squid HTTP Proxy
+    (3128, "squid-http"),
+
+    // Database Services
+    (1433, "mssql"),
+    (1521, "oracle"),
+    (3306, "mysql"),
+    (5432, "postgresql"),
+    (27017, "mongodb"),
+    (6379, "redis"),
+    (9200, "elasticsearch"),
+    (11211, "memcached"),
+
+    // Virtual Network Computing
+    (5900, "vnc"),
+    (5901, "vnc-1"),
+    (5902, "vnc-2"),
+
+    // Virtual Private Network
+    (1723, "pptp"),
+    (1194, "openvpn"),
+    (500, "isakmp"),
+    (4500, "ipsec-nat-t"),
+
+    // Container & Orchestration
+    (2375, "docker"),
+    (2376, "docker-tls"),
+    (6443, "kubernetes"),
+    (10250, "kubelet"),
+
+    // Message Queue Services
+    (5672, "amqp"),
+    (15672, "rabbitmq"),
+    (1883, "mqtt"),
+    (8883, "mqtts"),
+
+    // Monitoring & Metrics
+    (9090, "prometheus"),
+
+    // Common high ports (1000-2000 range)
+    (1000, "cadlock"),
+    (2000, "cisco-sccp"),
+];
+
+/// Ports whose services are normally reached over UDP
+/// (DNS, DHCP, TFTP, NTP, NetBIOS, SNMP, IKE, syslog, RIP, SSDP, mDNS, ...)
+pub static UDP_PORT_HINTS: &[u16] = &[
+    53, 67, 68, 69, 123, 137, 138, 161, 162, 500, 514, 520, 1900, 4500, 5353,
+];
+
+/// Protocol to probe `port` with when the user hasn't forced one
+pub fn protocol_hint(port: u16) -> Protocol {
+    if UDP_PORT_HINTS.contains(&port) {
+        Protocol::UDP
+    } else {
+        Protocol::TCP
+    }
+}
+
+/// Detect service from a TCP port number: the table installed with
+/// `set_port_table` first, then the built-in table
 pub fn detect_service_from_port(port: u16) -> Option<ServiceMatch> {
-    let service = match port {
-        // File Transfer Protocol
-        20 => "ftp-data",
-        21 => "ftp",
-        990 => "ftps",
-        
-        // Secure Shell
-        22 => "ssh",
-        
-        // Telnet
-        23 => "telnet",
-        
-        // Simple Mail Transfer Protocol
-        25 => "smtp",
-        465 => "smtps",
-        587 => "submission",
-        
-        // Domain Name System
-        53 => "domain",
-        
-        // Hypertext Transfer Protocol
-        80 => "http",
-        443 => "https",
-        8000 => "http-alt",
-        8080 => "http-proxy",
-        8443 => "https-alt",
-        8888 => "http-alt",
-        9000 => "http-alt",
-        3000 => "http-alt",
-        5000 => "http-alt",
-        
-        // Post Office Protocol
-        109 => "pop2",
-        110 => "pop3",
-        995 => "pop3s",
-        106 => "pop3pw",
-        
-        // Internet Message Access Protocol
-        143 => "imap",
-        220 => "imap3",
-        993 => "imaps",
-        
-        // Remote Procedure Call / Microsoft Services
-        111 => "rpcbind",
-        135 => "msrpc",
-        139 => "netbios-ssn",
-        445 => "microsoft-ds",
-        3389 => "rdp",
-        5985 => "wsman",
-        5986 => "wsmans",
-        
-        // Simple Network Management Protocol
-        161 => "snmp",
-        162 => "snmptrap",
-        
-        // Lightweight Directory Access Protocol
-        389 => "ldap",
-        636 => "ldaps",
-        
-        // Network Time Protocol
-        123 => "ntp",
-        
-        // Network News Transfer Protocol
-        119 => "nntp",
-        
-        // Border Gateway Protocol
-        179 => "bgp",
-        
-        // Finger Protocol
-        79 => "finger",
-        
-        // Kerberos
-        88 => "kerberos",
-        
-        // Talk / Chat Services
-        517 => "talk",
-        518 => "ntalk",
-        194 => "irc",
-        6667 => "irc",
-        6697 => "ircs",
-        
-        // Git
-        9418 => "git",
-        
-        // System Logging
-        514 => "syslog",
-        
-        // Remote Sync
-        873 => "rsync",
-        
-        // Network File System
-        2049 => "nfs",
-        
-        // SOCKS Proxy
-        1080 => "socks",
-        
-        // Squid HTTP Proxy
-        3128 => "squid-http",
-        
-        // Database Services
-        1433 => "mssql",
-        1521 => "oracle",
-        3306 => "mysql",
-        5432 => "postgresql",
-        27017 => "mongodb",
-        6379 => "redis",
-        9200 => "elasticsearch",
-        11211 => "memcached",
-        
-        // Virtual Network Computing
-        5900 => "vnc",
-        5901 => "vnc-1",
-        5902 => "vnc-2",
-        
-        // Virtual Private Network
-        1723 => "pptp",
-        1194 => "openvpn",
-        500 => "isakmp",
-        4500 => "ipsec-nat-t",
-        
-        // Container & Orchestration
-        2375 => "docker",
-        2376 => "docker-tls",
-        6443 => "kubernetes",
-        10250 => "kubelet",
-        
-        // Message Queue Services
-        5672 => "amqp",
-        15672 => "rabbitmq",
-        1883 => "mqtt",
-        8883 => "mqtts",
-        
-        // Monitoring & Metrics
-        9090 => "prometheus",
-        
-        // Common high ports (1000-2000 range)
-        1000 => "cadlock",
-        2000 => "cisco-sccp",
-        _ => return None,
-    };
-    
-    Some(ServiceMatch::new(service))
+    detect_service_for_protocol(port, Protocol::TCP)
+}
+
+/// Like `detect_service_from_port`, for a port of any protocol. Loaded
+/// entries are per protocol; the built-in table applies to all of them.
+pub fn detect_service_for_protocol(port: u16, protocol: Protocol) -> Option<ServiceMatch> {
+    use once_cell::sync::Lazy;
+    use std::collections::HashMap;
+
+    static PORT_INDEX: Lazy<HashMap<u16, &'static str>> =
+        Lazy::new(|| BUILTIN_PORT_SERVICES.iter().copied().collect());
+
+    if let Some(service) = lookup_port_table(port, protocol) {
+        return Some(ServiceMatch::new(service));
+    }
+    PORT_INDEX.get(&port).map(|service| ServiceMatch::new(*service))
 }
 
 /// Detect service from banner content with version extraction
 pub fn detect_service_from_banner(banner: &str, port: u16) -> Option<ServiceMatch> {
+    // Binary protocols first, so their byte layouts never reach the text heuristics
+    if let Some(svc) = detect_binary_service(banner.as_bytes(), port) {
+        return Some(svc);
+    }
+
     let banner_lower = banner.to_lowercase();
     
     // HTTP/HTTPS detection with server version
     if banner_lower.starts_with("http/") || banner_lower.contains("server:") {
-        let (service, product, version) = extract_http_info(&banner_lower, port);
-        let mut svc = ServiceMatch::new(service);
-        if let Some(p) = product {
-            svc = svc.with_product(p);
-        }
-        if let Some(v) = version {
-            svc = svc.with_version(v);
-        }
-        return Some(svc);
+        return Some(extract_http_info(banner, port));
     }
     
     // SSH detection with version
@@ -232,8 +262,8 @@ pub fn detect_service_from_banner(banner: &str, port: u16) -> Option<ServiceMatc
     }
     
     // MySQL detection with version
-    if banner_lower.contains("mysql") || (port == 3306 && banner.as_bytes().iter().any(|&b| b == 0)) {
-        let version = extract_mysql_version(banner);
+    if banner_lower.contains("mysql") {
+        let version = extract_mysql_version(banner.as_bytes());
         let mut svc = ServiceMatch::new("mysql");
         if let Some(v) = version {
             svc = svc.with_version(v);
@@ -242,8 +272,8 @@ pub fn detect_service_from_banner(banner: &str, port: u16) -> Option<ServiceMatc
     }
     
     // PostgreSQL detection
-    if banner_lower.contains("postgresql") || (banner.len() >= 4 && banner.as_bytes()[0..4.min(banner.len())].iter().all(|&b| b == 0)) {
-        let version = extract_postgresql_version(&banner_lower);
+    if banner_lower.contains("postgresql") {
+        let version = extract_postgresql_version(banner.as_bytes());
         let mut svc = ServiceMatch::new("postgresql");
         if let Some(v) = version {
             svc = svc.with_version(v);
@@ -317,8 +347,222 @@ pub fn detect_service_from_banner(banner: &str, port: u16) -> Option<ServiceMatc
     None
 }
 
-/// Extract HTTP server info (product and version)
-fn extract_http_info(banner: &str, port: u16) -> (String, Option<String>, Option<String>) {
+/// Parser for a binary protocol greeting
+type BinaryParser = fn(&[u8]) -> Option<ServiceMatch>;
+
+/// Binary protocol parsers keyed by their default port
+const BINARY_PARSERS: &[(u16, BinaryParser)] = &[
+    (3306, parse_mysql_handshake),
+    (5432, parse_postgresql_error),
+    (5432, parse_postgresql_auth),
+];
+
+/// Detect a service from raw reply bytes.
+///
+/// Binary greetings (MySQL, PostgreSQL) are parsed from the bytes as
+/// received; only then is the reply decoded (lossily) for the text
+/// heuristics of `detect_service_from_banner`. Prefer this over the `&str`
+/// entry point whenever the raw bytes are at hand, since lossy decoding
+/// replaces non-UTF-8 bytes and shifts the binary layouts.
+pub fn detect_service_from_bytes(data: &[u8], port: u16) -> Option<ServiceMatch> {
+    if let Some(svc) = detect_binary_service(data, port) {
+        return Some(svc);
+    }
+    detect_service_from_banner(String::from_utf8_lossy(data).trim(), port)
+}
+
+/// Detect binary (NUL-delimited) protocols. The parser registered for
+/// `port` is tried first; the others still run so services on
+/// non-standard ports are recognized.
+fn detect_binary_service(data: &[u8], port: u16) -> Option<ServiceMatch> {
+    let preferred = BINARY_PARSERS.iter().filter(|(p, _)| *p == port);
+    let others = BINARY_PARSERS.iter().filter(|(p, _)| *p != port);
+    preferred.chain(others).find_map(|(_, parse)| parse(data))
+}
+
+/// Parse a MySQL/MariaDB initial handshake packet, or the error packet a
+/// server sends to hosts it refuses (`Host '...' is not allowed to connect
+/// to this MySQL server`).
+/// Layout: 3-byte LE payload length, sequence id 0, then either protocol
+/// version 10 and a NUL-terminated server version string, or 0xff, a
+/// 2-byte error code and the message.
+fn parse_mysql_handshake(data: &[u8]) -> Option<ServiceMatch> {
+    if data.len() < 6 || data[3] != 0 {
+        return None;
+    }
+    let payload_len = u32::from_le_bytes([data[0], data[1], data[2], 0]) as usize;
+    if payload_len == 0 {
+        return None;
+    }
+
+    if data[4] == 0xff {
+        // Error packets from the greeting stage never carry a SQL state marker
+        let message = data.get(7..data.len().min(4 + payload_len))?;
+        let message = String::from_utf8_lossy(message);
+        return (message.contains("MySQL") || message.contains("MariaDB"))
+            .then(|| ServiceMatch::new("mysql"));
+    }
+
+    let server_version = mysql_server_version(&data[4..])?;
+
+    // MariaDB reports "5.5.5-10.6.12-MariaDB" for compatibility
+    let (product, version) = if server_version.to_lowercase().contains("mariadb") {
+        ("mariadb", server_version.strip_prefix("5.5.5-").unwrap_or(server_version))
+    } else {
+        ("mysql", server_version)
+    };
+    let version = version.split('-').next().unwrap_or(version);
+
+    Some(
+        ServiceMatch::new("mysql")
+            .with_product(product.to_string())
+            .with_version(version.to_string()),
+    )
+}
+
+/// Server version string of a MySQL handshake payload (without the
+/// 4-byte packet header): protocol version 10, then the version up to NUL.
+fn mysql_server_version(payload: &[u8]) -> Option<&str> {
+    let (&protocol, rest) = payload.split_first()?;
+    if protocol != 0x0a {
+        return None;
+    }
+    let end = rest.iter().position(|&b| b == 0)?;
+    let server_version = std::str::from_utf8(&rest[..end]).ok()?;
+    server_version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then_some(server_version)
+}
+
+/// Parse a PostgreSQL AuthenticationRequest (the reply to a valid startup
+/// packet). Layout: 'R', 4-byte BE length, 4-byte BE auth type; only
+/// type/length pairs a real server sends are accepted, as a lone 'R' is
+/// common in text.
+fn parse_postgresql_auth(data: &[u8]) -> Option<ServiceMatch> {
+    if data.len() < 9 || data[0] != b'R' {
+        return None;
+    }
+    let len = u32::from_be_bytes([data[1], data[2], data[3], data[4]]);
+    let auth_type = u32::from_be_bytes([data[5], data[6], data[7], data[8]]);
+    let valid = match auth_type {
+        // Ok, cleartext password, GSS, SSPI
+        0 | 3 | 7 | 9 => len == 8,
+        // MD5 with a 4-byte salt
+        5 => len == 12,
+        // SASL: NUL-terminated mechanism list
+        10 => len > 8 && data[9..].starts_with(b"SCRAM-SHA-256"),
+        _ => false,
+    };
+    valid.then(|| ServiceMatch::new("postgresql").with_product("postgresql".to_string()))
+}
+
+/// Parse a PostgreSQL ErrorResponse (what a server sends to an unexpected startup)
+/// Layout: 'E', 4-byte BE length, then NUL-terminated `<code><value>` fields.
+fn parse_postgresql_error(data: &[u8]) -> Option<ServiceMatch> {
+    if data.len() < 6 || data[0] != b'E' {
+        return None;
+    }
+    let len = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
+    if !(4..=0x10000).contains(&len) {
+        return None;
+    }
+
+    // Length counts itself but not the type byte; tolerate truncated reads
+    let body = &data[5..data.len().min(len + 1)];
+    let mut has_severity = false;
+    let mut has_sqlstate = false;
+    for field in body.split(|&b| b == 0) {
+        let Some((&code, value)) = field.split_first() else { break };
+        match code {
+            b'S' | b'V' => has_severity |= matches!(value, b"ERROR" | b"FATAL" | b"PANIC"),
+            b'C' => has_sqlstate |= value.len() == 5 && value.iter().all(u8::is_ascii_alphanumeric),
+            _ => {}
+        }
+    }
+
+    (has_severity && has_sqlstate)
+        .then(|| ServiceMatch::new("postgresql").with_product("postgresql".to_string()))
+}
+
+/// Extract HTTP server info (product, version, status code and page title)
+fn extract_http_info(banner: &str, port: u16) -> ServiceMatch {
+    let (service, product, version) = extract_http_server(&banner.to_lowercase(), port);
+    let mut svc = ServiceMatch::new(service);
+    if let Some(p) = product {
+        svc = svc.with_product(p);
+    }
+    if let Some(v) = version {
+        svc = svc.with_version(v);
+    }
+    if let Some(status) = extract_http_status(banner) {
+        svc = svc.with_http_status(status);
+    }
+    if let Some(title) = extract_http_title(banner) {
+        svc = svc.with_http_title(title);
+    }
+    if let Some(cdn) = detect_cdn(banner) {
+        svc = svc.with_cdn_edge(cdn.to_string());
+    }
+    svc
+}
+
+/// CDN and cloud WAF edges, by a header they add: (header name prefix, CDN)
+const CDN_HEADERS: &[(&str, &str)] = &[
+    ("cf-ray", "Cloudflare"),
+    ("x-amz-cf-", "CloudFront"),
+    ("x-akamai", "Akamai"),
+];
+
+/// ... and by what they put in `Server:` (lowercased substring, CDN)
+const CDN_SERVERS: &[(&str, &str)] = &[
+    ("cloudflare", "Cloudflare"),
+    ("cloudfront", "CloudFront"),
+    ("akamaighost", "Akamai"),
+];
+
+/// Name of the CDN whose edge sent an HTTP response, from its headers. The
+/// `Server:` banner of such a response describes the edge, not the origin.
+fn detect_cdn(banner: &str) -> Option<&'static str> {
+    // Headers end at the first blank line; the status line has no colon
+    for line in banner.lines().take_while(|l| !l.trim().is_empty()) {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let name = name.trim().to_ascii_lowercase();
+        if let Some(&(_, cdn)) = CDN_HEADERS.iter().find(|(prefix, _)| name.starts_with(prefix)) {
+            return Some(cdn);
+        }
+        if name == "server" {
+            let value = value.to_ascii_lowercase();
+            if let Some(&(_, cdn)) = CDN_SERVERS.iter().find(|(pattern, _)| value.contains(pattern)) {
+                return Some(cdn);
+            }
+        }
+    }
+    None
+}
+
+/// Status code from an HTTP status line: "HTTP/1.1 401 Unauthorized" -> 401
+fn extract_http_status(banner: &str) -> Option<u16> {
+    let status_line = banner.lines().next()?;
+    let mut parts = status_line.split_whitespace();
+    if !parts.next()?.to_ascii_uppercase().starts_with("HTTP/") {
+        return None;
+    }
+    parts.next()?.parse().ok().filter(|code| (100..=599).contains(code))
+}
+
+/// Contents of the `<title>` element when the banner includes body bytes
+fn extract_http_title(banner: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets valid for slicing `banner`
+    let lower = banner.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title").unwrap_or(lower.len() - start);
+    let title = banner[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Extract HTTP server product and version from a lowercased banner
+fn extract_http_server(banner: &str, port: u16) -> (String, Option<String>, Option<String>) {
     let service = if port == 443 || banner.contains("ssl") || banner.contains("tls") {
         "https"
     } else {
@@ -358,7 +602,7 @@ fn extract_ssh_info(banner: &str) -> (Option<String>, Option<String>) {
     // Pattern: "SSH-2.0-OpenSSH_8.2" or "SSH-1.99-OpenSSH_7.4"
     if let Some(start) = banner.find("ssh-") {
         let rest = &banner[start..];
-        let ssh_line = if let Some(end) = rest.find(|c: char| c == '\n' || c == '\r' || c == ' ') {
+        let ssh_line = if let Some(end) = rest.find(['\n', '\r', ' ']) {
             &rest[..end]
         } else {
             rest
@@ -432,16 +676,20 @@ fn extract_imap_info(banner: &str) -> (Option<String>, Option<String>) {
     (None, extract_version_number(banner))
 }
 
-/// Extract MySQL version
-fn extract_mysql_version(banner: &str) -> Option<String> {
-    // MySQL version is usually in the initial handshake packet
-    // Look for version patterns
-    extract_version_number(banner)
+/// Extract MySQL version: the server version of a handshake packet
+/// (with or without its 4-byte header), else a version-like number
+fn extract_mysql_version(banner: &[u8]) -> Option<String> {
+    let greeting = mysql_server_version(banner).or_else(|| mysql_server_version(banner.get(4..)?));
+    match greeting {
+        Some(version) => Some(version.split('-').next().unwrap_or(version).to_string()),
+        None => extract_version_number(&String::from_utf8_lossy(banner)),
+    }
 }
 
 /// Extract PostgreSQL version
-fn extract_postgresql_version(banner: &str) -> Option<String> {
+fn extract_postgresql_version(banner: &[u8]) -> Option<String> {
     // Pattern: "PostgreSQL 13.2"
+    let banner = String::from_utf8_lossy(banner).to_lowercase();
     if let Some(idx) = banner.find("postgresql") {
         let rest = &banner[idx..];
         let parts: Vec<&str> = rest.split_whitespace().collect();
@@ -449,7 +697,7 @@ fn extract_postgresql_version(banner: &str) -> Option<String> {
             return Some(parts[1].to_string());
         }
     }
-    extract_version_number(banner)
+    extract_version_number(&banner)
 }
 
 /// Extract Redis version
@@ -459,7 +707,7 @@ fn extract_redis_version(banner: &str) -> Option<String> {
         let rest = &banner[idx..];
         if let Some(v_idx) = rest.find("v=") {
             let version_part = &rest[v_idx + 2..];
-            if let Some(end) = version_part.find(|c: char| c == ' ' || c == '\n' || c == '\r') {
+            if let Some(end) = version_part.find([' ', '\n', '\r']) {
                 return Some(version_part[..end].to_string());
             }
         }
@@ -526,17 +774,18 @@ fn extract_version_number(text: &str) -> Option<String> {
     None
 }
 
-/// Detect service from port and banner (combines both methods)
+/// Detect service from port and banner
+///
+/// Resolution order: registered custom rules, registered signatures,
+/// banner heuristics, then the port table (see `registry`).
 pub fn detect_service(port: u16, banner: Option<&str>) -> Option<ServiceMatch> {
-    // First try banner-based detection (more accurate)
-    if let Some(b) = banner {
-        if let Some(service) = detect_service_from_banner(b, port) {
-            return Some(service);
-        }
-    }
-    
-    // Fall back to port-based detection
-    detect_service_from_port(port)
+    crate::registry::detect_with_registry(port, banner.map(str::as_bytes))
+}
+
+/// Like `detect_service`, for a banner still in its raw bytes so binary
+/// greetings are parsed intact (see `detect_service_from_bytes`)
+pub fn detect_service_raw(port: u16, banner: Option<&[u8]>) -> Option<ServiceMatch> {
+    crate::registry::detect_with_registry(port, banner)
 }
 
 #[cfg(test)]
@@ -561,6 +810,135 @@ mod tests {
         assert_eq!(ssh_service.service, "ssh");
     }
 
+    #[test]
+    fn test_http_status_and_title() {
+        let banner = "HTTP/1.1 401 Unauthorized\r\nServer: nginx/1.18.0\r\n\r\n\
+                      <html><head><TITLE>\n  Router   Login\n</TITLE></head>";
+        let svc = detect_service_from_banner(banner, 80).unwrap();
+        assert_eq!(svc.http_status, Some(401));
+        assert_eq!(svc.http_title.as_deref(), Some("Router Login"));
+        assert_eq!(svc.product.as_deref(), Some("nginx"));
+
+        // Headers only: status without a title
+        let svc = detect_service_from_banner("HTTP/1.0 200 OK\r\nServer: Apache\r\n", 80).unwrap();
+        assert_eq!((svc.http_status, svc.http_title), (Some(200), None));
+
+        // Non-HTTP services leave the fields empty
+        let svc = detect_service_from_banner("SSH-2.0-OpenSSH_8.2", 22).unwrap();
+        assert_eq!((svc.http_status, svc.http_title), (None, None));
+    }
+
+    #[test]
+    fn test_cdn_edge_detection() {
+        let banner = "HTTP/1.1 403 Forbidden\r\nServer: cloudflare\r\nCF-RAY: 8a1b2c3d4e5f6a7b-FRA\r\n\r\n";
+        let svc = detect_service_from_banner(banner, 80).unwrap();
+        assert!(svc.cdn_edge);
+        assert_eq!(svc.product.as_deref(), Some("Cloudflare"));
+        assert_eq!(svc.http_status, Some(403));
+
+        // A header alone is enough, and the origin's Server version is dropped
+        let banner = "HTTP/1.1 200 OK\r\nServer: nginx/1.18.0\r\nX-Amz-Cf-Id: abc==\r\n\r\n";
+        let svc = detect_service_from_banner(banner, 80).unwrap();
+        assert_eq!((svc.product.as_deref(), svc.version), (Some("CloudFront"), None));
+        let banner = "HTTP/1.1 400 Bad Request\r\nServer: AkamaiGHost\r\n\r\n";
+        assert_eq!(detect_service_from_banner(banner, 80).unwrap().product.as_deref(), Some("Akamai"));
+
+        // Mentions in the body don't count
+        let banner = "HTTP/1.1 200 OK\r\nServer: nginx/1.18.0\r\n\r\n<p>cf-ray: behind cloudflare</p>";
+        let svc = detect_service_from_banner(banner, 80).unwrap();
+        assert!(!svc.cdn_edge);
+        assert_eq!(svc.product.as_deref(), Some("nginx"));
+    }
+
+    #[test]
+    fn test_mysql_handshake_detection() {
+        let mut packet = vec![0x4a, 0x00, 0x00, 0x00, 0x0a];
+        packet.extend_from_slice(b"8.0.33\0");
+        packet.extend_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x3b, 0x27, 0x10, 0x5d]);
+        packet.extend_from_slice(b"\0mysql_native_password\0");
+        let banner = String::from_utf8_lossy(&packet);
+
+        for port in [3306, 5432, 13306] {
+            let svc = detect_service_from_banner(&banner, port).unwrap();
+            assert_eq!(svc.service, "mysql");
+            assert_eq!(svc.version.as_deref(), Some("8.0.33"));
+        }
+
+        let mut mariadb = vec![0x5b, 0x00, 0x00, 0x00, 0x0a];
+        mariadb.extend_from_slice(b"5.5.5-10.6.12-MariaDB\0");
+        let svc = detect_service_from_banner(&String::from_utf8_lossy(&mariadb), 3306).unwrap();
+        assert_eq!(svc.product.as_deref(), Some("mariadb"));
+        assert_eq!(svc.version.as_deref(), Some("10.6.12"));
+    }
+
+    #[test]
+    fn test_postgresql_error_detection() {
+        let body = b"SFATAL\0VFATAL\0C0A000\0Munsupported frontend protocol 0.0\0Fpostmaster.c\0L2138\0\0";
+        let mut packet = vec![b'E'];
+        packet.extend_from_slice(&(body.len() as u32 + 4).to_be_bytes());
+        packet.extend_from_slice(body);
+        let banner = String::from_utf8_lossy(&packet);
+
+        for port in [5432, 3306, 15432] {
+            assert_eq!(detect_service_from_banner(&banner, port).unwrap().service, "postgresql");
+        }
+    }
+
+    /// MySQL 8.0 greeting as sent on connect: header, protocol 10, version,
+    /// connection id, salt and capability flags (non-UTF-8 bytes included)
+    fn mysql8_greeting() -> Vec<u8> {
+        let mut payload = vec![0x0a];
+        payload.extend_from_slice(b"8.0.36-0ubuntu0.22.04.1\0");
+        payload.extend_from_slice(&[0xb3, 0x9c, 0x00, 0x00]); // connection id
+        payload.extend_from_slice(&[0x5e, 0x91, 0xe4, 0x7a, 0x8c, 0x3b, 0xf2, 0x10, 0x00]);
+        payload.extend_from_slice(&[0xff, 0xff, 0xff, 0x02, 0x00, 0xff, 0xdf, 0x15]);
+        payload.extend_from_slice(&[0; 10]);
+        payload.extend_from_slice(&[0x81, 0x6d, 0xc7, 0x2a, 0x9f, 0x0e, 0xa4, 0x55, 0xd3, 0x19, 0x66, 0xba, 0x00]);
+        payload.extend_from_slice(b"caching_sha2_password\0");
+        let mut packet = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+        packet.push(0); // sequence id
+        packet.extend_from_slice(&payload);
+        packet
+    }
+
+    #[test]
+    fn test_mysql_greeting_from_raw_bytes() {
+        let greeting = mysql8_greeting();
+        for port in [3306, 13306] {
+            let svc = detect_service_from_bytes(&greeting, port).unwrap();
+            assert_eq!(svc.service, "mysql");
+            assert_eq!(svc.product.as_deref(), Some("mysql"));
+            assert_eq!(svc.version.as_deref(), Some("8.0.36"));
+        }
+        assert_eq!(detect_service_raw(3306, Some(&greeting)).unwrap().version.as_deref(), Some("8.0.36"));
+        assert_eq!(extract_mysql_version(&greeting).as_deref(), Some("8.0.36"));
+        assert_eq!(extract_mysql_version(&greeting[4..]).as_deref(), Some("8.0.36"));
+
+        // Refused hosts get an error packet (0xff, code 1130) instead
+        let message = b"Host '10.0.0.9' is not allowed to connect to this MySQL server";
+        let mut refused = vec![(message.len() + 3) as u8, 0, 0, 0, 0xff, 0x6a, 0x04];
+        refused.extend_from_slice(message);
+        let svc = detect_service_from_bytes(&refused, 3306).unwrap();
+        assert_eq!((svc.service.as_str(), svc.version), ("mysql", None));
+    }
+
+    #[test]
+    fn test_postgresql_auth_request() {
+        // AuthenticationMD5Password with a binary salt
+        let md5 = [b'R', 0, 0, 0, 12, 0, 0, 0, 5, 0xe3, 0x81, 0xfa, 0x07];
+        assert_eq!(detect_service_from_bytes(&md5, 5432).unwrap().service, "postgresql");
+
+        // AuthenticationSASL offering SCRAM-SHA-256
+        let mut sasl = vec![b'R', 0, 0, 0, 23, 0, 0, 0, 10];
+        sasl.extend_from_slice(b"SCRAM-SHA-256\0\0");
+        assert_eq!(detect_service_from_bytes(&sasl, 15432).unwrap().service, "postgresql");
+
+        // Inconsistent length for the auth type is not PostgreSQL
+        let bogus = [b'R', 0, 0, 0, 9, 0, 0, 0, 5];
+        assert!(parse_postgresql_auth(&bogus).is_none());
+        assert!(parse_postgresql_auth(b"RFB 003.008\n").is_none());
+    }
+
     #[test]
     fn test_combined_detection() {
         // Banner takes precedence
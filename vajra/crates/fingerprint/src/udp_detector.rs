@@ -0,0 +1,211 @@
+//! Service detection for UDP replies
+//!
+//! UDP services answer in binary, so rather than banner text the raw reply
+//! is matched against the wire formats of common services: DNS, NTP, SNMP
+//! and the NetBIOS name service.
+
+use vajra_common::ServiceMatch;
+
+/// Identify the service behind a UDP reply received from `port`
+pub fn detect_udp_service(port: u16, response: &[u8]) -> Option<ServiceMatch> {
+    // SNMP's BER envelope is distinctive enough to trust on any port
+    if let Some(svc) = detect_snmp(response) {
+        return Some(svc);
+    }
+    match port {
+        53 | 5353 => detect_dns(response),
+        123 => detect_ntp(response),
+        137 => detect_netbios_ns(response),
+        _ => None,
+    }
+}
+
+/// DNS response header (QR bit set); a TXT answer such as the reply to a
+/// `version.bind` CHAOS query is reported as the version
+fn detect_dns(response: &[u8]) -> Option<ServiceMatch> {
+    if response.len() < 12 || response[2] & 0x80 == 0 {
+        return None;
+    }
+    let mut svc = ServiceMatch::new("domain");
+    if let Some(version) = dns_txt_answer(response) {
+        svc = svc.with_version(version);
+    }
+    Some(svc)
+}
+
+/// Text of the first answer record if it is a TXT record
+fn dns_txt_answer(response: &[u8]) -> Option<String> {
+    let qdcount = u16::from_be_bytes([response[4], response[5]]);
+    let ancount = u16::from_be_bytes([response[6], response[7]]);
+    if ancount == 0 {
+        return None;
+    }
+
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_dns_name(response, pos)? + 4; // QTYPE + QCLASS
+    }
+    pos = skip_dns_name(response, pos)?;
+    let fixed = response.get(pos..pos + 10)?;
+    let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+    let rdlen = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+    let rdata = response.get(pos + 10..pos + 10 + rdlen)?;
+    if rtype != 16 {
+        return None;
+    }
+
+    let len = *rdata.first()? as usize;
+    let text = String::from_utf8_lossy(rdata.get(1..1 + len)?).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Offset just past the (possibly compressed) name starting at `pos`
+fn skip_dns_name(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            l if l & 0xc0 == 0xc0 => return Some(pos + 2),
+            l => pos += 1 + l as usize,
+        }
+    }
+}
+
+/// NTP server reply (mode 4); the protocol version comes from the header
+fn detect_ntp(response: &[u8]) -> Option<ServiceMatch> {
+    if response.len() < 48 || response[0] & 0x07 != 4 {
+        return None;
+    }
+    let version = (response[0] >> 3) & 0x07;
+    Some(ServiceMatch::new("ntp").with_version(format!("v{}", version)))
+}
+
+/// NetBIOS name service response (QR bit set, opcode query, an answer)
+fn detect_netbios_ns(response: &[u8]) -> Option<ServiceMatch> {
+    if response.len() < 12 || response[2] & 0xf8 != 0x80 {
+        return None;
+    }
+    let ancount = u16::from_be_bytes([response[6], response[7]]);
+    (ancount > 0).then(|| ServiceMatch::new("netbios-ns"))
+}
+
+/// SNMP GetResponse PDU; an OCTET STRING value in the first varbind
+/// (sysDescr for the scanner's probe) is reported as the version
+fn detect_snmp(response: &[u8]) -> Option<ServiceMatch> {
+    let (tag, message, _) = read_tlv(response)?;
+    if tag != 0x30 {
+        return None;
+    }
+    let (version_tag, version, rest) = read_tlv(message)?;
+    let (community_tag, _, rest) = read_tlv(rest)?;
+    let (pdu_tag, pdu, _) = read_tlv(rest)?;
+    if version_tag != 0x02 || version.len() != 1 || community_tag != 0x04 || pdu_tag != 0xa2 {
+        return None;
+    }
+
+    let mut svc = ServiceMatch::new("snmp");
+    if let Some(descr) = first_varbind_string(pdu) {
+        svc = svc.with_version(descr);
+    }
+    Some(svc)
+}
+
+/// OCTET STRING value of the first varbind in a PDU body
+fn first_varbind_string(pdu: &[u8]) -> Option<String> {
+    // request-id, error-status, error-index, then the varbind list
+    let (_, _, rest) = read_tlv(pdu)?;
+    let (_, _, rest) = read_tlv(rest)?;
+    let (_, _, rest) = read_tlv(rest)?;
+    let (_, varbinds, _) = read_tlv(rest)?;
+    let (_, varbind, _) = read_tlv(varbinds)?;
+    let (_, _oid, rest) = read_tlv(varbind)?;
+    let (value_tag, value, _) = read_tlv(rest)?;
+    if value_tag != 0x04 {
+        return None;
+    }
+    let text = String::from_utf8_lossy(value).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Split one BER TLV off the front of `data`: (tag, value, rest)
+fn read_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let first = *data.get(1)?;
+    let (len, header) = if first & 0x80 == 0 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = data.get(2..2 + n)?.iter().fold(0usize, |acc, &b| acc << 8 | b as usize);
+        (len, 2 + n)
+    };
+    let value = data.get(header..header + len)?;
+    Some((tag, value, &data[header + len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dns_version_bind_reply() {
+        let mut reply = vec![0x13, 0x37, 0x84, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        reply.extend_from_slice(b"\x07version\x04bind\x00\x00\x10\x00\x03");
+        // Answer: pointer to the question name, TXT/CH, TTL 0, "9.18.1"
+        reply.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x10, 0x00, 0x03, 0, 0, 0, 0, 0x00, 0x07, 0x06]);
+        reply.extend_from_slice(b"9.18.1");
+
+        let svc = detect_udp_service(53, &reply).unwrap();
+        assert_eq!(svc.service, "domain");
+        assert_eq!(svc.version.as_deref(), Some("9.18.1"));
+
+        // A refused query is still a DNS server, just without a version
+        let refused = [0x13, 0x37, 0x81, 0x05, 0, 1, 0, 0, 0, 0, 0, 0];
+        assert_eq!(detect_udp_service(53, &refused).unwrap().version, None);
+        // Queries (QR clear) are not replies
+        assert!(detect_udp_service(53, &[0u8; 12]).is_none());
+    }
+
+    #[test]
+    fn test_ntp_server_reply() {
+        let mut reply = [0u8; 48];
+        reply[0] = 0x24; // LI 0, version 4, mode 4 (server)
+        reply[1] = 2;
+        let svc = detect_udp_service(123, &reply).unwrap();
+        assert_eq!((svc.service.as_str(), svc.version.as_deref()), ("ntp", Some("v4")));
+
+        reply[0] = 0x23; // mode 3 is a client request
+        assert!(detect_udp_service(123, &reply).is_none());
+    }
+
+    #[test]
+    fn test_snmp_get_response() {
+        let descr = b"Linux edge-router 5.15.0";
+        let mut varbind = vec![0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00];
+        varbind.push(0x04);
+        varbind.push(descr.len() as u8);
+        varbind.extend_from_slice(descr);
+        let varbind = [&[0x30, varbind.len() as u8][..], &varbind].concat();
+        let varbinds = [&[0x30, varbind.len() as u8][..], &varbind].concat();
+        let pdu_body = [&[0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00][..], &varbinds].concat();
+        let pdu = [&[0xa2, pdu_body.len() as u8][..], &pdu_body].concat();
+        let body = [&[0x02, 0x01, 0x01, 0x04, 0x06][..], b"public", &pdu].concat();
+        let message = [&[0x30, 0x81, body.len() as u8][..], &body].concat();
+
+        let svc = detect_udp_service(161, &message).unwrap();
+        assert_eq!(svc.service, "snmp");
+        assert_eq!(svc.version.as_deref(), Some("Linux edge-router 5.15.0"));
+
+        assert!(detect_udp_service(161, &message[..message.len() - 3]).is_none());
+    }
+
+    #[test]
+    fn test_netbios_ns_reply() {
+        let mut reply = vec![0x13, 0x37, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        reply.extend_from_slice(&[0x20; 34]);
+        assert_eq!(detect_udp_service(137, &reply).unwrap().service, "netbios-ns");
+        assert!(detect_udp_service(138, &reply).is_none());
+    }
+}
@@ -6,7 +6,8 @@ mod progress;
 
 pub use orchestrator::Orchestrator;
 pub use rate_limiter::RateLimiter;
-pub use progress::ProgressTracker;
+pub use progress::{ProgressCallback, ProgressTracker};
+pub use tokio_util::sync::CancellationToken;
 
 #[cfg(test)]
 mod tests {
@@ -25,4 +26,583 @@ mod tests {
 		let res = orch.run(None).await;
 		assert!(res.is_ok());
 	}
+
+	/// Scanner that takes a fixed time per probe and reports the port open.
+	struct SlowScanner(std::time::Duration);
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for SlowScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			tokio::time::sleep(self.0).await;
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Open))
+		}
+
+		fn name(&self) -> &str {
+			"slow"
+		}
+	}
+
+	#[tokio::test]
+	async fn host_timeout_skips_remaining_ports() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::time::Duration;
+		use vajra_common::{PortState, Protocol, ScanJob};
+
+		let mut orch = Orchestrator::new(1, 10_000).with_host_timeout(Duration::from_millis(250));
+		orch.add_scanner("tcp", std::sync::Arc::new(SlowScanner(Duration::from_millis(100))));
+
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let ports: Vec<u16> = (1..=10).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		let mut results = orch.get_results().await;
+		results.sort_by_key(|r| r.target.port);
+		assert_eq!(results.len(), 10);
+		assert_eq!(results[0].state, PortState::Open);
+
+		let skipped: Vec<_> = results.iter().filter(|r| r.state == PortState::Skipped).collect();
+		assert!(skipped.len() >= 5, "expected later ports skipped, got {}", skipped.len());
+		assert!(skipped.iter().all(|r| r.reason.as_deref() == Some("host-timeout")));
+		assert_eq!(results[9].state, PortState::Skipped);
+	}
+
+	/// Scanner that reports the port open and records its name as the reason.
+	struct NamedScanner(&'static str);
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for NamedScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Open).with_reason(self.0))
+		}
+
+		fn name(&self) -> &str {
+			self.0
+		}
+	}
+
+	#[tokio::test]
+	async fn protocol_routes_pick_scanner_per_target() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob, Target};
+
+		let mut orch = Orchestrator::new(2, 10_000);
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+		orch.add_scanner("udp", Arc::new(NamedScanner("udp")));
+		orch.route_protocol(Protocol::UDP, "udp");
+
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let job = ScanJob::new(vec![
+			Target::new(ip, 53).with_protocol(Protocol::UDP),
+			Target::new(ip, 80).with_protocol(Protocol::TCP),
+		]);
+		orch.submit_job(job).await.unwrap();
+		orch.run(Some("tcp")).await.unwrap();
+
+		let mut results = orch.get_results().await;
+		results.sort_by_key(|r| r.target.port);
+		let used: Vec<_> = results.iter().map(|r| r.reason.as_deref().unwrap()).collect();
+		assert_eq!(used, ["udp", "tcp"]);
+	}
+
+	#[tokio::test]
+	async fn grid_targets_are_all_scanned() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{PortSpec, Protocol, TargetGrid};
+
+		let mut orch = Orchestrator::new(4, 10_000);
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+
+		let ips = vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))];
+		let mut ports = PortSpec::new();
+		ports.push_range(20..=22);
+		orch.submit_grid(TargetGrid::new(ips, ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		let mut scanned: Vec<_> = orch.get_results().await.into_iter().map(|r| r.target).collect();
+		scanned.sort_by_key(|t| (t.ip, t.port));
+		assert_eq!(scanned.len(), 6);
+		assert_eq!((scanned[5].ip, scanned[5].port), (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 22));
+	}
+
+	#[tokio::test]
+	async fn duplicate_targets_are_scanned_once() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob, ScanOptions};
+
+		let mut orch = Orchestrator::new(4, 10_000);
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+
+		// Overlapping ranges: 10.0.0.1 and port 22 each appear twice
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+		let job = ScanJob::from_ips_and_ports(&[ip, other, ip], &[22, 80, 22], Protocol::TCP)
+			.with_options(ScanOptions { shuffle_seed: Some(7), ..Default::default() });
+		orch.submit_job(job).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		let mut scanned: Vec<_> = orch.get_results().await.into_iter().map(|r| (r.target.ip, r.target.port)).collect();
+		scanned.sort();
+		assert_eq!(scanned, [(ip, 22), (ip, 80), (other, 22), (other, 80)]);
+		let stats = orch.get_stats().await;
+		assert_eq!((stats.total_targets, stats.scanned), (4, 4));
+	}
+
+	#[tokio::test]
+	async fn results_are_stored_per_job() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{PortSpec, Protocol, ScanJob, Storage, TargetGrid};
+		use vajra_storage::MemoryStore;
+
+		let store = Arc::new(MemoryStore::new());
+		let mut orch = Orchestrator::new(2, 10_000).with_storage(store.clone());
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let job = ScanJob::from_ips_and_ports(&[ip], &[22, 80], Protocol::TCP);
+		let job_id = job.id;
+		orch.submit_job(job).await.unwrap();
+		orch.run(None).await.unwrap();
+		let mut ports = PortSpec::new();
+		ports.push_range(1000..=1002);
+		let grid_id = orch.submit_grid(TargetGrid::new(vec![ip], ports, Protocol::TCP)).await.unwrap();
+		let (handle, mut rx) = orch.run_streaming(None);
+		while rx.recv().await.is_some() {}
+		handle.await.unwrap().unwrap();
+
+		assert_eq!(store.get_results(job_id).await.unwrap().len(), 2);
+		assert_eq!(store.get_results(grid_id).await.unwrap().len(), 3);
+		assert!(store.export_csv(grid_id).await.unwrap().contains("10.0.0.1,1002,open"));
+	}
+
+	#[tokio::test]
+	async fn completed_targets_are_skipped() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{PortSpec, Protocol, Target, TargetGrid};
+
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let completed = [Target::new(ip, 20), Target::new(ip, 22)].into_iter().collect();
+		let mut orch = Orchestrator::new(2, 10_000).with_completed(completed);
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+
+		let mut ports = PortSpec::new();
+		ports.push_range(20..=23);
+		orch.submit_grid(TargetGrid::new(vec![ip], ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		let mut scanned: Vec<u16> = orch.get_results().await.into_iter().map(|r| r.target.port).collect();
+		scanned.sort_unstable();
+		assert_eq!(scanned, [21, 23]);
+		assert_eq!(orch.get_stats().await.total_targets, 2);
+	}
+
+	/// Scanner that records the order in which ports are scanned.
+	#[derive(Default)]
+	struct OrderScanner(std::sync::Mutex<Vec<u16>>);
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for OrderScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			self.0.lock().unwrap().push(target.port);
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Open))
+		}
+
+		fn name(&self) -> &str {
+			"order"
+		}
+	}
+
+	#[tokio::test]
+	async fn run_all_drains_queue_by_priority() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob};
+
+		// One worker makes the dispatch order observable
+		let scanner = Arc::new(OrderScanner::default());
+		let mut orch = Orchestrator::new(1, 10_000);
+		orch.add_scanner("tcp", scanner.clone());
+
+		let ip = [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+		let job = |ports: &[u16], priority| ScanJob::from_ips_and_ports(&ip, ports, Protocol::TCP).with_priority(priority);
+		orch.submit_job(job(&[1, 2], 0)).await.unwrap();
+		orch.submit_job(job(&[10, 11], 5)).await.unwrap();
+		orch.submit_job(job(&[3], 0)).await.unwrap();
+		orch.submit_job(job(&[20], 9)).await.unwrap();
+		orch.run_all(None).await.unwrap();
+
+		assert_eq!(*scanner.0.lock().unwrap(), [20, 10, 11, 1, 2, 3]);
+		assert_eq!(orch.get_results().await.len(), 6);
+
+		// The queue is empty afterwards
+		orch.run_all(None).await.unwrap();
+		assert_eq!(orch.get_results().await.len(), 6);
+	}
+
+	#[tokio::test]
+	async fn run_streaming_sends_results_then_closes() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob};
+
+		let mut orch = Orchestrator::new(2, 10_000);
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let ports: Vec<u16> = (1..=5).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &ports, Protocol::TCP)).await.unwrap();
+
+		let (handle, mut rx) = orch.run_streaming(None);
+		let mut streamed = Vec::new();
+		// `None` once the channel closes at the end of the scan
+		while let Some(result) = rx.recv().await {
+			streamed.push(result.target.port);
+		}
+		handle.await.unwrap().unwrap();
+
+		streamed.sort_unstable();
+		assert_eq!(streamed, ports);
+		// Streamed results are not also collected
+		assert!(orch.get_results().await.is_empty());
+	}
+
+	#[tokio::test]
+	async fn run_cancellable_stops_and_returns_partial_results() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use std::time::{Duration, Instant};
+		use tokio_util::sync::CancellationToken;
+		use vajra_common::{Protocol, ScanJob};
+
+		let mut orch = Orchestrator::new(2, 10_000);
+		orch.add_scanner("tcp", Arc::new(SlowScanner(Duration::from_millis(100))));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let ports: Vec<u16> = (1..=100).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &ports, Protocol::TCP)).await.unwrap();
+
+		let cancel = CancellationToken::new();
+		let trigger = cancel.clone();
+		tokio::spawn(async move {
+			tokio::time::sleep(Duration::from_millis(250)).await;
+			trigger.cancel();
+		});
+
+		let start = Instant::now();
+		let results = orch.run_cancellable(None, cancel).await.unwrap();
+		// The full job would take 5s; cancelling abandons the in-flight probes
+		assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+		assert!(!results.is_empty() && results.len() < ports.len(), "got {} results", results.len());
+	}
+
+	#[tokio::test]
+	async fn run_cancellable_returns_only_this_runs_results() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use tokio_util::sync::CancellationToken;
+		use vajra_common::{Protocol, ScanJob};
+
+		let mut orch = Orchestrator::new(2, 10_000);
+		orch.add_scanner("tcp", Arc::new(NamedScanner("tcp")));
+		let ip = [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+		orch.submit_job(ScanJob::from_ips_and_ports(&ip, &[1, 2, 3], Protocol::TCP)).await.unwrap();
+		orch.submit_job(ScanJob::from_ips_and_ports(&ip, &[4, 5], Protocol::TCP)).await.unwrap();
+
+		let first = orch.run_cancellable(None, CancellationToken::new()).await.unwrap();
+		let second = orch.run_cancellable(None, CancellationToken::new()).await.unwrap();
+		let mut ports: Vec<u16> = second.iter().map(|r| r.target.port).collect();
+		ports.sort_unstable();
+
+		assert_eq!(first.len(), 3);
+		assert_eq!(ports, [4, 5]);
+		// Both runs still land in the orchestrator's results
+		assert_eq!(orch.get_results().await.len(), 5);
+		// Nothing left to run
+		assert!(orch.run_cancellable(None, CancellationToken::new()).await.unwrap().is_empty());
+	}
+
+	/// Scanner that never gets an answer.
+	struct SilentScanner;
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for SilentScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Filtered))
+		}
+
+		fn name(&self) -> &str {
+			"silent"
+		}
+	}
+
+	#[tokio::test]
+	async fn adaptive_limiter_slows_down_on_timeouts() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob};
+
+		let limiter = Arc::new(RateLimiter::new_adaptive(10_000, 20_000));
+		let mut orch = Orchestrator::new(4, 10_000).with_rate_limiter(limiter.clone());
+		orch.add_scanner("tcp", Arc::new(SilentScanner));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let ports: Vec<u16> = (1..=100).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		// Two all-timeout windows halve the rate twice
+		assert_eq!(limiter.current_rate(), 2_500.0);
+	}
+
+	/// Limiter counting what the workers ask of it.
+	#[derive(Default)]
+	struct CountingLimiter {
+		acquired: std::sync::atomic::AtomicUsize,
+		timeouts: std::sync::atomic::AtomicUsize,
+	}
+
+	#[async_trait::async_trait]
+	impl vajra_common::RateLimiter for CountingLimiter {
+		async fn acquire(&self) {
+			self.acquired.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+		}
+		fn current_rate(&self) -> f64 {
+			f64::INFINITY
+		}
+		fn set_rate(&self, _rate: u64) {}
+		async fn record_outcome(&self, timed_out: bool) {
+			if timed_out {
+				self.timeouts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn custom_limiter_is_used() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::atomic::Ordering;
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob};
+
+		let limiter = Arc::new(CountingLimiter::default());
+		let mut orch = Orchestrator::new(4, 1).with_rate_limiter(limiter.clone());
+		orch.add_scanner("tcp", Arc::new(SilentScanner));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let ports: Vec<u16> = (1..=20).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		assert_eq!(limiter.acquired.load(Ordering::Relaxed), 20);
+		assert_eq!(limiter.timeouts.load(Ordering::Relaxed), 20);
+	}
+
+	/// Scanner answering every probe in 20ms and recording the timeout it
+	/// was given for each.
+	#[derive(Default)]
+	struct TimeoutRecordingScanner {
+		timeouts: std::sync::Mutex<Vec<Option<std::time::Duration>>>,
+	}
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for TimeoutRecordingScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			self.timeouts.lock().unwrap().push(None);
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Open))
+		}
+
+		async fn scan_with_timeout(
+			&self,
+			target: &vajra_common::Target,
+			timeout: std::time::Duration,
+		) -> anyhow::Result<vajra_common::ProbeResult> {
+			self.timeouts.lock().unwrap().push(Some(timeout));
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Closed)
+				.with_rtt(std::time::Duration::from_millis(20)))
+		}
+
+		fn name(&self) -> &str {
+			"timeout-recording"
+		}
+	}
+
+	#[tokio::test]
+	async fn adaptive_timeout_follows_measured_rtt() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use std::time::Duration;
+		use vajra_common::{Protocol, ScanJob, RTT_MIN_SAMPLES};
+
+		let initial = Duration::from_secs(2);
+		let scanner = Arc::new(TimeoutRecordingScanner::default());
+		// One worker, so each probe sees every earlier reply
+		let mut orch = Orchestrator::new(1, 100_000).with_adaptive_timeout(initial);
+		orch.add_scanner("tcp", scanner.clone());
+		assert_eq!(orch.current_timeout(), Some(initial));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let ports: Vec<u16> = (1..=10).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		let timeouts = scanner.timeouts.lock().unwrap().clone();
+		let warmup = RTT_MIN_SAMPLES as usize;
+		assert!(timeouts[..warmup].iter().all(|t| *t == Some(initial)), "{:?}", timeouts);
+		// 20ms replies give a timeout at the 100ms floor
+		let adapted = Some(Duration::from_millis(100));
+		assert!(timeouts[warmup..].iter().all(|t| *t == adapted), "{:?}", timeouts);
+		assert_eq!(orch.current_timeout(), adapted);
+
+		// Without adaptive timeouts scanners keep their own
+		let scanner = Arc::new(TimeoutRecordingScanner::default());
+		let mut orch = Orchestrator::new(1, 100_000);
+		orch.add_scanner("tcp", scanner.clone());
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &[80], Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+		assert_eq!(*scanner.timeouts.lock().unwrap(), vec![None]);
+		assert_eq!(orch.current_timeout(), None);
+	}
+
+	/// Scanner recording the most probes it saw in flight at once per host.
+	#[derive(Default)]
+	struct InFlightScanner {
+		in_flight: std::sync::Mutex<std::collections::HashMap<std::net::IpAddr, (usize, usize)>>,
+	}
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for InFlightScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			{
+				let mut in_flight = self.in_flight.lock().unwrap();
+				let (now, max) = in_flight.entry(target.ip).or_default();
+				*now += 1;
+				*max = (*max).max(*now);
+			}
+			tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+			self.in_flight.lock().unwrap().get_mut(&target.ip).unwrap().0 -= 1;
+			Ok(vajra_common::ProbeResult::new(target.clone(), vajra_common::PortState::Open))
+		}
+
+		fn name(&self) -> &str {
+			"in-flight"
+		}
+	}
+
+	#[tokio::test]
+	async fn per_host_concurrency_caps_each_host() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob};
+
+		let scanner = Arc::new(InFlightScanner::default());
+		let mut orch = Orchestrator::new(32, 100_000).with_per_host_concurrency(3);
+		orch.add_scanner("tcp", scanner.clone());
+		let ips = [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))];
+		let ports: Vec<u16> = (1..=30).collect();
+		orch.submit_job(ScanJob::from_ips_and_ports(&ips, &ports, Protocol::TCP)).await.unwrap();
+		orch.run(None).await.unwrap();
+
+		assert_eq!(orch.get_results().await.len(), 60);
+		let in_flight = scanner.in_flight.lock().unwrap();
+		for ip in ips {
+			assert_eq!(in_flight[&ip].1, 3, "host {}", ip);
+		}
+	}
+
+	/// Scanner answering every target with one state, or failing (`None`)
+	/// the way a raw scanner without permission does.
+	struct FixedScanner(Option<vajra_common::PortState>);
+
+	#[async_trait::async_trait]
+	impl vajra_common::Scanner for FixedScanner {
+		async fn scan(&self, target: &vajra_common::Target) -> anyhow::Result<vajra_common::ProbeResult> {
+			match self.0 {
+				Some(state) => Ok(vajra_common::ProbeResult::new(target.clone(), state)),
+				None => Err(anyhow::anyhow!("raw sockets not permitted")),
+			}
+		}
+
+		fn name(&self) -> &str {
+			"fixed"
+		}
+	}
+
+	#[tokio::test]
+	async fn fallbacks_rescan_ambiguous_and_failed_targets() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{PortState, Protocol, ScanJob};
+
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		let scan = |primary: Option<PortState>, fallback: Option<PortState>| async move {
+			let mut orch = Orchestrator::new(1, 1000);
+			orch.add_scanner("syn", Arc::new(FixedScanner(primary)));
+			orch.add_scanner("tcp", Arc::new(FixedScanner(fallback)));
+			orch.set_fallbacks("syn", &["tcp"]);
+			orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &[80], Protocol::TCP)).await.unwrap();
+			orch.run(Some("syn")).await.unwrap();
+			let result = orch.get_results().await.pop();
+			result.map(|r| (r.state, r.scanner.unwrap()))
+		};
+
+		// A decided answer is kept; ambiguous and failed ones move on
+		assert_eq!(scan(Some(PortState::Closed), Some(PortState::Open)).await, Some((PortState::Closed, "syn".into())));
+		assert_eq!(scan(Some(PortState::Filtered), Some(PortState::Open)).await, Some((PortState::Open, "tcp".into())));
+		assert_eq!(scan(None, Some(PortState::Open)).await, Some((PortState::Open, "tcp".into())));
+		// A failing fallback doesn't discard the primary's answer
+		assert_eq!(scan(Some(PortState::Filtered), None).await, Some((PortState::Filtered, "syn".into())));
+		assert_eq!(scan(None, None).await, None);
+	}
+
+	#[tokio::test]
+	async fn stats_count_every_result() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{PortState, Protocol, ScanJob};
+
+		let mut orch = Orchestrator::new(4, 10_000);
+		orch.add_scanner("tcp", Arc::new(FixedScanner(Some(PortState::Open))));
+		orch.add_scanner("silent", Arc::new(SilentScanner));
+		orch.add_scanner("broken", Arc::new(FixedScanner(None)));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		for ports in [&[1, 2, 3][..], &[4, 5][..], &[6][..]] {
+			orch.submit_job(ScanJob::from_ips_and_ports(&[ip], ports, Protocol::TCP)).await.unwrap();
+		}
+		orch.run(None).await.unwrap();
+		orch.run(Some("silent")).await.unwrap();
+		orch.run(Some("broken")).await.unwrap();
+
+		let stats = orch.get_stats().await;
+		assert_eq!(stats.total_targets, 6);
+		assert_eq!(stats.scanned, 5);
+		assert_eq!((stats.open_ports, stats.filtered_ports, stats.errors), (3, 2, 1));
+		assert!(stats.elapsed > std::time::Duration::ZERO);
+	}
+
+	#[tokio::test]
+	async fn failures_keep_target_and_reason() {
+		use std::net::{IpAddr, Ipv4Addr};
+		use std::sync::Arc;
+		use vajra_common::{Protocol, ScanJob};
+
+		let mut orch = Orchestrator::new(2, 10_000);
+		orch.add_scanner("syn", Arc::new(FixedScanner(None)));
+		let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+		orch.submit_job(ScanJob::from_ips_and_ports(&[ip], &[22, 80, 443], Protocol::TCP)).await.unwrap();
+		orch.run(Some("syn")).await.unwrap();
+
+		assert!(orch.get_results().await.is_empty());
+		let mut failures = orch.get_failures().await;
+		failures.sort_by_key(|(target, _)| target.port);
+		let ports: Vec<u16> = failures.iter().map(|(target, _)| target.port).collect();
+		assert_eq!(ports, [22, 80, 443]);
+		assert!(failures.iter().all(|(_, reason)| reason == "raw sockets not permitted"));
+	}
+
+	#[test]
+	fn failure_kind_groups_io_errors_by_kind() {
+		let denied = anyhow::Error::from(std::io::Error::from_raw_os_error(1)).context("sending probe to 10.0.0.1:80");
+		assert_eq!(orchestrator::failure_kind(&denied), "permission denied");
+		assert_eq!(orchestrator::failure_kind(&anyhow::anyhow!("raw sockets not permitted")), "raw sockets not permitted");
+	}
 }
@@ -2,23 +2,76 @@
 //! Orchestrator - job scheduling and worker coordination
 
 use anyhow::Result;
-use std::collections::{HashMap, VecDeque};
+use dashmap::DashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
 use std::sync::Arc;
-use tokio::sync::{Mutex};
-use tracing::{info, instrument};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
 
-use vajra_common::{ProbeResult, ScanJob, Scanner};
+use vajra_common::{
+    Permutation, PortState, ProbeResult, Protocol, RttEstimator, ScanJob, ScanStats, Scanner, Storage, Target,
+    TargetGrid,
+};
 use crate::progress::ProgressTracker;
 use crate::rate_limiter::RateLimiter;
+use vajra_common::RateLimiter as RateLimit;
+
+/// Results buffered between `run_streaming` workers and the receiver.
+const STREAM_CAPACITY: usize = 1024;
+
+/// A scanner with its registered name.
+type NamedScanner = (String, Arc<dyn Scanner + Send + Sync>);
+
+/// Scanners tried in turn for a target: the selected one, then its fallbacks.
+type ScannerChain = Arc<Vec<NamedScanner>>;
+
+/// Lazily produced targets of one job.
+type TargetSource = Box<dyn Iterator<Item = Target> + Send>;
+
+/// Targets shared by the workers of a run, each tagged with its job's shard
+/// and id.
+type WorkSource = Box<dyn Iterator<Item = (Target, Option<u32>, Uuid)> + Send>;
+
+/// A submitted job waiting to run.
+struct PendingJob {
+    id: Uuid,
+    label: String,
+    shard: Option<u32>,
+    priority: u8,
+    len: usize,
+    targets: TargetSource,
+}
 
 /// Orchestrator coordinates scan jobs, workers, rate limiting and collects results.
 pub struct Orchestrator {
-    job_queue: Arc<Mutex<VecDeque<ScanJob>>>,
-    rate_limiter: Arc<RateLimiter>,
+    job_queue: Arc<Mutex<VecDeque<PendingJob>>>,
+    rate_limiter: Arc<dyn RateLimit>,
     progress: Arc<ProgressTracker>,
     scanners: HashMap<String, Arc<dyn Scanner + Send + Sync>>,
+    /// Scanner name per target protocol; overrides the run's scanner
+    protocol_routes: HashMap<Protocol, String>,
+    /// Scanners to retry a target with, per primary scanner name
+    fallbacks: HashMap<String, Vec<String>>,
     concurrency: usize,
     results: Arc<Mutex<Vec<ProbeResult>>>,
+    /// Totals over every result produced, whichever way it was delivered
+    stats: Arc<Mutex<ScanStats>>,
+    /// Targets whose scan failed, with the error
+    failures: Arc<Mutex<Vec<(Target, String)>>>,
+    host_timeout: Option<Duration>,
+    per_host_concurrency: Option<usize>,
+    /// Targets already scanned (e.g. before a restart); never probed again
+    completed: Arc<HashSet<Target>>,
+    /// Where every result is also stored, keyed by its job's id
+    storage: Option<Arc<dyn Storage>>,
+    /// RTT estimate the per-probe timeout is derived from, if adaptive
+    rtt: Option<Arc<std::sync::Mutex<RttEstimator>>>,
 }
 
 impl Orchestrator {
@@ -29,20 +82,171 @@ impl Orchestrator {
             rate_limiter: Arc::new(RateLimiter::new(rate_limit)),
             progress: Arc::new(ProgressTracker::new()),
             scanners: HashMap::new(),
+            protocol_routes: HashMap::new(),
+            fallbacks: HashMap::new(),
             concurrency,
             results: Arc::new(Mutex::new(Vec::new())),
+            stats: Arc::new(Mutex::new(ScanStats::default())),
+            failures: Arc::new(Mutex::new(Vec::new())),
+            host_timeout: None,
+            per_host_concurrency: None,
+            completed: Arc::new(HashSet::new()),
+            storage: None,
+            rtt: None,
         }
     }
 
+    /// Limit the total time spent on each host. Once a host has used its
+    /// budget (measured from its first probe), in-flight probes are cancelled
+    /// and its remaining targets are reported as skipped ("host-timeout").
+    pub fn with_host_timeout(mut self, timeout: Duration) -> Self {
+        self.host_timeout = Some(timeout);
+        self
+    }
+
+    /// Allow at most `limit` probes in flight to any one host (unlimited by
+    /// default). Keeps a single small target from seeing the whole
+    /// concurrency at once; workers waiting on a busy host hold their slot
+    /// in the global pool until it frees up.
+    pub fn with_per_host_concurrency(mut self, limit: usize) -> Self {
+        self.per_host_concurrency = Some(limit.max(1));
+        self
+    }
+
+    /// Skip `completed` targets in jobs submitted from now on, e.g. those of a
+    /// `ScanState` saved before a restart. Progress and `total_targets`
+    /// count only what is left, assuming the completed targets belong to
+    /// the submitted job.
+    pub fn with_completed(mut self, completed: HashSet<Target>) -> Self {
+        self.completed = Arc::new(completed);
+        self
+    }
+
+    /// Also store every result in `storage` under the id of the job that
+    /// produced it (`ScanJob::id`, or the id `submit_grid` returns), however
+    /// the run delivers it. A failed store is logged, not fatal.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Derive each probe's timeout from the round trips measured so far
+    /// (`srtt + 4 * rttvar`, see `RttEstimator`) and pass it to the scanner
+    /// via `Scanner::scan_with_timeout`. Probes use `initial` until a few
+    /// targets have answered. The estimate spans every run of this
+    /// orchestrator; fallback scanners get the same timeout.
+    pub fn with_adaptive_timeout(mut self, initial: Duration) -> Self {
+        self.rtt = Some(Arc::new(std::sync::Mutex::new(RttEstimator::new(initial))));
+        self
+    }
+
+    /// Timeout the next probe would get, if adaptive timeouts are on
+    pub fn current_timeout(&self) -> Option<Duration> {
+        self.rtt.as_ref().map(|rtt| rtt.lock().unwrap().timeout())
+    }
+
+    /// Print "X% (done/total) at R/s" to stderr at most once per
+    /// `interval` while scanning, for runs whose progress would otherwise
+    /// be invisible (e.g. logs of non-interactive runs).
+    pub fn with_progress_reports(mut self, interval: Duration) -> Self {
+        self.progress = Arc::new(ProgressTracker::new().with_stderr_reports(interval));
+        self
+    }
+
+    /// Replace the rate limiter, e.g. with `RateLimiter::new_adaptive` or
+    /// any other implementation of `vajra_common::RateLimiter`.
+    /// Workers report each probe's outcome to it: unanswered probes
+    /// (filtered, open|filtered, or cut off by the host timeout) count as
+    /// timeouts. Keep a clone of the `Arc` to watch the current rate.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<dyn RateLimit>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Progress of the scan, e.g. to `register_callback` for a live display.
+    /// Take it after `with_progress_reports`, which replaces the tracker.
+    pub fn progress(&self) -> Arc<ProgressTracker> {
+        self.progress.clone()
+    }
+
     /// Register a scanner implementation under a name (e.g. "tcp").
     pub fn add_scanner(&mut self, name: &str, scanner: Arc<dyn Scanner + Send + Sync>) {
         self.scanners.insert(name.to_string(), scanner);
     }
 
-    /// Submit a scan job to the queue.
+    /// Send targets of `protocol` to the scanner registered as `name`,
+    /// whatever scanner the run selects. Lets one job mix TCP and UDP targets.
+    pub fn route_protocol(&mut self, protocol: Protocol, name: &str) {
+        self.protocol_routes.insert(protocol, name.to_string());
+    }
+
+    /// When the scanner registered as `name` leaves a target ambiguous
+    /// (filtered, open|filtered or unfiltered) or fails on it (e.g. raw
+    /// sockets not permitted), rescan it with each of `fallbacks` in order
+    /// until one decides it. The last answer stands, though a failure never
+    /// replaces an earlier answer; `ProbeResult::scanner` names its source.
+    pub fn set_fallbacks(&mut self, name: &str, fallbacks: &[&str]) {
+        self.fallbacks
+            .insert(name.to_string(), fallbacks.iter().map(|f| f.to_string()).collect());
+    }
+
+    /// Submit a scan job to the queue. Repeated targets (e.g. from
+    /// overlapping CIDRs) are dropped, keeping the first occurrence, so
+    /// each is scanned, counted and stored once. Targets are then
+    /// reordered if the job's options carry a `shuffle_seed`.
     pub async fn submit_job(&self, job: ScanJob) -> Result<()> {
-        let target_count = job.targets.len();
-        self.progress.set_total(target_count).await;
+        let mut targets = job.targets;
+        let submitted = targets.len();
+        let mut seen = HashSet::with_capacity(submitted);
+        targets.retain(|target| seen.insert(target.clone()));
+        if targets.len() < submitted {
+            info!("Job {}: dropped {} duplicate target(s)", job.id, submitted - targets.len());
+        }
+        if let Some(seed) = job.options.shuffle_seed {
+            let mut slots: Vec<Option<Target>> = targets.into_iter().map(Some).collect();
+            let order = Permutation::new(slots.len() as u64, seed);
+            targets = order.filter_map(|i| slots[i as usize].take()).collect();
+        }
+        let pending = PendingJob {
+            id: job.id,
+            label: job.id.to_string(),
+            shard: job.shard.map(|s| s.index),
+            priority: job.priority,
+            len: targets.len(),
+            targets: Box::new(targets.into_iter()),
+        };
+        self.enqueue(pending).await
+    }
+
+    /// Submit an IP × port grid. Targets are generated as workers pull
+    /// them, so large grids never exist in memory all at once. Returns the
+    /// id its results are stored under.
+    ///
+    /// Unlike `submit_job`, grid targets are not deduplicated (that would
+    /// mean remembering every one); a grid repeats none as long as its IPs
+    /// are distinct and its ports `normalized`, as the CLI builds them.
+    pub async fn submit_grid(&self, grid: TargetGrid) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        let pending = PendingJob {
+            id,
+            label: "grid".to_string(),
+            shard: None,
+            priority: 0,
+            len: grid.len(),
+            targets: Box::new(grid.into_targets()),
+        };
+        self.enqueue(pending).await?;
+        Ok(id)
+    }
+
+    async fn enqueue(&self, mut job: PendingJob) -> Result<()> {
+        if !self.completed.is_empty() {
+            let completed = self.completed.clone();
+            job.len = job.len.saturating_sub(completed.len());
+            job.targets = Box::new(job.targets.filter(move |target| !completed.contains(target)));
+        }
+        self.progress.set_total(job.len).await;
+        self.stats.lock().await.total_targets += job.len;
         self.job_queue.lock().await.push_back(job);
         Ok(())
     }
@@ -58,59 +262,332 @@ impl Orchestrator {
         };
         drop(queue);
 
-        info!("Starting job {} targets={}", job.id, job.targets.len());
+        self.run_jobs(vec![job], scanner_name).await
+    }
+
+    /// Drain the whole queue in one run, higher `priority` jobs first (ties
+    /// keep submission order). All jobs share one worker pool, so the
+    /// concurrency limit and rate limit apply across jobs rather than per
+    /// job; results accumulate as with `run`.
+    #[instrument(skip(self))]
+    pub async fn run_all(&self, scanner_name: Option<&str>) -> Result<()> {
+        let jobs = drain_by_priority(&self.job_queue).await;
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        self.run_jobs(jobs, scanner_name).await
+    }
+
+    /// Like `run`, but stops once `cancel` is triggered: workers take no
+    /// further targets and in-flight probes are abandoned. Returns the
+    /// results this run collected (all of them if the job completed); they
+    /// are also added to `get_results`.
+    #[instrument(skip(self, cancel))]
+    pub async fn run_cancellable(&self, scanner_name: Option<&str>, cancel: CancellationToken) -> Result<Vec<ProbeResult>> {
+        let job = self.job_queue.lock().await.pop_front();
+        let Some(job) = job else {
+            return Ok(Vec::new());
+        };
+
+        let mut context = self.context();
+        context.cancel = cancel;
+        let collected = Arc::new(Mutex::new(Vec::new()));
+        context
+            .run_jobs(vec![job], scanner_name, Delivery::Collect(collected.clone()))
+            .await?;
+
+        let collected = std::mem::take(&mut *collected.lock().await);
+        self.results.lock().await.extend(collected.iter().cloned());
+        Ok(collected)
+    }
+
+    /// Drain the whole queue and stream each result down the returned
+    /// channel as workers produce it, instead of collecting them for
+    /// `get_results`. Jobs run as with `run_all`. The channel closes once
+    /// the scan has finished, and the handle yields the run's outcome.
+    /// Dropping the receiver stops the scan early.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn run_streaming(&self, scanner_name: Option<&str>) -> (JoinHandle<Result<()>>, mpsc::Receiver<ProbeResult>) {
+        let (tx, rx) = mpsc::channel(STREAM_CAPACITY);
+        let job_queue = self.job_queue.clone();
+        let context = self.context();
+        let scanner_name = scanner_name.map(str::to_string);
+
+        let handle = tokio::spawn(async move {
+            let jobs = drain_by_priority(&job_queue).await;
+            if jobs.is_empty() {
+                return Ok(());
+            }
+            context.run_jobs(jobs, scanner_name.as_deref(), Delivery::Stream(tx)).await
+        });
+        (handle, rx)
+    }
+
+    /// Scan the targets of `jobs`, in order, with one pool of workers.
+    async fn run_jobs(&self, jobs: Vec<PendingJob>, scanner_name: Option<&str>) -> Result<()> {
+        self.context()
+            .run_jobs(jobs, scanner_name, Delivery::Collect(self.results.clone()))
+            .await
+    }
+
+    /// Snapshot of the state a run needs
+    fn context(&self) -> RunContext {
+        RunContext {
+            rate_limiter: self.rate_limiter.clone(),
+            progress: self.progress.clone(),
+            stats: self.stats.clone(),
+            failures: self.failures.clone(),
+            scanners: self.scanners.clone(),
+            protocol_routes: self.protocol_routes.clone(),
+            fallbacks: self.fallbacks.clone(),
+            concurrency: self.concurrency,
+            host_timeout: self.host_timeout,
+            per_host_concurrency: self.per_host_concurrency,
+            storage: self.storage.clone(),
+            rtt: self.rtt.clone(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Drain current results (clone) for external consumption.
+    pub async fn get_results(&self) -> Vec<ProbeResult> {
+        self.results.lock().await.clone()
+    }
+
+    /// Totals across all runs so far: per-state counts, failed probes,
+    /// average RTT, and `elapsed` as the time spent running. Streamed
+    /// results are counted too.
+    pub async fn get_stats(&self) -> ScanStats {
+        self.stats.lock().await.clone()
+    }
+
+    /// Targets whose scan failed (no result was produced), with the error.
+    pub async fn get_failures(&self) -> Vec<(Target, String)> {
+        self.failures.lock().await.clone()
+    }
+}
+
+/// Every queued job, higher `priority` first (ties keep submission order)
+async fn drain_by_priority(job_queue: &Mutex<VecDeque<PendingJob>>) -> Vec<PendingJob> {
+    let mut jobs: Vec<PendingJob> = job_queue.lock().await.drain(..).collect();
+    jobs.sort_by_key(|job| Reverse(job.priority));
+    jobs
+}
+
+/// Where a run's results go
+#[derive(Clone)]
+enum Delivery {
+    /// Collected for `get_results`
+    Collect(Arc<Mutex<Vec<ProbeResult>>>),
+    /// Sent as produced (`run_streaming`)
+    Stream(mpsc::Sender<ProbeResult>),
+}
+
+impl Delivery {
+    /// Hand over one result. Returns `false` once a stream's receiver is
+    /// gone, telling the worker to stop.
+    async fn deliver(&self, result: ProbeResult) -> bool {
+        match self {
+            Delivery::Collect(results) => {
+                results.lock().await.push(result);
+                true
+            }
+            Delivery::Stream(tx) => tx.send(result).await.is_ok(),
+        }
+    }
+}
+
+/// Orchestrator state shared by the workers of a run. Owned (the maps are
+/// cloned, the rest is behind `Arc`s) so a streaming run can be spawned.
+struct RunContext {
+    rate_limiter: Arc<dyn RateLimit>,
+    progress: Arc<ProgressTracker>,
+    stats: Arc<Mutex<ScanStats>>,
+    failures: Arc<Mutex<Vec<(Target, String)>>>,
+    scanners: HashMap<String, Arc<dyn Scanner + Send + Sync>>,
+    protocol_routes: HashMap<Protocol, String>,
+    fallbacks: HashMap<String, Vec<String>>,
+    concurrency: usize,
+    host_timeout: Option<Duration>,
+    per_host_concurrency: Option<usize>,
+    storage: Option<Arc<dyn Storage>>,
+    rtt: Option<Arc<std::sync::Mutex<RttEstimator>>>,
+    /// Stops the workers when triggered (never, unless `run_cancellable`)
+    cancel: CancellationToken,
+}
+
+impl RunContext {
+    /// Scan the targets of `jobs`, in order, with one pool of workers.
+    async fn run_jobs(self, jobs: Vec<PendingJob>, scanner_name: Option<&str>, delivery: Delivery) -> Result<()> {
+        let labels: Vec<&str> = jobs.iter().map(|job| job.label.as_str()).collect();
+        let labels = labels.join(",");
+        let started = Instant::now();
+        for job in &jobs {
+            info!("Starting job {} targets={} priority={}", job.label, job.len, job.priority);
+        }
 
         // Select scanner (TCP by default)
-        let scanner = match self.select_scanner(scanner_name) {
+        let scanner = match self.select_chain(scanner_name) {
             Ok(s) => s,
             Err(e) => {
-                info!("Job {} skipped: {}", job.id, e);
+                info!("Job {} skipped: {}", labels, e);
                 return Ok(()); // gracefully skip job
             }
         };
-
-    // Use a bounded channel and a fixed worker pool to avoid per-target task spawn overhead
-        // Shared queue pattern: push all targets into a VecDeque protected by a Mutex.
-        use std::collections::VecDeque;
-        let queue = Arc::new(Mutex::new(VecDeque::<vajra_common::Target>::new()));
-        {
-            let mut q = queue.lock().await;
-            for t in job.targets.into_iter() {
-                q.push_back(t);
+        let mut routes = HashMap::new();
+        for (protocol, name) in &self.protocol_routes {
+            match self.select_chain(Some(name)) {
+                Ok(s) => {
+                    routes.insert(*protocol, s);
+                }
+                Err(e) => {
+                    info!("Job {} skipped: {}", labels, e);
+                    return Ok(());
+                }
             }
         }
+        let routes = Arc::new(routes);
+
+        // Fixed worker pool pulling from one shared target source; targets
+        // are produced on demand rather than queued up front. Results from
+        // a shard are tagged so merged output can be traced back.
+        let source: WorkSource = Box::new(jobs.into_iter().flat_map(|job| {
+            let (shard, id) = (job.shard, job.id);
+            job.targets.map(move |target| (target, shard, id))
+        }));
+        let queue = Arc::new(Mutex::new(source));
+
+        // Per-host start times for the host-timeout budget
+        let host_started = Arc::new(Mutex::new(HashMap::<IpAddr, Instant>::new()));
+        let host_timeout = self.host_timeout;
+
+        // Per-host probe slots, created on a host's first target
+        let host_slots = Arc::new(DashMap::<IpAddr, Arc<Semaphore>>::new());
+        let per_host_concurrency = self.per_host_concurrency;
 
         // Spawn worker tasks equal to concurrency. Each worker pops from the shared queue.
         let mut workers = Vec::new();
         for _ in 0..self.concurrency {
             let queue = queue.clone();
+            let host_started = host_started.clone();
+            let host_slots = host_slots.clone();
             let rate_limiter = self.rate_limiter.clone();
             let scanner = scanner.clone();
+            let routes = routes.clone();
             let progress = self.progress.clone();
-            let results = self.results.clone();
+            let stats = self.stats.clone();
+            let failures = self.failures.clone();
+            let delivery = delivery.clone();
+            let storage = self.storage.clone();
+            let rtt = self.rtt.clone();
+            let cancel = self.cancel.clone();
 
             let worker = tokio::spawn(async move {
                 loop {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+
                     // Pop a target from the shared queue
                     let maybe_target = {
                         let mut q = queue.lock().await;
-                        q.pop_front()
+                        q.next()
                     };
 
-                    let target = match maybe_target {
+                    let (target, shard, job_id) = match maybe_target {
                         Some(t) => t,
                         None => break, // queue empty, exit worker
                     };
 
-                    rate_limiter.acquire().await;
-                    match scanner.scan(&target).await {
-                        Ok(result) => {
+                    // Wait for a free slot on this host, held until the probe
+                    // finishes; taken first so the host-timeout check sees the wait
+                    let _host_permit = match per_host_concurrency {
+                        Some(limit) => {
+                            let slots = host_slots
+                                .entry(target.ip)
+                                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                                .clone();
+                            tokio::select! {
+                                _ = cancel.cancelled() => break,
+                                permit = slots.acquire_owned() => Some(permit.expect("host slots are never closed")),
+                            }
+                        }
+                        None => None,
+                    };
+
+                    // Remaining budget for this host; exhausted hosts are skipped
+                    let remaining = match host_timeout {
+                        Some(budget) => {
+                            let started = *host_started.lock().await.entry(target.ip).or_insert_with(Instant::now);
+                            budget.checked_sub(started.elapsed()).filter(|r| !r.is_zero())
+                        }
+                        None => None,
+                    };
+                    if host_timeout.is_some() && remaining.is_none() {
+                        progress.increment_skipped().await;
+                        let result = host_timeout_result(target, shard);
+                        stats.lock().await.update(&result);
+                        store_result(&storage, job_id, &result).await;
+                        if !delivery.deliver(result).await {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    let scanner = routes.get(&target.protocol).unwrap_or(&scanner);
+                    tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        _ = rate_limiter.acquire() => {}
+                    }
+                    let probe_timeout = rtt.as_ref().map(|rtt| rtt.lock().unwrap().timeout());
+                    // `None` when the host's budget ran out mid-probe
+                    let scanned = tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        scanned = async {
+                            let scan = scan_with_fallbacks(scanner, &target, probe_timeout);
+                            match remaining {
+                                Some(r) => tokio::time::timeout(r, scan).await.ok(),
+                                None => Some(scan.await),
+                            }
+                        } => scanned,
+                    };
+                    let scanned = match scanned {
+                        Some(scanned) => scanned,
+                        None => {
+                            rate_limiter.record_outcome(true).await;
+                            progress.increment_skipped().await;
+                            let result = host_timeout_result(target, shard);
+                            stats.lock().await.update(&result);
+                            store_result(&storage, job_id, &result).await;
+                            if !delivery.deliver(result).await {
+                                break;
+                            }
+                            continue;
+                        }
+                    };
+                    match scanned {
+                        Ok(mut result) => {
+                            let unanswered = matches!(result.state, PortState::Filtered | PortState::OpenFiltered);
+                            rate_limiter.record_outcome(unanswered).await;
+                            if let Some(rtt) = &rtt {
+                                rtt.lock().unwrap().record_result(&result);
+                            }
+                            if shard.is_some() {
+                                result.shard = shard;
+                            }
                             progress.increment_completed().await;
-                            let mut r = results.lock().await;
-                            r.push(result);
+                            stats.lock().await.update(&result);
+                            store_result(&storage, job_id, &result).await;
+                            if !delivery.deliver(result).await {
+                                break;
+                            }
                         }
-                        Err(_) => {
-                            progress.increment_failed().await;
+                        Err(e) => {
+                            progress.increment_failed(&failure_kind(&e)).await;
+                            stats.lock().await.errors += 1;
+                            failures.lock().await.push((target, format!("{:#}", e)));
                         }
                     }
                 }
@@ -118,20 +595,19 @@ impl Orchestrator {
             workers.push(worker);
         }
 
+        // From here only the workers hold the stream open
+        drop(delivery);
+
         // Wait for workers to finish
         for w in workers {
             w.await?;
         }
 
+        self.stats.lock().await.elapsed += started.elapsed();
         self.progress.print_summary().await;
         Ok(())
     }
 
-    /// Drain current results (clone) for external consumption.
-    pub async fn get_results(&self) -> Vec<ProbeResult> {
-        self.results.lock().await.clone()
-    }
-
     /// Select a scanner by name. Defaults to "tcp" if name is None.
     fn select_scanner(
         &self,
@@ -143,4 +619,74 @@ impl Orchestrator {
             .cloned()
             .ok_or_else(|| anyhow::anyhow!("Scanner '{}' not registered", key))
     }
+
+    /// The scanner selected by `name` followed by its fallbacks, all of
+    /// which must be registered.
+    fn select_chain(&self, name: Option<&str>) -> Result<ScannerChain> {
+        let key = name.unwrap_or("tcp");
+        let mut chain = vec![(key.to_string(), self.select_scanner(Some(key))?)];
+        for fallback in self.fallbacks.get(key).into_iter().flatten() {
+            chain.push((fallback.clone(), self.select_scanner(Some(fallback))?));
+        }
+        Ok(Arc::new(chain))
+    }
+}
+
+/// Coarse grouping of a scan error for the summary: the kind of the first
+/// I/O error in its chain (e.g. "permission denied"), else its root cause.
+pub(crate) fn failure_kind(err: &anyhow::Error) -> String {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .map(|io| io.kind().to_string())
+        .unwrap_or_else(|| err.root_cause().to_string())
+}
+
+/// Whether a result leaves the port's state open to a fallback scanner.
+fn is_ambiguous(state: PortState) -> bool {
+    matches!(state, PortState::Filtered | PortState::OpenFiltered | PortState::Unfiltered)
+}
+
+/// Scan `target` with each scanner of `chain` until one decides it (see
+/// `Orchestrator::set_fallbacks`), tagging the result with its scanner.
+/// `timeout` overrides the scanners' own timeouts when given.
+async fn scan_with_fallbacks(chain: &[NamedScanner], target: &Target, timeout: Option<Duration>) -> Result<ProbeResult> {
+    let mut outcome = None;
+    for (i, (name, scanner)) in chain.iter().enumerate() {
+        if i > 0 {
+            debug!("Falling back to {} for {}:{}", name, target.ip, target.port);
+        }
+        let scanned = match timeout {
+            Some(timeout) => scanner.scan_with_timeout(target, timeout).await,
+            None => scanner.scan(target).await,
+        };
+        match scanned {
+            Ok(result) => {
+                let decided = !is_ambiguous(result.state);
+                outcome = Some(Ok(result.with_scanner(name.as_str())));
+                if decided {
+                    break;
+                }
+            }
+            // An earlier ambiguous answer beats a failure
+            Err(e) if !matches!(outcome, Some(Ok(_))) => outcome = Some(Err(e)),
+            Err(e) => debug!("Fallback scanner {} failed on {}:{}: {}", name, target.ip, target.port, e),
+        }
+    }
+    outcome.unwrap_or_else(|| Err(anyhow::anyhow!("No scanner for {}:{}", target.ip, target.port)))
+}
+
+/// Copy `result` to the attached store, if any
+async fn store_result(storage: &Option<Arc<dyn Storage>>, job_id: Uuid, result: &ProbeResult) {
+    if let Some(storage) = storage {
+        if let Err(e) = storage.store_result(job_id, result).await {
+            warn!("Failed to store result for {}: {:#}", result.target, e);
+        }
+    }
+}
+
+/// Result recorded for a target skipped because its host ran out of time.
+fn host_timeout_result(target: Target, shard: Option<u32>) -> ProbeResult {
+    let mut result = ProbeResult::new(target, PortState::Skipped).with_reason("host-timeout");
+    result.shard = shard;
+    result
 }
@@ -1,12 +1,33 @@
 //! Progress tracking
+//!
+//! Counts finished probes, feeds live updates to registered callbacks and,
+//! if enabled, prints a throttled "X% (done/total) at R/s" line to stderr
+//! for runs without a terminal.
 
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
+
+/// Live progress hook, called with (completed, failed, total)
+pub type ProgressCallback = Box<dyn Fn(usize, usize, usize) + Send + Sync>;
+
+/// Minimum time between callback invocations; the final update always
+/// goes through
+const CALLBACK_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct ProgressTracker {
     total: Mutex<usize>,
     completed: Mutex<usize>,
     failed: Mutex<usize>,
+    skipped: Mutex<usize>,
+    /// Failed probes per error kind
+    failure_kinds: Mutex<BTreeMap<String, usize>>,
+    started: Instant,
+    /// Set by `with_stderr_reports`
+    reports: Option<std::sync::Mutex<ReportThrottle>>,
+    callbacks: std::sync::Mutex<Vec<ProgressCallback>>,
+    callback_throttle: std::sync::Mutex<ReportThrottle>,
 }
 
 impl ProgressTracker {
@@ -15,30 +36,113 @@ impl ProgressTracker {
             total: Mutex::new(0),
             completed: Mutex::new(0),
             failed: Mutex::new(0),
+            skipped: Mutex::new(0),
+            failure_kinds: Mutex::new(BTreeMap::new()),
+            started: Instant::now(),
+            reports: None,
+            callbacks: std::sync::Mutex::new(Vec::new()),
+            callback_throttle: std::sync::Mutex::new(ReportThrottle::new(CALLBACK_INTERVAL, Instant::now())),
         }
     }
 
+    /// Print a progress line to stderr at most once per `interval`
+    pub fn with_stderr_reports(mut self, interval: Duration) -> Self {
+        self.reports = Some(std::sync::Mutex::new(ReportThrottle::new(interval, Instant::now())));
+        self
+    }
+
+    /// Call `callback` with (completed, failed, total) as probes finish, at
+    /// most every 100ms plus once when the last target is done. It runs on
+    /// the scan's workers, so keep it quick; it must not register callbacks.
+    pub fn register_callback(&self, callback: ProgressCallback) {
+        self.callbacks.lock().unwrap().push(callback);
+    }
+
+    /// Share of targets finished (completed, failed or skipped), 0–100
+    pub async fn percent(&self) -> f64 {
+        let total = *self.total.lock().await;
+        if total == 0 {
+            return 0.0;
+        }
+        let done = *self.completed.lock().await + *self.failed.lock().await + *self.skipped.lock().await;
+        (done as f64 / total as f64 * 100.0).min(100.0)
+    }
+
     pub async fn set_total(&self, total: usize) {
         *self.total.lock().await = total;
     }
 
     pub async fn increment_completed(&self) {
         *self.completed.lock().await += 1;
+        self.notify().await;
+        self.report().await;
     }
 
-    pub async fn increment_failed(&self) {
+    /// Count a failed probe under `kind`, the grouping shown in the summary
+    pub async fn increment_failed(&self, kind: &str) {
         *self.failed.lock().await += 1;
+        *self.failure_kinds.lock().await.entry(kind.to_string()).or_default() += 1;
+        self.notify().await;
+        self.report().await;
+    }
+
+    /// Run the callbacks, unless throttled
+    async fn notify(&self) {
+        if self.callbacks.lock().unwrap().is_empty() {
+            return;
+        }
+        let completed = *self.completed.lock().await;
+        let failed = *self.failed.lock().await;
+        let total = *self.total.lock().await;
+        let finished = completed + failed + *self.skipped.lock().await >= total;
+        if !self.callback_throttle.lock().unwrap().ready(Instant::now()) && !finished {
+            return;
+        }
+        for callback in self.callbacks.lock().unwrap().iter() {
+            callback(completed, failed, total);
+        }
+    }
+
+    pub async fn increment_skipped(&self) {
+        *self.skipped.lock().await += 1;
+        self.report().await;
+    }
+
+    /// Print a progress line if reports are on and the interval has passed
+    async fn report(&self) {
+        let Some(reports) = &self.reports else {
+            return;
+        };
+        if !reports.lock().unwrap().ready(Instant::now()) {
+            return;
+        }
+        let done = *self.completed.lock().await + *self.failed.lock().await + *self.skipped.lock().await;
+        let total = *self.total.lock().await;
+        eprintln!("{}", format_progress(done, total, self.started.elapsed()));
     }
 
     pub async fn print_summary(&self) {
         let total = *self.total.lock().await;
         let completed = *self.completed.lock().await;
         let failed = *self.failed.lock().await;
+        let skipped = *self.skipped.lock().await;
 
         info!("Scan Summary:");
         info!("  Total targets: {}", total);
         info!("  Completed: {}", completed);
         info!("  Failed: {}", failed);
+        let kinds = self.failure_kinds.lock().await;
+        for (kind, count) in kinds.iter() {
+            info!("    {} × {}", count, kind);
+        }
+        // One cause behind every probe (e.g. no raw-socket permission) is
+        // worth saying plainly
+        if let (true, Some((kind, _))) = (failed == total && kinds.len() == 1, kinds.iter().next()) {
+            warn!("All {} probes failed: {}", failed, kind);
+        }
+        if skipped > 0 {
+            info!("  Skipped: {}", skipped);
+        }
         if total > 0 {
             info!("  Success rate: {:.1}%", (completed as f64 / total as f64) * 100.0);
         }
@@ -50,3 +154,90 @@ impl Default for ProgressTracker {
         Self::new()
     }
 }
+
+/// Allows one report per interval
+pub struct ReportThrottle {
+    interval: Duration,
+    last: Instant,
+}
+
+impl ReportThrottle {
+    /// The first report is due one `interval` after `start`
+    pub fn new(interval: Duration, start: Instant) -> Self {
+        Self { interval, last: start }
+    }
+
+    /// Whether a report is due at `now`; if so, the next is an interval away
+    pub fn ready(&mut self, now: Instant) -> bool {
+        if now.saturating_duration_since(self.last) < self.interval {
+            return false;
+        }
+        self.last = now;
+        true
+    }
+}
+
+/// "X% (done/total) at R/s"
+fn format_progress(done: usize, total: usize, elapsed: Duration) -> String {
+    let percent = if total == 0 { 0.0 } else { done as f64 / total as f64 * 100.0 };
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { done as f64 / secs } else { 0.0 };
+    format!("{:.1}% ({}/{}) at {:.0}/s", percent, done, total, rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_emits_once_per_interval() {
+        let start = Instant::now();
+        let mut throttle = ReportThrottle::new(Duration::from_secs(5), start);
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Nothing before the first interval is up
+        assert!(!throttle.ready(at(0)));
+        assert!(!throttle.ready(at(4_999)));
+        assert!(throttle.ready(at(5_000)));
+        // Calls inside the next interval are suppressed, however many
+        let emitted = (5_001..10_000).step_by(7).filter(|&ms| throttle.ready(at(ms))).count();
+        assert_eq!(emitted, 0);
+        assert!(throttle.ready(at(10_000)));
+        // A long gap yields one report, not a backlog
+        assert!(throttle.ready(at(60_000)));
+        assert!(!throttle.ready(at(60_001)));
+    }
+
+    #[tokio::test]
+    async fn test_callback_gets_final_counts() {
+        use std::sync::{Arc, Mutex};
+
+        let tracker = ProgressTracker::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = calls.clone();
+        tracker.register_callback(Box::new(move |completed, failed, total| {
+            seen.lock().unwrap().push((completed, failed, total));
+        }));
+        tracker.set_total(200).await;
+
+        for i in 0..200 {
+            if i % 4 == 0 {
+                tracker.increment_failed("timed out").await;
+            } else {
+                tracker.increment_completed().await;
+            }
+        }
+
+        assert_eq!(tracker.percent().await, 100.0);
+        // Throttled well below one call per probe, ending on the totals
+        let calls = calls.lock().unwrap();
+        assert!(!calls.is_empty() && calls.len() < 200, "{} calls", calls.len());
+        assert_eq!(calls.last(), Some(&(150, 50, 200)));
+    }
+
+    #[test]
+    fn test_format_progress() {
+        assert_eq!(format_progress(250, 1000, Duration::from_secs(5)), "25.0% (250/1000) at 50/s");
+        assert_eq!(format_progress(0, 0, Duration::ZERO), "0.0% (0/0) at 0/s");
+    }
+}
@@ -1,37 +1,223 @@
+use async_trait::async_trait;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+/// Probes per adaptive window: the rate is reconsidered after this many outcomes.
+pub const AIMD_WINDOW: u32 = 50;
+/// A window with more than this fraction of timeouts counts as loss.
+pub const AIMD_LOSS_THRESHOLD: f64 = 0.05;
+/// Multiplicative decrease: the rate is scaled by this factor after a lossy window.
+pub const AIMD_DECREASE: f64 = 0.5;
+/// Additive increase per clean window, as a fraction of the maximum rate.
+pub const AIMD_INCREASE: f64 = 0.05;
+/// The adaptive rate never drops below this many requests per second.
+pub const AIMD_MIN_RATE: f64 = 1.0;
+/// Token bucket limiting requests per second.
+///
+/// A fixed limiter (`new`) always refills at the configured rate. An adaptive
+/// one (`new_adaptive`) uses AIMD congestion control, like nmap: outcomes fed
+/// through `record_outcome` are counted in windows of `AIMD_WINDOW` probes; a
+/// window with more than `AIMD_LOSS_THRESHOLD` timeouts multiplies the rate by
+/// `AIMD_DECREASE` (down to `AIMD_MIN_RATE`), while a clean one adds
+/// `AIMD_INCREASE` of the maximum (up to the maximum).
+///
+/// The bucket holds one second's worth of the current rate unless a burst
+/// size is set with `with_burst`.
 pub struct RateLimiter {
 tokens: Mutex<f64>,
-capacity: f64,
-refill_rate: f64,
+/// Read without awaiting, so it is behind a blocking lock
+refill_rate: std::sync::Mutex<f64>,
+/// Bucket size; `None` to follow the current rate
+capacity: Option<f64>,
 last_refill: Mutex<Instant>,
+/// Upper bound for the adaptive rate; `None` for a fixed limiter
+max_rate: Option<f64>,
+/// (successes, timeouts) in the current window
+window: Mutex<(u32, u32)>,
 }
 impl RateLimiter {
 pub fn new(requests_per_second: u32) -> Self {
 let capacity = requests_per_second as f64;
 Self {
 tokens: Mutex::new(capacity),
-capacity,
-refill_rate: requests_per_second as f64,
+refill_rate: std::sync::Mutex::new(requests_per_second as f64),
+capacity: None,
 last_refill: Mutex::new(Instant::now()),
+max_rate: None,
+window: Mutex::new((0, 0)),
 }
 }
+/// Fixed limiter sustaining `rate` requests per second with bursts of up
+/// to `burst` requests, available at once after an idle period.
+pub fn with_burst(rate: u32, burst: u32) -> Self {
+let capacity = burst.max(1) as f64;
+Self {
+tokens: Mutex::new(capacity),
+refill_rate: std::sync::Mutex::new(rate as f64),
+capacity: Some(capacity),
+last_refill: Mutex::new(Instant::now()),
+max_rate: None,
+window: Mutex::new((0, 0)),
+}
+}
+/// Adaptive limiter starting at `initial` requests per second and never exceeding `max`.
+pub fn new_adaptive(initial: u32, max: u32) -> Self {
+let max = max.max(1) as f64;
+let initial = (initial as f64).clamp(AIMD_MIN_RATE, max);
+Self {
+tokens: Mutex::new(initial),
+refill_rate: std::sync::Mutex::new(initial),
+capacity: None,
+last_refill: Mutex::new(Instant::now()),
+max_rate: Some(max),
+window: Mutex::new((0, 0)),
+}
+}
+/// Current refill rate in requests per second.
+pub fn current_rate(&self) -> f64 {
+*self.refill_rate.lock().unwrap()
+}
+/// Change the refill rate while running. An adaptive limiter carries on
+/// from the new rate, kept within its bounds.
+pub fn set_rate(&self, requests_per_second: u32) {
+let rate = requests_per_second as f64;
+*self.refill_rate.lock().unwrap() = match self.max_rate {
+Some(max_rate) => rate.clamp(AIMD_MIN_RATE, max_rate),
+None => rate,
+};
+}
+/// Report whether a probe was answered (`timed_out == false`) or lost.
+/// Adjusts the rate at the end of each window; no-op for fixed limiters.
+pub async fn record_outcome(&self, timed_out: bool) {
+let Some(max_rate) = self.max_rate else {
+return;
+};
+let mut window = self.window.lock().await;
+if timed_out {
+window.1 += 1;
+} else {
+window.0 += 1;
+}
+let (successes, timeouts) = *window;
+if successes + timeouts < AIMD_WINDOW {
+return;
+}
+*window = (0, 0);
+drop(window);
+let loss = timeouts as f64 / (successes + timeouts) as f64;
+let mut rate = self.refill_rate.lock().unwrap();
+*rate = if loss > AIMD_LOSS_THRESHOLD {
+(*rate * AIMD_DECREASE).max(AIMD_MIN_RATE)
+} else {
+(*rate + max_rate * AIMD_INCREASE).min(max_rate)
+};
+}
 pub async fn acquire(&self) {
 loop {
 let mut tokens = self.tokens.lock().await;
 let mut last_refill = self.last_refill.lock().await;
+// Without a burst size the bucket holds one second's worth of the current rate
+let refill_rate = *self.refill_rate.lock().unwrap();
+let capacity = self.capacity.unwrap_or(refill_rate).max(1.0);
 let now = Instant::now();
 let elapsed = now.duration_since(*last_refill).as_secs_f64();
-let new_tokens = (*tokens + elapsed * self.refill_rate).min(self.capacity);
+let new_tokens = (*tokens + elapsed * refill_rate).min(capacity);
 if new_tokens >= 1.0 {
 *tokens = new_tokens - 1.0;
 *last_refill = now;
 return;
 }
-let wait_time = Duration::from_secs_f64((1.0 - new_tokens) / self.refill_rate);
+let wait_time = Duration::from_secs_f64((1.0 - new_tokens) / refill_rate);
 drop(tokens);
 drop(last_refill);
 tokio::time::sleep(wait_time).await;
 }
 }
-}
\ No newline at end of file
+}
+/// Lets the orchestrator take this limiter, or any other, as a trait object
+#[async_trait]
+impl vajra_common::RateLimiter for RateLimiter {
+async fn acquire(&self) {
+RateLimiter::acquire(self).await
+}
+fn current_rate(&self) -> f64 {
+RateLimiter::current_rate(self)
+}
+fn set_rate(&self, rate: u64) {
+RateLimiter::set_rate(self, u32::try_from(rate).unwrap_or(u32::MAX))
+}
+async fn record_outcome(&self, timed_out: bool) {
+RateLimiter::record_outcome(self, timed_out).await
+}
+}
+#[cfg(test)]
+mod tests {
+use super::*;
+#[tokio::test]
+async fn adaptive_rate_is_aimd() {
+let limiter = RateLimiter::new_adaptive(1000, 2000);
+// A lossy window halves the rate
+for _ in 0..AIMD_WINDOW {
+limiter.record_outcome(true).await;
+}
+assert_eq!(limiter.current_rate(), 500.0);
+// A clean window adds 5% of the maximum
+for _ in 0..AIMD_WINDOW {
+limiter.record_outcome(false).await;
+}
+assert_eq!(limiter.current_rate(), 600.0);
+// Growth stops at the maximum
+for _ in 0..100 * AIMD_WINDOW {
+limiter.record_outcome(false).await;
+}
+assert_eq!(limiter.current_rate(), 2000.0);
+// Fixed limiters ignore outcomes
+let fixed = RateLimiter::new(100);
+for _ in 0..AIMD_WINDOW {
+fixed.record_outcome(true).await;
+}
+assert_eq!(fixed.current_rate(), 100.0);
+}
+#[tokio::test]
+async fn burst_is_available_after_idle() {
+let limiter = RateLimiter::with_burst(100, 50);
+// Drain the initial burst, then idle long enough to refill past it
+for _ in 0..50 {
+limiter.acquire().await;
+}
+tokio::time::sleep(Duration::from_millis(700)).await;
+let start = Instant::now();
+for _ in 0..50 {
+limiter.acquire().await;
+}
+assert!(start.elapsed() < Duration::from_millis(50), "burst took {:?}", start.elapsed());
+// The bucket held no more than the burst: the rest comes at 100/s
+let start = Instant::now();
+for _ in 0..10 {
+limiter.acquire().await;
+}
+assert!(start.elapsed() >= Duration::from_millis(80), "took {:?}", start.elapsed());
+}
+#[tokio::test]
+async fn set_rate_changes_refill_rate() {
+let fixed = RateLimiter::with_burst(100, 1);
+fixed.set_rate(5000);
+assert_eq!(fixed.current_rate(), 5000.0);
+// An adaptive limiter stays within its bounds
+let adaptive = RateLimiter::new_adaptive(100, 1000);
+adaptive.set_rate(5000);
+assert_eq!(adaptive.current_rate(), 1000.0);
+adaptive.set_rate(0);
+assert_eq!(adaptive.current_rate(), AIMD_MIN_RATE);
+}
+#[tokio::test]
+async fn usable_through_common_trait() {
+let limiter: Box<dyn vajra_common::RateLimiter> = Box::new(RateLimiter::new_adaptive(1000, 2000));
+for _ in 0..AIMD_WINDOW {
+limiter.record_outcome(true).await;
+}
+assert_eq!(limiter.current_rate(), 500.0);
+limiter.set_rate(u64::MAX);
+assert_eq!(limiter.current_rate(), 2000.0);
+limiter.acquire().await;
+}
+}
@@ -8,30 +8,75 @@
 //! - Fixed: Proper TCP response matching with sequence number validation
 
 use crate::error::SynError;
-use crate::packet::parse_packet;
+use crate::packet::{
+    parse_icmp_unreachable, parse_packet, parse_sctp_packet, stack_traits, tcp_flags, IcmpUnreachable, IPPROTO_SCTP,
+};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use std::net::IpAddr;
+use std::ops::RangeInclusive;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
-
-/// Key for pending probes: (dst_ip, dst_port, src_port, seq)
+use vajra_common::{DumpKind, DumpWriter, PcapWriter};
+
+/// Key for pending probes: (dst_ip, dst_port, src_port, expected_ack)
+///
+/// `expected_ack` is the value a valid reply carries: the ACK number when
+/// the reply has the ACK flag, otherwise its sequence number (RFC 793 reset
+/// generation). For a SYN probe it is `seq + 1`. For an SCTP INIT it is the
+/// Initiate Tag, which the reply carries as its verification tag.
 pub type PendingKey = (IpAddr, u16, u16, u32);
 
 /// Response data for a completed probe
 #[derive(Debug, Clone)]
 pub struct CaptureResponse {
+    /// TCP flags, or the first chunk type of an SCTP reply (0 for ICMP)
     pub flags: u8,
+    /// IP TTL (hop limit for IPv6) of the reply
+    pub ttl: u8,
+    /// TCP window of the reply (0 for SCTP and ICMP)
+    pub window: u16,
     pub rtt: Duration,
     pub recv_time: Instant,
+    /// (type, code) when the reply is an ICMP destination-unreachable
+    /// error quoting the probe rather than an answer from the port
+    pub icmp: Option<(u8, u8)>,
 }
 
 /// Global map of pending probes - shared between send and capture
 pub static PENDING_PROBES: Lazy<DashMap<PendingKey, (Instant, oneshot::Sender<CaptureResponse>)>> =
     Lazy::new(DashMap::new);
 
+/// Optional sink for the raw bytes of matched replies
+static PACKET_DUMP: Lazy<RwLock<Option<Arc<DumpWriter>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Record every reply that completes a probe (from the IP header on),
+/// keyed by the probed target. `None` turns recording off.
+pub fn set_packet_dump(writer: Option<Arc<DumpWriter>>) {
+    *PACKET_DUMP.write() = writer;
+}
+
+/// Optional pcap sink for sent probes and matched replies
+static PCAP: Lazy<RwLock<Option<Arc<PcapWriter>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Write sent probes and the replies that complete them to a pcap file.
+/// `None` turns recording off.
+pub fn set_pcap_writer(writer: Option<Arc<PcapWriter>>) {
+    *PCAP.write() = writer;
+}
+
+/// Record a packet (from the IP header on) to the pcap sink, if any
+pub(crate) fn record_pcap(packet: &[u8]) {
+    if let Some(pcap) = PCAP.read().as_ref() {
+        if let Err(e) = pcap.write_packet(packet) {
+            eprintln!("[CAPTURE] Failed to write pcap record: {}", e);
+        }
+    }
+}
+
 /// Capture loop statistics
 pub static CAPTURE_STATS: Lazy<CaptureStats> = Lazy::new(CaptureStats::default);
 
@@ -43,68 +88,123 @@ pub struct CaptureStats {
     pub packets_no_match: std::sync::atomic::AtomicU64,
 }
 
+/// Owner of a running capture loop.
+///
+/// Dropping the handle (or calling `stop`) signals the capture thread to
+/// exit, which closes its sockets, and joins it. Any background task
+/// attached with `attach_task` is aborted at the same time.
+pub struct CaptureHandle {
+    shutdown: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl CaptureHandle {
+    /// Whether the capture thread is still running
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// Stop the capture loop and wait for its thread to exit
+    pub fn stop(mut self) {
+        self.shutdown_and_join();
+    }
+
+    /// Tie a background task (e.g. expired-probe cleanup) to this capture
+    pub(crate) fn attach_task(&mut self, task: tokio::task::JoinHandle<()>) {
+        self.tasks.push(task);
+    }
+
+    fn shutdown_and_join(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for CaptureHandle {
+    fn drop(&mut self) {
+        self.shutdown_and_join();
+    }
+}
+
 /// Start the high-performance capture loop in a dedicated thread
 /// 
 /// This function spawns a blocking thread that uses AF_PACKET to capture
-/// all TCP packets and demultiplex them to pending probes.
-pub fn start_capture_loop(shutdown: Arc<AtomicBool>) -> Result<(), SynError> {
-    std::thread::Builder::new()
+/// all TCP and SCTP packets, and ICMP errors quoting our probes, and
+/// demultiplex them to pending probes. The loop runs until the returned
+/// handle is stopped or dropped.
+///
+/// With `port_filter`, a BPF program is attached so the kernel only
+/// delivers TCP and SCTP packets whose destination port (our probe source
+/// port) is in range, plus ICMP destination-unreachable errors. If
+/// attaching fails the loop logs it and captures unfiltered.
+pub fn start_capture_loop(port_filter: Option<RangeInclusive<u16>>) -> Result<CaptureHandle, SynError> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = shutdown.clone();
+    let thread = std::thread::Builder::new()
         .name("capture-loop".to_string())
         .spawn(move || {
-            if let Err(e) = run_capture_loop(&shutdown) {
+            if let Err(e) = run_capture_loop(&flag, port_filter) {
                 eprintln!("Capture loop error: {:?}", e);
             }
         })
-        .map_err(|e| SynError::Io(e))?;
+        .map_err(SynError::Io)?;
 
-    Ok(())
+    Ok(CaptureHandle {
+        shutdown,
+        thread: Some(thread),
+        tasks: Vec::new(),
+    })
 }
 
 /// Main capture loop - runs in dedicated thread
-fn run_capture_loop(shutdown: &AtomicBool) -> Result<(), SynError> {
+fn run_capture_loop(shutdown: &AtomicBool, port_filter: Option<RangeInclusive<u16>>) -> Result<(), SynError> {
     #[cfg(target_os = "linux")]
     {
-        use libc::{AF_PACKET, ETH_P_IP, SOCK_RAW};
-        
-        // Create raw packet socket
-        let sock_fd = unsafe {
-            libc::socket(AF_PACKET, SOCK_RAW, (ETH_P_IP as u16).to_be() as i32)
-        };
-
-        if sock_fd < 0 {
-            return Err(SynError::NotPermitted);
+        use libc::{ETH_P_IP, ETH_P_IPV6};
+
+        // One socket per EtherType; IPv6 capture is best effort so IPv4
+        // scanning still works on hosts without IPv6
+        let mut sock_fds = vec![open_capture_socket(ETH_P_IP)?];
+        match open_capture_socket(ETH_P_IPV6) {
+            Ok(fd) => sock_fds.push(fd),
+            Err(e) => eprintln!("[CAPTURE] IPv6 capture unavailable: {:?}", e),
         }
 
-        // Set socket to non-blocking
-        unsafe {
-            let flags = libc::fcntl(sock_fd, libc::F_GETFL, 0);
-            libc::fcntl(sock_fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
-        }
-
-        // Increase socket buffer size for high throughput
-        unsafe {
-            let bufsize: libc::c_int = 32 * 1024 * 1024; // 32MB (increased from 16MB)
-            libc::setsockopt(
-                sock_fd,
-                libc::SOL_SOCKET,
-                libc::SO_RCVBUF,
-                &bufsize as *const _ as *const libc::c_void,
-                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
-            );
+        if let Some(ports) = port_filter {
+            for (i, &fd) in sock_fds.iter().enumerate() {
+                let program = port_filter_program(i == 1, ports.clone());
+                if let Err(e) = attach_filter(fd, &program) {
+                    eprintln!("[CAPTURE] Failed to attach BPF filter, capturing unfiltered: {}", e);
+                }
+            }
         }
 
         let mut recv_buf = vec![0u8; 65536]; // Preallocate buffer
+        let mut next_fd = 0;
+        let mut idle_fds = 0;
 
         eprintln!("[CAPTURE] Started capture loop");
 
         // Main capture loop
         loop {
             if shutdown.load(Ordering::Relaxed) {
-                unsafe { libc::close(sock_fd); }
+                for &fd in &sock_fds {
+                    unsafe { libc::close(fd); }
+                }
                 eprintln!("[CAPTURE] Shutting down");
                 break;
             }
 
+            // Round-robin between the capture sockets
+            let sock_fd = sock_fds[next_fd];
+            next_fd = (next_fd + 1) % sock_fds.len();
+
             // Receive packet (non-blocking)
             let recv_len = unsafe {
                 libc::recv(
@@ -118,13 +218,18 @@ fn run_capture_loop(shutdown: &AtomicBool) -> Result<(), SynError> {
             if recv_len < 0 {
                 let err = std::io::Error::last_os_error();
                 if err.kind() == std::io::ErrorKind::WouldBlock {
-                    // No packets available, brief sleep
-                    std::thread::sleep(Duration::from_micros(50)); // Reduced from 100
+                    // Sleep briefly once every socket has come up empty
+                    idle_fds += 1;
+                    if idle_fds >= sock_fds.len() {
+                        idle_fds = 0;
+                        std::thread::sleep(Duration::from_micros(50)); // Reduced from 100
+                    }
                     continue;
                 }
                 CAPTURE_STATS.packets_dropped.fetch_add(1, Ordering::Relaxed);
                 continue;
             }
+            idle_fds = 0;
 
             if recv_len == 0 {
                 continue;
@@ -141,62 +246,7 @@ fn run_capture_loop(shutdown: &AtomicBool) -> Result<(), SynError> {
             }
             let ip_packet = &packet_data[14..];
 
-            if let Some((src_ip, src_port, _dst_ip, dst_port, flags, _, _)) = parse_packet(ip_packet) {
-                // CRITICAL FIX: Match all pending probes that match this response
-                // For a SYN-ACK or RST response:
-                // - src_ip/src_port = remote server (our dst in original probe)
-                // - dst_ip/dst_port = our local (our src in original probe)
-                
-                // Look for matching probes without collecting into vec first
-                // This allows us to match ALL pending probes for this response
-                let mut matched = false;
-                
-                // We need to find probes where:
-                // probe.dst_ip == src_ip (response from target)
-                // probe.dst_port == src_port (response from target port)
-                // probe.src_port == dst_port (response to our source port)
-                
-                // IMPORTANT: We can't match on seq here because we don't parse ACK number
-                // So we match on IP/port tuple only
-                
-                // Collect matching keys first to avoid holding iterator during removal
-                let matching_keys: Vec<PendingKey> = PENDING_PROBES
-                    .iter()
-                    .filter(|entry| {
-                        let key = entry.key();
-                        // key.0 = dst_ip (target we're scanning)
-                        // key.1 = dst_port (port we're scanning)
-                        // key.2 = src_port (our ephemeral port)
-                        // key.3 = seq (our sequence number)
-                        
-                        key.0 == src_ip && key.1 == src_port && key.2 == dst_port
-                    })
-                    .map(|entry| *entry.key())
-                    .collect();
-
-                // Process ALL matching probes (CRITICAL FIX - removed break statement)
-                for key in matching_keys {
-                    if let Some((_, (start_time, tx))) = PENDING_PROBES.remove(&key) {
-                        let rtt = start_time.elapsed();
-                        let response = CaptureResponse {
-                            flags,
-                            rtt,
-                            recv_time: Instant::now(),
-                        };
-
-                        // Send response to waiting probe (ignore if receiver dropped)
-                        if tx.send(response).is_ok() {
-                            matched = true;
-                            CAPTURE_STATS.packets_matched.fetch_add(1, Ordering::Relaxed);
-                        }
-                        // REMOVED: break; // This was causing the bug!
-                    }
-                }
-                
-                if !matched {
-                    CAPTURE_STATS.packets_no_match.fetch_add(1, Ordering::Relaxed);
-                }
-            }
+            handle_packet(ip_packet);
         }
 
         Ok(())
@@ -204,10 +254,228 @@ fn run_capture_loop(shutdown: &AtomicBool) -> Result<(), SynError> {
 
     #[cfg(not(target_os = "linux"))]
     {
+        let _ = (shutdown, port_filter);
         Err(SynError::NotImplemented)
     }
 }
 
+/// Classic BPF opcodes used by `port_filter_program` (linux/filter.h)
+#[cfg(target_os = "linux")]
+mod bpf {
+    pub const LD_B_ABS: u16 = 0x30;
+    pub const LD_H_ABS: u16 = 0x28;
+    pub const LD_H_IND: u16 = 0x48;
+    pub const LD_B_IND: u16 = 0x50;
+    pub const LDX_B_MSH: u16 = 0xb1;
+    pub const JEQ_K: u16 = 0x15;
+    pub const JGT_K: u16 = 0x25;
+    pub const JGE_K: u16 = 0x35;
+    pub const JSET_K: u16 = 0x45;
+    pub const RET_K: u16 = 0x06;
+    /// Snap length returned to accept a whole packet
+    pub const ACCEPT: u32 = 0x40000;
+}
+
+/// Build a BPF program for an `AF_PACKET` socket (offsets include the
+/// 14-byte Ethernet header) accepting only TCP and SCTP packets whose
+/// destination port is in `ports`; both put the ports in the first four
+/// bytes of their header. IPv4 fragments past the first are dropped since
+/// they carry no transport header. ICMP destination-unreachable errors
+/// are accepted whatever they quote; they are rare and matched later.
+#[cfg(target_os = "linux")]
+fn port_filter_program(ipv6: bool, ports: RangeInclusive<u16>) -> Vec<libc::sock_filter> {
+    let op = |code, jt, jf, k| libc::sock_filter { code, jt, jf, k };
+    let (lo, hi) = (u32::from(*ports.start()), u32::from(*ports.end()));
+
+    if ipv6 {
+        vec![
+            op(bpf::LD_B_ABS, 0, 0, 14 + 6),  // next header
+            op(bpf::JEQ_K, 0, 2, 58),         // ICMPv6?
+            op(bpf::LD_B_ABS, 0, 0, 14 + 40), // ICMPv6 type
+            op(bpf::JEQ_K, 5, 6, 1),          // destination unreachable?
+            op(bpf::JEQ_K, 1, 0, 6),          // TCP?
+            op(bpf::JEQ_K, 0, 4, 132),        // SCTP?
+            op(bpf::LD_H_ABS, 0, 0, 14 + 40 + 2), // destination port
+            op(bpf::JGE_K, 0, 2, lo),
+            op(bpf::JGT_K, 1, 0, hi),
+            op(bpf::RET_K, 0, 0, bpf::ACCEPT),
+            op(bpf::RET_K, 0, 0, 0),
+        ]
+    } else {
+        vec![
+            op(bpf::LD_B_ABS, 0, 0, 14 + 9),  // protocol
+            op(bpf::JEQ_K, 0, 3, 1),          // ICMP?
+            op(bpf::LDX_B_MSH, 0, 0, 14),     // X = IP header length
+            op(bpf::LD_B_IND, 0, 0, 14),      // ICMP type
+            op(bpf::JEQ_K, 8, 9, 3),          // destination unreachable?
+            op(bpf::JEQ_K, 1, 0, 6),          // TCP?
+            op(bpf::JEQ_K, 0, 7, 132),        // SCTP?
+            op(bpf::LD_H_ABS, 0, 0, 14 + 6),  // flags + fragment offset
+            op(bpf::JSET_K, 5, 0, 0x1fff),    // non-first fragment?
+            op(bpf::LDX_B_MSH, 0, 0, 14),     // X = IP header length
+            op(bpf::LD_H_IND, 0, 0, 14 + 2),  // destination port
+            op(bpf::JGE_K, 0, 2, lo),
+            op(bpf::JGT_K, 1, 0, hi),
+            op(bpf::RET_K, 0, 0, bpf::ACCEPT),
+            op(bpf::RET_K, 0, 0, 0),
+        ]
+    }
+}
+
+/// Attach a classic BPF program to a socket with `SO_ATTACH_FILTER`
+#[cfg(target_os = "linux")]
+fn attach_filter(fd: i32, program: &[libc::sock_filter]) -> std::io::Result<()> {
+    let prog = libc::sock_fprog {
+        len: program.len() as libc::c_ushort,
+        filter: program.as_ptr() as *mut libc::sock_filter,
+    };
+    let rc = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_ATTACH_FILTER,
+            &prog as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::sock_fprog>() as libc::socklen_t,
+        )
+    };
+    if rc < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Open a non-blocking `AF_PACKET` socket receiving frames of `ethertype`
+#[cfg(target_os = "linux")]
+fn open_capture_socket(ethertype: libc::c_int) -> Result<i32, SynError> {
+    let sock_fd = unsafe {
+        libc::socket(libc::AF_PACKET, libc::SOCK_RAW, (ethertype as u16).to_be() as i32)
+    };
+
+    if sock_fd < 0 {
+        return Err(SynError::NotPermitted);
+    }
+
+    // Set socket to non-blocking
+    unsafe {
+        let flags = libc::fcntl(sock_fd, libc::F_GETFL, 0);
+        libc::fcntl(sock_fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+
+    // Increase socket buffer size for high throughput
+    unsafe {
+        let bufsize: libc::c_int = 32 * 1024 * 1024; // 32MB (increased from 16MB)
+        libc::setsockopt(
+            sock_fd,
+            libc::SOL_SOCKET,
+            libc::SO_RCVBUF,
+            &bufsize as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        );
+    }
+
+    Ok(sock_fd)
+}
+
+/// Match a captured IP packet against pending probes, updating stats and
+/// recording it to the packet dump when it completes a probe.
+fn handle_packet(ip_packet: &[u8]) {
+    let traits = stack_traits(ip_packet).unwrap_or_default();
+    let (src_ip, src_port, matched) =
+        if let Some((src_ip, src_port, _, dst_port, flags, seq, ack, _, _)) = parse_packet(ip_packet) {
+            (src_ip, src_port, complete_probe(src_ip, src_port, dst_port, flags, seq, ack, traits))
+        } else if let Some((src_ip, src_port, _, dst_port, vtag, chunk)) = parse_sctp_packet(ip_packet) {
+            (src_ip, src_port, complete_sctp_probe(src_ip, src_port, dst_port, vtag, chunk, traits))
+        } else if let Some(icmp) = parse_icmp_unreachable(ip_packet) {
+            // Recorded under the probed target, like the replies it stands in for
+            (icmp.target, icmp.dst_port, complete_icmp_probe(&icmp, traits.0))
+        } else {
+            return;
+        };
+
+    if matched {
+        CAPTURE_STATS.packets_matched.fetch_add(1, Ordering::Relaxed);
+        record_pcap(ip_packet);
+        if let Some(dump) = PACKET_DUMP.read().as_ref() {
+            if let Err(e) = dump.write(DumpKind::Packet, src_ip, src_port, ip_packet) {
+                eprintln!("[CAPTURE] Failed to write packet dump: {}", e);
+            }
+        }
+    } else {
+        CAPTURE_STATS.packets_no_match.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Complete the pending probe answered by a captured response.
+///
+/// For a SYN-ACK or RST reply the response's source is the probe's target
+/// and its destination port is our source port. Matching on the
+/// acknowledgment (see `PendingKey`) keeps concurrent probes that share the
+/// same (ip, port, src_port) tuple from cross-matching.
+/// `traits` is the reply's (TTL, window) from `stack_traits`.
+/// Returns `true` if a waiting probe received the response.
+fn complete_probe(
+    src_ip: IpAddr,
+    src_port: u16,
+    dst_port: u16,
+    flags: u8,
+    seq: u32,
+    ack: u32,
+    traits: (u8, u16),
+) -> bool {
+    // A reset without ACK takes its sequence number from our probe's ACK field
+    let token = if flags & tcp_flags::ACK != 0 { ack } else { seq };
+    deliver((src_ip, src_port, dst_port, token), flags, traits)
+}
+
+/// Complete the pending SCTP INIT probe answered by a captured INIT-ACK or
+/// ABORT. Both carry the INIT's Initiate Tag as their verification tag
+/// (RFC 4960 sections 5.1 and 8.4), which plays the role of the ACK.
+/// `chunk` is delivered in place of TCP flags.
+fn complete_sctp_probe(src_ip: IpAddr, src_port: u16, dst_port: u16, vtag: u32, chunk: u8, traits: (u8, u16)) -> bool {
+    deliver((src_ip, src_port, dst_port, vtag), chunk, traits)
+}
+
+/// Complete the pending probe quoted by an ICMP destination-unreachable
+/// error. The quote carries the probe's own sequence number (or SCTP
+/// Initiate Tag) rather than an acknowledgment of it, so both keys a
+/// probe may be waiting on are tried: `seq + 1` (SYN, FIN, Xmas), then
+/// `seq` (NULL, ACK, SCTP INIT).
+fn complete_icmp_probe(icmp: &IcmpUnreachable, ttl: u8) -> bool {
+    let key = |token| (icmp.target, icmp.dst_port, icmp.src_port, token);
+    let first = if icmp.protocol == IPPROTO_SCTP {
+        icmp.token
+    } else {
+        icmp.token.wrapping_add(1)
+    };
+    let error = Some((icmp.icmp_type, icmp.code));
+    deliver_with(key(first), 0, (ttl, 0), error) || deliver_with(key(icmp.token), 0, (ttl, 0), error)
+}
+
+/// Hand `flags` and the (TTL, window) `traits` to the probe waiting on
+/// `key`, if any
+fn deliver(key: PendingKey, flags: u8, traits: (u8, u16)) -> bool {
+    deliver_with(key, flags, traits, None)
+}
+
+/// `deliver`, marking the response as the ICMP error `icmp` if given
+fn deliver_with(key: PendingKey, flags: u8, (ttl, window): (u8, u16), icmp: Option<(u8, u8)>) -> bool {
+    let Some((_, (start_time, tx))) = PENDING_PROBES.remove(&key) else {
+        return false;
+    };
+
+    let response = CaptureResponse {
+        flags,
+        ttl,
+        window,
+        rtt: start_time.elapsed(),
+        recv_time: Instant::now(),
+        icmp,
+    };
+    // Receiver may have timed out and been dropped
+    tx.send(response).is_ok()
+}
+
 /// Cleanup expired pending probes (should be called periodically)
 pub fn cleanup_expired_probes(max_age: Duration) {
     let now = Instant::now();
@@ -251,8 +519,12 @@ pub fn print_capture_stats() {
 mod tests {
     use super::*;
 
+    /// Serializes tests that use the global `PENDING_PROBES` map.
+    static MAP_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
     #[test]
     fn test_pending_map() {
+        let _map = MAP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         // Ensure no leftover entries from other tests
         let existing_keys: Vec<_> = PENDING_PROBES.iter().map(|e| *e.key()).collect();
         for k in existing_keys {
@@ -277,6 +549,7 @@ mod tests {
     
     #[test]
     fn test_multiple_probes_same_target() {
+        let _map = MAP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         // Ensure no leftover entries from other tests
         let existing_keys: Vec<_> = PENDING_PROBES.iter().map(|e| *e.key()).collect();
         for k in existing_keys {
@@ -299,5 +572,204 @@ mod tests {
         PENDING_PROBES.insert(key3, (Instant::now(), tx3));
         
         assert_eq!(PENDING_PROBES.len(), 3);
+        PENDING_PROBES.clear();
+    }
+
+    #[test]
+    fn test_match_requires_ack_of_seq() {
+        let _map = MAP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        PENDING_PROBES.clear();
+
+        // Two SYN probes sharing the same tuple, distinguished only by seq
+        // (seq 1000 and u32::MAX, keyed by the expected ACK of seq + 1)
+        let ip: IpAddr = "10.9.9.9".parse().unwrap();
+        let (tx1, mut rx1) = oneshot::channel();
+        let (tx2, mut rx2) = oneshot::channel();
+        PENDING_PROBES.insert((ip, 80, 40000, 1001), (Instant::now(), tx1));
+        PENDING_PROBES.insert((ip, 80, 40000, 0), (Instant::now(), tx2));
+
+        // ACK must be seq + 1; acknowledging the seq itself is not a match
+        assert!(!complete_probe(ip, 80, 40000, 0x12, 7, 1000, (64, 64240)));
+        assert!(complete_probe(ip, 80, 40000, 0x12, 7, 1001, (64, 64240)));
+        let response = rx1.try_recv().unwrap();
+        assert_eq!((response.flags, response.ttl, response.window), (0x12, 64, 64240));
+        assert!(rx2.try_recv().is_err());
+
+        // seq + 1 wraps around
+        assert!(complete_probe(ip, 80, 40000, 0x14, 0, 0, (64, 0)));
+        assert_eq!(rx2.try_recv().unwrap().flags, 0x14);
+        assert!(PENDING_PROBES.is_empty());
+
+        // A bare RST (answer to an ACK probe) is matched on its sequence number
+        let (tx3, mut rx3) = oneshot::channel();
+        PENDING_PROBES.insert((ip, 80, 40001, 5555), (Instant::now(), tx3));
+        assert!(complete_probe(ip, 80, 40001, tcp_flags::RST, 5555, 0, (64, 0)));
+        assert_eq!(rx3.try_recv().unwrap().flags, tcp_flags::RST);
+    }
+
+    #[test]
+    fn test_capture_handle_stop_joins_thread() {
+        // Without CAP_NET_RAW the loop exits on its own; with it, the
+        // shutdown flag ends it. Either way the thread is joined.
+        let mut handle = start_capture_loop(None).unwrap();
+        handle.shutdown_and_join();
+        assert!(handle.shutdown.load(Ordering::Relaxed));
+        assert!(!handle.is_running());
+        assert!(handle.thread.is_none());
+    }
+
+    #[test]
+    fn test_matched_reply_is_dumped() {
+        use crate::packet::{build_tcp_probe, DEFAULT_TTL};
+
+        let _map = MAP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = std::env::temp_dir().join(format!("vajra_capture_dump_{}.bin", std::process::id()));
+        set_packet_dump(Some(Arc::new(DumpWriter::create(&path).unwrap())));
+
+        let target: IpAddr = "192.0.2.80".parse().unwrap();
+        let us: IpAddr = "192.0.2.1".parse().unwrap();
+        let (tx, _rx) = oneshot::channel();
+        PENDING_PROBES.insert((target, 80, 40100, 1001), (Instant::now(), tx));
+
+        // SYN-ACK from the target acknowledging our seq 1000
+        let mut reply = vec![0u8; 60];
+        let len = build_tcp_probe(&mut reply, &target, &us, 80, 40100, 7, 1001, 0x12, DEFAULT_TTL);
+        handle_packet(&reply[..len]);
+        // Unmatched packets are not recorded
+        handle_packet(&reply[..len]);
+        set_packet_dump(None);
+
+        let records = vajra_common::dump::read_dump_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].kind, records[0].ip, records[0].port), (DumpKind::Packet, target, 80));
+        assert_eq!(records[0].data, &reply[..len]);
+    }
+
+    /// Minimal interpreter for the opcodes `port_filter_program` emits
+    #[cfg(target_os = "linux")]
+    fn run_filter(program: &[libc::sock_filter], pkt: &[u8]) -> u32 {
+        let (mut a, mut x, mut pc) = (0u32, 0u32, 0usize);
+        let half = |off: usize| u32::from(u16::from_be_bytes([pkt[off], pkt[off + 1]]));
+        loop {
+            let ins = &program[pc];
+            let k = ins.k;
+            pc += 1;
+            let jump = |cond: bool| if cond { ins.jt as usize } else { ins.jf as usize };
+            match ins.code {
+                bpf::LD_B_ABS => a = u32::from(pkt[k as usize]),
+                bpf::LD_H_ABS => a = half(k as usize),
+                bpf::LD_H_IND => a = half((x + k) as usize),
+                bpf::LD_B_IND => a = u32::from(pkt[(x + k) as usize]),
+                bpf::LDX_B_MSH => x = 4 * u32::from(pkt[k as usize] & 0x0f),
+                bpf::JEQ_K => pc += jump(a == k),
+                bpf::JGT_K => pc += jump(a > k),
+                bpf::JGE_K => pc += jump(a >= k),
+                bpf::JSET_K => pc += jump(a & k != 0),
+                bpf::RET_K => return k,
+                other => panic!("unexpected opcode {other:#x}"),
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_port_filter_program() {
+        use crate::packet::{build_tcp_probe, DEFAULT_TTL};
+
+        let frame = |src: IpAddr, dst: IpAddr, dst_port: u16| {
+            let mut buf = vec![0u8; 14 + 60];
+            let len = build_tcp_probe(&mut buf[14..], &src, &dst, 80, dst_port, 1, 0, 0x12, DEFAULT_TTL);
+            buf.truncate(14 + len);
+            buf
+        };
+
+        let (t4, us4): (IpAddr, IpAddr) = ("192.0.2.80".parse().unwrap(), "192.0.2.1".parse().unwrap());
+        let v4 = port_filter_program(false, 32768..=65535);
+        assert_eq!(run_filter(&v4, &frame(t4, us4, 40000)), bpf::ACCEPT);
+        assert_eq!(run_filter(&v4, &frame(t4, us4, 32768)), bpf::ACCEPT);
+        assert_eq!(run_filter(&v4, &frame(t4, us4, 443)), 0);
+
+        // Non-TCP and later fragments are dropped
+        let mut udp = frame(t4, us4, 40000);
+        udp[14 + 9] = 17;
+        assert_eq!(run_filter(&v4, &udp), 0);
+        let mut fragment = frame(t4, us4, 40000);
+        fragment[14 + 7] = 0x10;
+        assert_eq!(run_filter(&v4, &fragment), 0);
+
+        // SCTP replies are accepted on the same port range
+        let mut sctp = vec![0u8; 14 + 80];
+        let len = crate::packet::build_sctp_init(&mut sctp[14..], &t4, &us4, 2905, 40000, 1, DEFAULT_TTL);
+        sctp.truncate(14 + len);
+        assert_eq!(run_filter(&v4, &sctp), bpf::ACCEPT);
+
+        let (t6, us6): (IpAddr, IpAddr) = ("2001:db8::80".parse().unwrap(), "2001:db8::1".parse().unwrap());
+        let v6 = port_filter_program(true, 53..=53);
+        assert_eq!(run_filter(&v6, &frame(t6, us6, 53)), bpf::ACCEPT);
+        assert_eq!(run_filter(&v6, &frame(t6, us6, 54)), 0);
+
+        // ICMP destination unreachable passes whatever port it quotes;
+        // other ICMP (here an echo reply) does not
+        let mut icmp = frame(t4, us4, 443);
+        icmp[14 + 9] = 1;
+        icmp[14 + 20] = 3;
+        assert_eq!(run_filter(&v4, &icmp), bpf::ACCEPT);
+        icmp[14 + 20] = 0;
+        assert_eq!(run_filter(&v4, &icmp), 0);
+        let mut icmp6 = frame(t6, us6, 443);
+        icmp6[14 + 6] = 58;
+        icmp6[14 + 40] = 1;
+        assert_eq!(run_filter(&v6, &icmp6), bpf::ACCEPT);
+        icmp6[14 + 40] = 129;
+        assert_eq!(run_filter(&v6, &icmp6), 0);
+    }
+
+    #[test]
+    fn test_icmp_unreachable_completes_probe() {
+        use crate::packet::{build_syn_packet, DEFAULT_TTL};
+
+        let _map = MAP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let target: IpAddr = "192.0.2.95".parse().unwrap();
+        let us: IpAddr = "192.0.2.1".parse().unwrap();
+        let (tx, mut rx) = oneshot::channel();
+        PENDING_PROBES.insert((target, 443, 40300, 5001), (Instant::now(), tx));
+
+        // Admin-prohibited from a firewall, quoting our SYN with seq 5000
+        let mut probe = [0u8; 60];
+        let len = build_syn_packet(&mut probe, &us, &target, 40300, 443, 5000, DEFAULT_TTL);
+        let mut error = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 60, 1, 0, 0, 198, 51, 100, 1, 192, 0, 2, 1];
+        error.extend_from_slice(&[3, 13, 0, 0, 0, 0, 0, 0]);
+        error.extend_from_slice(&probe[..28.min(len)]);
+
+        handle_packet(&error);
+        let response = rx.try_recv().unwrap();
+        assert_eq!((response.icmp, response.flags, response.ttl), (Some((3, 13)), 0, 60));
+        assert!(PENDING_PROBES.is_empty());
+    }
+
+    #[test]
+    fn test_sctp_reply_completes_probe() {
+        use crate::packet::{build_sctp_init, sctp_chunks, DEFAULT_TTL};
+
+        let _map = MAP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let target: IpAddr = "192.0.2.90".parse().unwrap();
+        let us: IpAddr = "192.0.2.1".parse().unwrap();
+        let (tx, mut rx) = oneshot::channel();
+        PENDING_PROBES.insert((target, 2905, 40200, 0xabcd), (Instant::now(), tx));
+
+        // ABORT from the target carrying our Initiate Tag
+        let mut reply = vec![0u8; 80];
+        let len = build_sctp_init(&mut reply, &target, &us, 2905, 40200, 1, DEFAULT_TTL);
+        reply[24..28].copy_from_slice(&0xabcdu32.to_be_bytes());
+        reply[32] = sctp_chunks::ABORT;
+
+        // A different tag does not match
+        reply[27] ^= 1;
+        handle_packet(&reply[..len]);
+        assert!(rx.try_recv().is_err());
+        reply[27] ^= 1;
+        handle_packet(&reply[..len]);
+        assert_eq!(rx.try_recv().unwrap().flags, sctp_chunks::ABORT);
     }
 }
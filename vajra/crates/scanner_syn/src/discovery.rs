@@ -0,0 +1,206 @@
+//! Host discovery (ping sweep)
+//!
+//! Finds the hosts that are up before a port scan, so dead addresses in a
+//! large range cost no port probes. Each address gets an ICMP echo request
+//! over a raw ICMP socket; hosts that ignore ping can also be tried with a
+//! TCP SYN to ports 80 and 443 and an ACK to port 80. Any reply, even a
+//! reset, means the host is up.
+
+use crate::error::SynError;
+use crate::packet::{build_icmp_echo, parse_icmp_echo_reply};
+use crate::syn::{ScanFlavor, SynScanner};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::collections::HashSet;
+use std::io::{self, ErrorKind};
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use vajra_common::Target;
+
+/// Ports sent a TCP SYN when ping gets no answer
+const SYN_PORTS: [u16; 2] = [80, 443];
+/// Port sent a TCP ACK when ping gets no answer
+const ACK_PORT: u16 = 80;
+
+/// Ping sweep over a set of addresses
+pub struct HostDiscovery {
+    timeout: Duration,
+    retries: u32,
+    tcp_probes: bool,
+}
+
+impl HostDiscovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how long to wait for replies after each round of probes
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how many more rounds are sent to hosts that have not answered
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Also send TCP probes (SYN to 80 and 443, ACK to 80) to hosts that do
+    /// not answer ping. Needs the capture loop, which is started for the
+    /// duration of the probes.
+    pub fn with_tcp_probes(mut self, enabled: bool) -> Self {
+        self.tcp_probes = enabled;
+        self
+    }
+
+    /// Addresses among `ips` that answered. Fails with
+    /// [`SynError::NotPermitted`] without raw socket privileges.
+    pub async fn discover(&self, ips: &[IpAddr]) -> Result<HashSet<IpAddr>, SynError> {
+        if ips.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let (timeout, retries) = (self.timeout, self.retries);
+        let targets = ips.to_vec();
+        let mut alive = tokio::task::spawn_blocking(move || icmp_sweep(&targets, timeout, retries))
+            .await
+            .map_err(|e| SynError::Capture(format!("ping sweep task failed: {}", e)))??;
+
+        if self.tcp_probes {
+            let silent: Vec<IpAddr> = ips.iter().filter(|ip| !alive.contains(*ip)).copied().collect();
+            if !silent.is_empty() {
+                alive.extend(self.tcp_sweep(&silent).await?);
+            }
+        }
+        Ok(alive)
+    }
+
+    /// Hosts among `ips` that answer a SYN or ACK probe in any way
+    async fn tcp_sweep(&self, ips: &[IpAddr]) -> Result<HashSet<IpAddr>, SynError> {
+        let syn = SynScanner::new().with_timeout(self.timeout).with_retries(self.retries);
+        let ack = SynScanner::new()
+            .with_timeout(self.timeout)
+            .with_retries(self.retries)
+            .with_flavor(ScanFlavor::Ack);
+        // Both scanners use random ephemeral source ports, so one capture serves both
+        let _capture = crate::init(&syn)?;
+
+        let syn_targets = ips
+            .iter()
+            .flat_map(|&ip| SYN_PORTS.map(|port| Target::tcp(ip, port)))
+            .collect();
+        let ack_targets = ips.iter().map(|&ip| Target::tcp(ip, ACK_PORT)).collect();
+        let mut results = syn.probe_batch(syn_targets, self.timeout).await?;
+        results.extend(ack.probe_batch(ack_targets, self.timeout).await?);
+
+        // Unanswered SYNs are open|filtered and unanswered ACKs filtered
+        Ok(results
+            .into_iter()
+            .filter(|r| !r.is_filtered())
+            .map(|r| r.target.ip)
+            .collect())
+    }
+}
+
+impl Default for HostDiscovery {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            retries: 1,
+            tcp_probes: false,
+        }
+    }
+}
+
+/// Send echo requests to every address in rounds, collecting the sources
+/// of matching echo replies. Blocking; run it off the async runtime.
+fn icmp_sweep(ips: &[IpAddr], wait: Duration, retries: u32) -> Result<HashSet<IpAddr>, SynError> {
+    // Tells our replies apart from those to other pings on the host
+    let id = rand::random::<u16>();
+    let mut alive = HashSet::new();
+
+    for ipv6 in [false, true] {
+        let family: HashSet<IpAddr> = ips.iter().filter(|ip| ip.is_ipv6() == ipv6).copied().collect();
+        if family.is_empty() {
+            continue;
+        }
+        let socket = open_icmp_socket(ipv6)?;
+        let mut buf = [0u8; 1500];
+
+        for _ in 0..=retries {
+            for (seq, ip) in family.iter().enumerate().filter(|(_, ip)| !alive.contains(*ip)) {
+                let len = build_icmp_echo(&mut buf, ipv6, id, seq as u16);
+                // An unreachable host just stays silent
+                let _ = socket.send_to(&buf[..len], &SockAddr::from(SocketAddr::new(*ip, 0)));
+            }
+
+            let deadline = Instant::now() + wait;
+            while family.iter().any(|ip| !alive.contains(ip)) {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                socket.set_read_timeout(Some(remaining))?;
+                match recv_from(&socket, &mut buf) {
+                    Ok((n, src)) => {
+                        if parse_icmp_echo_reply(&buf[..n], ipv6) == Some(id) && family.contains(&src) {
+                            alive.insert(src);
+                        }
+                    }
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+    Ok(alive)
+}
+
+/// Raw ICMP (or ICMPv6) socket; the kernel writes the IP header
+fn open_icmp_socket(ipv6: bool) -> Result<Socket, SynError> {
+    let (domain, protocol) = if ipv6 {
+        (Domain::IPV6, Protocol::ICMPV6)
+    } else {
+        (Domain::IPV4, Protocol::ICMPV4)
+    };
+    Socket::new(domain, Type::RAW, Some(protocol)).map_err(|e| match e.kind() {
+        ErrorKind::PermissionDenied => SynError::NotPermitted,
+        _ => SynError::Io(e),
+    })
+}
+
+/// Receive one datagram into `buf`, returning its length and source address
+fn recv_from(socket: &Socket, buf: &mut [u8]) -> io::Result<(usize, IpAddr)> {
+    // SAFETY: an initialised buffer is a valid uninitialised one, and
+    // recv_from only ever writes initialised bytes into it
+    let uninit = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+    let (n, addr) = socket.recv_from(uninit)?;
+    let src = addr
+        .as_socket()
+        .map(|a| a.ip())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "reply from a non-IP address"))?;
+    Ok((n, src))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_ping_sweep_localhost() {
+        let discovery = HostDiscovery::new().with_timeout(Duration::from_millis(300));
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        match discovery.discover(&[localhost]).await {
+            Ok(alive) => assert_eq!(alive, HashSet::from([localhost])),
+            // Without CAP_NET_RAW the sweep must say so rather than report no hosts
+            Err(e) => assert!(matches!(e, SynError::NotPermitted), "{:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_sweep() {
+        assert!(HostDiscovery::new().discover(&[]).await.unwrap().is_empty());
+    }
+}
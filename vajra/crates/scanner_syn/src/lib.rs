@@ -10,16 +10,25 @@
 //! - No allocations in hot path
 
 pub mod capture;
+pub mod discovery;
 pub mod error;
+pub mod os;
 pub mod packet;
+pub mod privileges;
 pub mod syn;
 
+pub use discovery::HostDiscovery;
 pub use error::SynError;
-pub use syn::SynScanner;
+pub use os::guess_os;
+pub use privileges::{check_privileges, format_report, CheckStatus, PrivilegeCheck};
+pub use syn::{ScanFlavor, SynScanner};
 
 // Re-export commonly used types
-pub use capture::{start_capture_loop, cleanup_expired_probes, CAPTURE_STATS};
-pub use packet::tcp_flags;
+pub use capture::{
+    cleanup_expired_probes, set_packet_dump, set_pcap_writer, start_capture_loop, CaptureHandle,
+    CAPTURE_STATS,
+};
+pub use packet::{sctp_chunks, tcp_flags};
 
 /// Initialize the scanner subsystem
 /// 
@@ -27,31 +36,32 @@ pub use packet::tcp_flags;
 /// 1. Start the capture loop
 /// 2. Verify raw socket permissions
 /// 3. Set up any global state
-pub fn init() -> Result<(), SynError> {
-    use std::sync::atomic::AtomicBool;
-    use std::sync::Arc;
-
+///
+/// Capture is configured for `scanner` (see `SynScanner::capture_port_filter`)
+/// and runs until the returned handle is stopped or dropped, so keep it
+/// alive for the duration of the scan.
+pub fn init(scanner: &SynScanner) -> Result<CaptureHandle, SynError> {
     // Check permissions first
     if !SynScanner::is_raw_available() {
         eprintln!("WARNING: Raw sockets not available. Run with sudo or grant CAP_NET_RAW");
         eprintln!("  sudo setcap cap_net_raw+ep /path/to/binary");
+        eprintln!("  Run `vajra check-privileges` for details");
         return Err(SynError::NotPermitted);
     }
 
     // Start capture loop
-    let shutdown = Arc::new(AtomicBool::new(false));
-    start_capture_loop(shutdown)?;
+    let mut capture = start_capture_loop(scanner.capture_port_filter())?;
 
     // Spawn cleanup task for expired probes
-    tokio::spawn(async {
+    capture.attach_task(tokio::spawn(async {
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
         loop {
             interval.tick().await;
             cleanup_expired_probes(std::time::Duration::from_secs(30));
         }
-    });
+    }));
 
-    Ok(())
+    Ok(capture)
 }
 
 #[cfg(test)]
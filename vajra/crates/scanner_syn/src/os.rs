@@ -0,0 +1,55 @@
+//! Passive OS guessing from reply stack traits
+//!
+//! Stacks start their TTL at a few well-known values and pick
+//! characteristic default windows, so a SYN-ACK's TTL and window give a
+//! coarse OS family without sending anything extra (the idea behind p0f).
+//! The TTL seen on the wire has been decremented once per hop, so it is
+//! rounded up to the nearest initial value first.
+
+/// Initial TTLs used by common stacks, smallest first
+const INITIAL_TTLS: [u8; 4] = [32, 64, 128, 255];
+
+/// Built-in signatures: (initial TTL, window or `None` for any, OS family).
+/// Exact windows are listed before the catch-all for their TTL.
+const SIGNATURES: &[(u8, Option<u16>, &str)] = &[
+    (64, Some(65535), "FreeBSD/macOS"),
+    (64, None, "Linux"),
+    (128, None, "Windows"),
+    (255, Some(4128), "Cisco IOS"),
+    (255, None, "BSD/Cisco"),
+];
+
+/// Initial TTL a reply arriving with `ttl` most likely started with
+pub fn initial_ttl(ttl: u8) -> u8 {
+    INITIAL_TTLS.into_iter().find(|&initial| ttl <= initial).unwrap_or(255)
+}
+
+/// OS family for a reply with the given TTL and TCP window, if known
+pub fn guess_os(ttl: u8, window: u16) -> Option<&'static str> {
+    let initial = initial_ttl(ttl);
+    SIGNATURES
+        .iter()
+        .find(|(sig_ttl, sig_window, _)| *sig_ttl == initial && sig_window.map_or(true, |w| w == window))
+        .map(|(_, _, os)| *os)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_os_from_ttl_and_window() {
+        // A few hops away from each initial TTL
+        assert_eq!(guess_os(57, 64240), Some("Linux"));
+        assert_eq!(guess_os(64, 65535), Some("FreeBSD/macOS"));
+        assert_eq!(guess_os(116, 8192), Some("Windows"));
+        assert_eq!(guess_os(250, 4128), Some("Cisco IOS"));
+        assert_eq!(guess_os(240, 65535), Some("BSD/Cisco"));
+        // Initial TTL 32 has no signature
+        assert_eq!(guess_os(30, 8192), None);
+
+        assert_eq!(initial_ttl(1), 32);
+        assert_eq!(initial_ttl(65), 128);
+        assert_eq!(initial_ttl(255), 255);
+    }
+}
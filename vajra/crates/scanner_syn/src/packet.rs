@@ -18,6 +18,9 @@ pub mod tcp_flags {
     pub const URG: u8 = 0x20;
 }
 
+/// TTL (IPv4) / hop limit (IPv6) written when none is configured
+pub const DEFAULT_TTL: u8 = 64;
+
 /// Build a TCP SYN packet into the provided buffer.
 /// Returns the number of bytes written.
 ///
@@ -32,56 +35,85 @@ pub fn build_syn_packet(
     src_port: u16,
     dst_port: u16,
     seq: u32,
+    ttl: u8,
+) -> usize {
+    build_tcp_probe(buf, src_ip, dst_ip, src_port, dst_port, seq, 0, tcp_flags::SYN, ttl)
+}
+
+/// Build a TCP RST packet into the provided buffer.
+/// Returns the number of bytes written.
+///
+/// Used to tear down the half-open connection left by a SYN-ACK reply;
+/// `seq` should be the probe's sequence number + 1 (the SYN-ACK's ACK).
+pub fn build_rst_packet(
+    buf: &mut [u8],
+    src_ip: &IpAddr,
+    dst_ip: &IpAddr,
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+    ttl: u8,
+) -> usize {
+    build_tcp_probe(buf, src_ip, dst_ip, src_port, dst_port, seq, 0, tcp_flags::RST, ttl)
+}
+
+/// Build a header-only TCP packet with arbitrary flags (SYN, FIN, NULL,
+/// Xmas, ACK probes). `ack` is written to the acknowledgment field and is
+/// only meaningful when the ACK flag is set. `ttl` is the IPv4 TTL or IPv6
+/// hop limit. Returns the number of bytes written.
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+pub fn build_tcp_probe(
+    buf: &mut [u8],
+    src_ip: &IpAddr,
+    dst_ip: &IpAddr,
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    ttl: u8,
 ) -> usize {
     match (src_ip, dst_ip) {
         (IpAddr::V4(src), IpAddr::V4(dst)) => {
-            build_ipv4_syn(buf, src, dst, src_port, dst_port, seq)
+            build_ipv4_tcp(buf, src, dst, src_port, dst_port, seq, ack, flags, ttl)
         }
         (IpAddr::V6(src), IpAddr::V6(dst)) => {
-            build_ipv6_syn(buf, src, dst, src_port, dst_port, seq)
+            build_ipv6_tcp(buf, src, dst, src_port, dst_port, seq, ack, flags, ttl)
         }
         _ => 0, // Mismatched IP versions
     }
 }
 
-/// Build IPv4 + TCP SYN packet (40 bytes minimum)
+/// Build IPv4 + TCP header-only packet (40 bytes minimum)
+#[allow(clippy::too_many_arguments)]
 #[inline(always)]
-fn build_ipv4_syn(
+fn build_ipv4_tcp(
     buf: &mut [u8],
     src: &Ipv4Addr,
     dst: &Ipv4Addr,
     src_port: u16,
     dst_port: u16,
     seq: u32,
+    ack: u32,
+    flags: u8,
+    ttl: u8,
 ) -> usize {
-    if buf.len() < 40 {
+    // IPv4 Header (20 bytes)
+    if write_ipv4_header(buf, src, dst, 6, 20, ttl) == 0 {
         return 0;
     }
 
-    // IPv4 Header (20 bytes)
-    buf[0] = 0x45; // Version 4, IHL 5
-    buf[1] = 0x00; // DSCP/ECN
-    buf[2..4].copy_from_slice(&40u16.to_be_bytes()); // Total length
-    buf[4..6].copy_from_slice(&(rand::random::<u16>()).to_be_bytes()); // ID
-    buf[6..8].copy_from_slice(&0x4000u16.to_be_bytes()); // Flags: DF
-    buf[8] = 64; // TTL
-    buf[9] = 6; // Protocol: TCP
-    buf[10..12].copy_from_slice(&[0, 0]); // Checksum placeholder
-    buf[12..16].copy_from_slice(&src.octets());
-    buf[16..20].copy_from_slice(&dst.octets());
-
-    // Calculate IP header checksum
-    let ip_checksum = checksum(&buf[0..20]);
-    buf[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
-
     // TCP Header (20 bytes minimum)
     buf[20..22].copy_from_slice(&src_port.to_be_bytes());
     buf[22..24].copy_from_slice(&dst_port.to_be_bytes());
     buf[24..28].copy_from_slice(&seq.to_be_bytes());
-    buf[28..32].copy_from_slice(&0u32.to_be_bytes()); // ACK = 0
+    buf[28..32].copy_from_slice(&ack.to_be_bytes());
     buf[32] = 0x50; // Data offset: 5 (20 bytes)
-    buf[33] = tcp_flags::SYN;
-    buf[34..36].copy_from_slice(&65535u16.to_be_bytes()); // Window size
+    buf[33] = flags;
+    // RST carries no window
+    let window: u16 = if flags & tcp_flags::RST != 0 { 0 } else { 65535 };
+    buf[34..36].copy_from_slice(&window.to_be_bytes()); // Window size
     buf[36..38].copy_from_slice(&[0, 0]); // Checksum placeholder
     buf[38..40].copy_from_slice(&[0, 0]); // Urgent pointer
 
@@ -92,36 +124,34 @@ fn build_ipv4_syn(
     40
 }
 
-/// Build IPv6 + TCP SYN packet (60 bytes minimum)
+/// Build IPv6 + TCP header-only packet (60 bytes minimum)
+#[allow(clippy::too_many_arguments)]
 #[inline(always)]
-fn build_ipv6_syn(
+fn build_ipv6_tcp(
     buf: &mut [u8],
     src: &Ipv6Addr,
     dst: &Ipv6Addr,
     src_port: u16,
     dst_port: u16,
     seq: u32,
+    ack: u32,
+    flags: u8,
+    ttl: u8,
 ) -> usize {
-    if buf.len() < 60 {
+    // IPv6 Header (40 bytes)
+    if write_ipv6_header(buf, src, dst, 6, 20, ttl) == 0 {
         return 0;
     }
 
-    // IPv6 Header (40 bytes)
-    buf[0..4].copy_from_slice(&0x60000000u32.to_be_bytes()); // Version 6
-    buf[4..6].copy_from_slice(&20u16.to_be_bytes()); // Payload length
-    buf[6] = 6; // Next header: TCP
-    buf[7] = 64; // Hop limit
-    buf[8..24].copy_from_slice(&src.octets());
-    buf[24..40].copy_from_slice(&dst.octets());
-
     // TCP Header (20 bytes)
     buf[40..42].copy_from_slice(&src_port.to_be_bytes());
     buf[42..44].copy_from_slice(&dst_port.to_be_bytes());
     buf[44..48].copy_from_slice(&seq.to_be_bytes());
-    buf[48..52].copy_from_slice(&0u32.to_be_bytes());
+    buf[48..52].copy_from_slice(&ack.to_be_bytes());
     buf[52] = 0x50;
-    buf[53] = tcp_flags::SYN;
-    buf[54..56].copy_from_slice(&65535u16.to_be_bytes());
+    buf[53] = flags;
+    let window: u16 = if flags & tcp_flags::RST != 0 { 0 } else { 65535 };
+    buf[54..56].copy_from_slice(&window.to_be_bytes());
     buf[56..58].copy_from_slice(&[0, 0]); // Checksum placeholder
     buf[58..60].copy_from_slice(&[0, 0]);
 
@@ -131,9 +161,201 @@ fn build_ipv6_syn(
     60
 }
 
+/// SCTP chunk types (RFC 4960 section 3.2)
+pub mod sctp_chunks {
+    pub const INIT: u8 = 1;
+    pub const INIT_ACK: u8 = 2;
+    pub const ABORT: u8 = 6;
+}
+
+/// IP protocol number of SCTP
+pub const IPPROTO_SCTP: u8 = 132;
+
+/// Length of the SCTP common header plus a parameterless INIT chunk
+const SCTP_INIT_LEN: usize = 12 + 20;
+
+/// Build an SCTP packet carrying a single INIT chunk (nmap's `-sY`).
+/// The common header's verification tag is zero as RFC 4960 requires for
+/// INIT; `init_tag` is the chunk's Initiate Tag, which the peer echoes as
+/// the verification tag of its INIT-ACK or ABORT. Returns the number of
+/// bytes written (52 for IPv4, 72 for IPv6).
+pub fn build_sctp_init(
+    buf: &mut [u8],
+    src_ip: &IpAddr,
+    dst_ip: &IpAddr,
+    src_port: u16,
+    dst_port: u16,
+    init_tag: u32,
+    ttl: u8,
+) -> usize {
+    let ip_len = match (src_ip, dst_ip) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            write_ipv4_header(buf, src, dst, IPPROTO_SCTP, SCTP_INIT_LEN, ttl)
+        }
+        (IpAddr::V6(src), IpAddr::V6(dst)) => {
+            write_ipv6_header(buf, src, dst, IPPROTO_SCTP, SCTP_INIT_LEN, ttl)
+        }
+        _ => 0, // Mismatched IP versions
+    };
+    if ip_len == 0 {
+        return 0;
+    }
+
+    let sctp = &mut buf[ip_len..ip_len + SCTP_INIT_LEN];
+
+    // Common header (12 bytes)
+    sctp[0..2].copy_from_slice(&src_port.to_be_bytes());
+    sctp[2..4].copy_from_slice(&dst_port.to_be_bytes());
+    sctp[4..8].copy_from_slice(&[0, 0, 0, 0]); // Verification tag
+    sctp[8..12].copy_from_slice(&[0, 0, 0, 0]); // Checksum placeholder
+
+    // INIT chunk (20 bytes, no optional parameters)
+    sctp[12] = sctp_chunks::INIT;
+    sctp[13] = 0; // Chunk flags
+    sctp[14..16].copy_from_slice(&20u16.to_be_bytes()); // Chunk length
+    sctp[16..20].copy_from_slice(&init_tag.to_be_bytes());
+    sctp[20..24].copy_from_slice(&65535u32.to_be_bytes()); // a_rwnd
+    sctp[24..26].copy_from_slice(&10u16.to_be_bytes()); // Outbound streams
+    sctp[26..28].copy_from_slice(&2048u16.to_be_bytes()); // Inbound streams
+    sctp[28..32].copy_from_slice(&init_tag.to_be_bytes()); // Initial TSN
+
+    // CRC32c is transmitted least significant byte first (RFC 4960 appendix B)
+    let crc = crc32c(sctp);
+    sctp[8..12].copy_from_slice(&crc.to_le_bytes());
+
+    ip_len + SCTP_INIT_LEN
+}
+
+/// Write an IPv4 header for a `payload_len`-byte payload.
+/// Returns the header length (20), or 0 if the packet does not fit.
+#[inline(always)]
+fn write_ipv4_header(
+    buf: &mut [u8],
+    src: &Ipv4Addr,
+    dst: &Ipv4Addr,
+    protocol: u8,
+    payload_len: usize,
+    ttl: u8,
+) -> usize {
+    let total = 20 + payload_len;
+    if buf.len() < total {
+        return 0;
+    }
+
+    buf[0] = 0x45; // Version 4, IHL 5
+    buf[1] = 0x00; // DSCP/ECN
+    buf[2..4].copy_from_slice(&(total as u16).to_be_bytes()); // Total length
+    buf[4..6].copy_from_slice(&(rand::random::<u16>()).to_be_bytes()); // ID
+    buf[6..8].copy_from_slice(&0x4000u16.to_be_bytes()); // Flags: DF
+    buf[8] = ttl;
+    buf[9] = protocol;
+    buf[10..12].copy_from_slice(&[0, 0]); // Checksum placeholder
+    buf[12..16].copy_from_slice(&src.octets());
+    buf[16..20].copy_from_slice(&dst.octets());
+
+    let ip_checksum = checksum(&buf[0..20]);
+    buf[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+    20
+}
+
+/// Write an IPv6 header for a `payload_len`-byte payload.
+/// Returns the header length (40), or 0 if the packet does not fit.
+#[inline(always)]
+fn write_ipv6_header(
+    buf: &mut [u8],
+    src: &Ipv6Addr,
+    dst: &Ipv6Addr,
+    next_header: u8,
+    payload_len: usize,
+    ttl: u8,
+) -> usize {
+    if buf.len() < 40 + payload_len {
+        return 0;
+    }
+
+    buf[0..4].copy_from_slice(&0x60000000u32.to_be_bytes()); // Version 6
+    buf[4..6].copy_from_slice(&(payload_len as u16).to_be_bytes());
+    buf[6] = next_header;
+    buf[7] = ttl; // Hop limit
+    buf[8..24].copy_from_slice(&src.octets());
+    buf[24..40].copy_from_slice(&dst.octets());
+    40
+}
+
+/// Fields extracted from a captured SCTP packet:
+/// (src_ip, src_port, dst_ip, dst_port, verification_tag, first_chunk_type)
+pub type ParsedSctp = (IpAddr, u16, IpAddr, u16, u32, u8);
+
+/// Parse a captured packet and extract SCTP information.
+/// Only the first chunk is reported; INIT-ACK and ABORT are never bundled
+/// behind other chunks.
+pub fn parse_sctp_packet(buf: &[u8]) -> Option<ParsedSctp> {
+    let (src_ip, dst_ip, sctp) = match buf.first()? >> 4 {
+        4 => {
+            let ihl = (buf[0] & 0x0f) as usize * 4;
+            if buf.len() < 20 || buf[9] != IPPROTO_SCTP {
+                return None;
+            }
+            let src = Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]);
+            let dst = Ipv4Addr::new(buf[16], buf[17], buf[18], buf[19]);
+            (IpAddr::V4(src), IpAddr::V4(dst), buf.get(ihl..)?)
+        }
+        6 => {
+            if buf.len() < 40 || buf[6] != IPPROTO_SCTP {
+                return None;
+            }
+            let src: [u8; 16] = buf[8..24].try_into().ok()?;
+            let dst: [u8; 16] = buf[24..40].try_into().ok()?;
+            (IpAddr::V6(Ipv6Addr::from(src)), IpAddr::V6(Ipv6Addr::from(dst)), &buf[40..])
+        }
+        _ => return None,
+    };
+
+    // Common header plus at least one chunk header
+    if sctp.len() < 16 {
+        return None;
+    }
+    let src_port = u16::from_be_bytes([sctp[0], sctp[1]]);
+    let dst_port = u16::from_be_bytes([sctp[2], sctp[3]]);
+    let vtag = u32::from_be_bytes([sctp[4], sctp[5], sctp[6], sctp[7]]);
+
+    Some((src_ip, src_port, dst_ip, dst_port, vtag, sctp[12]))
+}
+
+/// CRC32c (Castagnoli) as used by the SCTP checksum
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0x82F6_3B78 & mask);
+        }
+    }
+    !crc
+}
+
+/// IP TTL (hop limit for IPv6) and TCP window of a captured packet, the
+/// stack traits used for OS guessing. The window is 0 for non-TCP packets.
+pub fn stack_traits(buf: &[u8]) -> Option<(u8, u16)> {
+    let (ttl, protocol, header_len) = match buf.first()? >> 4 {
+        4 => (buf.get(8).copied()?, buf.get(9).copied()?, (buf[0] & 0x0f) as usize * 4),
+        6 => (buf.get(7).copied()?, buf.get(6).copied()?, 40),
+        _ => return None,
+    };
+    let window = match (protocol, buf.get(header_len + 14..header_len + 16)) {
+        (6, Some(w)) => u16::from_be_bytes([w[0], w[1]]),
+        _ => 0,
+    };
+    Some((ttl, window))
+}
+
+/// Fields extracted from a captured TCP packet:
+/// (src_ip, src_port, dst_ip, dst_port, tcp_flags, seq, ack, payload_offset, payload_len)
+pub type ParsedTcp = (IpAddr, u16, IpAddr, u16, u8, u32, u32, usize, usize);
+
 /// Parse a captured packet and extract TCP information.
-/// Returns: (src_ip, src_port, dst_ip, dst_port, tcp_flags, payload_offset, payload_len)
-pub fn parse_packet(buf: &[u8]) -> Option<(IpAddr, u16, IpAddr, u16, u8, usize, usize)> {
+pub fn parse_packet(buf: &[u8]) -> Option<ParsedTcp> {
     if buf.len() < 40 {
         return None;
     }
@@ -149,7 +371,7 @@ pub fn parse_packet(buf: &[u8]) -> Option<(IpAddr, u16, IpAddr, u16, u8, usize,
 }
 
 #[inline(always)]
-fn parse_ipv4_packet(buf: &[u8]) -> Option<(IpAddr, u16, IpAddr, u16, u8, usize, usize)> {
+fn parse_ipv4_packet(buf: &[u8]) -> Option<ParsedTcp> {
     if buf.len() < 40 {
         return None;
     }
@@ -178,16 +400,18 @@ fn parse_ipv4_packet(buf: &[u8]) -> Option<(IpAddr, u16, IpAddr, u16, u8, usize,
     let src_port = u16::from_be_bytes([tcp[0], tcp[1]]);
     let dst_port = u16::from_be_bytes([tcp[2], tcp[3]]);
     let flags = tcp[13];
+    let seq = u32::from_be_bytes([tcp[4], tcp[5], tcp[6], tcp[7]]);
+    let ack = u32::from_be_bytes([tcp[8], tcp[9], tcp[10], tcp[11]]);
     let data_offset = ((tcp[12] >> 4) as usize) * 4;
 
     let payload_offset = tcp_offset + data_offset;
     let payload_len = buf.len().saturating_sub(payload_offset);
 
-    Some((src_ip, src_port, dst_ip, dst_port, flags, payload_offset, payload_len))
+    Some((src_ip, src_port, dst_ip, dst_port, flags, seq, ack, payload_offset, payload_len))
 }
 
 #[inline(always)]
-fn parse_ipv6_packet(buf: &[u8]) -> Option<(IpAddr, u16, IpAddr, u16, u8, usize, usize)> {
+fn parse_ipv6_packet(buf: &[u8]) -> Option<ParsedTcp> {
     if buf.len() < 60 {
         return None;
     }
@@ -216,12 +440,172 @@ fn parse_ipv6_packet(buf: &[u8]) -> Option<(IpAddr, u16, IpAddr, u16, u8, usize,
     let src_port = u16::from_be_bytes([tcp[0], tcp[1]]);
     let dst_port = u16::from_be_bytes([tcp[2], tcp[3]]);
     let flags = tcp[13];
+    let seq = u32::from_be_bytes([tcp[4], tcp[5], tcp[6], tcp[7]]);
+    let ack = u32::from_be_bytes([tcp[8], tcp[9], tcp[10], tcp[11]]);
     let data_offset = ((tcp[12] >> 4) as usize) * 4;
 
     let payload_offset = 40 + data_offset;
     let payload_len = buf.len().saturating_sub(payload_offset);
 
-    Some((src_ip, src_port, dst_ip, dst_port, flags, payload_offset, payload_len))
+    Some((src_ip, src_port, dst_ip, dst_port, flags, seq, ack, payload_offset, payload_len))
+}
+
+/// ICMP message types used by the scanner (IPv4, IPv6)
+pub mod icmp_types {
+    pub const ECHO_REPLY_V4: u8 = 0;
+    pub const DEST_UNREACH_V4: u8 = 3;
+    pub const ECHO_REQUEST_V4: u8 = 8;
+    pub const DEST_UNREACH_V6: u8 = 1;
+    pub const ECHO_REQUEST_V6: u8 = 128;
+    pub const ECHO_REPLY_V6: u8 = 129;
+}
+
+/// Protocol numbers of ICMP and ICMPv6
+const IPPROTO_ICMP: u8 = 1;
+const IPPROTO_ICMPV6: u8 = 58;
+
+/// A destination-unreachable error and the probe it quotes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IcmpUnreachable {
+    /// Sender of the error: the target, or a router or firewall on the way
+    pub from: IpAddr,
+    pub icmp_type: u8,
+    pub code: u8,
+    /// Destination of the quoted probe (the scanned target)
+    pub target: IpAddr,
+    /// Transport protocol of the quoted probe
+    pub protocol: u8,
+    /// Source port of the quoted probe (ours)
+    pub src_port: u16,
+    /// Destination port of the quoted probe (the scanned port)
+    pub dst_port: u16,
+    /// Sequence number of a quoted TCP probe, or the Initiate Tag of a
+    /// quoted SCTP INIT (0 if the error quotes too little to include it)
+    pub token: u32,
+}
+
+/// Whether an unreachable `code` says why a probe did not get through
+/// (no route, prohibited, port unreachable) rather than e.g. that it
+/// needed fragmenting
+fn is_unreachable_code(ipv6: bool, code: u8) -> bool {
+    if ipv6 {
+        // no route, admin prohibited, address/port unreachable, policy, reject route
+        matches!(code, 0 | 1 | 3 | 4 | 5 | 6)
+    } else {
+        // net/host/protocol/port unreachable, net/host/admin prohibited
+        matches!(code, 0 | 1 | 2 | 3 | 9 | 10 | 13)
+    }
+}
+
+/// Parse a captured ICMP (or ICMPv6) destination-unreachable error and
+/// the start of the probe it quotes. RFC 792 only guarantees the quoted
+/// IP header plus 8 bytes (the ports and TCP sequence number); the SCTP
+/// Initiate Tag is read when the sender quoted more, as Linux does.
+pub fn parse_icmp_unreachable(buf: &[u8]) -> Option<IcmpUnreachable> {
+    let (from, ipv6, icmp) = match buf.first()? >> 4 {
+        4 => {
+            let ihl = (buf[0] & 0x0f) as usize * 4;
+            if buf.len() < 20 || buf[9] != IPPROTO_ICMP {
+                return None;
+            }
+            let src = Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]);
+            (IpAddr::V4(src), false, buf.get(ihl..)?)
+        }
+        6 => {
+            if buf.len() < 40 || buf[6] != IPPROTO_ICMPV6 {
+                return None;
+            }
+            let src: [u8; 16] = buf[8..24].try_into().ok()?;
+            (IpAddr::V6(Ipv6Addr::from(src)), true, &buf[40..])
+        }
+        _ => return None,
+    };
+
+    let unreach = if ipv6 { icmp_types::DEST_UNREACH_V6 } else { icmp_types::DEST_UNREACH_V4 };
+    if icmp.len() < 8 || icmp[0] != unreach || !is_unreachable_code(ipv6, icmp[1]) {
+        return None;
+    }
+
+    // The quoted packet starts after the 8-byte ICMP header
+    let quoted = &icmp[8..];
+    let (target, protocol, transport) = if ipv6 {
+        if quoted.len() < 40 || quoted[0] >> 4 != 6 {
+            return None;
+        }
+        let dst: [u8; 16] = quoted[24..40].try_into().ok()?;
+        (IpAddr::V6(Ipv6Addr::from(dst)), quoted[6], &quoted[40..])
+    } else {
+        if quoted.len() < 20 || quoted[0] >> 4 != 4 {
+            return None;
+        }
+        let ihl = (quoted[0] & 0x0f) as usize * 4;
+        let dst = Ipv4Addr::new(quoted[16], quoted[17], quoted[18], quoted[19]);
+        (IpAddr::V4(dst), quoted[9], quoted.get(ihl..)?)
+    };
+    if transport.len() < 8 {
+        return None;
+    }
+
+    let token = match protocol {
+        6 => u32::from_be_bytes([transport[4], transport[5], transport[6], transport[7]]),
+        // Initiate Tag: the INIT's verification tag is always 0
+        IPPROTO_SCTP => transport
+            .get(16..20)
+            .map_or(0, |tag| u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]])),
+        _ => 0,
+    };
+
+    Some(IcmpUnreachable {
+        from,
+        icmp_type: icmp[0],
+        code: icmp[1],
+        target,
+        protocol,
+        src_port: u16::from_be_bytes([transport[0], transport[1]]),
+        dst_port: u16::from_be_bytes([transport[2], transport[3]]),
+        token,
+    })
+}
+
+/// Payload carried by echo requests (so replies are easy to spot in a capture)
+const ECHO_PAYLOAD: &[u8] = b"vajra-ping";
+
+/// Build an ICMP (or ICMPv6) echo request without an IP header, for a
+/// raw socket that adds its own. The ICMPv6 checksum covers a
+/// pseudo-header and is left for the kernel to fill in.
+/// Returns the message length, or 0 if it does not fit.
+pub fn build_icmp_echo(buf: &mut [u8], ipv6: bool, id: u16, seq: u16) -> usize {
+    let len = 8 + ECHO_PAYLOAD.len();
+    if buf.len() < len {
+        return 0;
+    }
+
+    buf[0] = if ipv6 { icmp_types::ECHO_REQUEST_V6 } else { icmp_types::ECHO_REQUEST_V4 };
+    buf[1] = 0; // Code
+    buf[2..4].copy_from_slice(&[0, 0]); // Checksum placeholder
+    buf[4..6].copy_from_slice(&id.to_be_bytes());
+    buf[6..8].copy_from_slice(&seq.to_be_bytes());
+    buf[8..len].copy_from_slice(ECHO_PAYLOAD);
+    if !ipv6 {
+        let sum = checksum(&buf[..len]);
+        buf[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    len
+}
+
+/// Identifier of an echo reply as read from a raw ICMP socket: IPv4
+/// sockets deliver the IP header too, IPv6 sockets only the message.
+pub fn parse_icmp_echo_reply(buf: &[u8], ipv6: bool) -> Option<u16> {
+    let (icmp, reply_type) = if ipv6 {
+        (buf, icmp_types::ECHO_REPLY_V6)
+    } else {
+        let ihl = ((*buf.first()? & 0x0F) as usize) * 4;
+        (buf.get(ihl..)?, icmp_types::ECHO_REPLY_V4)
+    };
+    if icmp.len() < 8 || icmp[0] != reply_type || icmp[1] != 0 {
+        return None;
+    }
+    Some(u16::from_be_bytes([icmp[4], icmp[5]]))
 }
 
 /// Fast IP checksum calculation (inline for speed)
@@ -322,7 +706,7 @@ mod tests {
         let src = Ipv4Addr::new(192, 168, 1, 1);
         let dst = Ipv4Addr::new(192, 168, 1, 2);
 
-        let len = build_ipv4_syn(&mut buf, &src, &dst, 12345, 80, 1000);
+        let len = build_syn_packet(&mut buf, &IpAddr::V4(src), &IpAddr::V4(dst), 12345, 80, 1000, DEFAULT_TTL);
         assert_eq!(len, 40);
 
         // Verify IP version
@@ -341,7 +725,7 @@ mod tests {
         let src = Ipv4Addr::new(10, 0, 0, 1);
         let dst = Ipv4Addr::new(10, 0, 0, 2);
 
-        build_ipv4_syn(&mut buf, &src, &dst, 5000, 443, 9999);
+        build_syn_packet(&mut buf, &IpAddr::V4(src), &IpAddr::V4(dst), 5000, 443, 9999, DEFAULT_TTL);
 
         let parsed = parse_packet(&buf).unwrap();
         assert_eq!(parsed.0, IpAddr::V4(src));
@@ -349,5 +733,213 @@ mod tests {
         assert_eq!(parsed.2, IpAddr::V4(dst));
         assert_eq!(parsed.3, 443);
         assert_eq!(parsed.4, tcp_flags::SYN);
+        assert_eq!(parsed.5, 9999);
+        assert_eq!(parsed.6, 0);
+    }
+
+    #[test]
+    fn test_stack_traits() {
+        let mut buf = vec![0u8; 60];
+        let src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let dst = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let len = build_syn_packet(&mut buf, &src, &dst, 443, 5000, 7, 57);
+        buf[34..36].copy_from_slice(&64240u16.to_be_bytes());
+        assert_eq!(stack_traits(&buf[..len]), Some((57, 64240)));
+
+        let mut buf = vec![0u8; 80];
+        let src = IpAddr::V6("2001:db8::2".parse().unwrap());
+        let dst = IpAddr::V6("2001:db8::1".parse().unwrap());
+        let len = build_sctp_init(&mut buf, &src, &dst, 5000, 80, 1, 120);
+        assert_eq!(stack_traits(&buf[..len]), Some((120, 0)));
+        assert_eq!(stack_traits(&[]), None);
+    }
+
+    #[test]
+    fn test_parse_ack_number() {
+        let mut buf = vec![0u8; 60];
+        let src = Ipv4Addr::new(10, 0, 0, 2);
+        let dst = Ipv4Addr::new(10, 0, 0, 1);
+
+        build_syn_packet(&mut buf, &IpAddr::V4(src), &IpAddr::V4(dst), 443, 5000, 7, DEFAULT_TTL);
+        // Turn it into a SYN-ACK acknowledging seq 9999
+        buf[28..32].copy_from_slice(&10_000u32.to_be_bytes());
+        buf[33] = tcp_flags::SYN | tcp_flags::ACK;
+
+        let parsed = parse_packet(&buf).unwrap();
+        assert_eq!(parsed.4, tcp_flags::SYN | tcp_flags::ACK);
+        assert_eq!(parsed.5, 7);
+        assert_eq!(parsed.6, 10_000);
+    }
+
+    #[test]
+    fn test_build_rst() {
+        let mut buf = vec![0u8; 60];
+        let src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let dst = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        let len = build_rst_packet(&mut buf, &src, &dst, 40000, 80, 1001, DEFAULT_TTL);
+        assert_eq!(len, 40);
+
+        let parsed = parse_packet(&buf[..len]).unwrap();
+        assert_eq!((parsed.1, parsed.3), (40000, 80));
+        assert_eq!(parsed.4, tcp_flags::RST);
+        assert_eq!(u32::from_be_bytes([buf[24], buf[25], buf[26], buf[27]]), 1001);
+        assert_eq!(&buf[34..36], &[0, 0]); // zero window
+
+        // Checksum over the segment including the stored checksum folds to zero
+        if let (IpAddr::V4(s), IpAddr::V4(d)) = (src, dst) {
+            assert_eq!(tcp_checksum_v4(&s, &d, &buf[20..40]), 0);
+        }
+    }
+
+    #[test]
+    fn test_configured_ttl() {
+        let mut buf = vec![0u8; 60];
+        let v4 = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        build_tcp_probe(&mut buf, &v4.0, &v4.1, 40000, 80, 1, 0, tcp_flags::SYN, 7);
+        assert_eq!(buf[8], 7);
+        build_syn_packet(&mut buf, &v4.0, &v4.1, 40000, 80, 1, DEFAULT_TTL);
+        assert_eq!(buf[8], 64);
+
+        let v6 = (IpAddr::V6(Ipv6Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST));
+        let len = build_tcp_probe(&mut buf, &v6.0, &v6.1, 40000, 80, 1, 0, tcp_flags::SYN, 3);
+        assert_eq!(len, 60);
+        assert_eq!(buf[7], 3);
+    }
+
+    #[test]
+    fn test_crc32c() {
+        // Standard check value for CRC-32C
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    }
+
+    #[test]
+    fn test_icmp_echo() {
+        let mut buf = [0u8; 64];
+        let len = build_icmp_echo(&mut buf, false, 0x1234, 7);
+        assert_eq!(len, 18);
+        assert_eq!(&buf[..2], &[icmp_types::ECHO_REQUEST_V4, 0]);
+        assert_eq!(checksum(&buf[..len]), 0);
+        // A request is not a reply
+        assert_eq!(parse_icmp_echo_reply(&buf[..len], true), None);
+
+        // The reply a host sends back, behind a 20-byte IPv4 header
+        let mut reply = vec![0x45; 20];
+        reply.extend_from_slice(&buf[..len]);
+        reply[20] = icmp_types::ECHO_REPLY_V4;
+        assert_eq!(parse_icmp_echo_reply(&reply, false), Some(0x1234));
+
+        let len = build_icmp_echo(&mut buf, true, 0xbeef, 1);
+        buf[0] = icmp_types::ECHO_REPLY_V6;
+        assert_eq!(parse_icmp_echo_reply(&buf[..len], true), Some(0xbeef));
+        assert_eq!(build_icmp_echo(&mut [0u8; 8], false, 1, 1), 0);
+    }
+
+    /// ICMP error from `from` quoting the first `quote` bytes of `probe`
+    fn unreachable(from: [u8; 4], code: u8, probe: &[u8], quote: usize) -> Vec<u8> {
+        let mut packet = vec![0u8; 28];
+        packet[0] = 0x45;
+        packet[8] = 250;
+        packet[9] = IPPROTO_ICMP;
+        packet[12..16].copy_from_slice(&from);
+        packet[20] = icmp_types::DEST_UNREACH_V4;
+        packet[21] = code;
+        packet.extend_from_slice(&probe[..quote]);
+        packet
+    }
+
+    #[test]
+    fn test_parse_icmp_unreachable() {
+        let us = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let target = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+        let mut probe = vec![0u8; 60];
+        let len = build_syn_packet(&mut probe, &us, &target, 40000, 443, 1000, DEFAULT_TTL);
+
+        // A firewall quoting the minimum: IP header plus 8 bytes
+        let packet = unreachable([10, 0, 0, 1], 13, &probe[..len], 28);
+        let icmp = parse_icmp_unreachable(&packet).unwrap();
+        assert_eq!(icmp.from, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!((icmp.icmp_type, icmp.code), (icmp_types::DEST_UNREACH_V4, 13));
+        assert_eq!((icmp.target, icmp.protocol), (target, 6));
+        assert_eq!((icmp.src_port, icmp.dst_port, icmp.token), (40000, 443, 1000));
+        assert_eq!(stack_traits(&packet), Some((250, 0)));
+
+        // Too short a quote, or a code that is not about reachability
+        assert_eq!(parse_icmp_unreachable(&packet[..packet.len() - 1]), None);
+        assert_eq!(parse_icmp_unreachable(&unreachable([10, 0, 0, 1], 4, &probe[..len], 28)), None);
+        // Not ICMP at all
+        assert_eq!(parse_icmp_unreachable(&probe[..len]), None);
+
+        // A quoted SCTP INIT yields its Initiate Tag when quoted in full
+        let len = build_sctp_init(&mut probe, &us, &target, 40000, 2905, 0xfeed, DEFAULT_TTL);
+        let icmp = parse_icmp_unreachable(&unreachable([192, 168, 1, 2], 3, &probe[..len], len)).unwrap();
+        assert_eq!((icmp.protocol, icmp.dst_port, icmp.token), (IPPROTO_SCTP, 2905, 0xfeed));
+        let short = parse_icmp_unreachable(&unreachable([192, 168, 1, 2], 3, &probe[..len], 28)).unwrap();
+        assert_eq!(short.token, 0);
+
+        // ICMPv6 admin prohibited quoting an IPv6 SYN
+        let (us6, target6) = (IpAddr::V6("2001:db8::1".parse().unwrap()), IpAddr::V6("2001:db8::2".parse().unwrap()));
+        let len = build_syn_packet(&mut probe, &us6, &target6, 40001, 22, 7, DEFAULT_TTL);
+        let mut packet = vec![0u8; 48];
+        packet[0] = 0x60;
+        packet[6] = IPPROTO_ICMPV6;
+        packet[8..24].copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfe]);
+        packet[40] = icmp_types::DEST_UNREACH_V6;
+        packet[41] = 1;
+        packet.extend_from_slice(&probe[..len]);
+        let icmp = parse_icmp_unreachable(&packet).unwrap();
+        assert_eq!((icmp.target, icmp.src_port, icmp.dst_port, icmp.token), (target6, 40001, 22, 7));
+        assert_eq!(icmp.from, "2001:db8::fe".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_build_sctp_init() {
+        let mut buf = vec![0u8; 80];
+        let src = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let dst = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+
+        let len = build_sctp_init(&mut buf, &src, &dst, 40000, 2905, 0xdead_beef, DEFAULT_TTL);
+        assert_eq!(len, 52);
+        assert_eq!(buf[9], IPPROTO_SCTP);
+        assert_eq!(u16::from_be_bytes([buf[2], buf[3]]), 52);
+        assert_eq!(checksum(&buf[0..20]), 0);
+
+        let sctp = &buf[20..len];
+        assert_eq!(&sctp[4..8], &[0, 0, 0, 0]); // INIT carries a zero tag
+        assert_eq!(sctp[12], sctp_chunks::INIT);
+        assert_eq!(u16::from_be_bytes([sctp[14], sctp[15]]), 20);
+        assert_eq!(&sctp[16..20], &0xdead_beefu32.to_be_bytes());
+
+        // Recomputing with the checksum field zeroed reproduces it
+        let mut copy = sctp.to_vec();
+        let stored = u32::from_le_bytes([copy[8], copy[9], copy[10], copy[11]]);
+        copy[8..12].copy_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(crc32c(&copy), stored);
+
+        let v6 = (IpAddr::V6(Ipv6Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(build_sctp_init(&mut buf, &v6.0, &v6.1, 40000, 2905, 1, DEFAULT_TTL), 72);
+        assert_eq!((buf[6], u16::from_be_bytes([buf[4], buf[5]])), (IPPROTO_SCTP, 32));
+        // Too small a buffer writes nothing
+        assert_eq!(build_sctp_init(&mut buf[..60], &v6.0, &v6.1, 40000, 2905, 1, DEFAULT_TTL), 0);
+    }
+
+    #[test]
+    fn test_parse_sctp_reply() {
+        let mut buf = vec![0u8; 80];
+        let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let us = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        // Turn an INIT from the target into an INIT-ACK tagged with our tag
+        let len = build_sctp_init(&mut buf, &target, &us, 2905, 40000, 77, DEFAULT_TTL);
+        buf[24..28].copy_from_slice(&0x1234_5678u32.to_be_bytes());
+        buf[32] = sctp_chunks::INIT_ACK;
+
+        let parsed = parse_sctp_packet(&buf[..len]).unwrap();
+        assert_eq!(parsed, (target, 2905, us, 40000, 0x1234_5678, sctp_chunks::INIT_ACK));
+
+        // SCTP packets are not TCP and vice versa
+        assert!(parse_packet(&buf[..len]).is_none());
+        let len = build_syn_packet(&mut buf, &target, &us, 80, 40000, 1, DEFAULT_TTL);
+        assert!(parse_sctp_packet(&buf[..len]).is_none());
     }
 }
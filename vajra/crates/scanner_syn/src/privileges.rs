@@ -0,0 +1,178 @@
+//! Raw-socket capability check
+//!
+//! Opens (and immediately closes) each socket a raw scan needs, without
+//! sending anything, and explains what is missing: the raw IP sockets used
+//! to send probes and the `AF_PACKET` sockets used to capture replies.
+
+use std::fmt::Write as _;
+use std::io;
+
+/// Outcome of trying to open one socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    /// EPERM/EACCES: the process lacks CAP_NET_RAW
+    PermissionDenied,
+    /// The kernel or platform does not provide this socket type
+    NotSupported(String),
+    /// Any other error
+    Failed(String),
+}
+
+impl CheckStatus {
+    /// Classify the error from a failed `socket(2)` call
+    pub fn from_error(err: &io::Error) -> Self {
+        #[cfg(target_os = "linux")]
+        match err.raw_os_error() {
+            Some(libc::EPERM) | Some(libc::EACCES) => return CheckStatus::PermissionDenied,
+            Some(libc::EAFNOSUPPORT) | Some(libc::EPROTONOSUPPORT) | Some(libc::ESOCKTNOSUPPORT) => {
+                return CheckStatus::NotSupported(err.to_string())
+            }
+            _ => {}
+        }
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => CheckStatus::PermissionDenied,
+            io::ErrorKind::Unsupported => CheckStatus::NotSupported(err.to_string()),
+            _ => CheckStatus::Failed(err.to_string()),
+        }
+    }
+}
+
+/// One capability and whether it is available
+#[derive(Debug, Clone)]
+pub struct PrivilegeCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// Whether SYN scanning needs it (IPv6 is optional)
+    pub required: bool,
+}
+
+impl PrivilegeCheck {
+    /// True if the check passed or is optional
+    pub fn is_satisfied(&self) -> bool {
+        self.status == CheckStatus::Ok || !self.required
+    }
+}
+
+/// Try every socket a raw scan opens
+pub fn check_privileges() -> Vec<PrivilegeCheck> {
+    #[cfg(target_os = "linux")]
+    {
+        let eth_p = |ethertype: libc::c_int| (ethertype as u16).to_be() as libc::c_int;
+        vec![
+            probe("send raw IPv4 packets", true, libc::AF_INET, libc::IPPROTO_RAW),
+            probe("send raw IPv6 packets", false, libc::AF_INET6, libc::IPPROTO_RAW),
+            probe("capture IPv4 replies (AF_PACKET)", true, libc::AF_PACKET, eth_p(libc::ETH_P_IP)),
+            probe("capture IPv6 replies (AF_PACKET)", false, libc::AF_PACKET, eth_p(libc::ETH_P_IPV6)),
+        ]
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        vec![PrivilegeCheck {
+            name: "raw sockets",
+            status: CheckStatus::NotSupported("raw scans are only implemented on Linux".to_string()),
+            required: true,
+        }]
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn probe(name: &'static str, required: bool, family: libc::c_int, protocol: libc::c_int) -> PrivilegeCheck {
+    let fd = unsafe { libc::socket(family, libc::SOCK_RAW, protocol) };
+    let status = if fd < 0 {
+        CheckStatus::from_error(&io::Error::last_os_error())
+    } else {
+        unsafe { libc::close(fd) };
+        CheckStatus::Ok
+    };
+    PrivilegeCheck { name, status, required }
+}
+
+/// Human-readable report: one line per check, then how to fix what is
+/// missing. `exe` is the binary path to put in the `setcap` command.
+pub fn format_report(checks: &[PrivilegeCheck], exe: &str) -> String {
+    let mut out = String::new();
+    for check in checks {
+        let (mark, detail) = match &check.status {
+            CheckStatus::Ok => ("ok", String::new()),
+            CheckStatus::PermissionDenied => ("MISSING", " (permission denied: CAP_NET_RAW)".to_string()),
+            CheckStatus::NotSupported(e) => ("UNSUPPORTED", format!(" ({})", e)),
+            CheckStatus::Failed(e) => ("FAILED", format!(" ({})", e)),
+        };
+        let optional = if check.required { "" } else { " [optional]" };
+        let _ = writeln!(out, "  {:<12} {}{}{}", mark, check.name, optional, detail);
+    }
+
+    if checks.iter().any(|c| c.status == CheckStatus::PermissionDenied) {
+        let _ = writeln!(out, "\nRaw sockets need CAP_NET_RAW. Either:");
+        let _ = writeln!(out, "  - run as root (sudo), or");
+        let _ = writeln!(out, "  - grant the capability to the binary: sudo setcap cap_net_raw+ep {}", exe);
+        let _ = writeln!(out, "  - in a container, add the capability: docker run --cap-add=NET_RAW ...");
+        let _ = writeln!(out, "    (Kubernetes: securityContext.capabilities.add: [\"NET_RAW\"])");
+    }
+    if checks.iter().any(|c| matches!(c.status, CheckStatus::NotSupported(_))) {
+        let _ = writeln!(
+            out,
+            "\nSome socket types are not available on this system (kernel without AF_PACKET or IPv6, \
+             or a non-Linux platform). Use --scan-type tcp, which needs no privileges."
+        );
+    }
+
+    let ready = checks.iter().all(PrivilegeCheck::is_satisfied);
+    let _ = writeln!(
+        out,
+        "\n{}",
+        if ready { "Raw scans (syn, fin, null, xmas, ack, sctp) will work." } else { "Raw scans will not work." }
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &'static str, status: CheckStatus, required: bool) -> PrivilegeCheck {
+        PrivilegeCheck { name, status, required }
+    }
+
+    #[test]
+    fn test_report_permission_denied() {
+        let checks = [
+            check("send raw IPv4 packets", CheckStatus::PermissionDenied, true),
+            check("capture IPv4 replies (AF_PACKET)", CheckStatus::PermissionDenied, true),
+        ];
+        let report = format_report(&checks, "/usr/local/bin/vajra");
+
+        assert!(report.contains("MISSING      send raw IPv4 packets"));
+        assert!(report.contains("sudo setcap cap_net_raw+ep /usr/local/bin/vajra"));
+        assert!(report.contains("--cap-add=NET_RAW"));
+        assert!(report.contains("Raw scans will not work."));
+    }
+
+    #[test]
+    fn test_report_not_supported() {
+        let checks = [
+            check("send raw IPv4 packets", CheckStatus::Ok, true),
+            check("send raw IPv6 packets", CheckStatus::NotSupported("Address family not supported".into()), false),
+        ];
+        let report = format_report(&checks, "vajra");
+
+        assert!(report.contains("UNSUPPORTED  send raw IPv6 packets [optional] (Address family not supported)"));
+        assert!(report.contains("not available on this system"));
+        assert!(!report.contains("setcap"));
+        // A missing optional capability does not block raw scans
+        assert!(report.contains("Raw scans (syn, fin, null, xmas, ack, sctp) will work."));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_status_from_errno() {
+        let denied = io::Error::from_raw_os_error(libc::EPERM);
+        assert_eq!(CheckStatus::from_error(&denied), CheckStatus::PermissionDenied);
+        let unsupported = io::Error::from_raw_os_error(libc::EAFNOSUPPORT);
+        assert!(matches!(CheckStatus::from_error(&unsupported), CheckStatus::NotSupported(_)));
+        let other = io::Error::from_raw_os_error(libc::EMFILE);
+        assert!(matches!(CheckStatus::from_error(&other), CheckStatus::Failed(_)));
+    }
+}
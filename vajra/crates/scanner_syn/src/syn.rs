@@ -3,23 +3,222 @@
 //! This file fixes the compilation error by using the correct
 //! ProbeResult API from vajra-common
 
-use crate::capture::{PendingKey, PENDING_PROBES};
+use crate::capture::{record_pcap, CaptureResponse, PendingKey, PENDING_PROBES};
 use crate::error::SynError;
-use crate::packet::{build_syn_packet, tcp_flags};
+use crate::os::guess_os;
+use crate::packet::{
+    build_rst_packet, build_sctp_init, build_tcp_probe, icmp_types, sctp_chunks, tcp_flags, DEFAULT_TTL, IPPROTO_SCTP,
+};
 use parking_lot::Mutex;
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{oneshot, Semaphore};
 use tokio::time::timeout;
-use vajra_common::{PortState, ProbeResult, Scanner, Target};
+use vajra_common::{PortState, ProbeResult, RateLimiter, Scanner, Target};
 use async_trait::async_trait;
 use anyhow::Result;
+use dashmap::DashSet;
+use once_cell::sync::Lazy;
+
+/// Random source ports are drawn from this range
+const EPHEMERAL_PORTS: RangeInclusive<u16> = 32768..=65535;
+
+/// Number of ports in `EPHEMERAL_PORTS`
+const EPHEMERAL_PORT_COUNT: u32 = 32768;
+
+/// Random source ports held by outstanding probes, keyed by
+/// (dst_ip, dst_port, src_port)
+static LEASED_PORTS: Lazy<DashSet<(IpAddr, u16, u16)>> = Lazy::new(DashSet::new);
+
+/// Source port reserved for one probe. Released on drop, so a port is
+/// only reused for a destination once the probe holding it has finished.
+struct SourcePortLease {
+    port: u16,
+    key: Option<(IpAddr, u16, u16)>,
+}
+
+impl SourcePortLease {
+    /// Reserve a random ephemeral port that no outstanding probe to
+    /// `dst_ip:dst_port` is using. Starts at a random port and walks the
+    /// range, so it only fails when every port is in flight to that
+    /// destination; the port is then shared unreserved (replies are still
+    /// told apart by sequence number).
+    fn acquire(dst_ip: IpAddr, dst_port: u16) -> Self {
+        let base = *EPHEMERAL_PORTS.start();
+        let first = rand::random::<u32>() % EPHEMERAL_PORT_COUNT;
+        for i in 0..EPHEMERAL_PORT_COUNT {
+            let port = base + ((first + i) % EPHEMERAL_PORT_COUNT) as u16;
+            let key = (dst_ip, dst_port, port);
+            if LEASED_PORTS.insert(key) {
+                return Self { port, key: Some(key) };
+            }
+        }
+        Self::fixed(base + first as u16)
+    }
+
+    /// A configured source port: shared by every probe, nothing to reserve
+    fn fixed(port: u16) -> Self {
+        Self { port, key: None }
+    }
+
+    fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for SourcePortLease {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            LEASED_PORTS.remove(&key);
+        }
+    }
+}
+
+/// Raw probe type sent by `SynScanner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanFlavor {
+    /// Half-open SYN scan
+    #[default]
+    Syn,
+    /// FIN only
+    Fin,
+    /// No flags set
+    Null,
+    /// FIN | PSH | URG
+    Xmas,
+    /// ACK only; maps firewall rules (filtered vs unfiltered)
+    Ack,
+    /// SCTP INIT chunk instead of a TCP segment (nmap `-sY`)
+    SctpInit,
+}
+
+impl ScanFlavor {
+    /// TCP flag byte sent by this probe (none for SCTP)
+    pub fn flags(self) -> u8 {
+        match self {
+            ScanFlavor::Syn => tcp_flags::SYN,
+            ScanFlavor::Fin => tcp_flags::FIN,
+            ScanFlavor::Null => 0,
+            ScanFlavor::Xmas => tcp_flags::FIN | tcp_flags::PSH | tcp_flags::URG,
+            ScanFlavor::Ack => tcp_flags::ACK,
+            ScanFlavor::SctpInit => 0,
+        }
+    }
+
+    /// Acknowledgment value a valid reply carries (see `PendingKey`).
+    /// SYN and FIN each consume one sequence number; an ACK probe's reset
+    /// echoes the probe's ACK field, which is sent equal to `seq`. SCTP
+    /// replies echo the INIT's Initiate Tag, which is sent equal to `seq`.
+    fn expected_ack(self, seq: u32) -> u32 {
+        match self {
+            ScanFlavor::Syn | ScanFlavor::Fin | ScanFlavor::Xmas => seq.wrapping_add(1),
+            ScanFlavor::Null | ScanFlavor::Ack | ScanFlavor::SctpInit => seq,
+        }
+    }
+
+    /// Port state when no reply arrives before the timeout. Silence alone
+    /// cannot tell a dropped probe from a lost reply, so stateless probes
+    /// report `OpenFiltered`; `Filtered` is kept for explicit evidence.
+    fn no_response_state(self) -> PortState {
+        match self {
+            // An ACK scan only maps filtering: no reset means filtered
+            ScanFlavor::Ack => PortState::Filtered,
+            // RFC 793: an open port silently drops FIN/NULL/Xmas segments
+            ScanFlavor::Syn | ScanFlavor::SctpInit | ScanFlavor::Fin | ScanFlavor::Null | ScanFlavor::Xmas => {
+                PortState::OpenFiltered
+            }
+        }
+    }
+
+    /// Evidence for a reply carrying `flags`, recorded as the result's
+    /// reason: the flag names plus the raw byte, e.g. `syn-ack flags=0x12`
+    /// (for SCTP, the chunk name and type)
+    fn reply_reason(self, flags: u8) -> String {
+        if self == ScanFlavor::SctpInit {
+            let name = match flags {
+                sctp_chunks::INIT_ACK => "init-ack",
+                sctp_chunks::ABORT => "abort",
+                _ => "chunk",
+            };
+            return format!("{} chunk={}", name, flags);
+        }
+
+        const NAMES: [(u8, &str); 6] = [
+            (tcp_flags::SYN, "syn"),
+            (tcp_flags::RST, "rst"),
+            (tcp_flags::FIN, "fin"),
+            (tcp_flags::PSH, "psh"),
+            (tcp_flags::ACK, "ack"),
+            (tcp_flags::URG, "urg"),
+        ];
+        let names: Vec<&str> = NAMES
+            .iter()
+            .filter(|(bit, _)| flags & bit != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        let names = if names.is_empty() { "none".to_string() } else { names.join("-") };
+        format!("{} flags=0x{:02x}", names, flags)
+    }
+
+    /// Result for `target` answered by `reply`. A SYN-ACK also yields an
+    /// OS guess from its TTL and window; resets carry a zero window and
+    /// SCTP has none, so neither is fingerprinted.
+    fn reply_result(self, target: Target, reply: &CaptureResponse) -> ProbeResult {
+        let (state, reason) = match reply.icmp {
+            Some((icmp_type, code)) => {
+                let protocol = if self == ScanFlavor::SctpInit { IPPROTO_SCTP } else { 6 };
+                (unreachable_state(protocol, icmp_type, code), unreachable_reason(icmp_type, code))
+            }
+            None => (self.classify(reply.flags), self.reply_reason(reply.flags)),
+        };
+        let mut result = ProbeResult::new(target, state)
+            .with_rtt(reply.rtt)
+            .with_reason(reason);
+        // The capture measured the RTT from when this attempt was sent
+        result.started_at = reply.recv_time.checked_sub(reply.rtt);
+        if self != ScanFlavor::SctpInit && reply.flags & tcp_flags::SYN != 0 {
+            if let Some(os) = guess_os(reply.ttl, reply.window) {
+                result = result.with_os_guess(os);
+            }
+        }
+        result
+    }
+
+    /// Port state for a reply carrying `flags` (the chunk type for SCTP)
+    fn classify(self, flags: u8) -> PortState {
+        match self {
+            ScanFlavor::Syn => classify_response(flags),
+            ScanFlavor::Fin | ScanFlavor::Null | ScanFlavor::Xmas => {
+                if flags & tcp_flags::RST != 0 {
+                    PortState::Closed
+                } else {
+                    PortState::Filtered
+                }
+            }
+            ScanFlavor::Ack => {
+                if flags & tcp_flags::RST != 0 {
+                    PortState::Unfiltered
+                } else {
+                    PortState::Filtered
+                }
+            }
+            ScanFlavor::SctpInit => match flags {
+                sctp_chunks::INIT_ACK => PortState::Open,
+                sctp_chunks::ABORT => PortState::Closed,
+                _ => PortState::Filtered,
+            },
+        }
+    }
+}
 
 /// Optimized SYN scanner with socket reuse and high concurrency
 pub struct SynScanner {
     /// Reusable raw socket (shared across all probes)
     raw_socket: Arc<Mutex<Option<RawSocket>>>,
+    /// Raw socket for IPv6 targets, opened on first use
+    raw_socket6: Arc<Mutex<Option<RawSocket>>>,
     /// Buffer pool for zero-allocation sends
     buffer_pool: Arc<BufferPool>,
     /// Maximum concurrent probes
@@ -28,6 +227,18 @@ pub struct SynScanner {
     timeout: Duration,
     /// Number of retries per target
     retries: u32,
+    /// Probe type (SYN by default)
+    flavor: ScanFlavor,
+    /// Fixed source port (random high port per probe when unset)
+    source_port: Option<u16>,
+    /// Source address written into the IP header (kernel-chosen when unset)
+    source_ip: Option<Ipv4Addr>,
+    /// IPv4 TTL / IPv6 hop limit of outgoing probes
+    ttl: u8,
+    /// Filter captured packets in the kernel by probe source port
+    bpf_filter: bool,
+    /// Paces every probe sent (unlimited when unset)
+    rate_limiter: Option<Arc<dyn RateLimiter>>,
 }
 
 /// Raw socket wrapper (Linux-specific)
@@ -36,10 +247,17 @@ struct RawSocket {
 }
 
 impl RawSocket {
-    fn new() -> Result<Self, SynError> {
+    /// Open an `AF_INET` (or `AF_INET6` when `ipv6`) raw socket that sends
+    /// caller-built IP headers.
+    fn new(ipv6: bool) -> Result<Self, SynError> {
         #[cfg(target_os = "linux")]
         {
-            let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_RAW, libc::IPPROTO_RAW) };
+            let (family, level, hdrincl) = if ipv6 {
+                (libc::AF_INET6, libc::IPPROTO_IPV6, libc::IPV6_HDRINCL)
+            } else {
+                (libc::AF_INET, libc::IPPROTO_IP, libc::IP_HDRINCL)
+            };
+            let fd = unsafe { libc::socket(family, libc::SOCK_RAW, libc::IPPROTO_RAW) };
             if fd < 0 {
                 return Err(SynError::NotPermitted);
             }
@@ -48,8 +266,8 @@ impl RawSocket {
                 let one: libc::c_int = 1;
                 libc::setsockopt(
                     fd,
-                    libc::IPPROTO_IP,
-                    libc::IP_HDRINCL,
+                    level,
+                    hdrincl,
                     &one as *const _ as *const libc::c_void,
                     std::mem::size_of::<libc::c_int>() as libc::socklen_t,
                 );
@@ -69,6 +287,7 @@ impl RawSocket {
 
         #[cfg(not(target_os = "linux"))]
         {
+            let _ = ipv6;
             Err(SynError::NotImplemented)
         }
     }
@@ -98,7 +317,27 @@ impl RawSocket {
                         Ok(())
                     }
                 },
-                _ => Err(SynError::NotImplemented),
+                IpAddr::V6(addr) => unsafe {
+                    // Port must be zero for raw sockets
+                    let mut sa: libc::sockaddr_in6 = std::mem::zeroed();
+                    sa.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                    sa.sin6_addr.s6_addr = addr.octets();
+
+                    let result = libc::sendto(
+                        self.fd,
+                        buf.as_ptr() as *const libc::c_void,
+                        buf.len(),
+                        0,
+                        &sa as *const _ as *const libc::sockaddr,
+                        std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t,
+                    );
+
+                    if result < 0 {
+                        Err(SynError::Io(std::io::Error::last_os_error()))
+                    } else {
+                        Ok(())
+                    }
+                },
             }
         }
 
@@ -118,6 +357,9 @@ impl Drop for RawSocket {
     }
 }
 
+/// Pooled buffer size; fits the largest probe (IPv6 + SCTP INIT, 72 bytes)
+const PACKET_BUF_LEN: usize = 80;
+
 /// Zero-allocation buffer pool for packet building
 struct BufferPool {
     buffers: Mutex<Vec<Vec<u8>>>,
@@ -127,7 +369,7 @@ impl BufferPool {
     fn new(capacity: usize) -> Self {
         let mut buffers = Vec::with_capacity(capacity);
         for _ in 0..capacity {
-            buffers.push(vec![0u8; 60]); // 60 bytes is enough for IPv4+TCP
+            buffers.push(vec![0u8; PACKET_BUF_LEN]);
         }
 
         BufferPool {
@@ -139,7 +381,7 @@ impl BufferPool {
         self.buffers
             .lock()
             .pop()
-            .unwrap_or_else(|| vec![0u8; 60])
+            .unwrap_or_else(|| vec![0u8; PACKET_BUF_LEN])
     }
 
     fn release(&self, buf: Vec<u8>) {
@@ -160,10 +402,17 @@ impl SynScanner {
     pub fn with_concurrency(max_concurrency: usize) -> Self {
         Self {
             raw_socket: Arc::new(Mutex::new(None)),
+            raw_socket6: Arc::new(Mutex::new(None)),
             buffer_pool: Arc::new(BufferPool::new(max_concurrency / 10)),
             max_concurrency,
             timeout: Duration::from_secs(2),
             retries: 1,
+            flavor: ScanFlavor::Syn,
+            source_port: None,
+            source_ip: None,
+            ttl: DEFAULT_TTL,
+            bpf_filter: true,
+            rate_limiter: None,
         }
     }
 
@@ -177,40 +426,180 @@ impl SynScanner {
         self
     }
 
+    /// Select the probe type (SYN, FIN, NULL, Xmas, ACK or SCTP INIT)
+    pub fn with_flavor(mut self, flavor: ScanFlavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Send every probe from a fixed source port (e.g. 53 or 20 to pass
+    /// firewalls that trust those ports).
+    ///
+    /// Responses are matched on (target, port, source port, ACK), so a fixed
+    /// source port shrinks the tuple space: concurrent probes to the same
+    /// target port are told apart only by sequence number, and the remote
+    /// stack may treat them as one connection. Lower the concurrency when
+    /// probing many times per port.
+    pub fn with_source_port(mut self, port: u16) -> Self {
+        self.source_port = Some(port);
+        self
+    }
+
+    /// Write `ip` as the source address of every probe (and into the TCP
+    /// pseudo-header checksum). Replies only come back if `ip` routes here.
+    pub fn with_source_ip(mut self, ip: Ipv4Addr) -> Self {
+        self.source_ip = Some(ip);
+        self
+    }
+
+    /// Set the TTL (IPv4) / hop limit (IPv6) of outgoing probes.
+    /// A low value makes probes expire in transit, for hop-by-hop
+    /// reachability testing. Defaults to 64.
+    pub fn with_ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Attach a BPF filter to the capture sockets so only replies to this
+    /// scanner's source ports reach userspace (on by default). Cuts syscalls
+    /// and parsing on busy links; see `capture_port_filter`.
+    pub fn with_bpf_filter(mut self, enabled: bool) -> Self {
+        self.bpf_filter = enabled;
+        self
+    }
+
+    /// Wait on `rate_limiter` before sending each probe, retries included,
+    /// e.g. `vajra_orchestrator::RateLimiter::new(1000)` for 1000 packets
+    /// per second. Scans run through the orchestrator are already paced
+    /// there; this is for calling `probe_one`/`probe_batch` directly.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<dyn RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Destination ports the capture loop must accept for this scanner's
+    /// replies, or `None` when BPF filtering is disabled. Pass to
+    /// `start_capture_loop` (as `init` does).
+    pub fn capture_port_filter(&self) -> Option<RangeInclusive<u16>> {
+        if !self.bpf_filter {
+            return None;
+        }
+        Some(match self.source_port {
+            Some(port) => port..=port,
+            None => EPHEMERAL_PORTS,
+        })
+    }
+
+    /// Source address and port for the next probe to `dst`:`dst_port`.
+    /// Random ports are unique among outstanding probes to that destination
+    /// (see `SourcePortLease`); hold the lease until the probe completes.
+    /// IPv6 probes use the address the kernel would route `dst` from, since
+    /// the source must be filled in for the TCP checksum.
+    fn probe_source(&self, dst: &IpAddr, dst_port: u16) -> (IpAddr, SourcePortLease) {
+        let port = match self.source_port {
+            Some(port) => SourcePortLease::fixed(port),
+            None => SourcePortLease::acquire(*dst, dst_port),
+        };
+        let ip = match dst {
+            IpAddr::V4(_) => IpAddr::V4(self.source_ip.unwrap_or(Ipv4Addr::UNSPECIFIED)),
+            IpAddr::V6(dst) => IpAddr::V6(route_source_v6(*dst).unwrap_or(Ipv6Addr::UNSPECIFIED)),
+        };
+        (ip, port)
+    }
+
     pub fn is_raw_available() -> bool {
         #[cfg(target_os = "linux")]
-        match RawSocket::new() {
-            Ok(_) => true,
-            Err(_) => false,
+        {
+            RawSocket::new(false).is_ok()
         }
 
         #[cfg(not(target_os = "linux"))]
         false
     }
 
-    fn ensure_socket(&self) -> Result<(), SynError> {
-        let mut sock = self.raw_socket.lock();
+    /// Raw socket slot for the address family of `dst`
+    fn socket_for(&self, dst: &IpAddr) -> &Mutex<Option<RawSocket>> {
+        if dst.is_ipv6() {
+            &self.raw_socket6
+        } else {
+            &self.raw_socket
+        }
+    }
+
+    /// Open the raw socket for `dst`'s address family if not yet open
+    fn ensure_socket(&self, dst: &IpAddr) -> Result<(), SynError> {
+        let mut sock = self.socket_for(dst).lock();
         if sock.is_none() {
-            *sock = Some(RawSocket::new()?);
+            *sock = Some(RawSocket::new(dst.is_ipv6())?);
         }
         Ok(())
     }
 
+    /// Tear down the half-open connection left by a SYN-ACK (best effort).
+    fn send_rst(&self, src_ip: &IpAddr, dst_ip: &IpAddr, src_port: u16, dst_port: u16, seq: u32) {
+        let mut buf = self.buffer_pool.acquire();
+        let pkt_len = build_rst_packet(&mut buf, src_ip, dst_ip, src_port, dst_port, seq, self.ttl);
+        if pkt_len > 0 {
+            if let Some(ref socket) = *self.socket_for(dst_ip).lock() {
+                if socket.send(&buf[0..pkt_len], dst_ip).is_ok() {
+                    record_pcap(&buf[0..pkt_len]);
+                }
+            }
+        }
+        self.buffer_pool.release(buf);
+    }
+
+    /// Probe one target, resending up to `retries` times while no reply
+    /// arrives. Each attempt uses a fresh sequence number (and so its own
+    /// pending-probe entry) and a full `timeout_duration` window; the
+    /// no-response state is only reported once every attempt has timed out.
     pub async fn probe_one(
         &self,
         target: Target,
         timeout_duration: Duration,
     ) -> Result<ProbeResult, SynError> {
-        self.ensure_socket()?;
+        self.ensure_socket(&target.ip)?;
+
+        let started = Instant::now();
+        for _ in 0..=self.retries {
+            if let Some(reply) = self.probe_attempt(&target, timeout_duration).await? {
+                return Ok(self.flavor.reply_result(target, &reply));
+            }
+        }
+        let state = self.flavor.no_response_state();
+        Ok(ProbeResult::new(target, state).with_started_at(started))
+    }
+
+    /// Send a single probe and wait for its reply.
+    /// Returns the captured reply, or `None` if nothing arrives within
+    /// `timeout_duration`.
+    async fn probe_attempt(
+        &self,
+        target: &Target,
+        timeout_duration: Duration,
+    ) -> Result<Option<CaptureResponse>, SynError> {
+        // Wait before the clock starts so pacing does not count as RTT
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
         let start = Instant::now();
-        let src_port = rand::random::<u16>() % 32768 + 32768;
-        let seq = rand::random::<u32>();
-        let src_ip = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
         let dst_ip = target.ip;
         let dst_port = target.port;
+        let (src_ip, lease) = self.probe_source(&dst_ip, dst_port);
+        let src_port = lease.port();
+        // SCTP forbids a zero Initiate Tag
+        let seq = rand::random::<u32>().max(1);
+
+        let flavor = self.flavor;
+        // ACK probes send ACK = seq so the reset's sequence number identifies them
+        let ack = if flavor == ScanFlavor::Ack { seq } else { 0 };
 
         let mut buf = self.buffer_pool.acquire();
-        let pkt_len = build_syn_packet(&mut buf, &src_ip, &dst_ip, src_port, dst_port, seq);
+        let pkt_len = if flavor == ScanFlavor::SctpInit {
+            build_sctp_init(&mut buf, &src_ip, &dst_ip, src_port, dst_port, seq, self.ttl)
+        } else {
+            build_tcp_probe(&mut buf, &src_ip, &dst_ip, src_port, dst_port, seq, ack, flavor.flags(), self.ttl)
+        };
 
         if pkt_len == 0 {
             self.buffer_pool.release(buf);
@@ -218,37 +607,36 @@ impl SynScanner {
         }
 
         let (tx, rx) = oneshot::channel();
-        let key: PendingKey = (dst_ip, dst_port, src_port, seq);
+        let key: PendingKey = (dst_ip, dst_port, src_port, flavor.expected_ack(seq));
         PENDING_PROBES.insert(key, (start, tx));
 
-        {
-            let sock = self.raw_socket.lock();
-            if let Some(ref socket) = *sock {
-                socket.send(&buf[0..pkt_len], &dst_ip)?;
-            } else {
-                PENDING_PROBES.remove(&key);
-                self.buffer_pool.release(buf);
-                return Err(SynError::NotPermitted);
-            }
+        let sent = match *self.socket_for(&dst_ip).lock() {
+            Some(ref socket) => socket.send(&buf[0..pkt_len], &dst_ip),
+            None => Err(SynError::NotPermitted),
+        };
+        if sent.is_ok() {
+            record_pcap(&buf[0..pkt_len]);
         }
-
         self.buffer_pool.release(buf);
+        if let Err(e) = sent {
+            PENDING_PROBES.remove(&key);
+            return Err(e);
+        }
 
-        match timeout(timeout_duration, rx).await {
+        let reply = timeout(timeout_duration, rx).await;
+        PENDING_PROBES.remove(&key);
+        match reply {
             Ok(Ok(response)) => {
-                PENDING_PROBES.remove(&key);
-                let state = classify_response(response.flags);
-                let result = ProbeResult::new(target, state).with_rtt(response.rtt);
-                Ok(result)
-            }
-            Ok(Err(_)) => {
-                PENDING_PROBES.remove(&key);
-                Err(SynError::Capture("Channel closed".to_string()))
-            }
-            Err(_) => {
-                PENDING_PROBES.remove(&key);
-                Ok(ProbeResult::new(target, PortState::Filtered))
+                let state = flavor.classify(response.flags);
+                // An INIT-ACK leaves no state on the peer (it lives in the
+                // cookie), so only TCP needs tearing down
+                if state == PortState::Open && flavor != ScanFlavor::SctpInit {
+                    self.send_rst(&src_ip, &dst_ip, src_port, dst_port, seq.wrapping_add(1));
+                }
+                Ok(Some(response))
             }
+            Ok(Err(_)) => Err(SynError::Capture("Channel closed".to_string())),
+            Err(_) => Ok(None),
         }
     }
 
@@ -257,7 +645,13 @@ impl SynScanner {
         targets: Vec<Target>,
         timeout_duration: Duration,
     ) -> Result<Vec<ProbeResult>, SynError> {
-        self.ensure_socket()?;
+        // Fail fast if a needed raw socket cannot be opened
+        if let Some(t) = targets.iter().find(|t| t.ip.is_ipv4()) {
+            self.ensure_socket(&t.ip)?;
+        }
+        if let Some(t) = targets.iter().find(|t| t.ip.is_ipv6()) {
+            self.ensure_socket(&t.ip)?;
+        }
         let semaphore = Arc::new(Semaphore::new(self.max_concurrency));
         let mut tasks = Vec::with_capacity(targets.len());
 
@@ -286,14 +680,38 @@ impl SynScanner {
     fn clone_for_task(&self) -> Self {
         Self {
             raw_socket: self.raw_socket.clone(),
+            raw_socket6: self.raw_socket6.clone(),
             buffer_pool: self.buffer_pool.clone(),
             max_concurrency: self.max_concurrency,
             timeout: self.timeout,
             retries: self.retries,
+            flavor: self.flavor,
+            source_port: self.source_port,
+            source_ip: self.source_ip,
+            ttl: self.ttl,
+            bpf_filter: self.bpf_filter,
+            rate_limiter: self.rate_limiter.clone(),
         }
     }
 }
 
+impl Default for SynScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Local address the kernel would use to reach `dst`. Connecting a UDP
+/// socket performs the route lookup without sending anything.
+fn route_source_v6(dst: Ipv6Addr) -> Option<Ipv6Addr> {
+    let sock = std::net::UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)).ok()?;
+    sock.connect((dst, 9)).ok()?;
+    match sock.local_addr().ok()?.ip() {
+        IpAddr::V6(ip) => Some(ip),
+        IpAddr::V4(_) => None,
+    }
+}
+
 #[inline(always)]
 fn classify_response(flags: u8) -> PortState {
     if flags & tcp_flags::SYN != 0 && flags & tcp_flags::ACK != 0 {
@@ -305,6 +723,42 @@ fn classify_response(flags: u8) -> PortState {
     }
 }
 
+/// Port state for a probe of transport `protocol` answered by an ICMP
+/// destination-unreachable error. Port unreachable comes from the target's
+/// own stack, so for UDP it means closed; everything else, and any error
+/// quoting a TCP or SCTP probe, means something on the way filters it.
+pub fn unreachable_state(protocol: u8, icmp_type: u8, code: u8) -> PortState {
+    let port_unreachable = match icmp_type {
+        icmp_types::DEST_UNREACH_V4 => code == 3,
+        icmp_types::DEST_UNREACH_V6 => code == 4,
+        _ => false,
+    };
+    if protocol == 17 && port_unreachable {
+        PortState::Closed
+    } else {
+        PortState::Filtered
+    }
+}
+
+/// Evidence for an ICMP destination-unreachable reply, recorded as the
+/// result's reason, e.g. `admin-prohibited icmp=3/13`
+fn unreachable_reason(icmp_type: u8, code: u8) -> String {
+    let name = match (icmp_type, code) {
+        (icmp_types::DEST_UNREACH_V4, 0) => "net-unreachable",
+        (icmp_types::DEST_UNREACH_V4, 1) | (icmp_types::DEST_UNREACH_V6, 3) => "host-unreachable",
+        (icmp_types::DEST_UNREACH_V4, 2) => "proto-unreachable",
+        (icmp_types::DEST_UNREACH_V4, 3) | (icmp_types::DEST_UNREACH_V6, 4) => "port-unreachable",
+        (icmp_types::DEST_UNREACH_V4, 9) => "net-prohibited",
+        (icmp_types::DEST_UNREACH_V4, 10) => "host-prohibited",
+        (icmp_types::DEST_UNREACH_V4, 13) | (icmp_types::DEST_UNREACH_V6, 1) => "admin-prohibited",
+        (icmp_types::DEST_UNREACH_V6, 0) => "no-route",
+        (icmp_types::DEST_UNREACH_V6, 5) => "policy-rejected",
+        (icmp_types::DEST_UNREACH_V6, 6) => "reject-route",
+        _ => "unreachable",
+    };
+    format!("{} icmp={}/{}", name, icmp_type, code)
+}
+
 #[async_trait]
 impl Scanner for SynScanner {
     fn name(&self) -> &str {
@@ -316,21 +770,14 @@ impl Scanner for SynScanner {
     }
 
     async fn scan(&self, target: &Target) -> Result<ProbeResult> {
-        // Implement retries using probe_one
-        let mut last_err = None;
-        for _ in 0..=self.retries {
-            match self.probe_one(target.clone(), self.timeout).await {
-                Ok(res) => return Ok(res),
-                Err(e) => last_err = Some(e),
-            }
-        }
-        Err(anyhow::anyhow!(
-            "Failed to scan {}:{} after {} retries: {:?}",
-            target.ip,
-            target.port,
-            self.retries,
-            last_err
-        ))
+        self.scan_with_timeout(target, self.timeout).await
+    }
+
+    async fn scan_with_timeout(&self, target: &Target, timeout: Duration) -> Result<ProbeResult> {
+        // probe_one resends on timeout up to `retries` times
+        self.probe_one(target.clone(), timeout).await.map_err(|e| {
+            anyhow::anyhow!("Failed to scan {}:{}: {:?}", target.ip, target.port, e)
+        })
     }
 }
 
@@ -351,10 +798,10 @@ mod tests {
     fn test_buffer_pool() {
         let pool = BufferPool::new(10);
         let buf1 = pool.acquire();
-        assert_eq!(buf1.len(), 60);
+        assert_eq!(buf1.len(), PACKET_BUF_LEN);
         pool.release(buf1);
         let buf2 = pool.acquire();
-        assert_eq!(buf2.len(), 60);
+        assert_eq!(buf2.len(), PACKET_BUF_LEN);
     }
 
     #[test]
@@ -367,9 +814,221 @@ mod tests {
         assert_eq!(classify_response(tcp_flags::ACK), PortState::Filtered);
     }
 
+    #[test]
+    fn test_flavor_flags() {
+        assert_eq!(ScanFlavor::Syn.flags(), tcp_flags::SYN);
+        assert_eq!(ScanFlavor::Null.flags(), 0);
+        assert_eq!(ScanFlavor::Fin.flags(), tcp_flags::FIN);
+        assert_eq!(
+            ScanFlavor::Xmas.flags(),
+            tcp_flags::FIN | tcp_flags::PSH | tcp_flags::URG
+        );
+        assert_eq!(ScanFlavor::Ack.flags(), tcp_flags::ACK);
+    }
+
+    #[test]
+    fn test_flavor_classification() {
+        for flavor in [ScanFlavor::Fin, ScanFlavor::Null, ScanFlavor::Xmas] {
+            assert_eq!(flavor.no_response_state(), PortState::OpenFiltered);
+            assert_eq!(flavor.classify(tcp_flags::RST | tcp_flags::ACK), PortState::Closed);
+        }
+        assert_eq!(ScanFlavor::Ack.classify(tcp_flags::RST), PortState::Unfiltered);
+        assert_eq!(ScanFlavor::Ack.no_response_state(), PortState::Filtered);
+        assert_eq!(ScanFlavor::Syn.no_response_state(), PortState::OpenFiltered);
+        assert_eq!(
+            ScanFlavor::Syn.classify(tcp_flags::SYN | tcp_flags::ACK),
+            PortState::Open
+        );
+    }
+
+    /// Captured reply with the given flags, TTL and window
+    fn reply(flags: u8, ttl: u8, window: u16) -> CaptureResponse {
+        CaptureResponse {
+            flags,
+            ttl,
+            window,
+            rtt: Duration::from_millis(3),
+            recv_time: Instant::now(),
+            icmp: None,
+        }
+    }
+
+    #[test]
+    fn test_reply_evidence_recorded() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 5)), 443);
+
+        let synack = reply(tcp_flags::SYN | tcp_flags::ACK, 64, 0);
+        let open = ScanFlavor::Syn.reply_result(target.clone(), &synack);
+        assert_eq!(open.state, PortState::Open);
+        assert_eq!(open.rtt, Duration::from_millis(3));
+        assert_eq!(open.started_at.unwrap() + open.rtt, synack.recv_time);
+        assert_eq!(open.reason.as_deref(), Some("syn-ack flags=0x12"));
+
+        let closed = ScanFlavor::Syn.reply_result(target.clone(), &reply(tcp_flags::RST | tcp_flags::ACK, 64, 0));
+        assert_eq!(closed.state, PortState::Closed);
+        assert_eq!(closed.reason.as_deref(), Some("rst-ack flags=0x14"));
+
+        // Odd middlebox replies are visible as-is
+        let odd = ScanFlavor::Syn.reply_result(target.clone(), &reply(tcp_flags::SYN | tcp_flags::RST, 64, 0));
+        assert_eq!(odd.reason.as_deref(), Some("syn-rst flags=0x06"));
+
+        let abort = ScanFlavor::SctpInit.reply_result(target, &reply(sctp_chunks::ABORT, 64, 0));
+        assert_eq!((abort.state, abort.reason.as_deref()), (PortState::Closed, Some("abort chunk=6")));
+    }
+
+    #[test]
+    fn test_icmp_unreachable_classification() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 5)), 443);
+        let icmp = |icmp_type, code| CaptureResponse { icmp: Some((icmp_type, code)), ..reply(0, 250, 0) };
+
+        let prohibited = ScanFlavor::Syn.reply_result(target.clone(), &icmp(3, 13));
+        assert_eq!(prohibited.state, PortState::Filtered);
+        assert_eq!(prohibited.reason.as_deref(), Some("admin-prohibited icmp=3/13"));
+        assert_eq!(prohibited.os_guess, None);
+        let v6 = ScanFlavor::SctpInit.reply_result(target, &icmp(1, 1));
+        assert_eq!((v6.state, v6.reason.as_deref()), (PortState::Filtered, Some("admin-prohibited icmp=1/1")));
+
+        // Port unreachable closes a UDP port; for TCP it is still a filter
+        assert_eq!(unreachable_state(17, 3, 3), PortState::Closed);
+        assert_eq!(unreachable_state(17, 1, 4), PortState::Closed);
+        assert_eq!(unreachable_state(17, 3, 13), PortState::Filtered);
+        assert_eq!(unreachable_state(6, 3, 3), PortState::Filtered);
+        assert_eq!(unreachable_reason(3, 10), "host-prohibited icmp=3/10");
+    }
+
+    #[test]
+    fn test_os_guess_from_syn_ack() {
+        let target = Target::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 5)), 443);
+        let syn_ack = tcp_flags::SYN | tcp_flags::ACK;
+
+        let linux = ScanFlavor::Syn.reply_result(target.clone(), &reply(syn_ack, 52, 65160));
+        assert_eq!(linux.os_guess.as_deref(), Some("Linux"));
+        let windows = ScanFlavor::Syn.reply_result(target.clone(), &reply(syn_ack, 113, 8192));
+        assert_eq!(windows.os_guess.as_deref(), Some("Windows"));
+
+        // Resets and SCTP replies are not fingerprinted
+        let rst = ScanFlavor::Syn.reply_result(target.clone(), &reply(tcp_flags::RST | tcp_flags::ACK, 52, 0));
+        assert_eq!(rst.os_guess, None);
+        let init_ack = ScanFlavor::SctpInit.reply_result(target, &reply(sctp_chunks::INIT_ACK, 52, 0));
+        assert_eq!(init_ack.os_guess, None);
+    }
+
+    #[test]
+    fn test_sctp_init_classification() {
+        let sctp = ScanFlavor::SctpInit;
+        assert_eq!(sctp.classify(sctp_chunks::INIT_ACK), PortState::Open);
+        assert_eq!(sctp.classify(sctp_chunks::ABORT), PortState::Closed);
+        assert_eq!(sctp.classify(sctp_chunks::INIT), PortState::Filtered);
+        assert_eq!(sctp.no_response_state(), PortState::OpenFiltered);
+        assert_eq!(sctp.expected_ack(42), 42);
+
+        // The probe fits a pooled buffer for both address families
+        let mut buf = BufferPool::new(1).acquire();
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(build_sctp_init(&mut buf, &v6, &v6, 40000, 2905, 42, DEFAULT_TTL), 72);
+    }
+
+    #[test]
+    fn test_probe_source() {
+        let dst = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 20));
+        let (ip, lease) = SynScanner::new().probe_source(&dst, 80);
+        assert_eq!(ip, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert!(lease.port() >= 32768);
+
+        let src = Ipv4Addr::new(192, 0, 2, 10);
+        let scanner = SynScanner::new().with_source_port(53).with_source_ip(src);
+        let (ip, lease) = scanner.probe_source(&dst, 80);
+        let port = lease.port();
+        assert_eq!((ip, port), (IpAddr::V4(src), 53));
+
+        // Source IP lands in the IP header and matches the parsed packet
+        let mut buf = vec![0u8; 60];
+        let len = build_tcp_probe(&mut buf, &ip, &dst, port, 80, 1, 0, ScanFlavor::Syn.flags(), scanner.ttl);
+        assert_eq!(&buf[12..16], &src.octets());
+        let parsed = crate::packet::parse_packet(&buf[..len]).unwrap();
+        assert_eq!((parsed.0, parsed.1), (ip, 53));
+    }
+
+    #[test]
+    fn test_concurrent_probes_get_distinct_source_ports() {
+        let scanner = SynScanner::new();
+        let dst = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 30));
+
+        // Outstanding probes to one destination never share a port
+        let leases: Vec<_> = (0..2000).map(|_| scanner.probe_source(&dst, 443).1).collect();
+        let mut ports: Vec<u16> = leases.iter().map(SourcePortLease::port).collect();
+        ports.sort_unstable();
+        ports.dedup();
+        assert_eq!(ports.len(), leases.len());
+
+        // Finished probes release their ports
+        let held = leases[0].port();
+        drop(leases);
+        assert!(!LEASED_PORTS.contains(&(dst, 443, held)));
+    }
+
+    #[derive(Default)]
+    struct CountingLimiter {
+        acquired: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl RateLimiter for CountingLimiter {
+        async fn acquire(&self) {
+            self.acquired.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+        fn current_rate(&self) -> f64 {
+            f64::INFINITY
+        }
+        fn set_rate(&self, _rate: u64) {}
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_paces_each_send() {
+        let limiter = Arc::new(CountingLimiter::default());
+        let scanner = SynScanner::with_concurrency(4)
+            .with_retries(0)
+            .with_rate_limiter(limiter.clone());
+        let targets = vec![Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), 9); 3];
+
+        // Another test's capture loop may answer these, so no retries are
+        // asked for: each target is exactly one send either way
+        let outcome = scanner.probe_batch(targets, Duration::from_millis(20)).await;
+        let acquired = limiter.acquired.load(std::sync::atomic::Ordering::Relaxed);
+        match outcome {
+            Ok(results) => {
+                assert_eq!(results.len(), 3);
+                assert_eq!(acquired, 3);
+            }
+            // The socket check fails before anything is sent, or paced
+            Err(e) => {
+                assert!(matches!(e, SynError::NotPermitted), "{:?}", e);
+                assert_eq!(acquired, 0);
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_raw_socket_check() {
         let available = SynScanner::is_raw_available();
         println!("Raw sockets available: {}", available);
     }
+
+    #[test]
+    fn test_ipv6_probe_source() {
+        // IPv6 probes get a v6 source so the packet builds (and checksums)
+        let dst = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let (ip, lease) = SynScanner::new().probe_source(&dst, 80);
+        let port = lease.port();
+        assert!(ip.is_ipv6());
+        if route_source_v6(Ipv6Addr::LOCALHOST).is_some() {
+            assert_eq!(ip, dst);
+        }
+
+        let mut buf = vec![0u8; 60];
+        let len = build_tcp_probe(&mut buf, &ip, &dst, port, 80, 1, 0, ScanFlavor::Syn.flags(), DEFAULT_TTL);
+        assert_eq!(len, 60);
+        let parsed = crate::packet::parse_packet(&buf[..len]).unwrap();
+        assert_eq!((parsed.2, parsed.3), (dst, 80));
+    }
 }
@@ -1,57 +1,131 @@
 //! Banner grabbing functionality
 
 use anyhow::Result;
+use std::fmt;
+use std::io::ErrorKind;
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 use tracing::{debug, instrument};
 
+/// The peer accepted the connection, then closed it before sending
+/// anything (nmap's "tcpwrapped")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClosedWithoutData;
+
+impl fmt::Display for ClosedWithoutData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("connection closed without data")
+    }
+}
+
+impl std::error::Error for ClosedWithoutData {}
+
+/// Banner bytes kept when no limit is set
+pub const DEFAULT_BANNER_BYTES: usize = 512;
+
+/// Highest version intensity: every probe is sent
+pub const MAX_VERSION_INTENSITY: u8 = 9;
+
+/// Version intensity when none is set (nmap's default too); sends every
+/// port-specific probe but no generic HTTP fallback
+pub const DEFAULT_VERSION_INTENSITY: u8 = 7;
+
+/// From this intensity on, the reply to an active probe gets the whole
+/// banner timeout instead of half of it
+const PATIENT_INTENSITY: u8 = 8;
+
 pub struct BannerGrabber {
     timeout: Duration,
-    // reserved: max_bytes not currently used but kept for future limits
+    max_bytes: usize,
+    intensity: u8,
 }
 
 impl BannerGrabber {
     pub fn new(timeout: Duration) -> Self {
         Self {
             timeout,
+            max_bytes: DEFAULT_BANNER_BYTES,
+            intensity: DEFAULT_VERSION_INTENSITY,
         }
     }
 
+    /// Keep at most `max_bytes` of the banner (at least 1)
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes.max(1);
+        self
+    }
+
+    /// Set how aggressively the service is probed, like nmap's
+    /// `--version-intensity` (0-9, higher values are clamped to 9). Only
+    /// probes whose rarity is at most `intensity` are sent (see
+    /// [`probe_for_intensity`]): 0 reads the passive banner only, 9 sends
+    /// everything and waits the full timeout for the reply.
+    pub fn with_intensity(mut self, intensity: u8) -> Self {
+        self.intensity = intensity.min(MAX_VERSION_INTENSITY);
+        self
+    }
+
+    /// Grab a banner from a stream connected to `port`, decoded as text
+    /// (see [`grab_bytes`](Self::grab_bytes)).
+    pub async fn grab(&self, stream: &mut TcpStream, port: u16) -> Result<String> {
+        let data = self.grab_bytes(stream, port).await?;
+        Ok(String::from_utf8_lossy(&data).trim().to_string())
+    }
+
     #[instrument(skip(self, stream))]
-    /// Grab a banner from a connected stream.
-    pub async fn grab(&self, stream: &mut TcpStream) -> Result<String> {
-        // Use smaller buffer for faster reads (limit to 512 bytes for speed)
-        let mut buf = vec![0u8; 512];
+    /// Grab the raw banner bytes from a stream connected to `port`.
+    ///
+    /// Reads first in case the service speaks on connect, then sends the
+    /// port's active probe allowed by the intensity (see
+    /// [`probe_for_intensity`]) and reads the reply.
+    /// Binary greetings (MySQL, PostgreSQL) are returned untouched. A peer
+    /// that closes before sending anything fails with [`ClosedWithoutData`].
+    /// At most `max_bytes` are returned: the first chunk that arrives plus
+    /// whatever else is already buffered, without waiting for more.
+    pub async fn grab_bytes(&self, stream: &mut TcpStream, port: u16) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; self.max_bytes];
+        let probe = probe_for_intensity(port, self.intensity);
 
-        // Try passive banner grab first with very short timeout
+        // Passive grab; services that speak first get the whole budget
         let short_timeout = Duration::from_millis(self.timeout.as_millis() as u64 / 2);
-        match timeout(short_timeout, stream.read(&mut buf)).await {
+        let passive_timeout = if probe.is_some() { short_timeout } else { self.timeout };
+        match timeout(passive_timeout, read_available(stream, &mut buf)).await {
             Ok(Ok(n)) if n > 0 => {
-                let banner = String::from_utf8_lossy(&buf[..n]).trim().to_string();
                 debug!("Passive banner grab: {} bytes", n);
-                return Ok(banner);
+                buf.truncate(n);
+                return Ok(buf);
+            }
+            Ok(Ok(_)) => {
+                debug!("Connection closed without data");
+                return Err(ClosedWithoutData.into());
+            }
+            Ok(Err(e)) if matches!(e.kind(), ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted) => {
+                debug!("Connection reset without data");
+                return Err(ClosedWithoutData.into());
             }
             _ => {
-                debug!("No passive banner, trying active probe");
+                debug!("No passive banner");
             }
         }
 
-        // Try active probe - use generic HTTP probe for now
-        // Protocol-specific probes can be added later if needed
+        let Some(probe) = probe else {
+            return Err(anyhow::anyhow!("No banner available"));
+        };
         let write_timeout = Duration::from_millis(100);
-        if let Err(_) = timeout(write_timeout, stream.write_all(b"GET / HTTP/1.0\r\n\r\n")).await {
-            debug!("Failed to send HTTP probe");
+        if timeout(write_timeout, stream.write_all(probe)).await.is_err() {
+            debug!("Failed to send active probe");
             return Err(anyhow::anyhow!("No banner available"));
         }
 
-        // Read response with short timeout
-        match timeout(short_timeout, stream.read(&mut buf)).await {
+        // Read response with short timeout, unless asked to be thorough
+        let reply_timeout = if self.intensity >= PATIENT_INTENSITY { self.timeout } else { short_timeout };
+        match timeout(reply_timeout, read_available(stream, &mut buf)).await {
             Ok(Ok(n)) if n > 0 => {
-                let banner = String::from_utf8_lossy(&buf[..n]).trim().to_string();
                 debug!("Active banner grab: {} bytes", n);
-                Ok(banner)
+                buf.truncate(n);
+                Ok(buf)
             }
             Ok(Ok(_)) => {
                 debug!("Empty response");
@@ -69,6 +143,66 @@ impl BannerGrabber {
     }
 }
 
+/// Wait for the first chunk of data, then add whatever else has already
+/// arrived, up to the size of `buf`. Returns 0 only if the peer closed.
+async fn read_available(stream: &mut TcpStream, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut n = stream.read(buf).await?;
+    while n > 0 && n < buf.len() {
+        match stream.try_read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(more) => n += more,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+/// HTTP request used for web ports and ports with no better probe
+const HTTP_PROBE: &[u8] = b"GET / HTTP/1.0\r\n\r\n";
+
+/// PostgreSQL startup packet for protocol 0.0; the server answers with an
+/// "unsupported frontend protocol" error that identifies it
+const POSTGRES_PROBE: &[u8] = &[0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00];
+
+/// MongoDB legacy OP_QUERY `{isMaster: 1}` against `admin.$cmd`; still
+/// accepted by current servers as a connection handshake
+const MONGODB_PROBE: &[u8] = b"\x3a\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\xd4\x07\x00\x00\
+\x00\x00\x00\x00admin.$cmd\x00\x00\x00\x00\x00\x01\x00\x00\x00\
+\x13\x00\x00\x00\x10isMaster\x00\x01\x00\x00\x00\x00";
+
+/// Active probe to send to `port` when the service stays silent on connect.
+///
+/// `None` means nothing is sent: either the server speaks first (FTP, SSH,
+/// Telnet, SMTP, POP3, IMAP, MySQL), where a stray request only risks an
+/// error or a hang, or there is no known probe for the port. HTTP is only
+/// sent to web ports.
+pub fn probe_for_port(port: u16) -> Option<&'static [u8]> {
+    port_probe(port).map(|(_, probe)| probe)
+}
+
+/// The probe [`probe_for_port`] picks for `port`, if its rarity is at most
+/// `intensity`. Rarities follow nmap's scale: 1 for HTTP on web ports, 3
+/// for Redis, 5 for the database handshakes. At the maximum intensity
+/// ports with no probe of their own get the HTTP request as well.
+pub fn probe_for_intensity(port: u16, intensity: u8) -> Option<&'static [u8]> {
+    match port_probe(port) {
+        Some((rarity, probe)) => (rarity <= intensity).then_some(probe),
+        None => (intensity >= MAX_VERSION_INTENSITY).then_some(HTTP_PROBE),
+    }
+}
+
+/// Rarity and payload of the probe for `port`
+fn port_probe(port: u16) -> Option<(u8, &'static [u8])> {
+    match port {
+        6379 => Some((3, b"PING\r\n")),
+        5432 => Some((5, POSTGRES_PROBE)),
+        27017 => Some((5, MONGODB_PROBE)),
+        80 | 443 | 3000 | 5000 | 8000 | 8080 | 8443 | 8888 | 9000 | 9200 => Some((1, HTTP_PROBE)),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,5 +211,120 @@ mod tests {
     fn test_banner_grabber_creation() {
         let grabber = BannerGrabber::new(Duration::from_secs(2));
     assert_eq!(grabber.timeout, Duration::from_secs(2));
+        assert_eq!(grabber.max_bytes, DEFAULT_BANNER_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_binary_banner_kept_up_to_max_bytes() {
+        use tokio::net::TcpListener;
+
+        // MySQL-style greeting: length prefix, NULs and non-UTF-8 bytes
+        let mut greeting = vec![0x4a, 0x00, 0x00, 0x00, 0x0a];
+        greeting.extend_from_slice(b"8.0.36\x00");
+        greeting.extend((0..1000u32).map(|i| (i % 256) as u8));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sent = greeting.clone();
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            conn.write_all(&sent).await.unwrap();
+            // Stay open until the client is done
+            let _ = conn.read(&mut [0u8; 1]).await;
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        // Let the whole greeting arrive before reading
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let grabber = BannerGrabber::new(Duration::from_millis(500)).with_max_bytes(800);
+        let banner = grabber.grab_bytes(&mut stream, 3306).await.unwrap();
+        assert_eq!(banner, greeting[..800]);
+        drop(stream);
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_probe_for_port() {
+        for port in [21, 22, 25, 110, 143, 3306] {
+            assert_eq!(probe_for_port(port), None, "port {}", port);
+        }
+        assert_eq!(probe_for_port(6379), Some(&b"PING\r\n"[..]));
+        assert_eq!(probe_for_port(5432), Some(POSTGRES_PROBE));
+        assert_eq!(probe_for_port(8080), Some(HTTP_PROBE));
+        assert_eq!(probe_for_port(12345), None);
+
+        // Wire length prefix matches the handshake size
+        let mongo = probe_for_port(27017).unwrap();
+        assert_eq!(u32::from_le_bytes(mongo[..4].try_into().unwrap()) as usize, mongo.len());
+    }
+
+    #[test]
+    fn test_probe_for_intensity() {
+        // Passive only
+        for port in [80, 6379, 5432, 12345] {
+            assert_eq!(probe_for_intensity(port, 0), None, "port {}", port);
+        }
+        assert_eq!(probe_for_intensity(8080, 2), Some(HTTP_PROBE));
+        assert_eq!(probe_for_intensity(6379, 2), None);
+        assert_eq!(probe_for_intensity(27017, 5), Some(MONGODB_PROBE));
+        // The default sends what probe_for_port picks, nothing more
+        for port in [22, 80, 5432, 6379, 12345] {
+            assert_eq!(probe_for_intensity(port, DEFAULT_VERSION_INTENSITY), probe_for_port(port));
+        }
+        assert_eq!(probe_for_intensity(12345, MAX_VERSION_INTENSITY), Some(HTTP_PROBE));
+        assert_eq!(BannerGrabber::new(Duration::from_secs(1)).with_intensity(42).intensity, MAX_VERSION_INTENSITY);
+    }
+
+    #[tokio::test]
+    async fn test_intensity_zero_sends_nothing() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            conn.read(&mut buf).await.unwrap()
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let grabber = BannerGrabber::new(Duration::from_millis(100)).with_intensity(0);
+        assert!(grabber.grab(&mut stream, 80).await.is_err());
+        drop(stream);
+        assert_eq!(server.await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_speak_first_port_gets_no_probe() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            // Nothing arrives before the client gives up and closes
+            conn.read(&mut buf).await.unwrap()
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let grabber = BannerGrabber::new(Duration::from_millis(100));
+        assert!(grabber.grab(&mut stream, 22).await.is_err());
+        drop(stream);
+        assert_eq!(server.await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_immediate_close_is_closed_without_data() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { drop(listener.accept().await.unwrap()) });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        server.await.unwrap();
+        let grabber = BannerGrabber::new(Duration::from_millis(500));
+        let err = grabber.grab_bytes(&mut stream, 22).await.unwrap_err();
+        assert!(err.is::<ClosedWithoutData>(), "got {}", err);
     }
 }
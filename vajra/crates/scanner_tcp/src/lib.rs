@@ -2,6 +2,11 @@
 
 mod scanner;
 mod banner;
+mod tls;
 
-pub use scanner::TcpScanner;
-pub use banner::BannerGrabber;
+pub use scanner::{TcpScanner, DEFAULT_BANNER_PORTS, DEFAULT_BANNER_TIMEOUT};
+pub use banner::{
+    probe_for_intensity, probe_for_port, BannerGrabber, ClosedWithoutData, DEFAULT_BANNER_BYTES,
+    DEFAULT_VERSION_INTENSITY, MAX_VERSION_INTENSITY,
+};
+pub use tls::{TlsProber, TLS_PORTS};
@@ -3,22 +3,58 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use socket2::{SockRef, TcpKeepalive};
+use std::collections::HashSet;
 use std::io::ErrorKind;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::net::TcpStream;
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::sync::Semaphore;
 use tokio::time::timeout;
-use tracing::instrument;
+use tracing::{debug, instrument, warn};
 
-use vajra_common::{PortState, ProbeResult, Scanner, Target};
-use crate::banner::BannerGrabber;
-use vajra_fingerprint::detect_service;
+use vajra_common::pcap::{synthesize_tcp_packet, tcp_flags};
+use vajra_common::{
+    DumpKind, DumpWriter, PcapWriter, PortState, ProbeResult, ScanOptions, Scanner, ServiceMatch, Target,
+};
+use crate::banner::{BannerGrabber, ClosedWithoutData, DEFAULT_BANNER_BYTES, DEFAULT_VERSION_INTENSITY};
+use crate::tls::{TlsProber, TLS_PORTS};
+use vajra_fingerprint::{detect_service_from_port, detect_service_raw};
+
+/// Reason recorded for an open port whose peer closed the connection
+/// without sending anything, like nmap's `tcpwrapped`
+const TCPWRAPPED_REASON: &str = "no banner, tcpwrapped?";
+
+/// Ports whose banner is grabbed by default: common services worth a
+/// version. Ports the service table can't name are grabbed as well.
+pub const DEFAULT_BANNER_PORTS: &[u16] = &[
+    21, 22, 25, 80, 110, 143, 443, 465, 587, 993, 995, 3306, 5432, 6379, 27017, 9200, 8080, 8443, 8000, 8888,
+    9000,
+];
+
+/// How long an open port is given to send a banner unless configured
+pub const DEFAULT_BANNER_TIMEOUT: Duration = Duration::from_millis(300);
 
 /// Simple TCP connect scanner implementation.
+#[derive(Clone)]
 pub struct TcpScanner {
     timeout: Duration,
+    initial_timeout: Option<Duration>,
     retries: u32,
+    max_concurrency: usize,
     banner_timeout: Duration,
+    banner_retries: u32,
+    banner_max_bytes: usize,
+    version_intensity: u8,
+    banner_ports: Arc<HashSet<u16>>,
+    banner_all_ports: bool,
+    source_port: Option<u16>,
+    keepalive: Option<Duration>,
+    linger_abort: bool,
+    dump: Option<Arc<DumpWriter>>,
+    pcap: Option<Arc<PcapWriter>>,
+    tls: Option<Arc<TlsProber>>,
 }
 
 impl TcpScanner {
@@ -33,29 +69,277 @@ impl TcpScanner {
         self
     }
 
+    /// Set the timeout of the fast first connect attempt (default
+    /// `min(400ms, timeout)`, capped at `timeout`).
+    ///
+    /// Closed ports answer quickly, so the first attempt is short; if it
+    /// times out or fails the port gets one more attempt with the full
+    /// `timeout`. With `retries > 0` the first attempt is the fast one and
+    /// every retry uses the full timeout. Setting this to `timeout` makes
+    /// a single full-length attempt (plus retries), which suits
+    /// high-latency links where open ports would miss the fast phase.
+    pub fn with_initial_timeout(mut self, timeout: Duration) -> Self {
+        self.initial_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of the first connect attempt when the full one is `full`
+    fn initial_timeout(&self, full: Duration) -> Duration {
+        self.initial_timeout
+            .unwrap_or(Duration::from_millis(400))
+            .min(full)
+    }
+
     /// Set retry count for connect attempts.
     pub fn with_retries(mut self, retries: u32) -> Self {
         self.retries = retries;
         self
     }
 
+    /// Set how many connects `scan_batch` keeps in flight at once
+    /// (at least 1). Each one holds a file descriptor.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
     /// Set banner grab timeout
     pub fn with_banner_timeout(mut self, timeout: Duration) -> Self {
         self.banner_timeout = timeout;
         self
     }
 
+    /// Set how many times a failed banner grab is retried on a fresh
+    /// connection (separate from connect retries). Some services drop the
+    /// first connection but answer the next. All attempts, reconnects
+    /// included, share a budget of `banner_timeout * (retries + 1)`.
+    pub fn with_banner_retries(mut self, retries: u32) -> Self {
+        self.banner_retries = retries;
+        self
+    }
+
+    /// Set how many banner bytes are kept per port (default 512). The raw
+    /// bytes go to service detection before any text decoding.
+    pub fn with_banner_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.banner_max_bytes = max_bytes;
+        self
+    }
+
+    /// Set the version intensity of banner grabs (0-9, default 7), as in
+    /// [`BannerGrabber::with_intensity`]. Higher levels send rarer probes
+    /// and wait longer for their replies.
+    pub fn with_version_intensity(mut self, intensity: u8) -> Self {
+        self.version_intensity = intensity;
+        self
+    }
+
+    /// Set the ports whose banner is grabbed, replacing
+    /// [`DEFAULT_BANNER_PORTS`], e.g. to catch SSH on 2222. Ports the
+    /// service table can't name are still grabbed.
+    pub fn with_banner_ports(mut self, ports: HashSet<u16>) -> Self {
+        self.banner_ports = Arc::new(ports);
+        self
+    }
+
+    /// Try to grab a banner from every open port, whatever the port set
+    pub fn with_banner_all_ports(mut self, enabled: bool) -> Self {
+        self.banner_all_ports = enabled;
+        self
+    }
+
+    /// Whether an open `port` gets a banner grab. Ports the table can't
+    /// name are grabbed too, since the banner is all we have.
+    fn should_grab_banner(&self, port: u16) -> bool {
+        self.banner_all_ports || self.banner_ports.contains(&port) || detect_service_from_port(port).is_none()
+    }
+
+    /// Bind outgoing connections to a fixed local source port.
+    ///
+    /// Some firewalls allow traffic from well-known ports such as 53 (DNS)
+    /// or 20 (FTP-data). The socket is bound with `SO_REUSEADDR` so the port
+    /// can be reused across targets. Ports below 1024 need root (or
+    /// `CAP_NET_BIND_SERVICE`) on Unix. Because every connection shares one
+    /// local port, reconnecting to the same ip:port soon after may fail
+    /// while the previous connection is in TIME_WAIT.
+    pub fn with_source_port(mut self, port: u16) -> Self {
+        self.source_port = Some(port);
+        self
+    }
+
+    /// Enable TCP keepalive on connected streams, probing after `idle`
+    /// without traffic (disabled with `None`, the default). Keeps a long
+    /// banner interaction from hanging on a peer that silently went away.
+    pub fn with_keepalive(mut self, idle: Option<Duration>) -> Self {
+        self.keepalive = idle;
+        self
+    }
+
+    /// Close connections with a RST instead of a FIN by setting `SO_LINGER`
+    /// to 0 before connecting. Sockets skip TIME_WAIT, so teardown is cheaper
+    /// under high concurrency, and services see an abort rather than a
+    /// clean close.
+    pub fn with_linger_abort(mut self, enabled: bool) -> Self {
+        self.linger_abort = enabled;
+        self
+    }
+
+    /// Record raw banner bytes to `dump`, keyed by target
+    pub fn with_dump(mut self, dump: Arc<DumpWriter>) -> Self {
+        self.dump = Some(dump);
+        self
+    }
+
+    /// Write stand-in packets for each connect outcome to `pcap`. The
+    /// kernel's real packets are not visible to a connect scan, so the
+    /// handshake, reset or banner is synthesized from what was observed.
+    pub fn with_pcap(mut self, pcap: Arc<PcapWriter>) -> Self {
+        self.pcap = Some(pcap);
+        self
+    }
+
+    /// Handshake with TLS ports (`TLS_PORTS`) instead of sending a plaintext
+    /// banner probe, attaching the negotiated version, cipher and leaf
+    /// certificate names to the service. Off by default: the handshake costs
+    /// a few round trips per open TLS port.
+    pub fn with_tls_probe(mut self, enabled: bool) -> Self {
+        self.tls = enabled.then(|| Arc::new(TlsProber::new()));
+        self
+    }
+
+    /// Record the exchange with `remote` as synthesized packets.
+    /// `local` is unknown for failed connects and falls back to the
+    /// unspecified address.
+    fn record_pcap(&self, local: Option<SocketAddr>, remote: SocketAddr, state: PortState, banner: Option<&[u8]>) {
+        let Some(pcap) = &self.pcap else {
+            return;
+        };
+        let local = local.unwrap_or_else(|| {
+            let ip = match remote.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            };
+            SocketAddr::new(ip, self.source_port.unwrap_or(0))
+        });
+
+        let mut packets = vec![synthesize_tcp_packet(local, remote, tcp_flags::SYN, 0, 0, &[])];
+        match state {
+            PortState::Open => {
+                packets.push(synthesize_tcp_packet(remote, local, tcp_flags::SYN | tcp_flags::ACK, 0, 1, &[]));
+                packets.push(synthesize_tcp_packet(local, remote, tcp_flags::ACK, 1, 1, &[]));
+                if let Some(data) = banner {
+                    packets.push(synthesize_tcp_packet(remote, local, tcp_flags::PSH | tcp_flags::ACK, 1, 1, data));
+                }
+            }
+            PortState::Closed => {
+                packets.push(synthesize_tcp_packet(remote, local, tcp_flags::RST | tcp_flags::ACK, 0, 1, &[]));
+            }
+            _ => {}
+        }
+        for packet in packets {
+            if let Err(e) = pcap.write_packet(&packet) {
+                debug!("Failed to write pcap record for {}: {}", remote, e);
+            }
+        }
+    }
+
+    /// Apply socket options to a connected stream before banner grabbing:
+    /// TCP_NODELAY so small probe writes go out immediately, plus keepalive
+    /// if configured.
+    fn tune_stream(&self, stream: &TcpStream) -> std::io::Result<()> {
+        stream.set_nodelay(true)?;
+        if let Some(idle) = self.keepalive {
+            let keepalive = TcpKeepalive::new().with_time(idle).with_interval(idle);
+            SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+        }
+        Ok(())
+    }
+
+    /// Grab the banner of `port` over `stream`, reconnecting to `addr` and
+    /// trying again on failure up to `banner_retries` times. The error is
+    /// that of the last attempt.
+    async fn grab_banner(&self, mut stream: TcpStream, addr: SocketAddr, port: u16) -> Result<Vec<u8>> {
+        let grabber = BannerGrabber::new(self.banner_timeout)
+            .with_max_bytes(self.banner_max_bytes)
+            .with_intensity(self.version_intensity);
+        let deadline = Instant::now() + self.banner_timeout * (self.banner_retries + 1);
+        let mut last_err = anyhow::anyhow!("Banner timeout");
+
+        for attempt in 0..=self.banner_retries {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            if attempt > 0 {
+                stream = match timeout(remaining.min(self.timeout), self.connect(addr)).await {
+                    Ok(Ok(stream)) => stream,
+                    _ => break,
+                };
+                if let Err(e) = self.tune_stream(&stream) {
+                    debug!("Failed to set socket options for {}: {}", addr, e);
+                }
+                debug!("Banner retry {} for {}", attempt, addr);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            // Use a race: try banner grab but don't wait too long
+            let grab = grabber.grab_bytes(&mut stream, port);
+            match timeout(self.banner_timeout.min(remaining), grab).await {
+                Ok(Ok(banner)) => return Ok(banner),
+                Ok(Err(e)) => last_err = e,
+                Err(_) => last_err = anyhow::anyhow!("Banner timeout"),
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Connect to `addr`, binding to the configured source port if any and
+    /// applying the linger setting before the SYN goes out.
+    async fn connect(&self, addr: SocketAddr) -> std::io::Result<TcpStream> {
+        if self.source_port.is_none() && !self.linger_abort {
+            return TcpStream::connect(addr).await;
+        }
+
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        if self.linger_abort {
+            // A zero linger aborts at once, so drop never blocks the thread
+            SockRef::from(&socket).set_linger(Some(Duration::ZERO))?;
+        }
+        if let Some(port) = self.source_port {
+            let local = match addr {
+                SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], port)),
+                SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], port)),
+            };
+            socket.set_reuseaddr(true)?;
+            socket.bind(local)?;
+        }
+        socket.connect(addr).await
+    }
+
     /// Try to establish a TCP connection with optimized timeouts.
-    /// Uses shorter initial timeout for faster closed port detection.
+    /// Uses shorter initial timeout for faster closed port detection; see
+    /// [`with_initial_timeout`](Self::with_initial_timeout). `full` is the
+    /// timeout of every later attempt.
     #[instrument(skip(self))]
-    async fn try_connect(&self, addr: SocketAddr) -> Result<TcpStream> {
+    async fn try_connect(&self, addr: SocketAddr, full: Duration) -> Result<TcpStream> {
         // Use shorter timeout for initial attempt (closed ports respond quickly)
         // This matches nmap's behavior: fast detection of closed ports
-        let initial_timeout = Duration::from_millis(400.min(self.timeout.as_millis() as u64));
-        
+        let initial_timeout = self.initial_timeout(full);
+
+        // Single phase: the first attempt already gets the full timeout
+        if self.retries == 0 && initial_timeout >= full {
+            return match timeout(full, self.connect(addr)).await {
+                Ok(Ok(stream)) => Ok(stream),
+                Ok(Err(e)) => Err(anyhow::Error::from(e)),
+                Err(_) => Err(connect_timeout()),
+            };
+        }
+
         // Fast path: no retries
         if self.retries == 0 {
-            match timeout(initial_timeout, TcpStream::connect(addr)).await {
+            match timeout(initial_timeout, self.connect(addr)).await {
                 Ok(Ok(stream)) => return Ok(stream),
                 Ok(Err(e)) => {
                     // Use OS error codes for accurate detection
@@ -66,28 +350,28 @@ impl TcpScanner {
                         }
                         ErrorKind::TimedOut | ErrorKind::WouldBlock => {
                             // Might be filtered - try once more with full timeout
-                            match timeout(self.timeout, TcpStream::connect(addr)).await {
+                            match timeout(full, self.connect(addr)).await {
                                 Ok(Ok(stream)) => return Ok(stream),
                                 Ok(Err(e2)) => return Err(anyhow::Error::from(e2)),
-                                Err(_) => return Err(anyhow::anyhow!("Connection timeout")),
+                                Err(_) => return Err(connect_timeout()),
                             }
                         }
                         _ => {
                             // Other errors - try once more with full timeout
-                            match timeout(self.timeout, TcpStream::connect(addr)).await {
+                            match timeout(full, self.connect(addr)).await {
                                 Ok(Ok(stream)) => return Ok(stream),
                                 Ok(Err(e2)) => return Err(anyhow::Error::from(e2)),
-                                Err(_) => return Err(anyhow::anyhow!("Connection timeout")),
+                                Err(_) => return Err(connect_timeout()),
                             }
                         }
                     }
                 }
                 Err(_) => {
                     // Initial timeout - try once more with full timeout for filtered ports
-                    match timeout(self.timeout, TcpStream::connect(addr)).await {
+                    match timeout(full, self.connect(addr)).await {
                         Ok(Ok(stream)) => return Ok(stream),
                         Ok(Err(e)) => return Err(anyhow::Error::from(e)),
-                        Err(_) => return Err(anyhow::anyhow!("Connection timeout")),
+                        Err(_) => return Err(connect_timeout()),
                     }
                 }
             }
@@ -102,11 +386,15 @@ impl TcpScanner {
                 tokio::time::sleep(backoff).await;
             }
 
-            let attempt_timeout = if attempt == 0 { initial_timeout } else { self.timeout };
-            match timeout(attempt_timeout, TcpStream::connect(addr)).await {
+            let attempt_timeout = if attempt == 0 { initial_timeout } else { full };
+            match timeout(attempt_timeout, self.connect(addr)).await {
                 Ok(Ok(stream)) => return Ok(stream),
+                // A refusal is a definitive answer; retrying only inflates the RTT
+                Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => {
+                    return Err(anyhow::Error::from(e).context("Connection refused"));
+                }
                 Ok(Err(e)) => last_error = Some(anyhow::Error::from(e)),
-                Err(_) => last_error = Some(anyhow::anyhow!("Connection timeout")),
+                Err(_) => last_error = Some(connect_timeout()),
             }
         }
 
@@ -118,112 +406,144 @@ impl Default for TcpScanner {
     fn default() -> Self {
         Self {
             timeout: Duration::from_millis(800), // 800ms timeout (nmap uses adaptive ~500-1000ms)
+            initial_timeout: None,
             retries: 0, // No retries by default - rely on concurrency for speed
-            banner_timeout: Duration::from_millis(300), // Banner timeout (300ms) to improve version grabs
+            max_concurrency: ScanOptions::default().max_concurrency,
+            banner_timeout: DEFAULT_BANNER_TIMEOUT,
+            banner_retries: 0,
+            banner_max_bytes: DEFAULT_BANNER_BYTES,
+            version_intensity: DEFAULT_VERSION_INTENSITY,
+            banner_ports: Arc::new(DEFAULT_BANNER_PORTS.iter().copied().collect()),
+            banner_all_ports: false,
+            source_port: None,
+            keepalive: None,
+            linger_abort: false,
+            dump: None,
+            pcap: None,
+            tls: None,
         }
     }
 }
 
+/// Error for a connect attempt cut off by our own timeout
+fn connect_timeout() -> anyhow::Error {
+    std::io::Error::new(ErrorKind::TimedOut, "Connection timeout").into()
+}
+
+/// Map a failed connect to a port state, from the I/O error kind alone:
+///
+/// | error kind                             | state      | meaning                 |
+/// |----------------------------------------|------------|-------------------------|
+/// | `ConnectionRefused`, `ConnectionReset` | `Closed`   | the host answered a RST |
+/// | `TimedOut` (ours or the OS's)          | `Filtered` | nothing came back       |
+/// | anything else (e.g. host unreachable)  | `Filtered` | no answer from the port |
+///
+/// How long the failure took plays no part, so a slow refusal is still
+/// `Closed` and a fast ICMP unreachable is still `Filtered`.
+fn classify_connect_error(err: &anyhow::Error) -> PortState {
+    let io_kind = err
+        .chain()
+        .find_map(|e| e.downcast_ref::<std::io::Error>())
+        .map(std::io::Error::kind);
+
+    match io_kind {
+        Some(ErrorKind::ConnectionRefused) | Some(ErrorKind::ConnectionReset) => PortState::Closed,
+        _ => PortState::Filtered,
+    }
+}
+
 #[async_trait]
 impl Scanner for TcpScanner {
     /// Scan a single target and produce a ProbeResult.
     async fn scan(&self, target: &Target) -> Result<ProbeResult> {
+        self.scan_with_timeout(target, self.timeout).await
+    }
+
+    /// Scan with `connect_timeout` in place of the configured connect
+    /// timeout; the fast first phase is capped by it too.
+    async fn scan_with_timeout(&self, target: &Target, connect_timeout: Duration) -> Result<ProbeResult> {
         let addr = SocketAddr::new(target.ip, target.port);
         let start = Instant::now();
 
-        match self.try_connect(addr).await {
-            Ok(mut stream) => {
+        match self.try_connect(addr, connect_timeout).await {
+            Ok(stream) => {
                 let rtt = start.elapsed();
+                if let Err(e) = self.tune_stream(&stream) {
+                    debug!("Failed to set socket options for {}: {}", addr, e);
+                }
                 
-                // Fast banner grab: only for common service ports to save time
-                // Expanded list for better service detection
-                let should_grab_banner = matches!(
-                    target.port,
-                    21 | 22 | 25 | 80 | 110 | 143 | 443 | 465 | 587 | 993 | 995 | 
-                    3306 | 5432 | 6379 | 27017 | 9200 | 8080 | 8443 | 8000 | 8888 | 9000
-                );
-                
-                let banner = if should_grab_banner {
-                    let banner_grabber = BannerGrabber::new(self.banner_timeout);
-                    // Use a race: try banner grab but don't wait too long
-                    tokio::time::timeout(
-                        self.banner_timeout,
-                        banner_grabber.grab(&mut stream)
-                    )
-                    .await
-                    .ok()
-                    .and_then(|r| r.ok())
+                // Fast banner grab: only for the banner ports to save time
+                let should_grab_banner = self.should_grab_banner(target.port);
+
+                let local = stream.local_addr().ok();
+                let tls_prober = self.tls.as_ref().filter(|_| TLS_PORTS.contains(&target.port));
+                let mut tls_info = None;
+                let mut closed_without_data = false;
+                let raw_banner = if let Some(prober) = tls_prober {
+                    // A handshake needs a few round trips; give it the connect timeout
+                    let budget = connect_timeout.max(self.banner_timeout);
+                    match tokio::time::timeout(budget, prober.probe(stream, target.ip)).await {
+                        Ok(Ok(info)) => tls_info = Some(info),
+                        Ok(Err(e)) => debug!("TLS handshake with {} failed: {}", addr, e),
+                        Err(_) => debug!("TLS handshake with {} timed out", addr),
+                    }
+                    None
+                } else if should_grab_banner {
+                    match self.grab_banner(stream, addr, target.port).await {
+                        Ok(banner) => Some(banner),
+                        Err(e) => {
+                            closed_without_data = e.is::<ClosedWithoutData>();
+                            None
+                        }
+                    }
                 } else {
                     None
                 };
 
-                // Detect service from port and/or banner
-                let service = detect_service(target.port, banner.as_deref());
+                // Detect service from port and/or banner; binary greetings
+                // are parsed from the raw bytes before they are decoded
+                let mut service = detect_service_raw(target.port, raw_banner.as_deref());
+                let banner = raw_banner
+                    .as_deref()
+                    .map(|b| String::from_utf8_lossy(b).trim().to_string());
+                if let Some(info) = tls_info {
+                    service = Some(service.unwrap_or_else(|| ServiceMatch::new("ssl")).with_tls(info));
+                }
                 
-                let mut result = ProbeResult::new(target.clone(), PortState::Open).with_rtt(rtt);
+                if let (Some(dump), Some(b)) = (&self.dump, &raw_banner) {
+                    if let Err(e) = dump.write(DumpKind::Banner, target.ip, target.port, b) {
+                        debug!("Failed to dump banner for {}: {}", addr, e);
+                    }
+                }
+
+                self.record_pcap(local, addr, PortState::Open, raw_banner.as_deref());
+
+                let mut result = ProbeResult::new(target.clone(), PortState::Open)
+                    .with_started_at(start)
+                    .with_rtt(rtt);
                 if let Some(b) = banner {
                     result = result.with_banner(b);
                 }
                 if let Some(s) = service {
                     result = result.with_service(s);
                 }
+                if closed_without_data {
+                    // Likely a TCP wrapper or firewall that accepts then drops
+                    result = result.with_reason(TCPWRAPPED_REASON);
+                }
                 Ok(result)
             }
             Err(e) => {
                 let rtt = start.elapsed();
-                let err_str = e.to_string().to_lowercase();
-                
-                // Better port state detection using OS error codes and RTT
-                // Closed ports: ConnectionRefused error (RTT typically < 100ms)
-                // Filtered ports: Timeout or other errors (RTT >= timeout)
-                let state = {
-                    // Try to extract the underlying IO error from the error chain
-                    let mut current: Option<&dyn std::error::Error> = Some(&*e);
-                    let mut found_io_error = false;
-                    let mut io_kind = None;
-                    
-                    // Walk the error chain to find an IO error
-                    while let Some(err) = current {
-                        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
-                            io_kind = Some(io_err.kind());
-                            found_io_error = true;
-                            break;
-                        }
-                        current = err.source();
-                    }
-                    
-                    // Use OS error kind if found, otherwise fall back to string matching and RTT
-                    if found_io_error {
-                        match io_kind.unwrap() {
-                            ErrorKind::ConnectionRefused => PortState::Closed,
-                            ErrorKind::TimedOut => PortState::Filtered,
-                            _ => {
-                                // Fall back to string matching and RTT
-                                if err_str.contains("refused") {
-                                    PortState::Closed
-                                } else if err_str.contains("timeout") || rtt >= self.timeout {
-                                    PortState::Filtered
-                                } else if rtt < Duration::from_millis(100) {
-                                    PortState::Closed
-                                } else {
-                                    PortState::Filtered
-                                }
-                            }
-                        }
-                    } else if err_str.contains("refused") {
-                        PortState::Closed
-                    } else if err_str.contains("timeout") || rtt >= self.timeout {
-                        PortState::Filtered
-                    } else if rtt < Duration::from_millis(100) {
-                        PortState::Closed
-                    } else {
-                        PortState::Filtered
-                    }
-                };
-                
+                let state = classify_connect_error(&e);
+
+                self.record_pcap(None, addr, state, None);
+
                 // Detect service from port number for all port states (like nmap)
-                let service = vajra_fingerprint::detect_service_from_port(target.port);
-                let mut result = ProbeResult::new(target.clone(), state).with_rtt(rtt);
+                let service = detect_service_from_port(target.port);
+                let mut result = ProbeResult::new(target.clone(), state)
+                    .with_started_at(start)
+                    .with_rtt(rtt);
                 if let Some(s) = service {
                     result = result.with_service(s);
                 }
@@ -232,6 +552,44 @@ impl Scanner for TcpScanner {
         }
     }
 
+    /// Scan all targets concurrently, at most `max_concurrency` at a time.
+    /// Results come back in target order, one per target: a target whose
+    /// probe fails is reported as skipped, with the error as its reason.
+    async fn scan_batch(&self, targets: &[Target]) -> Result<Vec<ProbeResult>> {
+        let semaphore = Arc::new(Semaphore::new(self.max_concurrency));
+        let mut tasks = Vec::with_capacity(targets.len());
+
+        for target in targets {
+            let sem = semaphore.clone();
+            let scanner = self.clone();
+            let target = target.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = sem.acquire().await.unwrap();
+                scanner.scan(&target).await
+            }));
+        }
+
+        let mut results = Vec::with_capacity(tasks.len());
+        let mut failed = 0;
+        for (target, task) in targets.iter().zip(tasks) {
+            let error = match task.await {
+                Ok(Ok(result)) => {
+                    results.push(result);
+                    continue;
+                }
+                Ok(Err(e)) => format!("{:#}", e),
+                Err(e) => e.to_string(),
+            };
+            debug!("Scan error for {}: {}", target, error);
+            failed += 1;
+            results.push(ProbeResult::new(target.clone(), PortState::Skipped).with_reason(format!("error: {}", error)));
+        }
+        if failed > 0 {
+            warn!("{} of {} probe(s) failed and were reported as skipped", failed, targets.len());
+        }
+        Ok(results)
+    }
+
     fn name(&self) -> &str {
         "TCP Connect Scanner"
     }
@@ -240,3 +598,257 @@ impl Scanner for TcpScanner {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use tokio::net::TcpListener;
+
+    /// Listener whose accept queue is full, so further SYNs go unanswered.
+    /// Keep the returned value alive for as long as the port should stay so.
+    async fn blackhole() -> ((TcpListener, Vec<TcpStream>), u16) {
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut queued = Vec::new();
+        while let Ok(Ok(stream)) = timeout(Duration::from_millis(100), TcpStream::connect(addr)).await {
+            queued.push(stream);
+        }
+        ((listener, queued), addr.port())
+    }
+
+    #[tokio::test]
+    async fn test_scan_with_source_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_port = listener.local_addr().unwrap().port();
+
+        // Pick a free unprivileged port to use as the source
+        let source_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let scanner = TcpScanner::new().with_source_port(source_port);
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), target_port);
+        let (result, accepted) = tokio::join!(scanner.scan(&target), listener.accept());
+
+        assert_eq!(result.unwrap().state, PortState::Open);
+        assert_eq!(accepted.unwrap().1.port(), source_port);
+    }
+
+    #[tokio::test]
+    async fn test_stream_options_applied() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let idle = Duration::from_secs(30);
+
+        let scanner = TcpScanner::new().with_keepalive(Some(idle));
+        let (stream, _accepted) = tokio::join!(scanner.connect(addr), listener.accept());
+        let stream = stream.unwrap();
+        scanner.tune_stream(&stream).unwrap();
+
+        assert!(stream.nodelay().unwrap());
+        let sock = SockRef::from(&stream);
+        assert!(sock.keepalive().unwrap());
+        assert_eq!(sock.keepalive_time().unwrap(), idle);
+    }
+
+    #[tokio::test]
+    async fn test_linger_abort_resets_on_drop() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let scanner = TcpScanner::new().with_linger_abort(true);
+        let (stream, accepted) = tokio::join!(scanner.connect(addr), listener.accept());
+        let stream = stream.unwrap();
+        assert_eq!(SockRef::from(&stream).linger().unwrap(), Some(Duration::ZERO));
+
+        // The peer sees a reset rather than a clean EOF
+        drop(stream);
+        let (mut peer, _) = accepted.unwrap();
+        let err = peer.read(&mut [0u8; 1]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn test_localhost_refusal_is_closed() {
+        // Bind then drop to get a port with nothing listening
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let target = Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+
+        for retries in [0, 2] {
+            let scanner = TcpScanner::new().with_retries(retries);
+            let result = scanner.scan(&target).await.unwrap();
+            assert_eq!(result.state, PortState::Closed);
+            assert!(result.rtt < Duration::from_millis(1), "rtt {:?}", result.rtt);
+        }
+    }
+
+    #[test]
+    fn test_classify_connect_error() {
+        let refused = anyhow::Error::from(std::io::Error::from(ErrorKind::ConnectionRefused));
+        assert_eq!(classify_connect_error(&refused.context("Connection refused")), PortState::Closed);
+        let reset = anyhow::Error::from(std::io::Error::from(ErrorKind::ConnectionReset));
+        assert_eq!(classify_connect_error(&reset), PortState::Closed);
+        let timed_out = anyhow::Error::from(std::io::Error::from(ErrorKind::TimedOut));
+        assert_eq!(classify_connect_error(&timed_out), PortState::Filtered);
+        assert_eq!(classify_connect_error(&connect_timeout()), PortState::Filtered);
+        // Error text is not consulted
+        assert_eq!(classify_connect_error(&anyhow::anyhow!("connection refused")), PortState::Filtered);
+    }
+
+    #[tokio::test]
+    async fn test_open_closed_filtered() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let scanner = TcpScanner::new().with_timeout(Duration::from_millis(200));
+
+        let open = Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), open_port);
+        assert_eq!(scanner.scan(&open).await.unwrap().state, PortState::Open);
+        let closed = Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), closed_port);
+        assert_eq!(scanner.scan(&closed).await.unwrap().state, PortState::Closed);
+        let (_blackhole, port) = blackhole().await;
+        let filtered = Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        assert_eq!(scanner.scan(&filtered).await.unwrap().state, PortState::Filtered);
+    }
+
+    #[tokio::test]
+    async fn test_scan_batch_runs_concurrently() {
+        let (_blackhole, filtered) = blackhole().await;
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut ports = vec![filtered; 5];
+        ports.push(closed);
+        let targets: Vec<Target> = ports
+            .iter()
+            .map(|&port| Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), port))
+            .collect();
+
+        // Five 200ms timeouts in series would take a second
+        let scanner = TcpScanner::new().with_timeout(Duration::from_millis(200));
+        let start = Instant::now();
+        let results = scanner.scan_batch(&targets).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(600), "took {:?}", start.elapsed());
+        let states: Vec<_> = results.iter().map(|r| (r.target.port, r.state)).collect();
+        let mut expected = vec![(filtered, PortState::Filtered); 5];
+        expected.push((closed, PortState::Closed));
+        assert_eq!(states, expected);
+
+        // One at a time, they queue behind each other
+        let serial = scanner.with_max_concurrency(1);
+        let start = Instant::now();
+        serial.scan_batch(&targets[..3]).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(600), "took {:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_initial_timeout_phases() {
+        let full = Duration::from_millis(300);
+        assert_eq!(TcpScanner::new().initial_timeout(full), full);
+        assert_eq!(TcpScanner::new().initial_timeout(Duration::from_secs(1)), Duration::from_millis(400));
+        let capped = TcpScanner::new().with_initial_timeout(Duration::from_secs(5));
+        assert_eq!(capped.initial_timeout(full), full);
+
+        let (_blackhole, port) = blackhole().await;
+        let target = Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        // A fast phase plus a full one, versus a single full attempt
+        let two_phase = TcpScanner::new().with_timeout(full).with_initial_timeout(Duration::from_millis(100));
+        let result = two_phase.scan(&target).await.unwrap();
+        assert!(result.rtt >= Duration::from_millis(400), "rtt {:?}", result.rtt);
+        let single = TcpScanner::new().with_timeout(full).with_initial_timeout(full);
+        let result = single.scan(&target).await.unwrap();
+        assert_eq!(result.state, PortState::Filtered);
+        assert!(result.rtt < Duration::from_millis(400), "rtt {:?}", result.rtt);
+
+        // A per-probe timeout overrides the configured one
+        let result = single.scan_with_timeout(&target, Duration::from_millis(50)).await.unwrap();
+        assert_eq!(result.state, PortState::Filtered);
+        assert!(result.rtt < Duration::from_millis(250), "rtt {:?}", result.rtt);
+    }
+
+    #[tokio::test]
+    async fn test_banner_retry_after_dropped_connection() {
+        use tokio::io::AsyncWriteExt;
+
+        // Drop the first connection unanswered, greet on the second
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            let (mut second, _) = listener.accept().await.unwrap();
+            second.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        let scanner = TcpScanner::new().with_banner_retries(1);
+        let stream = scanner.connect(addr).await.unwrap();
+        // Port 22 selects the passive SSH grab; the listener's port is random
+        let banner = scanner.grab_banner(stream, addr, 22).await.unwrap();
+        assert_eq!(banner, b"SSH-2.0-OpenSSH_9.6\r\n");
+
+        // Without retries the dropped connection yields nothing
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let scanner = TcpScanner::new();
+        let (stream, accepted) = tokio::join!(scanner.connect(addr), listener.accept());
+        drop(accepted.unwrap());
+        assert!(scanner.grab_banner(stream.unwrap(), addr, 22).await.is_err());
+    }
+
+    #[test]
+    fn test_banner_port_selection() {
+        let scanner = TcpScanner::new();
+        assert!(scanner.should_grab_banner(22));
+        // Telnet is in the service table but not a default banner port
+        assert!(!scanner.should_grab_banner(23));
+
+        let scanner = scanner.with_banner_ports(HashSet::from([23, 2222]));
+        assert!(scanner.should_grab_banner(23));
+        assert!(!scanner.should_grab_banner(22));
+
+        let scanner = scanner.with_banner_all_ports(true);
+        assert!(scanner.should_grab_banner(22));
+        assert!(scanner.should_grab_banner(3389));
+    }
+
+    #[tokio::test]
+    async fn test_immediate_close_is_annotated_tcpwrapped() {
+        // A port the service table can't name, so only the grab can tell
+        let listener = loop {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            if detect_service_from_port(listener.local_addr().unwrap().port()).is_none() {
+                break listener;
+            }
+        };
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (conn, _) = listener.accept().await.unwrap();
+                drop(conn);
+            }
+        });
+
+        let target = Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let result = TcpScanner::new().scan(&target).await.unwrap();
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(result.banner, None);
+        assert_eq!(result.reason.as_deref(), Some(TCPWRAPPED_REASON));
+    }
+}
@@ -0,0 +1,5 @@
+//! UDP Scanner
+
+mod scanner;
+
+pub use scanner::UdpScanner;
@@ -0,0 +1,216 @@
+// crates/scanner_udp/src/scanner.rs
+//! UDP scanner implementation
+//!
+//! UDP has no handshake, so a port's state is inferred from what comes back:
+//! - a data reply means `Open`
+//! - ICMP port-unreachable (surfaced as `ConnectionRefused` on a connected
+//!   socket) means `Closed`
+//! - silence means `OpenFiltered`: the port may be open and ignoring the
+//!   payload, or a firewall may be dropping it
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+use vajra_common::{PortState, ProbeResult, Protocol, Scanner, Target};
+use vajra_fingerprint::{detect_service_for_protocol, detect_udp_service};
+
+/// Simple UDP scanner implementation.
+pub struct UdpScanner {
+    timeout: Duration,
+    retries: u32,
+}
+
+impl UdpScanner {
+    /// Create a new scanner with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the time to wait for a reply per attempt.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how many times the probe is resent when nothing comes back.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Send the probe and wait up to `wait` for a reply, resending on
+    /// silence. Returns `None` if no attempt got an answer.
+    async fn probe(&self, addr: SocketAddr, wait: Duration) -> Result<Option<std::io::Result<Vec<u8>>>> {
+        let bind: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(addr).await?;
+
+        let payload = probe_payload(addr.port());
+        let mut buf = [0u8; 512];
+        for _ in 0..=self.retries {
+            // An ICMP error from a previous attempt can surface on send
+            if let Err(e) = socket.send(payload).await {
+                return Ok(Some(Err(e)));
+            }
+            if let Ok(reply) = timeout(wait, socket.recv(&mut buf)).await {
+                return Ok(Some(reply.map(|n| buf[..n].to_vec())));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Default for UdpScanner {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(1000),
+            retries: 1, // UDP probes are lossy; one resend avoids most false OpenFiltered
+        }
+    }
+}
+
+/// Map the outcome of a probe to a port state.
+/// `None` means no reply arrived before the timeout.
+fn classify_reply(reply: Option<std::io::Result<usize>>) -> Result<PortState> {
+    match reply {
+        None => Ok(PortState::OpenFiltered),
+        Some(Ok(_)) => Ok(PortState::Open),
+        Some(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => Ok(PortState::Closed),
+        Some(Err(e)) => Err(e.into()),
+    }
+}
+
+/// Payload likely to elicit a reply from the service on `port`.
+fn probe_payload(port: u16) -> &'static [u8] {
+    match port {
+        // DNS: CHAOS TXT query for version.bind (servers that refuse still answer)
+        53 => b"\x13\x37\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x07version\x04bind\x00\x00\x10\x00\x03",
+        // NTP: version 3 client request
+        123 => &[
+            0x1b, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ],
+        // NetBIOS: node status (NBSTAT) query for the wildcard name
+        137 => b"\x13\x37\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x20CKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\x00\x00\x21\x00\x01",
+        // SNMP: v1 GetRequest for sysDescr.0 with community "public"
+        161 => &[
+            0x30, 0x26, 0x02, 0x01, 0x00, 0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', 0xa0, 0x19,
+            0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x0e, 0x30, 0x0c, 0x06, 0x08,
+            0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00, 0x05, 0x00,
+        ],
+        _ => b"\r\n",
+    }
+}
+
+#[async_trait]
+impl Scanner for UdpScanner {
+    /// Scan a single target and produce a ProbeResult.
+    async fn scan(&self, target: &Target) -> Result<ProbeResult> {
+        self.scan_with_timeout(target, self.timeout).await
+    }
+
+    /// Scan waiting `wait` per attempt instead of the configured timeout
+    async fn scan_with_timeout(&self, target: &Target, wait: Duration) -> Result<ProbeResult> {
+        let addr = SocketAddr::new(target.ip, target.port);
+        let start = Instant::now();
+
+        let (reply, response) = match self.probe(addr, wait).await? {
+            Some(Ok(data)) => (Some(Ok(data.len())), Some(data)),
+            Some(Err(e)) => (Some(Err(e)), None),
+            None => (None, None),
+        };
+        let state = classify_reply(reply)?;
+        let mut result = ProbeResult::new(target.clone(), state).with_started_at(start);
+        if state != PortState::OpenFiltered {
+            result = result.with_rtt(start.elapsed());
+        }
+        let service = response
+            .and_then(|data| detect_udp_service(target.port, &data))
+            .or_else(|| detect_service_for_protocol(target.port, Protocol::UDP));
+        if let Some(s) = service {
+            result = result.with_service(s);
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "UDP Scanner"
+    }
+
+    fn requires_root(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn local_target(port: u16) -> Target {
+        Target::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port).with_protocol(Protocol::UDP)
+    }
+
+    fn scanner() -> UdpScanner {
+        UdpScanner::new()
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(0)
+    }
+
+    #[tokio::test]
+    async fn test_udp_reply_is_open() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (n, peer) = server.recv_from(&mut buf).await.unwrap();
+            server.send_to(&buf[..n], peer).await.unwrap();
+        });
+
+        let result = scanner().scan(&local_target(port)).await.unwrap();
+        assert_eq!(result.state, PortState::Open);
+        assert!(result.started_at.unwrap() <= result.monotonic.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_udp_port_unreachable_is_closed() {
+        // Bind then drop to get a port with nothing listening
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let result = scanner().scan(&local_target(port)).await.unwrap();
+        assert_eq!(result.state, PortState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_udp_no_response_is_open_filtered() {
+        // Listener that swallows the probe without replying
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = silent.local_addr().unwrap().port();
+
+        let result = scanner().scan(&local_target(port)).await.unwrap();
+        assert_eq!(result.state, PortState::OpenFiltered);
+        drop(silent);
+    }
+
+    #[test]
+    fn test_classify_reply() {
+        assert_eq!(classify_reply(None).unwrap(), PortState::OpenFiltered);
+        assert_eq!(classify_reply(Some(Ok(4))).unwrap(), PortState::Open);
+        let refused = std::io::Error::from(ErrorKind::ConnectionRefused);
+        assert_eq!(classify_reply(Some(Err(refused))).unwrap(), PortState::Closed);
+        assert!(classify_reply(Some(Err(ErrorKind::PermissionDenied.into()))).is_err());
+    }
+}
@@ -1,14 +1,10 @@
 //! Storage - Persistence layer
-//! TODO: Implement
+//!
+//! Backends for the `vajra_common::Storage` trait: results kept in memory,
+//! or in a SQLite database that outlives the process.
 
-pub struct SqliteStorage;
+mod memory;
+mod sqlite;
 
-#[cfg(test)]
-mod tests {
-	use super::*;
-
-	#[test]
-	fn create_storage() {
-		let _ = SqliteStorage;
-	}
-}
+pub use memory::MemoryStore;
+pub use sqlite::SqliteStorage;
@@ -0,0 +1,98 @@
+//! In-memory result store
+//!
+//! Keeps every job's results in a concurrent map for the life of the
+//! process: useful for tests, for embedding the orchestrator, and as the
+//! reference behaviour for persistent backends.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use uuid::Uuid;
+use vajra_common::{ProbeResult, Storage};
+
+/// [`Storage`] backed by a `DashMap` of results per job id
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    jobs: DashMap<Uuid, Vec<ProbeResult>>,
+}
+
+impl MemoryStore {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ids of the jobs with stored results
+    pub fn job_ids(&self) -> Vec<Uuid> {
+        self.jobs.iter().map(|entry| *entry.key()).collect()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStore {
+    async fn store_result(&self, job_id: Uuid, result: &ProbeResult) -> Result<()> {
+        self.jobs.entry(job_id).or_default().push(result.clone());
+        Ok(())
+    }
+
+    async fn store_batch(&self, job_id: Uuid, results: &[ProbeResult]) -> Result<()> {
+        self.jobs.entry(job_id).or_default().extend_from_slice(results);
+        Ok(())
+    }
+
+    /// Results in the order they were stored; empty for an unknown job
+    async fn get_results(&self, job_id: Uuid) -> Result<Vec<ProbeResult>> {
+        Ok(self.jobs.get(&job_id).map(|r| r.clone()).unwrap_or_default())
+    }
+
+    async fn clear_results(&self, job_id: Uuid) -> Result<()> {
+        self.jobs.remove(&job_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use vajra_common::{PortState, Target};
+
+    fn result(port: u16, state: PortState) -> ProbeResult {
+        ProbeResult::new(Target::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), port), state)
+    }
+
+    #[tokio::test]
+    async fn results_are_kept_per_job() {
+        let store = MemoryStore::new();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        store.store_result(a, &result(22, PortState::Open)).await.unwrap();
+        store.store_batch(a, &[result(80, PortState::Closed), result(443, PortState::Open)]).await.unwrap();
+        store.store_result(b, &result(53, PortState::Filtered)).await.unwrap();
+
+        let ports: Vec<u16> = store.get_results(a).await.unwrap().iter().map(|r| r.target.port).collect();
+        assert_eq!(ports, [22, 80, 443]);
+        assert_eq!(store.get_results(b).await.unwrap().len(), 1);
+
+        store.clear_results(a).await.unwrap();
+        assert!(store.get_results(a).await.unwrap().is_empty());
+        assert_eq!(store.job_ids(), [b]);
+    }
+
+    #[tokio::test]
+    async fn exports_use_report_formats() {
+        let store = MemoryStore::new();
+        let job = Uuid::new_v4();
+        store.store_batch(job, &[result(22, PortState::Open), result(80, PortState::Closed)]).await.unwrap();
+
+        let csv = store.export_csv(job).await.unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], vajra_common::export::CSV_HEADER);
+        assert!(lines[1].starts_with("10.0.0.1,22,open,"));
+        assert_eq!(lines.len(), 3);
+
+        let json: serde_json::Value = serde_json::from_str(&store.export_json(job).await.unwrap()).unwrap();
+        assert_eq!(json["job_id"], job.to_string());
+        assert_eq!(json["total_scanned"], 2);
+        assert_eq!(json["results"]["10.0.0.1"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,262 @@
+//! SQLite result store
+//!
+//! One database file holds any number of jobs, so recurring scans can be
+//! kept side by side and compared. The schema is normalized: a `jobs` table,
+//! and a `results` table with one row per result and a column per field
+//! worth querying (ip, port, state, service, version, banner, rtt). Each row
+//! also keeps the whole result as JSON so `get_results` returns it intact.
+//! The schema is created on first open and versioned with `user_version`.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{Row, Sqlite, Transaction};
+use uuid::Uuid;
+use vajra_common::{ProbeResult, Storage};
+
+/// Schema version written to `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 1;
+
+/// Statements creating schema version 1
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS jobs (
+        id TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS results (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        job_id TEXT NOT NULL REFERENCES jobs (id) ON DELETE CASCADE,
+        ip TEXT NOT NULL,
+        port INTEGER NOT NULL,
+        protocol TEXT NOT NULL,
+        state TEXT NOT NULL,
+        service TEXT,
+        product TEXT,
+        version TEXT,
+        banner TEXT,
+        rtt_us INTEGER NOT NULL,
+        timestamp_us INTEGER NOT NULL,
+        data TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS results_job_ip_port ON results (job_id, ip, port)",
+];
+
+/// [`Storage`] in a SQLite database
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Open (creating if needed) the database at `path`
+    pub async fn open(path: &Path) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .with_context(|| format!("Failed to open database {}", path.display()))?;
+        Self::with_pool(pool).await
+    }
+
+    /// Private database that disappears when the store is dropped
+    pub async fn in_memory() -> Result<Self> {
+        let options = SqliteConnectOptions::new().in_memory(true).foreign_keys(true);
+        // Every connection to `:memory:` is a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    async fn with_pool(pool: SqlitePool) -> Result<Self> {
+        let store = Self { pool };
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Create the schema in a new database; refuse one written by a newer version
+    async fn migrate(&self) -> Result<()> {
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&self.pool).await?;
+        if version > SCHEMA_VERSION {
+            return Err(anyhow!(
+                "database schema version {} is newer than this build supports ({})",
+                version,
+                SCHEMA_VERSION
+            ));
+        }
+        if version == SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        // PRAGMA values can't be bound
+        sqlx::query(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Ids of the stored jobs, oldest first
+    pub async fn job_ids(&self) -> Result<Vec<Uuid>> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM jobs ORDER BY created_at, rowid")
+            .fetch_all(&self.pool)
+            .await?;
+        ids.iter()
+            .map(|id| Uuid::parse_str(id).with_context(|| format!("invalid job id '{}' in database", id)))
+            .collect()
+    }
+
+    /// Insert `results` for `job_id` within `tx`, creating the job row on first use
+    async fn insert(tx: &mut Transaction<'_, Sqlite>, job_id: Uuid, results: &[ProbeResult]) -> Result<()> {
+        let job = job_id.to_string();
+        sqlx::query("INSERT OR IGNORE INTO jobs (id, created_at) VALUES (?, ?)")
+            .bind(&job)
+            .bind(unix_micros(SystemTime::now()))
+            .execute(&mut **tx)
+            .await?;
+
+        for result in results {
+            let service = result.service.as_ref();
+            sqlx::query(
+                "INSERT INTO results (job_id, ip, port, protocol, state, service, product, version, banner,
+                    rtt_us, timestamp_us, data)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&job)
+            .bind(result.target.ip.to_string())
+            .bind(i64::from(result.target.port))
+            .bind(result.target.protocol.as_str())
+            .bind(result.state.to_string())
+            .bind(service.map(|s| s.service.as_str()))
+            .bind(service.and_then(|s| s.product.as_deref()))
+            .bind(service.and_then(|s| s.version.as_deref()))
+            .bind(result.banner.as_deref())
+            .bind(result.rtt.as_micros() as i64)
+            .bind(unix_micros(result.timestamp))
+            .bind(serde_json::to_string(result)?)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Microseconds since the Unix epoch (0 for earlier times)
+fn unix_micros(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_micros() as i64).unwrap_or(0)
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn store_result(&self, job_id: Uuid, result: &ProbeResult) -> Result<()> {
+        self.store_batch(job_id, std::slice::from_ref(result)).await
+    }
+
+    /// Stores the whole batch in one transaction
+    async fn store_batch(&self, job_id: Uuid, results: &[ProbeResult]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert(&mut tx, job_id, results).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Results in the order they were stored; empty for an unknown job
+    async fn get_results(&self, job_id: Uuid) -> Result<Vec<ProbeResult>> {
+        let rows = sqlx::query("SELECT data FROM results WHERE job_id = ? ORDER BY id")
+            .bind(job_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let data: String = row.try_get("data")?;
+                serde_json::from_str(&data).context("invalid result in database")
+            })
+            .collect()
+    }
+
+    async fn clear_results(&self, job_id: Uuid) -> Result<()> {
+        // Results go with their job (ON DELETE CASCADE)
+        sqlx::query("DELETE FROM jobs WHERE id = ?")
+            .bind(job_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+    use vajra_common::{PortState, ServiceMatch, Target};
+
+    fn result(port: u16, state: PortState) -> ProbeResult {
+        ProbeResult::new(Target::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), port), state)
+            .with_rtt(Duration::from_micros(1500))
+    }
+
+    #[tokio::test]
+    async fn results_round_trip_per_job() {
+        let store = SqliteStorage::in_memory().await.unwrap();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let ssh = result(22, PortState::Open)
+            .with_banner("SSH-2.0-OpenSSH_9.6".to_string())
+            .with_service(ServiceMatch::new("ssh").with_version("9.6".to_string()));
+        store.store_result(a, &ssh).await.unwrap();
+        store.store_batch(a, &[result(80, PortState::Closed)]).await.unwrap();
+        store.store_result(b, &result(53, PortState::Filtered)).await.unwrap();
+
+        let results = store.get_results(a).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].target, ssh.target);
+        assert_eq!(results[0].rtt, Duration::from_micros(1500));
+        assert_eq!(results[0].service.as_ref().unwrap().version.as_deref(), Some("9.6"));
+        assert_eq!(results[1].state, PortState::Closed);
+        assert_eq!(store.job_ids().await.unwrap(), [a, b]);
+
+        // The normalized columns are queryable directly
+        let open: Vec<(String, i64, Option<String>)> =
+            sqlx::query_as("SELECT ip, port, version FROM results WHERE job_id = ? AND state = 'open'")
+                .bind(a.to_string())
+                .fetch_all(&store.pool)
+                .await
+                .unwrap();
+        assert_eq!(open, [("10.0.0.1".to_string(), 22, Some("9.6".to_string()))]);
+
+        store.clear_results(a).await.unwrap();
+        assert!(store.get_results(a).await.unwrap().is_empty());
+        assert_eq!(store.get_results(b).await.unwrap().len(), 1);
+        assert_eq!(store.job_ids().await.unwrap(), [b]);
+    }
+
+    #[tokio::test]
+    async fn reopened_database_keeps_results() {
+        let path = std::env::temp_dir().join(format!("vajra-store-{}.db", std::process::id()));
+        let job = Uuid::new_v4();
+        {
+            let store = SqliteStorage::open(&path).await.unwrap();
+            store.store_result(job, &result(443, PortState::Open)).await.unwrap();
+            store.pool.close().await;
+        }
+        let store = SqliteStorage::open(&path).await.unwrap();
+        let csv = store.export_csv(job).await.unwrap();
+        store.pool.close().await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(csv.lines().nth(1).unwrap().starts_with("10.0.0.1,443,open,"));
+    }
+}
@@ -7,112 +7,640 @@
 //! - CIDR: "192.168.1.0/24"
 //! - range: "192.168.1.1-192.168.1.10"
 //! - hostname: "example.com"
+//!
+//! Tokens may carry a trailing port, as in `example.com:8080` or
+//! `[2001:db8::1]:443` (see `TargetResolver::resolve_endpoints`).
+//!
+//! Addresses can be excluded with the same IP/CIDR/range forms
+//! (see `TargetResolver::resolve_targets_with_excludes`).
+//!
+//! Targets can also be read from a file with one token per line
+//! (see `TargetResolver::resolve_from_file`), expanded lazily
+//! (see `TargetResolver::resolve_targets_iter`), or streamed as DNS lookups
+//! complete (see `TargetResolver::resolve_stream`). Scanned addresses can
+//! be mapped back to names with `TargetResolver::reverse_lookup_all`.
+//!
+//! Hostnames are looked up in parallel, with at most
+//! `TargetResolver::dns_concurrency` lookups in flight at once (see
+//! `TargetResolver::with_dns_concurrency`). By default
+//! every A record of a hostname is a target, so each backend behind a
+//! load-balanced name is scanned; `TargetResolver::with_first_ip_only`
+//! keeps just the first address the resolver returns. Hostnames resolve
+//! to IPv4 only: AAAA records are ignored, so an IPv6 target has to be
+//! written as an address (see `TargetResolver::resolve_endpoints`).
 
 use anyhow::{Context, Result};
+use futures::stream::{self, FuturesOrdered, FuturesUnordered, Stream, StreamExt};
 use ipnet::Ipv4Net;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
-pub struct TargetResolver;
+/// Default limit on concurrent DNS lookups.
+pub const DEFAULT_DNS_CONCURRENCY: usize = 64;
+
+/// Expands target strings into addresses, with its own DNS settings.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetResolver {
+    dns_concurrency: usize,
+    first_ip_only: bool,
+}
 
 impl TargetResolver {
-    pub fn new() -> Self { Self }
+    pub fn new() -> Self {
+        Self { dns_concurrency: DEFAULT_DNS_CONCURRENCY, first_ip_only: false }
+    }
+
+    /// Set the maximum number of DNS lookups in flight at once.
+    /// Each lookup occupies a blocking-pool thread, so this keeps large
+    /// hostname lists from exhausting the pool. Zero is treated as one.
+    pub fn with_dns_concurrency(mut self, limit: usize) -> Self {
+        self.dns_concurrency = limit.max(1);
+        self
+    }
+
+    /// Limit on concurrent DNS lookups.
+    pub fn dns_concurrency(&self) -> usize {
+        self.dns_concurrency
+    }
+
+    /// Keep only the first address each hostname resolves to.
+    /// Off by default: every A record is kept. Literal IPs, CIDRs and
+    /// ranges are unaffected.
+    pub fn with_first_ip_only(mut self, enabled: bool) -> Self {
+        self.first_ip_only = enabled;
+        self
+    }
+
+    /// Whether hostnames resolve to their first address only.
+    pub fn first_ip_only(&self) -> bool {
+        self.first_ip_only
+    }
 
     /// Resolve a comma-separated target string into unique IPv4 addresses.
     /// This is async-friendly: DNS resolution is performed inside
     /// `tokio::task::spawn_blocking` to avoid blocking the async runtime.
-    pub async fn resolve_targets(targets: &str) -> Result<Vec<IpAddr>> {
+    pub async fn resolve_targets(&self, targets: &str) -> Result<Vec<IpAddr>> {
         if targets.trim().is_empty() {
             anyhow::bail!("No targets specified");
         }
 
-        let mut ips: Vec<IpAddr> = Vec::new();
-        let mut hostnames: Vec<String> = Vec::new();
+        let mut pending = PendingTargets::default();
+        for token in targets.split(',') {
+            let t = token.trim();
+            if t.is_empty() { continue; }
+            pending.add_token(t)?;
+        }
+
+        pending.resolve(self).await
+    }
+
+    /// Like `resolve_targets`, but yields addresses lazily.
+    /// CIDR and range tokens are expanded on demand so scanning can start
+    /// before a large block is fully materialized; hostnames are resolved
+    /// up front. Collecting the iterator gives the same list as
+    /// `resolve_targets`.
+    pub async fn resolve_targets_iter(&self, targets: &str) -> Result<TargetIter> {
+        if targets.trim().is_empty() {
+            anyhow::bail!("No targets specified");
+        }
 
+        let mut pending = PendingTargets::default();
         for token in targets.split(',') {
             let t = token.trim();
             if t.is_empty() { continue; }
+            pending.add_token(t)?;
+        }
 
-            // CIDR
-            if let Ok(net) = t.parse::<Ipv4Net>() {
-                // safety guard: expand only up to MAX_HOSTS unless overridden by env
-                const MAX_HOSTS: u128 = 4096;
-                // compute host count from prefix length to avoid iterating the whole range
-                let prefix = net.prefix_len();
-                // compute host count as a shift to avoid any pow edge-cases
-                let hosts_count = if prefix >= 32 { 1u128 } else { 1u128 << (32 - prefix) };
-                let allow_large = std::env::var("VAJRA_ALLOW_LARGE_CIDR").ok().map(|v| v == "1").unwrap_or(false);
-                if hosts_count > MAX_HOSTS && !allow_large {
-                    anyhow::bail!("CIDR {} expands to {} hosts which exceeds the allowed limit of {}. Set VAJRA_ALLOW_LARGE_CIDR=1 to override.", net, hosts_count, MAX_HOSTS);
-                }
+        pending.resolve_iter(self).await
+    }
 
-                // iterate hosts in the CIDR
-                for addr in net.hosts() {
-                    let ip = IpAddr::V4(addr);
-                    if !ips.contains(&ip) { ips.push(ip); }
+    /// Stream addresses as they become available, for pipelining
+    /// resolution with scanning. Literal tokens are expanded lazily while
+    /// every hostname is looked up concurrently; addresses from the two are
+    /// interleaved, so order is not deterministic. Duplicates are dropped.
+    /// A parse error or failed DNS task is yielded as an `Err` item.
+    pub fn resolve_stream(&self, targets: &str) -> impl Stream<Item = Result<IpAddr>> + Send + 'static {
+        let mut pending = PendingTargets::default();
+        let parsed = if targets.trim().is_empty() {
+            Err(anyhow::anyhow!("No targets specified"))
+        } else {
+            targets
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .try_for_each(|t| pending.add_token(t))
+        };
+        if let Err(e) = parsed {
+            return stream::once(async { Err(e) }).left_stream();
+        }
+
+        let literals = stream::iter(pending.sources.into_iter().flatten().map(Ok));
+        let lookups: FuturesUnordered<_> =
+            bounded_lookups(pending.hostnames, self.dns_concurrency, self.first_ip_only, lookup_host)
+                .into_iter()
+                .collect();
+        let resolved = lookups.flat_map(|r| match r {
+            Ok((_, addrs)) => stream::iter(addrs.into_iter().map(Ok)).left_stream(),
+            Err(e) => stream::once(async { Err(e) }).right_stream(),
+        });
+
+        let mut seen = HashSet::new();
+        stream::select(literals, resolved)
+            .filter(move |item| {
+                let keep = match item {
+                    Ok(ip) => seen.insert(*ip),
+                    Err(_) => true,
+                };
+                async move { keep }
+            })
+            .right_stream()
+    }
+
+    /// Resolve targets, keeping the hostname each address came from.
+    /// Every A record of a hostname (only the first with
+    /// `with_first_ip_only`) is kept and tagged with that name;
+    /// literal IPs, CIDRs and ranges map to `None`. When an address appears
+    /// more than once, the first occurrence (and its source) wins.
+    pub async fn resolve_with_names(&self, targets: &str) -> Result<Vec<(IpAddr, Option<String>)>> {
+        if targets.trim().is_empty() {
+            anyhow::bail!("No targets specified");
+        }
+
+        let mut pending = PendingTargets::default();
+        for token in targets.split(',') {
+            let t = token.trim();
+            if t.is_empty() { continue; }
+            pending.add_token(t)?;
+        }
+
+        pending.resolve_named(self).await
+    }
+
+    /// Resolve targets that may carry a port, as other tools write them:
+    /// `example.com:8080`, `10.0.0.5:22`, `10.0.0.0/30:443` or
+    /// `[2001:db8::1]:443`. Each address comes with its token's port, or
+    /// `None` for a token without one. Addresses are grouped by port, in
+    /// the order each port first appears, and listed once per port. Unlike
+    /// `resolve_targets`, IPv6 addresses, bracketed or not, are kept.
+    pub async fn resolve_endpoints(&self, targets: &str) -> Result<Vec<(IpAddr, Option<u16>)>> {
+        if targets.trim().is_empty() {
+            anyhow::bail!("No targets specified");
+        }
+
+        let mut groups: Vec<(Option<u16>, PendingTargets)> = Vec::new();
+        for token in targets.split(',') {
+            let t = token.trim();
+            if t.is_empty() { continue; }
+            let (host, port) = split_port(t)?;
+            let idx = match groups.iter().position(|(p, _)| *p == port) {
+                Some(idx) => idx,
+                None => {
+                    groups.push((port, PendingTargets { ipv6: true, ..Default::default() }));
+                    groups.len() - 1
                 }
-                continue;
+            };
+            groups[idx].1.add_token(host)?;
+        }
+
+        let mut endpoints = Vec::new();
+        for (port, pending) in groups {
+            endpoints.extend(pending.resolve_entries(self).await?.into_iter().map(|(ip, _)| (ip, port)));
+        }
+        if endpoints.is_empty() {
+            anyhow::bail!("No valid addresses found in targets");
+        }
+        Ok(endpoints)
+    }
+
+    /// Resolve targets and remove any addresses matched by `excludes`.
+    ///
+    /// `excludes` is a comma-separated list of IPs, CIDRs or ranges (hostnames
+    /// are rejected). Exclusions are applied after CIDR expansion and DNS
+    /// resolution, so a hostname resolving to an excluded address is dropped
+    /// as well. An empty excludes string is a no-op.
+    pub async fn resolve_targets_with_excludes(&self, targets: &str, excludes: &str) -> Result<Vec<IpAddr>> {
+        let excluded = ExcludeSet::parse(excludes)?;
+        let mut ips = self.resolve_targets(targets).await?;
+        if excluded.is_empty() {
+            return Ok(ips);
+        }
+
+        ips.retain(|ip| !excluded.contains(ip));
+        if ips.is_empty() {
+            anyhow::bail!("All targets were excluded");
+        }
+        Ok(ips)
+    }
+
+    /// Resolve targets listed in a file, one token per line.
+    /// Each line accepts the same forms as `resolve_targets` (IP, CIDR,
+    /// range, hostname); blank lines and `#` comments are ignored.
+    /// Addresses are deduplicated across all lines.
+    pub async fn resolve_from_file(&self, path: &Path) -> Result<Vec<IpAddr>> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read target file {}", path.display()))?;
+
+        let mut pending = PendingTargets::default();
+        for (idx, line) in content.lines().enumerate() {
+            let t = line.split('#').next().unwrap_or("").trim();
+            if t.is_empty() { continue; }
+            pending.add_token(t).with_context(|| {
+                format!("{}:{}: invalid target '{}'", path.display(), idx + 1, t)
+            })?;
+        }
+
+        pending.resolve(self).await
+    }
+
+    /// Reverse-resolve (PTR) each distinct address in `ips` with the system
+    /// resolver, at most `dns_concurrency` lookups at once. Every address is
+    /// looked up once however often it appears; addresses without a name
+    /// are left out of the map.
+    pub async fn reverse_lookup_all<I>(&self, ips: I) -> HashMap<IpAddr, String>
+    where
+        I: IntoIterator<Item = IpAddr>,
+    {
+        reverse_lookups(ips, self.dns_concurrency, lookup_ptr).await
+    }
+}
+
+/// Run `lookup` once per distinct address in `ips`, at most `limit` at a
+/// time, keeping the names found
+async fn reverse_lookups<I, F, Fut>(ips: I, limit: usize, mut lookup: F) -> HashMap<IpAddr, String>
+where
+    I: IntoIterator<Item = IpAddr>,
+    F: FnMut(IpAddr) -> Fut,
+    Fut: Future<Output = Option<String>> + Send + 'static,
+{
+    let permits = Arc::new(Semaphore::new(limit.max(1)));
+    let mut seen = HashSet::new();
+    let lookups: FuturesUnordered<_> = ips
+        .into_iter()
+        .filter(|ip| seen.insert(*ip))
+        .map(|ip| {
+            let permits = Arc::clone(&permits);
+            let lookup = lookup(ip);
+            async move {
+                let _permit = permits.acquire_owned().await.ok()?;
+                lookup.await.map(|name| (ip, name))
             }
+        })
+        .collect();
+    lookups.filter_map(|found| async move { found }).collect().await
+}
+
+/// PTR name of one address via `getnameinfo(3)` on the blocking pool, or
+/// `None` if it has none
+async fn lookup_ptr(ip: IpAddr) -> Option<String> {
+    tokio::task::spawn_blocking(move || reverse_name(ip)).await.ok().flatten()
+}
+
+fn reverse_name(ip: IpAddr) -> Option<String> {
+    // NI_MAXHOST from <netdb.h>
+    let mut host = [0 as libc::c_char; 1025];
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let len = match ip {
+        IpAddr::V4(v4) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr.s_addr = u32::from_ne_bytes(v4.octets());
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(v6) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_addr.s6_addr = v6.octets();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    // NI_NAMEREQD: fail rather than return the address as text
+    let rc = unsafe {
+        libc::getnameinfo(
+            &storage as *const _ as *const libc::sockaddr,
+            len as libc::socklen_t,
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if rc != 0 {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(host.as_ptr()) };
+    name.to_str().ok().filter(|n| !n.is_empty()).map(str::to_string)
+}
+
+/// Lazily expanded, deduplicated target addresses
+/// (see `TargetResolver::resolve_targets_iter`).
+/// `seen` gives O(1) dedup while sources are drained in first-seen order.
+pub struct TargetIter {
+    sources: std::vec::IntoIter<Source>,
+    current: Option<Source>,
+    seen: HashSet<IpAddr>,
+}
 
-            // Range a.b.c.d-e.f.g.h
-            if t.contains('-') && t.chars().any(|c| c.is_ascii_digit()) {
-                if let Ok(range_ips) = parse_ip_range(t) {
-                    for ip in range_ips { if !ips.contains(&ip) { ips.push(ip); } }
-                    continue;
+impl Iterator for TargetIter {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        loop {
+            if let Some(src) = self.current.as_mut() {
+                for ip in src.by_ref() {
+                    if self.seen.insert(ip) {
+                        return Some(ip);
+                    }
                 }
             }
+            self.current = Some(self.sources.next()?);
+        }
+    }
+}
 
-            // Direct IP
-            if let Ok(ip) = t.parse::<IpAddr>() {
-                if ip.is_ipv4() && !ips.contains(&ip) { ips.push(ip); }
-                continue;
-            }
+/// One expanded target token
+enum Source {
+    /// Host addresses of a CIDR block
+    Hosts(ipnet::Ipv4AddrRange),
+    /// Inclusive numeric range `a.b.c.d-e.f.g.h`
+    Range(std::ops::RangeInclusive<u32>),
+    /// Single IPs and DNS results
+    Addrs(std::vec::IntoIter<IpAddr>),
+}
 
+impl Iterator for Source {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        match self {
+            Source::Hosts(hosts) => hosts.next().map(IpAddr::V4),
+            Source::Range(range) => range.next().map(|v| IpAddr::V4(Ipv4Addr::from(v))),
+            Source::Addrs(addrs) => addrs.next(),
+        }
+    }
+}
+
+/// Targets accumulated from tokens: literal tokens become lazy sources,
+/// hostnames are batched for a single DNS pass.
+#[derive(Default)]
+struct PendingTargets {
+    sources: Vec<Source>,
+    hostnames: Vec<String>,
+    /// Keep IPv6 literals instead of skipping them
+    ipv6: bool,
+}
+
+impl PendingTargets {
+    /// Parse a single target token (IP, CIDR, range or hostname).
+    fn add_token(&mut self, t: &str) -> Result<()> {
+        match literal_source(t, self.ipv6)? {
+            Some(src) => self.sources.push(src),
             // Treat as hostname to resolve
-            hostnames.push(t.to_string());
+            None => self.hostnames.push(t.to_string()),
         }
+        Ok(())
+    }
 
-        if !hostnames.is_empty() {
-            let host_batch = hostnames.clone();
-            let resolved: Vec<Vec<IpAddr>> = tokio::task::spawn_blocking(move || {
-                host_batch.into_iter().map(|h| {
-                    match (h.as_str(), 0).to_socket_addrs() {
-                        Ok(addrs) => addrs.filter(|a| a.ip().is_ipv4()).map(|a| a.ip()).collect::<Vec<IpAddr>>(),
-                        Err(_) => Vec::new(),
-                    }
-                }).collect()
-            }).await.context("Blocking DNS resolution failed")?;
+    /// Resolve batched hostnames with `resolver`'s settings and return the
+    /// deduplicated address list.
+    async fn resolve(self, resolver: &TargetResolver) -> Result<Vec<IpAddr>> {
+        Ok(self.resolve_iter(resolver).await?.collect())
+    }
 
-            for v in resolved.into_iter().flatten() {
-                if !ips.contains(&v) { ips.push(v); }
-            }
+    /// Resolve batched hostnames and return a lazy address iterator.
+    async fn resolve_iter(mut self, resolver: &TargetResolver) -> Result<TargetIter> {
+        if !self.hostnames.is_empty() {
+            let resolved = resolve_hostnames(std::mem::take(&mut self.hostnames), resolver).await?;
+            let addrs: Vec<IpAddr> = resolved.into_iter().flat_map(|(_, addrs)| addrs).collect();
+            self.sources.push(Source::Addrs(addrs.into_iter()));
         }
 
-        if ips.is_empty() {
+        // CIDR and range sources are never empty; address lists may be
+        self.sources.retain(|src| !matches!(src, Source::Addrs(a) if a.len() == 0));
+        if self.sources.is_empty() {
             anyhow::bail!("No valid IPv4 addresses found in targets");
         }
 
-        Ok(ips)
+        Ok(TargetIter {
+            sources: self.sources.into_iter(),
+            current: None,
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Resolve batched hostnames, keeping the originating name of each
+    /// DNS-resolved address (`None` for literal tokens).
+    async fn resolve_named(self, resolver: &TargetResolver) -> Result<Vec<(IpAddr, Option<String>)>> {
+        let entries = self.resolve_entries(resolver).await?;
+        if entries.is_empty() {
+            anyhow::bail!("No valid IPv4 addresses found in targets");
+        }
+        Ok(entries)
+    }
+
+    /// `resolve_named` without the check for an empty result
+    async fn resolve_entries(self, resolver: &TargetResolver) -> Result<Vec<(IpAddr, Option<String>)>> {
+        let resolved = resolve_hostnames(self.hostnames, resolver).await?;
+
+        let literals = self.sources.into_iter().flatten().map(|ip| (ip, None));
+        let named = resolved.into_iter().flat_map(|(host, addrs)| {
+            addrs.into_iter().map(move |ip| (ip, Some(host.clone())))
+        });
+
+        let mut seen = HashSet::new();
+        Ok(literals.chain(named).filter(|(ip, _)| seen.insert(*ip)).collect())
+    }
+}
+
+/// Resolve hostnames to all of their IPv4 addresses (or the first one,
+/// see `TargetResolver::with_first_ip_only`), in input order.
+/// Lookups run in parallel, at most `dns_concurrency` at once.
+/// Names that fail to resolve map to an empty list.
+async fn resolve_hostnames(hostnames: Vec<String>, resolver: &TargetResolver) -> Result<Vec<(String, Vec<IpAddr>)>> {
+    let lookups: FuturesOrdered<_> =
+        bounded_lookups(hostnames, resolver.dns_concurrency, resolver.first_ip_only, lookup_host)
+            .into_iter()
+            .collect();
+    lookups.collect::<Vec<_>>().await.into_iter().collect()
+}
+
+/// Wrap one `lookup` per hostname so that at most `limit` run at once.
+/// With `first_only`, each name keeps only its first address.
+/// The futures are lazy; the caller decides how to drive them.
+fn bounded_lookups<F, Fut>(
+    hostnames: Vec<String>,
+    limit: usize,
+    first_only: bool,
+    mut lookup: F,
+) -> Vec<impl Future<Output = Result<(String, Vec<IpAddr>)>> + Send + 'static>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<Vec<IpAddr>>> + Send + 'static,
+{
+    let permits = Arc::new(Semaphore::new(limit.max(1)));
+    hostnames.into_iter().map(|host| {
+        let permits = Arc::clone(&permits);
+        let lookup = lookup(host.clone());
+        async move {
+            let _permit = permits.acquire_owned().await.context("DNS semaphore closed")?;
+            let mut addrs = lookup.await?;
+            if first_only {
+                addrs.truncate(1);
+            }
+            Ok((host, addrs))
+        }
+    }).collect()
+}
+
+/// Resolve one hostname to its IPv4 addresses on the blocking pool.
+/// AAAA records are dropped (hostname targets are scanned over IPv4
+/// only); a name that fails to resolve yields an empty list.
+async fn lookup_host(host: String) -> Result<Vec<IpAddr>> {
+    tokio::task::spawn_blocking(move || match (host.as_str(), 0).to_socket_addrs() {
+        Ok(addrs) => addrs.filter(|a| a.ip().is_ipv4()).map(|a| a.ip()).collect(),
+        Err(_) => Vec::new(),
+    }).await.context("Blocking DNS resolution failed")
+}
+
+/// Addresses to skip. Single IPs live in a `HashSet`; CIDRs and ranges are
+/// kept as bounds so excluding a large block (e.g. `10.0.0.0/8`) is cheap
+/// and not subject to the CIDR expansion guard.
+#[derive(Default)]
+struct ExcludeSet {
+    addrs: HashSet<IpAddr>,
+    ranges: Vec<(u32, u32)>,
+}
+
+impl ExcludeSet {
+    /// Parse a comma-separated excludes string.
+    fn parse(excludes: &str) -> Result<Self> {
+        let mut set = Self::default();
+        for token in excludes.split(',') {
+            let t = token.trim();
+            if t.is_empty() { continue; }
+
+            if let Ok(net) = t.parse::<Ipv4Net>() {
+                set.ranges.push((u32::from(net.network()), u32::from(net.broadcast())));
+            } else if let Ok(ip) = t.parse::<IpAddr>() {
+                set.addrs.insert(ip);
+            } else if t.contains('-') {
+                let (start, end) = parse_range_bounds(t).with_context(|| format!("Invalid exclude '{}'", t))?;
+                set.ranges.push((start, end));
+            } else {
+                anyhow::bail!("Invalid exclude '{}': expected an IP, CIDR or range", t);
+            }
+        }
+        Ok(set)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.addrs.is_empty() && self.ranges.is_empty()
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        if self.addrs.contains(ip) {
+            return true;
+        }
+        match ip {
+            IpAddr::V4(v4) => {
+                let v = u32::from(*v4);
+                self.ranges.iter().any(|&(start, end)| start <= v && v <= end)
+            }
+            IpAddr::V6(_) => false,
+        }
+    }
+}
+
+/// Turn a literal token (IP, CIDR or range) into a lazy address source;
+/// an IPv6 address yields nothing unless `ipv6` is set. Returns `None`
+/// when the token is not a literal and should be treated as a hostname.
+fn literal_source(t: &str, ipv6: bool) -> Result<Option<Source>> {
+    // CIDR
+    if let Ok(net) = t.parse::<Ipv4Net>() {
+        // compute host count from prefix length to avoid iterating the whole range
+        let prefix = net.prefix_len();
+        // compute host count as a shift to avoid any pow edge-cases
+        let hosts_count = if prefix >= 32 { 1u128 } else { 1u128 << (32 - prefix) };
+        check_host_count("CIDR", &net.to_string(), hosts_count)?;
+
+        // iterate hosts in the CIDR
+        return Ok(Some(Source::Hosts(net.hosts())));
+    }
+
+    // Range a.b.c.d-e.f.g.h
+    if t.contains('-') && t.chars().any(|c| c.is_ascii_digit()) {
+        if let Ok((start, end)) = parse_range_bounds(t) {
+            check_host_count("Range", t, u128::from(end - start) + 1)?;
+            return Ok(Some(Source::Range(start..=end)));
+        }
+    }
+
+    // Direct IP
+    if let Ok(ip) = t.parse::<IpAddr>() {
+        let addrs = if ip.is_ipv4() || ipv6 { vec![ip] } else { Vec::new() };
+        return Ok(Some(Source::Addrs(addrs.into_iter())));
+    }
+
+    Ok(None)
+}
+
+/// Split a trailing `:port` off a target token, `host:port` or
+/// `[ipv6]:port`. A bare IPv6 address (more than one colon) has no port.
+fn split_port(t: &str) -> Result<(&str, Option<u16>)> {
+    if let Some(rest) = t.strip_prefix('[') {
+        let (host, after) = rest
+            .split_once(']')
+            .with_context(|| format!("Invalid target '{}': missing ']'", t))?;
+        return match after.strip_prefix(':') {
+            Some(port) => Ok((host, Some(parse_port(t, port)?))),
+            None if after.is_empty() => Ok((host, None)),
+            None => anyhow::bail!("Invalid target '{}': expected ':port' after ']'", t),
+        };
+    }
+    match t.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => Ok((host, Some(parse_port(t, port)?))),
+        _ => Ok((t, None)),
     }
 }
 
-fn parse_ip_range(range: &str) -> Result<Vec<IpAddr>> {
+/// Port of a `host:port` token (1-65535)
+fn parse_port(token: &str, port: &str) -> Result<u16> {
+    port.parse::<u16>()
+        .ok()
+        .filter(|&p| p != 0)
+        .with_context(|| format!("Invalid port '{}' in target '{}'", port, token))
+}
+
+/// Safety guard: refuse to expand a CIDR or range token to more than
+/// `MAX_HOSTS` addresses unless overridden by env
+fn check_host_count(kind: &str, token: &str, hosts_count: u128) -> Result<()> {
+    const MAX_HOSTS: u128 = 4096;
+    let allow_large = std::env::var("VAJRA_ALLOW_LARGE_CIDR").ok().map(|v| v == "1").unwrap_or(false);
+    if hosts_count > MAX_HOSTS && !allow_large {
+        anyhow::bail!("{} {} expands to {} hosts which exceeds the allowed limit of {}. Set VAJRA_ALLOW_LARGE_CIDR=1 to override.", kind, token, hosts_count, MAX_HOSTS);
+    }
+    Ok(())
+}
+
+/// Parse `a.b.c.d-e.f.g.h` into inclusive numeric bounds.
+fn parse_range_bounds(range: &str) -> Result<(u32, u32)> {
     let parts: Vec<&str> = range.split('-').collect();
     if parts.len() != 2 {
         anyhow::bail!("Invalid IP range: {}", range);
     }
 
-    let start: Ipv4Addr = parts[0].parse().context(format!("Invalid start IP: {}", parts[0]))?;
-    let end: Ipv4Addr = parts[1].parse().context(format!("Invalid end IP: {}", parts[1]))?;
+    let start: Ipv4Addr = parts[0].trim().parse().context(format!("Invalid start IP: {}", parts[0]))?;
+    let end: Ipv4Addr = parts[1].trim().parse().context(format!("Invalid end IP: {}", parts[1]))?;
 
     let start_u32 = u32::from(start);
     let end_u32 = u32::from(end);
     if start_u32 > end_u32 { anyhow::bail!("Invalid IP range: start > end"); }
-
-    let mut ips = Vec::new();
-    for v in start_u32..=end_u32 {
-        ips.push(IpAddr::V4(Ipv4Addr::from(v)));
-    }
-    Ok(ips)
+    Ok((start_u32, end_u32))
 }
 
 impl Default for TargetResolver { fn default() -> Self { Self::new() } }
@@ -121,41 +649,276 @@ impl Default for TargetResolver { fn default() -> Self { Self::new() } }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serializes tests that read or modify `VAJRA_ALLOW_LARGE_CIDR`.
+    static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
 
     #[tokio::test]
     async fn test_resolve_single_ip() {
-        let ips = TargetResolver::resolve_targets("8.8.8.8").await.unwrap();
+        let ips = TargetResolver::new().resolve_targets("8.8.8.8").await.unwrap();
         assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(8,8,8,8))]);
     }
 
     #[tokio::test]
     async fn test_resolve_cidr() {
-        let ips = TargetResolver::resolve_targets("192.168.1.0/30").await.unwrap();
+        let ips = TargetResolver::new().resolve_targets("192.168.1.0/30").await.unwrap();
         // /30 -> 2 hosts (192.168.1.1 and 192.168.1.2) when using hosts(), but ipnet.hosts() yields host addresses
-        assert!(ips.len() >= 1);
+        assert!(!ips.is_empty());
     }
 
     #[tokio::test]
     async fn test_resolve_range() {
-        let ips = TargetResolver::resolve_targets("192.168.1.1-192.168.1.3").await.unwrap();
+        let ips = TargetResolver::new().resolve_targets("192.168.1.1-192.168.1.3").await.unwrap();
         assert_eq!(ips.len(), 3);
     }
 
     #[tokio::test]
     async fn test_large_cidr_rejected() {
+    let _env = ENV_LOCK.lock().await;
     // ensure override is not set
     std::env::remove_var("VAJRA_ALLOW_LARGE_CIDR");
     // /16 is 65536 hosts - should be rejected by default
-    let r = TargetResolver::resolve_targets("10.0.0.0/16").await;
+    let r = TargetResolver::new().resolve_targets("10.0.0.0/16").await;
         assert!(r.is_err());
     }
 
     #[tokio::test]
     async fn test_large_cidr_allowed_with_env() {
+        let _env = ENV_LOCK.lock().await;
         std::env::set_var("VAJRA_ALLOW_LARGE_CIDR", "1");
-        let r = TargetResolver::resolve_targets("10.0.0.0/24").await;
+        let r = TargetResolver::new().resolve_targets("10.0.0.0/24").await;
         // /24 -> 256 hosts should be allowed even without override, but env should not break it
         assert!(r.is_ok());
         std::env::remove_var("VAJRA_ALLOW_LARGE_CIDR");
     }
+
+    #[tokio::test]
+    async fn test_large_range_guarded_like_cidr() {
+        let _env = ENV_LOCK.lock().await;
+        std::env::remove_var("VAJRA_ALLOW_LARGE_CIDR");
+        // Crosses the third octet: 65536 hosts
+        let err = TargetResolver::new().resolve_targets("10.0.0.0-10.0.255.255").await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the allowed limit"), "{}", err);
+        // At the limit is fine
+        assert_eq!(TargetResolver::new().resolve_targets("10.0.0.0-10.0.15.255").await.unwrap().len(), 4096);
+
+        std::env::set_var("VAJRA_ALLOW_LARGE_CIDR", "1");
+        let r = TargetResolver::new().resolve_targets_iter("10.0.0.0-10.0.255.255").await;
+        std::env::remove_var("VAJRA_ALLOW_LARGE_CIDR");
+        let mut iter = r.unwrap();
+        assert_eq!(iter.nth(256), Some(IpAddr::V4(Ipv4Addr::new(10, 0, 1, 0))));
+        assert_eq!(iter.count(), 65536 - 257);
+    }
+
+    #[tokio::test]
+    async fn test_large_cidr_dedup_preserves_order() {
+        let _env = ENV_LOCK.lock().await;
+        std::env::set_var("VAJRA_ALLOW_LARGE_CIDR", "1");
+        let r = TargetResolver::new().resolve_targets("10.1.255.254,10.1.0.0/16,10.1.0.1-10.1.0.9").await;
+        std::env::remove_var("VAJRA_ALLOW_LARGE_CIDR");
+
+        let ips = r.unwrap();
+        // 65534 hosts; the leading single IP is already part of the /16
+        assert_eq!(ips.len(), 65534);
+        assert_eq!(ips[0], IpAddr::V4(Ipv4Addr::new(10, 1, 255, 254)));
+        assert_eq!(ips[1], IpAddr::V4(Ipv4Addr::new(10, 1, 0, 1)));
+        assert_eq!(ips.iter().collect::<HashSet<_>>().len(), ips.len());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_targets_iter_is_lazy() {
+        let targets = "10.0.0.5,10.0.0.0/29,10.0.0.6-10.0.0.9";
+        let mut iter = TargetResolver::new().resolve_targets_iter(targets).await.unwrap();
+        assert_eq!(iter.next(), Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))));
+        assert_eq!(iter.next(), Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+
+        let collected: Vec<IpAddr> = TargetResolver::new().resolve_targets_iter(targets).await.unwrap().collect();
+        assert_eq!(collected, TargetResolver::new().resolve_targets(targets).await.unwrap());
+        assert_eq!(collected.len(), 9);
+
+        assert!(TargetResolver::new().resolve_targets_iter("::1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_with_names() {
+        let entries = TargetResolver::new().resolve_with_names("localhost,10.0.0.1,10.0.0.1/32").await.unwrap();
+        assert_eq!(entries[0], (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), None));
+        assert!(entries.len() >= 2);
+        for (ip, name) in &entries[1..] {
+            assert!(ip.is_loopback());
+            assert_eq!(name.as_deref(), Some("localhost"));
+        }
+    }
+
+    #[test]
+    fn test_split_port() {
+        assert_eq!(split_port("example.com:8080").unwrap(), ("example.com", Some(8080)));
+        assert_eq!(split_port("10.0.0.0/30:443").unwrap(), ("10.0.0.0/30", Some(443)));
+        assert_eq!(split_port("10.0.0.5").unwrap(), ("10.0.0.5", None));
+        assert_eq!(split_port("[2001:db8::1]:443").unwrap(), ("2001:db8::1", Some(443)));
+        assert_eq!(split_port("[2001:db8::1]").unwrap(), ("2001:db8::1", None));
+        assert_eq!(split_port("2001:db8::1").unwrap(), ("2001:db8::1", None));
+        for bad in ["10.0.0.5:http", "10.0.0.5:0", "10.0.0.5:70000", "[::1", "[::1]443"] {
+            assert!(split_port(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_endpoints() {
+        let ip = |last| IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+        let endpoints = TargetResolver::new().resolve_endpoints("10.0.0.5:22, 10.0.0.6, 10.0.0.7-10.0.0.8:443, 10.0.0.5:22, 10.0.0.5")
+            .await
+            .unwrap();
+        assert_eq!(
+            endpoints,
+            [(ip(5), Some(22)), (ip(6), None), (ip(5), None), (ip(7), Some(443)), (ip(8), Some(443))]
+        );
+
+        let v6: IpAddr = "2001:db8::1".parse().unwrap();
+        let endpoints = TargetResolver::new().resolve_endpoints("[2001:db8::1]:443, 2001:db8::1, 10.0.0.5").await.unwrap();
+        assert_eq!(endpoints, [(v6, Some(443)), (v6, None), (ip(5), None)]);
+        // Plain target lists still skip IPv6
+        assert!(TargetResolver::new().resolve_targets("2001:db8::1").await.is_err());
+        assert!(TargetResolver::new().resolve_endpoints("10.0.0.5:ssh").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_stream_matches_eager() {
+        let targets = "localhost,10.0.0.0/29,10.0.0.3,10.0.0.6-10.0.0.9";
+        let streamed: Vec<IpAddr> = TargetResolver::new().resolve_stream(targets)
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        let eager = TargetResolver::new().resolve_targets(targets).await.unwrap();
+
+        assert_eq!(streamed.len(), eager.len());
+        let streamed: HashSet<IpAddr> = streamed.into_iter().collect();
+        assert_eq!(streamed, eager.into_iter().collect());
+    }
+
+    #[tokio::test]
+    async fn test_dns_concurrency_is_bounded() {
+        use std::time::Duration;
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let hostnames: Vec<String> = (0..10).map(|i| format!("host{i}.test")).collect();
+
+        let lookups = bounded_lookups(hostnames, 2, false, |_| {
+            let (in_flight, peak) = (Arc::clone(&in_flight), Arc::clone(&peak));
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])
+            }
+        });
+        let resolved: Vec<_> = lookups.into_iter().collect::<FuturesUnordered<_>>().collect().await;
+
+        assert_eq!(resolved.len(), 10);
+        assert!(resolved.iter().all(|r| r.as_ref().is_ok_and(|(_, addrs)| addrs.len() == 1)));
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        assert_eq!(TargetResolver::new().dns_concurrency(), DEFAULT_DNS_CONCURRENCY);
+        assert_eq!(TargetResolver::new().with_dns_concurrency(0).dns_concurrency(), 1);
+        assert!(!TargetResolver::new().first_ip_only());
+        assert!(TargetResolver::new().with_first_ip_only(true).first_ip_only());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_lookups_once_per_address() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let host = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10));
+        let unnamed = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 11));
+        // 1000 ports on one host, plus a host without a PTR record
+        let ips = (0..1000).map(|_| host).chain([unnamed, host]);
+
+        let names = reverse_lookups(ips, 4, |ip| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move { (ip == host).then(|| "web1.example.test".to_string()) }
+        })
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[&host], "web1.example.test");
+    }
+
+    #[tokio::test]
+    async fn test_first_ip_only_keeps_one_address() {
+        // Mock resolver for a load-balanced name with three A records
+        let backends = || async {
+            Ok((1..=3).map(|i| IpAddr::V4(Ipv4Addr::new(192, 0, 2, i))).collect::<Vec<_>>())
+        };
+        let hostnames = || vec!["lb.example.test".to_string()];
+
+        let all: Vec<_> = bounded_lookups(hostnames(), 4, false, |_| backends())
+            .into_iter()
+            .collect::<FuturesOrdered<_>>()
+            .collect()
+            .await;
+        assert_eq!(all[0].as_ref().unwrap().1.len(), 3);
+
+        let first: Vec<_> = bounded_lookups(hostnames(), 4, true, |_| backends())
+            .into_iter()
+            .collect::<FuturesOrdered<_>>()
+            .collect()
+            .await;
+        assert_eq!(first[0].as_ref().unwrap().1, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_from_file() {
+        let path = std::env::temp_dir().join(format!("vajra_targets_{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "# lab hosts\n10.0.0.1\n\n10.0.0.0/30  # small subnet\n10.0.0.2-10.0.0.4\n",
+        ).unwrap();
+
+        let ips = TargetResolver::new().resolve_from_file(&path).await.unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // 10.0.0.1-10.0.0.4, deduplicated across lines in first-seen order
+        let expected: Vec<IpAddr> = (1..=4).map(|i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect();
+        assert_eq!(ips, expected);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_from_file_reports_line() {
+        let _env = ENV_LOCK.lock().await;
+        std::env::remove_var("VAJRA_ALLOW_LARGE_CIDR");
+        let path = std::env::temp_dir().join(format!("vajra_targets_bad_{}.txt", std::process::id()));
+        std::fs::write(&path, "10.0.0.1\n10.0.0.0/8\n").unwrap();
+
+        let err = TargetResolver::new().resolve_from_file(&path).await.unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(format!("{:#}", err).contains(":2:"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_with_excludes() {
+        let ips = TargetResolver::new().resolve_targets_with_excludes(
+            "192.168.1.0/29",
+            "192.168.1.1, 192.168.1.4-192.168.1.5, 192.168.1.6/32",
+        ).await.unwrap();
+        let expected: Vec<IpAddr> = [2, 3].iter().map(|&i| IpAddr::V4(Ipv4Addr::new(192, 168, 1, i))).collect();
+        assert_eq!(ips, expected);
+
+        // empty excludes is a no-op
+        let ips = TargetResolver::new().resolve_targets_with_excludes("192.168.1.1-192.168.1.3", "").await.unwrap();
+        assert_eq!(ips.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_excludes_apply_after_dns() {
+        let ips = TargetResolver::new().resolve_targets_with_excludes("localhost,10.0.0.1", "127.0.0.0/8").await.unwrap();
+        assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    }
+
+    #[tokio::test]
+    async fn test_excludes_reject_hostnames() {
+        assert!(TargetResolver::new().resolve_targets_with_excludes("10.0.0.1", "example.com").await.is_err());
+    }
 }